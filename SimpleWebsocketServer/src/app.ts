@@ -1,49 +1,1439 @@
 import WebSocket, { WebSocketServer } from 'ws';
+import http from 'http';
+import https from 'https';
+import crypto from 'crypto';
+import fs from 'fs';
+import dns from 'dns';
+import net from 'net';
+import { EventEmitter } from 'events';
 
 const PORT = process.env.PORT ? parseInt(process.env.PORT) : 7000;
 interface User {
     ws: WebSocket;
     nick: String;
     isAlive: boolean;
+    // Handed out on registering; presenting it again takes the name back from this socket
+    reclaim: string;
 }
 
 interface Message {
     messageType: String;
     data: String;
     dataArray: String[];
+    replyTo?: { from: String; message: String };
+    clientId?: String;
+    reclaim?: String;
+    // Image/GIF attachment layout hints, passed through untouched
+    media?: { width: number; height: number; placeholder?: String; sensitive?: boolean };
+    // A shared position; the message text is a map link for clients that don't render it
+    location?: { lat: number; lon: number; accuracy?: number };
 }
 
 let users: User[] = [];
+// Serialized chat messages, oldest first, served to clients paging back through history
+const history: { time: number; seq: number; json: string }[] = [];
+const HISTORY_LIMIT = 1000;
+let nextMessageId = 1;
+// Room sequence numbers are gapless so clients can detect dropped frames
+let nextSeq = 1;
+
+// The server hosts a single room; its name is sent with room-scoped payloads
+const ROOM = 'main';
+
+// With ROOM_PASSWORD set, clients must enter the password before they can read or post
+const ROOM_PASSWORD = process.env.ROOM_PASSWORD || '';
+// Clients send SHA-256("room:password") as hex so the password itself never crosses the wire
+const ROOM_KEY = ROOM_PASSWORD ? crypto.createHash('sha256').update(`${ROOM}:${ROOM_PASSWORD}`).digest() : null;
+const unlocked = new WeakSet<WebSocket>();
+// What a socket may send before it has entered the room password
+const OPEN_TYPES = [
+    'register',
+    'solve',
+    'ping',
+    'joinroom',
+    'joinbytoken',
+    'signup',
+    'login',
+    'logout',
+    'profileupdate',
+    'sessions',
+    'signout',
+];
+
+const canRead = (ws: WebSocket): boolean => !ROOM_KEY || unlocked.has(ws);
+
+const passwordMatches = (passphrase: unknown): boolean => {
+    if (!ROOM_KEY) {
+        return true;
+    }
+    const given = Buffer.from(String(passphrase || ''), 'hex');
+    return given.length === ROOM_KEY.length && crypto.timingSafeEqual(given, ROOM_KEY);
+};
+
+type Role = 'owner' | 'moderator' | 'member';
+
+const nickList = (value?: string): string[] =>
+    (value || '')
+        .split(',')
+        .map((nick) => nick.trim())
+        .filter((nick) => nick.length > 0);
+
+// Room staff are configured by nickname, e.g. ROOM_OWNERS=alice ROOM_MODERATORS=bob,carol
+const OWNERS = nickList(process.env.ROOM_OWNERS);
+const MODERATORS = nickList(process.env.ROOM_MODERATORS);
+
+const isStaff = (nick: String): boolean => OWNERS.includes(nick as string) || MODERATORS.includes(nick as string);
+
+const roleOf = (nick: String): Role =>
+    OWNERS.includes(nick as string) ? 'owner' : MODERATORS.includes(nick as string) ? 'moderator' : 'member';
+
+type Audience = 'everyone' | 'moderators' | 'owners';
+type Action = 'post' | 'pin' | 'topic';
+
+const audience = (value: string | undefined, fallback: Audience): Audience =>
+    value === 'everyone' || value === 'moderators' || value === 'owners' ? value : fallback;
+
+// Who may do what in the room, e.g. ROOM_POST=moderators for an announcement channel
+const POLICY: Record<Action, Audience> = {
+    post: audience(process.env.ROOM_POST, 'everyone'),
+    pin: audience(process.env.ROOM_PIN, 'moderators'),
+    topic: audience(process.env.ROOM_TOPIC, 'moderators'),
+};
+
+const RANK: Record<Role, number> = { member: 0, moderator: 1, owner: 2 };
+const REQUIRED_RANK: Record<Audience, number> = { everyone: 0, moderators: 1, owners: 2 };
+
+const may = (nick: String, action: Action): boolean => RANK[roleOf(nick)] >= REQUIRED_RANK[POLICY[action]];
+
+// Sent to each user on their own, since the flags depend on who is asking
+const permissionsPayload = (nick: String): string => {
+    const flag = (action: Action) => ({ allowed: may(nick, action), audience: POLICY[action] });
+    return JSON.stringify({
+        messageType: 'permissions',
+        data: JSON.stringify({ room: ROOM, post: flag('post'), pin: flag('pin'), topic: flag('topic') }),
+    });
+};
+
+interface Profile {
+    bio: string;
+    pronouns: string;
+    timezone: string;
+    links: string[];
+    avatar: { provider: 'identicon' } | { provider: 'gravatar'; hash: string };
+}
+
+// What members tell others about themselves, by nickname
+const profiles = new Map<String, Profile>();
+const MAX_BIO_LENGTH = 300;
+const MAX_PRONOUNS_LENGTH = 30;
+const MAX_TIMEZONE_LENGTH = 64;
+const MAX_PROFILE_LINKS = 5;
+const MAX_LINK_LENGTH = 200;
+
+// Trims a client's profile to what we're willing to store and show others
+const cleanProfile = (raw: any): Profile => {
+    const text = (value: unknown, max: number) => String(value || '').trim().slice(0, max);
+    const links = Array.isArray(raw.links) ? raw.links : [];
+    return {
+        bio: text(raw.bio, MAX_BIO_LENGTH),
+        pronouns: text(raw.pronouns, MAX_PRONOUNS_LENGTH),
+        timezone: text(raw.timezone, MAX_TIMEZONE_LENGTH),
+        links: links
+            .map((link: unknown) => text(link, MAX_LINK_LENGTH))
+            .filter((link: string) => /^https?:\/\//.test(link))
+            .slice(0, MAX_PROFILE_LINKS),
+        // Gravatars are identified by the SHA-256 of an email address, never the address itself
+        avatar:
+            raw.avatar && raw.avatar.provider === 'gravatar' && /^[0-9a-f]{64}$/.test(String(raw.avatar.hash))
+                ? { provider: 'gravatar', hash: String(raw.avatar.hash) }
+                : { provider: 'identicon' },
+    };
+};
+
+// `dataArray` keeps older clients working; `data` carries the room and everyone's role and profile
+const usersPayload = (): string =>
+    JSON.stringify({
+        messageType: 'users',
+        dataArray: users.map((u) => u.nick),
+        data: JSON.stringify({
+            room: ROOM,
+            members: users.map((u) => ({ nick: u.nick, role: roleOf(u.nick), profile: profiles.get(u.nick) })),
+        }),
+    });
+
+// Shown next to the room name; changed by whoever may change the topic
+let roomIcon: string | null = process.env.ROOM_ICON || null;
+// Enough code points for an emoji with modifiers or joiners
+const MAX_ICON_LENGTH = 8;
+
+// `locked` differs per socket, so this is always sent to one client at a time
+const roomPayload = (ws: WebSocket): string =>
+    JSON.stringify({ messageType: 'room', data: JSON.stringify({ room: ROOM, icon: roomIcon, locked: !canRead(ws) }) });
+
+type Level = 'info' | 'warning';
+
+// A banner shown above the chat until each reader dismisses it
+interface Announcement {
+    // Dismissals are remembered by this, so a new notice shows again
+    id: string;
+    text: string;
+    level: Level;
+    from: String | null;
+    time: number;
+}
+
+const MAX_ANNOUNCEMENT_LENGTH = 500;
+
+// Message of the day for everyone who connects; its id follows the text, so editing it shows it again
+const MOTD_TEXT = (process.env.MOTD || '').trim().slice(0, MAX_ANNOUNCEMENT_LENGTH);
+const motd: Announcement | null = MOTD_TEXT
+    ? {
+          id: 'motd-' + crypto.createHash('sha256').update(MOTD_TEXT).digest('hex').slice(0, 12),
+          text: MOTD_TEXT,
+          level: 'info',
+          from: null,
+          time: Date.now(),
+      }
+    : null;
+// The latest broadcast from an owner, e.g. a maintenance notice; shown to the room, not before the password
+let notice: Announcement | null = null;
+
+// An empty `text` withdraws the announcement with that id
+const announcementPayload = (announcement: Announcement): string =>
+    JSON.stringify({ messageType: 'announcement', data: JSON.stringify(announcement) });
+
+// Invite links stay valid for a week
+const INVITE_TTL_MS = 7 * 24 * 60 * 60 * 1000;
+const invites = new Map<string, { room: string; expires: number }>();
+
+const createInvite = (): string => {
+    const now = Date.now();
+    invites.forEach((invite, token) => {
+        if (invite.expires < now) {
+            invites.delete(token);
+        }
+    });
+    const token = crypto.randomBytes(12).toString('hex');
+    invites.set(token, { room: ROOM, expires: now + INVITE_TTL_MS });
+    return token;
+};
+
+interface Account {
+    name: string;
+    salt: string;
+    hash: string;
+}
+
+// Accounts reserve their name; they're kept in ACCOUNTS_FILE when set, otherwise until the server restarts
+const ACCOUNTS_FILE = process.env.ACCOUNTS_FILE || '';
+const accounts = new Map<string, Account>();
+if (ACCOUNTS_FILE && fs.existsSync(ACCOUNTS_FILE)) {
+    (JSON.parse(fs.readFileSync(ACCOUNTS_FILE, 'utf8')) as Account[]).forEach((a) => accounts.set(a.name, a));
+}
+const MIN_PASSWORD_LENGTH = 8;
+// Password guesses, for accounts and the room alike, are limited per name and per address.
+// Each attempt counts as it's made, so guesses sent in parallel are held to the limit as well
+const MAX_PASSWORD_ATTEMPTS = 5;
+const PASSWORD_ATTEMPT_WINDOW_MS = 15 * 60 * 1000;
+const passwordAttempts = new Map<string, { count: number; resetAt: number }>();
+// How long a sign-in token works; the client decides whether to keep it beyond the tab
+const SESSION_TTL_MS = 30 * 24 * 60 * 60 * 1000;
+const sessions = new Map<string, { name: string; expires: number }>();
+// The account each socket signed in as, from the token in its URL
+const signedIn = new WeakMap<WebSocket, string>();
+// Where each socket connected from, which password attempts are counted against
+const addressOf = new WeakMap<WebSocket, string>();
+
+// What a signed-in socket is listed as to the account's other sessions
+interface Connection {
+    id: string;
+    token: string;
+    device: string;
+    since: number;
+}
+const connections = new WeakMap<WebSocket, Connection>();
+
+// "Browser on OS" from a User-Agent header, enough to tell someone's devices apart
+const describeDevice = (userAgent: string): string => {
+    const browser = /Edg\//.test(userAgent)
+        ? 'Edge'
+        : /OPR\//.test(userAgent)
+        ? 'Opera'
+        : /Firefox\//.test(userAgent)
+        ? 'Firefox'
+        : /Chrome\//.test(userAgent)
+        ? 'Chrome'
+        : /Safari\//.test(userAgent)
+        ? 'Safari'
+        : 'Unknown browser';
+    const os = /Android/.test(userAgent)
+        ? 'Android'
+        : /iPhone|iPad/.test(userAgent)
+        ? 'iOS'
+        : /Windows/.test(userAgent)
+        ? 'Windows'
+        : /Mac OS X/.test(userAgent)
+        ? 'macOS'
+        : /Linux/.test(userAgent)
+        ? 'Linux'
+        : 'unknown system';
+    return `${browser} on ${os}`;
+};
+
+// Open sockets signed in to the same account as `ws`, including itself
+const sameAccount = (ws: WebSocket): WebSocket[] => {
+    const account = signedIn.get(ws);
+    return Array.from(wss.clients).filter(
+        (client) => account !== undefined && client.readyState === WebSocket.OPEN && signedIn.get(client) === account
+    );
+};
+
+// `current` marks sessions using the asking socket's token, so this goes to one socket at a time
+const sessionsPayload = (ws: WebSocket): string => {
+    const own = connections.get(ws);
+    const list = sameAccount(ws)
+        .map((client) => connections.get(client))
+        .filter((connection): connection is Connection => connection !== undefined)
+        .map((c) => ({ id: c.id, device: c.device, since: c.since, current: !!own && c.token === own.token }));
+    return JSON.stringify({ messageType: 'sessions', data: JSON.stringify({ sessions: list }) });
+};
+
+// How far each reader got in each room, so their devices agree on what's unread. Signed-in
+// readers are tracked by account, guests by name
+interface ReadMarker {
+    room: string;
+    seq?: number;
+    time: number;
+}
+const readMarkers = new Map<string, Map<string, ReadMarker>>();
+
+const readerKey = (ws: WebSocket): string | undefined => {
+    const account = signedIn.get(ws);
+    if (account) {
+        return `account:${account}`;
+    }
+    const user = users.find((u) => u.ws === ws);
+    return user ? `guest:${user.nick}` : undefined;
+};
+
+// Sequence numbers order messages exactly; times are the fallback for messages without one
+const isAfter = (a: ReadMarker, b: ReadMarker): boolean =>
+    a.seq !== undefined && b.seq !== undefined ? a.seq > b.seq : a.time > b.time;
+
+const readMarkerPayload = (marker: ReadMarker): string =>
+    JSON.stringify({ messageType: 'readmarker', data: JSON.stringify(marker) });
+
+// Unsent text per reader and room, for clients that sync drafts between devices
+interface Draft {
+    room: string;
+    text: string;
+    time: number;
+}
+const drafts = new Map<string, Map<string, Draft>>();
+// Only accounts keep drafts: a guest's nickname is anyone's once they leave
+const draftKey = (ws: WebSocket): string | undefined => {
+    const account = signedIn.get(ws);
+    return account ? `account:${account}` : undefined;
+};
+const MAX_DRAFT_LENGTH = 4000;
+
+const draftPayload = (draft: Draft): string => JSON.stringify({ messageType: 'draft', data: JSON.stringify(draft) });
+
+// Sockets in the room's voice channel and whether they're muted; the audio itself goes
+// directly between browsers, the server only passes their connection setup along
+const voiceMembers = new Map<WebSocket, boolean>();
+
+const voicePayload = (): string =>
+    JSON.stringify({
+        messageType: 'voice',
+        data: JSON.stringify({
+            room: ROOM,
+            participants: users
+                .filter((u) => voiceMembers.has(u.ws))
+                .map((u) => ({ name: u.nick, muted: voiceMembers.get(u.ws) })),
+        }),
+    });
+
+const saveAccounts = () => {
+    if (ACCOUNTS_FILE) {
+        fs.writeFile(ACCOUNTS_FILE, JSON.stringify([...accounts.values()]), (e) => e && console.log('Error saving accounts', e));
+    }
+};
+
+// Counts an attempt against each of `keys`, answering whether all of them are still within the limit
+const allowAttempt = (keys: string[]): boolean => {
+    const now = Date.now();
+    passwordAttempts.forEach((attempts, key) => attempts.resetAt <= now && passwordAttempts.delete(key));
+    return keys
+        .map((key) => {
+            const attempts = passwordAttempts.get(key) || { count: 0, resetAt: now + PASSWORD_ATTEMPT_WINDOW_MS };
+            attempts.count += 1;
+            passwordAttempts.set(key, attempts);
+            return attempts.count;
+        })
+        .every((count) => count <= MAX_PASSWORD_ATTEMPTS);
+};
+
+// Hashed off the event loop, so a flood of sign-ins doesn't stall everyone else's messages
+const hashPassword = (password: string, salt: string): Promise<string> =>
+    new Promise((resolve, reject) =>
+        crypto.scrypt(password, salt, 64, (e, key) => (e ? reject(e) : resolve(key.toString('hex'))))
+    );
+
+// Names without an account are checked against this, so they take as long to refuse as a wrong password
+const NO_ACCOUNT: Account = { name: '', salt: crypto.randomBytes(16).toString('hex'), hash: crypto.randomBytes(64).toString('hex') };
+
+const checkPassword = (account: Account | undefined, password: string): Promise<boolean> => {
+    const { salt, hash } = account || NO_ACCOUNT;
+    return hashPassword(password, salt).then(
+        (attempt) => crypto.timingSafeEqual(Buffer.from(attempt, 'hex'), Buffer.from(hash, 'hex')) && !!account
+    );
+};
+
+const sessionPayload = (name: string): string => {
+    const now = Date.now();
+    sessions.forEach((session, token) => {
+        if (session.expires < now) {
+            sessions.delete(token);
+        }
+    });
+    const token = crypto.randomBytes(24).toString('hex');
+    sessions.set(token, { name, expires: now + SESSION_TTL_MS });
+    return JSON.stringify({ messageType: 'session', data: JSON.stringify({ name, token }) });
+};
+
+const sessionName = (token: string | null): string | null => {
+    const session = token ? sessions.get(token) : undefined;
+    return session && session.expires >= Date.now() ? session.name : null;
+};
+
+const MAX_NICK_LENGTH = 20;
+
+// Returns why a nickname can't be registered, or null if it's acceptable.
+// An account's name is only available to `account`, the one the socket signed in as.
+const validateNick = (nick: unknown, account?: string): string | null => {
+    if (typeof nick !== 'string' || nick.trim().length === 0) {
+        return 'Username is required';
+    }
+    if ([...nick].length > MAX_NICK_LENGTH) {
+        return `Username can be at most ${MAX_NICK_LENGTH} characters`;
+    }
+    if (!/^[A-Za-z0-9_.-]+$/.test(nick)) {
+        return "Username may only contain letters, digits, '_', '-' or '.'";
+    }
+    if (account !== nick && accounts.has(nick)) {
+        return `The name "${nick}" belongs to an account, sign in to use it`;
+    }
+    // Roles go by nickname, so staff names need the account too
+    if (account !== nick && isStaff(nick)) {
+        return `The name "${nick}" is reserved for room staff, sign in to use it`;
+    }
+    if (users.some((u) => u.nick === nick)) {
+        return `The name "${nick}" is already taken`;
+    }
+    return null;
+};
+
+type CaptchaProvider = 'hcaptcha' | 'recaptcha' | 'turnstile';
+
+// Where each provider confirms that a challenge was really solved
+const CAPTCHA_VERIFY_URLS: Record<CaptchaProvider, string> = {
+    hcaptcha: 'https://api.hcaptcha.com/siteverify',
+    recaptcha: 'https://www.google.com/recaptcha/api/siteverify',
+    turnstile: 'https://challenges.cloudflare.com/turnstile/v0/siteverify',
+};
+
+interface Captcha {
+    provider: CaptchaProvider;
+    siteKey: string;
+    secret: string;
+}
+
+// New users must solve a CAPTCHA before joining when CAPTCHA_PROVIDER, CAPTCHA_SITE_KEY and CAPTCHA_SECRET are set
+const captchaProvider = process.env.CAPTCHA_PROVIDER || '';
+const CAPTCHA: Captcha | null =
+    captchaProvider in CAPTCHA_VERIFY_URLS && process.env.CAPTCHA_SITE_KEY && process.env.CAPTCHA_SECRET
+        ? {
+              provider: captchaProvider as CaptchaProvider,
+              siteKey: process.env.CAPTCHA_SITE_KEY,
+              secret: process.env.CAPTCHA_SECRET,
+          }
+        : null;
+// A solved challenge also covers reconnecting for this long. Passes are kept by the reclaim token
+// of the client that solved it, so whoever takes the name next doesn't get one with it
+const CAPTCHA_PASS_TTL_MS = 60 * 60 * 1000;
+const captchaPasses = new Map<string, number>();
+// Verification of the token a socket sent ahead of registering
+const captchaChecks = new WeakMap<WebSocket, Promise<boolean>>();
+
+const challengePayload = (captcha: Captcha, error: string | null): string =>
+    JSON.stringify({
+        messageType: 'challenge',
+        data: JSON.stringify({ provider: captcha.provider, siteKey: captcha.siteKey, error }),
+    });
+
+const verifyCaptcha = (captcha: Captcha, token: string): Promise<boolean> =>
+    new Promise((resolve) => {
+        const body = new URLSearchParams({ secret: captcha.secret, response: token }).toString();
+        const headers = { 'Content-Type': 'application/x-www-form-urlencoded', 'Content-Length': Buffer.byteLength(body) };
+        const req = https.request(CAPTCHA_VERIFY_URLS[captcha.provider], { method: 'POST', timeout: 5000, headers }, (res) => {
+            let text = '';
+            res.setEncoding('utf8');
+            res.on('data', (chunk: string) => (text += chunk));
+            res.on('end', () => {
+                try {
+                    resolve(JSON.parse(text).success === true);
+                } catch (e) {
+                    resolve(false);
+                }
+            });
+        });
+        req.on('timeout', () => req.destroy());
+        req.on('error', () => resolve(false));
+        req.end(body);
+    });
+
+// When the pass held under `reclaim` runs out, if there is one. It's taken off the old token
+// because registering hands out a new one, which `register` moves it to
+const takeCaptchaPass = (reclaim: String | undefined): number | undefined => {
+    const now = Date.now();
+    captchaPasses.forEach((expires, token) => {
+        if (expires < now) {
+            captchaPasses.delete(token);
+        }
+    });
+    if (!reclaim) {
+        return undefined;
+    }
+    const expires = captchaPasses.get(reclaim as string);
+    captchaPasses.delete(reclaim as string);
+    return expires;
+};
+
+const register = (ws: WebSocket, nick: String, captchaPass?: number) => {
+    const reclaim = crypto.randomBytes(16).toString('hex');
+    if (captchaPass) {
+        captchaPasses.set(reclaim, captchaPass);
+    }
+    users.push({ ws, nick, isAlive: true, reclaim });
+    ws.send(JSON.stringify({ messageType: 'registered', data: JSON.stringify({ nick, token: reclaim }) }));
+    broadcast(usersPayload());
+    ws.send(permissionsPayload(nick));
+    ws.send(roomPayload(ws));
+    if (motd) {
+        ws.send(announcementPayload(motd));
+    }
+    if (notice && canRead(ws)) {
+        ws.send(announcementPayload(notice));
+    }
+    const key = readerKey(ws);
+    const markers = key ? readMarkers.get(key) : undefined;
+    if (markers) {
+        markers.forEach((marker) => ws.send(readMarkerPayload(marker)));
+    }
+    const roomDrafts = drafts.get(draftKey(ws) || '');
+    if (roomDrafts) {
+        roomDrafts.forEach((draft) => ws.send(draftPayload(draft)));
+    }
+    if (voiceMembers.size > 0 && canRead(ws)) {
+        ws.send(voicePayload());
+    }
+};
+
+// Link titles for the clients' "shared links" list, cached so a URL asked for again is fetched once
+const titleCache = new Map<string, Promise<string | null>>();
+const TITLE_CACHE_LIMIT = 500;
+const UNFURL_TIMEOUT_MS = 5000;
+// Titles live in <head>, so there's no need to read further than this
+const UNFURL_MAX_BYTES = 64 * 1024;
+
+// Don't let the server be used to probe its own network. Addresses are checked once a name
+// has resolved, so neither names pointing inwards nor redirects to them get through
+const PRIVATE_ADDRESSES = new net.BlockList();
+[
+    ['0.0.0.0', 8],
+    ['10.0.0.0', 8],
+    ['100.64.0.0', 10],
+    ['127.0.0.0', 8],
+    ['169.254.0.0', 16],
+    ['172.16.0.0', 12],
+    ['192.0.0.0', 24],
+    ['192.168.0.0', 16],
+    ['198.18.0.0', 15],
+    // Multicast, reserved and broadcast
+    ['224.0.0.0', 3],
+].forEach(([address, prefix]) => PRIVATE_ADDRESSES.addSubnet(address as string, prefix as number, 'ipv4'));
+[
+    ['::', 128],
+    ['::1', 128],
+    // IPv4-mapped, which would otherwise reach any of the above
+    ['::ffff:0:0', 96],
+    ['fc00::', 7],
+    ['fe80::', 10],
+    ['ff00::', 8],
+].forEach(([address, prefix]) => PRIVATE_ADDRESSES.addSubnet(address as string, prefix as number, 'ipv6'));
+
+const isPrivateAddress = (address: string, family: number): boolean =>
+    PRIVATE_ADDRESSES.check(address, family === 6 ? 'ipv6' : 'ipv4');
+
+// `dns.lookup`, failing for names that resolve to a private address
+const publicLookup = (
+    hostname: string,
+    options: dns.LookupOptions,
+    callback: (err: NodeJS.ErrnoException | null, address: string | dns.LookupAddress[], family?: number) => void
+): void =>
+    dns.lookup(hostname, options, (err, address, family) => {
+        const addresses = Array.isArray(address) ? address : [{ address, family }];
+        if (!err && addresses.some((a) => isPrivateAddress(a.address, a.family))) {
+            return callback(new Error(`${hostname} resolves to a private address`), address, family);
+        }
+        callback(err, address, family);
+    });
+
+const decodeEntities = (text: string): string =>
+    text
+        .replace(/&lt;/g, '<')
+        .replace(/&gt;/g, '>')
+        .replace(/&quot;/g, '"')
+        .replace(/&#0*39;|&apos;/g, "'")
+        .replace(/&#(\d+);/g, (_, code) => String.fromCodePoint(Number(code)))
+        .replace(/&amp;/g, '&');
+
+const fetchTitle = (url: string, redirects = 3): Promise<string | null> =>
+    new Promise((resolve) => {
+        let parsed: URL;
+        try {
+            parsed = new URL(url);
+        } catch (e) {
+            return resolve(null);
+        }
+        const client = parsed.protocol === 'https:' ? https : parsed.protocol === 'http:' ? http : null;
+        // Addresses written into the URL are connected to without a lookup
+        const host = parsed.hostname.replace(/^\[|\]$/g, '');
+        if (!client || (net.isIP(host) && isPrivateAddress(host, net.isIP(host)))) {
+            return resolve(null);
+        }
+        const options = { timeout: UNFURL_TIMEOUT_MS, lookup: publicLookup, headers: { 'User-Agent': 'YewChat link preview' } };
+        const req = client.get(parsed, options, (res) => {
+            const status = res.statusCode || 0;
+            if (status >= 300 && status < 400 && res.headers.location && redirects > 0) {
+                res.resume();
+                return resolve(fetchTitle(new URL(res.headers.location, parsed).toString(), redirects - 1));
+            }
+            if (status >= 400 || !(res.headers['content-type'] || '').includes('text/html')) {
+                res.resume();
+                return resolve(null);
+            }
+            let body = '';
+            const finish = () => {
+                const match = /<title[^>]*>([^<]*)<\/title>/i.exec(body);
+                resolve(match ? decodeEntities(match[1]).replace(/\s+/g, ' ').trim() || null : null);
+            };
+            res.setEncoding('utf8');
+            res.on('data', (chunk: string) => {
+                body += chunk;
+                if (/<\/title>/i.test(body) || body.length > UNFURL_MAX_BYTES) {
+                    res.destroy();
+                    finish();
+                }
+            });
+            res.on('end', finish);
+        });
+        req.on('timeout', () => req.destroy());
+        req.on('error', () => resolve(null));
+    });
+
+const unfurl = (url: string): Promise<string | null> => {
+    const title = titleCache.get(url) || fetchTitle(url);
+    // Maps iterate in insertion order, so re-inserting keeps the oldest entry first to evict
+    titleCache.delete(url);
+    titleCache.set(url, title);
+    if (titleCache.size > TITLE_CACHE_LIMIT) {
+        titleCache.delete(titleCache.keys().next().value as string);
+    }
+    return title;
+};
+
+// Clients behind proxies that block websockets fall back to HTTP: POST /poll opens a
+// session and POST /poll/<id> sends a frame. Frames come back as Server-Sent Events from
+// GET /poll/<id>/events, or where proxies buffer those, from long polls on GET /poll/<id>
+const POLL_WAIT_MS = 25000;
+// Comment lines sent down idle event streams so proxies don't time them out
+const STREAM_KEEPALIVE_MS = 15000;
+// A polling client that stops coming back is treated like a closed socket
+const POLL_IDLE_MS = 60000;
+const POLL_MAX_BODY_BYTES = 10 * 1024 * 1024;
+const pollSessions = new Map<string, PollSocket>();
+
+// Stands in for a websocket, so the rest of the server can't tell polling clients apart
+class PollSocket extends EventEmitter {
+    readyState: number = WebSocket.OPEN;
+    private queue: string[] = [];
+    private waiting: http.ServerResponse | null = null;
+    private streaming: http.ServerResponse | null = null;
+    private keepalive: NodeJS.Timeout | null = null;
+    private waitTimer: NodeJS.Timeout | null = null;
+    private idleTimer: NodeJS.Timeout | null = null;
+
+    constructor(readonly id: string) {
+        super();
+        this.touch();
+    }
+
+    send(data: any) {
+        if (this.readyState !== WebSocket.OPEN) {
+            return;
+        }
+        if (this.streaming) {
+            this.streaming.write(eventPayload(data.toString()));
+            return;
+        }
+        this.queue.push(data.toString());
+        this.flush();
+    }
+
+    // Sends frames down a Server-Sent Events stream for as long as the client keeps it open
+    stream(res: http.ServerResponse) {
+        this.endStream();
+        res.writeHead(200, {
+            'Content-Type': 'text/event-stream',
+            'Cache-Control': 'no-store',
+            'Access-Control-Allow-Origin': '*',
+            // Asks nginx not to buffer; the client falls back to polling if something else does
+            'X-Accel-Buffering': 'no',
+        });
+        res.write('event: ready\ndata:\n\n');
+        this.queue.forEach((frame) => res.write(eventPayload(frame)));
+        this.queue = [];
+        this.streaming = res;
+        this.keepalive = setInterval(() => {
+            res.write(': keepalive\n\n');
+            this.touch();
+        }, STREAM_KEEPALIVE_MS);
+        res.on('close', () => {
+            if (this.streaming === res) {
+                this.endStream();
+            }
+        });
+    }
+
+    // Holds the request open until there's something to deliver, or answers empty after a while
+    poll(res: http.ServerResponse) {
+        this.touch();
+        this.release();
+        this.waiting = res;
+        this.waitTimer = setTimeout(() => this.release(), POLL_WAIT_MS);
+        res.on('close', () => {
+            if (this.waiting === res) {
+                this.waiting = null;
+            }
+        });
+        this.flush();
+    }
+
+    // Any request from the client shows it's still there
+    touch() {
+        if (this.idleTimer) {
+            clearTimeout(this.idleTimer);
+        }
+        this.idleTimer = setTimeout(() => this.close(), POLL_IDLE_MS);
+    }
+
+    close() {
+        if (this.readyState !== WebSocket.OPEN) {
+            return;
+        }
+        this.readyState = WebSocket.CLOSED;
+        this.release();
+        this.endStream();
+        if (this.idleTimer) {
+            clearTimeout(this.idleTimer);
+        }
+        pollSessions.delete(this.id);
+        wss.clients.delete(this as unknown as WebSocket);
+        this.emit('close');
+    }
+
+    private endStream() {
+        if (this.keepalive) {
+            clearInterval(this.keepalive);
+            this.keepalive = null;
+        }
+        const res = this.streaming;
+        this.streaming = null;
+        res?.end();
+    }
+
+    private flush() {
+        if (this.queue.length > 0) {
+            this.release();
+        }
+    }
+
+    // Answers the waiting poll with whatever is queued, possibly nothing
+    private release() {
+        if (this.waitTimer) {
+            clearTimeout(this.waitTimer);
+            this.waitTimer = null;
+        }
+        const res = this.waiting;
+        if (!res) {
+            return;
+        }
+        this.waiting = null;
+        respond(res, 200, JSON.stringify(this.queue));
+        this.queue = [];
+    }
+}
+
+// Frames are single-line JSON, but a stray newline would otherwise split the event
+const eventPayload = (frame: string): string => frame.split('\n').map((line) => `data: ${line}`).join('\n') + '\n\n';
+
+// Files sent from the composer, kept in memory and posted to the room as links
+const UPLOAD_MAX_BYTES = process.env.UPLOAD_MAX_BYTES ? parseInt(process.env.UPLOAD_MAX_BYTES) : 10 * 1024 * 1024;
+// MIME types the composer may upload, `type/*` standing for a whole kind
+const UPLOAD_TYPES = (process.env.UPLOAD_TYPES || 'image/*,video/*,audio/*,application/pdf,text/plain')
+    .split(',')
+    .map((t) => t.trim().toLowerCase())
+    .filter((t) => t);
+// Oldest uploads are forgotten once they take up more than this altogether
+const UPLOAD_TOTAL_BYTES = process.env.UPLOAD_TOTAL_BYTES ? parseInt(process.env.UPLOAD_TOTAL_BYTES) : 200 * 1024 * 1024;
+const uploads = new Map<string, { type: string; data: Buffer }>();
+let uploadedBytes = 0;
+
+// Uploads are sent with the token handed out on registering, so only someone in the room,
+// and past its password, can store files here
+const uploader = (req: http.IncomingMessage): User | undefined => {
+    const token = (req.headers.authorization || '').replace(/^Bearer /, '');
+    return token ? users.find((u) => u.reclaim === token && canRead(u.ws)) : undefined;
+};
+
+const uploadTypeAllowed = (type: string): boolean => {
+    const mime = type.split(';')[0].trim().toLowerCase();
+    return UPLOAD_TYPES.some((allowed) =>
+        allowed === '*' || allowed === '*/*' || (allowed.endsWith('/*') ? mime.startsWith(allowed.slice(0, -1)) : mime === allowed));
+};
+
+// Keeps the extension, which is how clients tell images and videos from other files
+const uploadName = (name: string): string => {
+    const ext = (name.match(/\.[a-z0-9]{1,8}$/i) || [''])[0].toLowerCase();
+    return crypto.randomBytes(16).toString('hex') + ext;
+};
+
+const handleUpload = (req: http.IncomingMessage, res: http.ServerResponse, url: URL, file?: string) => {
+    if (req.method === 'GET' && file) {
+        const stored = uploads.get(file);
+        if (!stored) {
+            return respond(res, 404);
+        }
+        res.writeHead(200, {
+            'Content-Type': stored.type,
+            'Content-Length': stored.data.length,
+            'Cache-Control': 'public, max-age=31536000, immutable',
+            'Access-Control-Allow-Origin': '*',
+            'X-Content-Type-Options': 'nosniff',
+            // Whatever was uploaded, it can't run script on this origin
+            'Content-Security-Policy': 'sandbox',
+        });
+        return res.end(stored.data);
+    }
+    if (req.method === 'GET' && !file) {
+        // Clients check files against these before uploading them
+        return respond(res, 200, JSON.stringify({ maxBytes: UPLOAD_MAX_BYTES, types: UPLOAD_TYPES }));
+    }
+    if (req.method !== 'POST' || file) {
+        return respond(res, 405);
+    }
+    if (!uploader(req)) {
+        return respond(res, 401);
+    }
+    if (!uploadTypeAllowed(req.headers['content-type'] || '')) {
+        return respond(res, 415);
+    }
+    const declared = Number(req.headers['content-length'] || 0);
+    if (declared > UPLOAD_MAX_BYTES) {
+        return respond(res, 413);
+    }
+    const chunks: Buffer[] = [];
+    let size = 0;
+    req.on('data', (chunk: Buffer) => {
+        size += chunk.length;
+        if (size > UPLOAD_MAX_BYTES) {
+            respond(res, 413);
+            req.destroy();
+            return;
+        }
+        chunks.push(chunk);
+    });
+    req.on('end', () => {
+        if (size > UPLOAD_MAX_BYTES) {
+            return;
+        }
+        const name = uploadName(url.searchParams.get('name') || '');
+        uploads.set(name, { type: req.headers['content-type'] || 'application/octet-stream', data: Buffer.concat(chunks) });
+        uploadedBytes += size;
+        while (uploadedBytes > UPLOAD_TOTAL_BYTES) {
+            const oldest = uploads.keys().next().value as string;
+            uploadedBytes -= (uploads.get(oldest) as { data: Buffer }).data.length;
+            uploads.delete(oldest);
+        }
+        respond(res, 201, JSON.stringify({ url: `/uploads/${name}` }));
+    });
+};
+
+const respond = (res: http.ServerResponse, status: number, body?: string) => {
+    res.writeHead(status, {
+        'Content-Type': 'application/json',
+        'Cache-Control': 'no-store',
+        'Access-Control-Allow-Origin': '*',
+        'Access-Control-Allow-Methods': 'GET, POST, DELETE',
+        'Access-Control-Allow-Headers': 'Content-Type, Authorization',
+    });
+    res.end(body);
+};
+
+// For self-hosters checking what they're running; read from package.json next to src/ or dist/
+const VERSION: string = (() => {
+    try {
+        return JSON.parse(fs.readFileSync(`${__dirname}/../package.json`, 'utf8')).version;
+    } catch {
+        return 'unknown';
+    }
+})();
+
+const serverInfoPayload = (): string =>
+    JSON.stringify({
+        messageType: 'serverinfo',
+        data: JSON.stringify({
+            software: 'SimpleWebsocketServer',
+            version: VERSION,
+            uptime: Math.floor(process.uptime()),
+            users: users.length,
+            rooms: 1,
+            limits: { maxNickLength: MAX_NICK_LENGTH, historySize: HISTORY_LIMIT, uploadMaxBytes: UPLOAD_MAX_BYTES },
+        }),
+    });
+
+const handleHttp = (req: http.IncomingMessage, res: http.ServerResponse) => {
+    const url = new URL(req.url || '/', 'http://localhost');
+    const [, prefix, id, stream] = url.pathname.split('/');
+    if (prefix === 'uploads') {
+        return req.method === 'OPTIONS' ? respond(res, 204) : handleUpload(req, res, url, id);
+    }
+    if (prefix !== 'poll') {
+        return respond(res, 404);
+    }
+    if (req.method === 'OPTIONS') {
+        return respond(res, 204);
+    }
+    if (!id) {
+        if (req.method !== 'POST') {
+            return respond(res, 405);
+        }
+        const socket = new PollSocket(crypto.randomBytes(16).toString('hex'));
+        pollSessions.set(socket.id, socket);
+        wss.clients.add(socket as unknown as WebSocket);
+        wss.emit('connection', socket, req);
+        return respond(res, 200, JSON.stringify({ id: socket.id }));
+    }
+    const socket = pollSessions.get(id);
+    if (!socket) {
+        // Expired or from before a restart; the client opens a new session
+        return respond(res, 404);
+    }
+    switch (req.method) {
+        case 'GET':
+            return stream === 'events' ? socket.stream(res) : socket.poll(res);
+        case 'POST': {
+            socket.touch();
+            const chunks: Buffer[] = [];
+            let size = 0;
+            req.on('data', (chunk: Buffer) => {
+                size += chunk.length;
+                if (size > POLL_MAX_BODY_BYTES) {
+                    respond(res, 413);
+                    req.destroy();
+                    return;
+                }
+                chunks.push(chunk);
+            });
+            req.on('end', () => {
+                socket.emit('message', Buffer.concat(chunks));
+                respond(res, 204);
+            });
+            return;
+        }
+        case 'DELETE':
+            socket.close();
+            return respond(res, 204);
+        default:
+            return respond(res, 405);
+    }
+};
 
 console.log(`Listening on port ${PORT}`);
-const wss = new WebSocketServer({ port: PORT });
+const server = http.createServer(handleHttp);
+const wss = new WebSocketServer({ server });
+server.listen(PORT);
 
-wss.on('connection', (ws: WebSocket) => {
+wss.on('connection', (ws: WebSocket, req: http.IncomingMessage) => {
     console.log('ws connected');
+    // Signed-in clients connect with their session token, e.g. ws://host:7000/?token=...
+    const token = new URL(req.url || '/', 'http://localhost').searchParams.get('token');
+    const account = sessionName(token);
+    addressOf.set(ws, req.socket.remoteAddress || '');
+    if (account) {
+        signedIn.set(ws, account);
+        connections.set(ws, {
+            id: crypto.randomBytes(8).toString('hex'),
+            token: token as string,
+            device: describeDevice(req.headers['user-agent'] || ''),
+            since: Date.now(),
+        });
+    }
 
     ws.on('message', (data) => {
         const raw_data = data.toString();
         try {
             const parsed_data: Message = JSON.parse(raw_data);
+            if (!canRead(ws) && !OPEN_TYPES.includes(parsed_data.messageType as string)) {
+                return;
+            }
             switch (parsed_data.messageType) {
-                case 'register':
-                    users.push({ ws, nick: parsed_data.data, isAlive: true });
-                    broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+                case 'register': {
+                    // Forget sockets that already closed so a reconnecting client can reclaim its name
+                    users = users.filter((u) => u.ws.readyState === WebSocket.OPEN);
+                    // A socket can look open long after the client lost it; with the name's token the
+                    // client takes the name over instead of being told it's taken
+                    const stale = users.find(
+                        (u) => u.ws !== ws && u.nick === parsed_data.data && !!parsed_data.reclaim && u.reclaim === parsed_data.reclaim
+                    );
+                    if (stale) {
+                        users = users.filter((u) => u !== stale);
+                        stale.ws.close();
+                    }
+                    const error = validateNick(parsed_data.data, signedIn.get(ws));
+                    if (error) {
+                        ws.send(JSON.stringify({ messageType: 'registererror', data: error }));
+                        break;
+                    }
+                    const nick = parsed_data.data;
+                    const captchaPass = CAPTCHA ? takeCaptchaPass(parsed_data.reclaim) : undefined;
+                    if (!CAPTCHA || captchaPass) {
+                        register(ws, nick, captchaPass);
+                        break;
+                    }
+                    const check = captchaChecks.get(ws);
+                    captchaChecks.delete(ws);
+                    if (!check) {
+                        ws.send(challengePayload(CAPTCHA, null));
+                        break;
+                    }
+                    const captcha = CAPTCHA;
+                    check.then((passed) => {
+                        // Someone may have taken the name while the token was being checked
+                        const error = validateNick(nick, signedIn.get(ws));
+                        if (!passed) {
+                            ws.send(challengePayload(captcha, 'The CAPTCHA could not be verified, please try again'));
+                        } else if (error) {
+                            ws.send(JSON.stringify({ messageType: 'registererror', data: error }));
+                        } else {
+                            register(ws, nick, Date.now() + CAPTCHA_PASS_TTL_MS);
+                        }
+                    });
                     break;
+                }
+                case 'solve':
+                    // The solved CAPTCHA's token, sent just before `register`
+                    if (CAPTCHA) {
+                        captchaChecks.set(ws, verifyCaptcha(CAPTCHA, String(parsed_data.data)));
+                    }
+                    break;
+                case 'rename': {
+                    const user = users.find((u) => u.ws === ws);
+                    if (!user) {
+                        break;
+                    }
+                    const { to } = JSON.parse(parsed_data.data as string);
+                    const error = validateNick(to, signedIn.get(ws));
+                    if (error) {
+                        ws.send(JSON.stringify({ messageType: 'renameerror', data: error }));
+                        break;
+                    }
+                    const from = user.nick;
+                    user.nick = to;
+                    const profile = profiles.get(from);
+                    profiles.delete(from);
+                    if (profile) {
+                        profiles.set(to, profile);
+                    }
+                    broadcast(JSON.stringify({ messageType: 'rename', data: JSON.stringify({ from, to }) }));
+                    broadcast(usersPayload());
+                    // Roles are assigned by nickname, so the new name may come with different rights
+                    ws.send(permissionsPayload(to));
+                    if (voiceMembers.has(ws)) {
+                        broadcast(voicePayload());
+                    }
+                    break;
+                }
                 case 'message':
                     const sender = users.find((u) => u.ws === ws);
-                    if (sender) {
+                    if (sender && may(sender.nick, 'post')) {
+                        const time = Date.now();
+                        const seq = nextSeq++;
+                        const json = JSON.stringify({
+                            id: String(nextMessageId++),
+                            seq,
+                            from: sender.nick,
+                            message: parsed_data.data,
+                            replyTo: parsed_data.replyTo,
+                            clientId: parsed_data.clientId,
+                            media: parsed_data.media,
+                            location: parsed_data.location,
+                            time,
+                        });
+                        history.push({ time, seq, json });
+                        if (history.length > HISTORY_LIMIT) {
+                            history.shift();
+                        }
+                        broadcast(JSON.stringify({ messageType: 'message', data: json }));
+                    }
+                    break;
+                case 'catchup': {
+                    const { after } = JSON.parse(parsed_data.data as string);
+                    ws.send(
+                        JSON.stringify({
+                            messageType: 'catchup',
+                            dataArray: history.filter((m) => m.seq > after).map((m) => m.json),
+                        })
+                    );
+                    break;
+                }
+                case 'resync': {
+                    const { from, to } = JSON.parse(parsed_data.data as string);
+                    ws.send(
+                        JSON.stringify({
+                            messageType: 'resync',
+                            dataArray: history.filter((m) => m.seq >= from && m.seq <= to).map((m) => m.json),
+                        })
+                    );
+                    break;
+                }
+                case 'history': {
+                    const { before, limit } = JSON.parse(parsed_data.data as string);
+                    const older = history.filter((m) => before == null || m.time < before);
+                    ws.send(
+                        JSON.stringify({
+                            messageType: 'history',
+                            dataArray: older.slice(-limit).map((m) => m.json),
+                        })
+                    );
+                    break;
+                }
+                case 'roomicon': {
+                    const actor = users.find((u) => u.ws === ws);
+                    const icon = String(parsed_data.data || '').trim();
+                    if (!actor || !may(actor.nick, 'topic') || [...icon].length > MAX_ICON_LENGTH) {
+                        break;
+                    }
+                    roomIcon = icon.length > 0 ? icon : null;
+                    wss.clients.forEach((client) => {
+                        if (client.readyState === WebSocket.OPEN) {
+                            client.send(roomPayload(client));
+                        }
+                    });
+                    break;
+                }
+                case 'createinvite': {
+                    if (!users.some((u) => u.ws === ws)) {
+                        break;
+                    }
+                    const token = createInvite();
+                    ws.send(JSON.stringify({ messageType: 'invite', data: JSON.stringify({ room: ROOM, token }) }));
+                    break;
+                }
+                case 'joinbytoken': {
+                    const invite = invites.get(parsed_data.data as string);
+                    const reply =
+                        invite && invite.expires >= Date.now()
+                            ? { room: invite.room }
+                            : { error: 'This invite link is invalid or has expired' };
+                    ws.send(JSON.stringify({ messageType: 'joined', data: JSON.stringify(reply) }));
+                    break;
+                }
+                case 'unfurl': {
+                    if (!users.some((u) => u.ws === ws)) {
+                        break;
+                    }
+                    const { url } = JSON.parse(parsed_data.data as string);
+                    unfurl(String(url)).then((title) =>
+                        ws.send(JSON.stringify({ messageType: 'unfurl', data: JSON.stringify({ url, title }) }))
+                    );
+                    break;
+                }
+                case 'joinroom': {
+                    const { passphrase } = JSON.parse(parsed_data.data as string);
+                    const joined = (reply: { error?: string }) =>
+                        ws.send(JSON.stringify({ messageType: 'joinedroom', data: JSON.stringify({ room: ROOM, ...reply }) }));
+                    const attemptKey = `join@${addressOf.get(ws)}`;
+                    if (!allowAttempt([attemptKey])) {
+                        joined({ error: 'Too many attempts, try again later' });
+                        break;
+                    }
+                    if (!passwordMatches(passphrase)) {
+                        joined({ error: 'Wrong password' });
+                        break;
+                    }
+                    // Reconnecting clients enter the password again, which mustn't use up the attempts
+                    passwordAttempts.delete(attemptKey);
+                    unlocked.add(ws);
+                    joined({});
+                    // Everything that was held back while the room was locked
+                    ws.send(usersPayload());
+                    ws.send(roomPayload(ws));
+                    if (notice) {
+                        ws.send(announcementPayload(notice));
+                    }
+                    break;
+                }
+                case 'announce': {
+                    // Owners only; `{ text, level }`, where empty text takes the current one down
+                    const actor = users.find((u) => u.ws === ws);
+                    if (!actor || roleOf(actor.nick) !== 'owner') {
+                        break;
+                    }
+                    const { text, level } = JSON.parse(parsed_data.data as string);
+                    const body = String(text || '').trim().slice(0, MAX_ANNOUNCEMENT_LENGTH);
+                    if (!body) {
+                        if (notice) {
+                            broadcast(announcementPayload({ ...notice, text: '' }));
+                            notice = null;
+                        }
+                        break;
+                    }
+                    notice = {
+                        id: crypto.randomBytes(8).toString('hex'),
+                        text: body,
+                        level: level === 'warning' ? 'warning' : 'info',
+                        from: actor.nick,
+                        time: Date.now(),
+                    };
+                    broadcast(announcementPayload(notice));
+                    break;
+                }
+                case 'signup': {
+                    const { name, password } = JSON.parse(parsed_data.data as string);
+                    const error = accounts.has(name)
+                        ? `The name "${name}" is already registered`
+                        : validateNick(name, name) ||
+                          (String(password || '').length < MIN_PASSWORD_LENGTH
+                              ? `Passwords need at least ${MIN_PASSWORD_LENGTH} characters`
+                              : null);
+                    if (error) {
+                        ws.send(JSON.stringify({ messageType: 'autherror', data: error }));
+                        break;
+                    }
+                    const salt = crypto.randomBytes(16).toString('hex');
+                    hashPassword(String(password), salt).then((hash) => {
+                        // Someone else may have taken the name while this was hashed
+                        if (accounts.has(name)) {
+                            ws.send(JSON.stringify({ messageType: 'autherror', data: `The name "${name}" is already registered` }));
+                            return;
+                        }
+                        accounts.set(name, { name, salt, hash });
+                        saveAccounts();
+                        ws.send(sessionPayload(name));
+                    });
+                    break;
+                }
+                case 'login': {
+                    const { name, password } = JSON.parse(parsed_data.data as string);
+                    const refuse = (reason: string) => ws.send(JSON.stringify({ messageType: 'autherror', data: reason }));
+                    const nameKey = `login:${String(name)}`;
+                    if (!allowAttempt([nameKey, `login@${addressOf.get(ws)}`])) {
+                        refuse('Too many attempts, try again later');
+                        break;
+                    }
+                    const account = accounts.get(String(name));
+                    checkPassword(account, String(password || '')).then((matches) => {
+                        if (!matches || !account) {
+                            refuse('Wrong username or password');
+                            return;
+                        }
+                        passwordAttempts.delete(nameKey);
+                        ws.send(sessionPayload(account.name));
+                    });
+                    break;
+                }
+                case 'logout':
+                    sessions.delete(String(parsed_data.data));
+                    break;
+                case 'sessions':
+                    if (signedIn.has(ws)) {
+                        ws.send(sessionsPayload(ws));
+                    }
+                    break;
+                case 'signout': {
+                    // Ends another sign-in of the same account: its token stops working and its sockets are told why
+                    const own = connections.get(ws);
+                    const target = sameAccount(ws)
+                        .map((client) => connections.get(client))
+                        .find((connection) => connection !== undefined && connection.id === parsed_data.data);
+                    if (!own || !target || target.token === own.token) {
+                        break;
+                    }
+                    sessions.delete(target.token);
+                    sameAccount(ws).forEach((client) => {
+                        const connection = connections.get(client);
+                        if (connection && connection.token === target.token) {
+                            client.send(JSON.stringify({ messageType: 'signedout' }));
+                            client.close();
+                        }
+                    });
+                    ws.send(sessionsPayload(ws));
+                    break;
+                }
+                case 'readmarker': {
+                    // Markers only move forward: a newer one goes to the reader's other devices, an
+                    // older one is answered with what the server already has
+                    const key = readerKey(ws);
+                    const sent = JSON.parse(parsed_data.data as string);
+                    // Only for the one room here, so clients can't fill the map with made-up ones
+                    if (!key || !sent || sent.room !== ROOM || typeof sent.time !== 'number') {
+                        break;
+                    }
+                    const marker: ReadMarker = {
+                        room: sent.room,
+                        seq: typeof sent.seq === 'number' ? sent.seq : undefined,
+                        time: sent.time,
+                    };
+                    const markers = readMarkers.get(key) || new Map<string, ReadMarker>();
+                    readMarkers.set(key, markers);
+                    const known = markers.get(marker.room);
+                    if (known && !isAfter(marker, known)) {
+                        if (isAfter(known, marker)) {
+                            ws.send(readMarkerPayload(known));
+                        }
+                        break;
+                    }
+                    markers.set(marker.room, marker);
+                    sameAccount(ws)
+                        .filter((client) => client !== ws)
+                        .forEach((client) => client.send(readMarkerPayload(marker)));
+                    break;
+                }
+                case 'draft': {
+                    // Only clients that opted in send these; the most recently typed draft wins
+                    const key = draftKey(ws);
+                    const sent = JSON.parse(parsed_data.data as string);
+                    const valid =
+                        !!sent && sent.room === ROOM && typeof sent.text === 'string' && typeof sent.time === 'number';
+                    if (!key || !valid) {
+                        break;
+                    }
+                    const draft: Draft = { room: sent.room, text: sent.text.slice(0, MAX_DRAFT_LENGTH), time: sent.time };
+                    const roomDrafts = drafts.get(key) || new Map<string, Draft>();
+                    drafts.set(key, roomDrafts);
+                    const known = roomDrafts.get(draft.room);
+                    if (known && known.time >= draft.time) {
+                        if (known.time > draft.time) {
+                            ws.send(draftPayload(known));
+                        }
+                        break;
+                    }
+                    roomDrafts.set(draft.room, draft);
+                    sameAccount(ws)
+                        .filter((client) => client !== ws)
+                        .forEach((client) => client.send(draftPayload(draft)));
+                    break;
+                }
+                case 'profileupdate': {
+                    const member = users.find((u) => u.ws === ws);
+                    if (member) {
+                        profiles.set(member.nick, cleanProfile(JSON.parse(parsed_data.data as string)));
+                        broadcast(usersPayload());
+                    }
+                    break;
+                }
+                case 'voice': {
+                    // Joining, leaving or muting in the room's voice channel
+                    const member = users.find((u) => u.ws === ws);
+                    const sent = JSON.parse(parsed_data.data as string);
+                    if (!member || !sent || sent.room !== ROOM) {
+                        break;
+                    }
+                    if (sent.joined) {
+                        voiceMembers.set(ws, !!sent.muted);
+                    } else if (!voiceMembers.delete(ws)) {
+                        break;
+                    }
+                    broadcast(voicePayload());
+                    break;
+                }
+                case 'signal': {
+                    // Offers, answers and ICE candidates, passed only between members of the call
+                    const member = users.find((u) => u.ws === ws);
+                    const sent = JSON.parse(parsed_data.data as string);
+                    if (!member || !voiceMembers.has(ws) || !sent || typeof sent.to !== 'string') {
+                        break;
+                    }
+                    const frame = JSON.stringify({
+                        messageType: 'signal',
+                        data: JSON.stringify({ ...sent, from: member.nick }),
+                    });
+                    users
+                        .filter((u) => u.nick === sent.to && voiceMembers.has(u.ws))
+                        .forEach((u) => u.ws.send(frame));
+                    break;
+                }
+                case 'call': {
+                    // Video call setup between two users; the video itself never comes through here
+                    const caller = users.find((u) => u.ws === ws);
+                    const sent = JSON.parse(parsed_data.data as string);
+                    if (!caller || !sent || typeof sent.to !== 'string') {
+                        break;
+                    }
+                    const frame = JSON.stringify({
+                        messageType: 'call',
+                        data: JSON.stringify({ ...sent, from: caller.nick }),
+                    });
+                    users.filter((u) => u.nick === sent.to).forEach((u) => u.ws.send(frame));
+                    break;
+                }
+                case 'serverinfo':
+                    ws.send(serverInfoPayload());
+                    break;
+                case 'ping':
+                    // Lets clients measure round-trip time; control frames aren't visible to browsers
+                    ws.send(JSON.stringify({ messageType: 'pong', data: parsed_data.data }));
+                    break;
+                case 'edit': {
+                    // Clients only apply edits whose sender wrote the message, as with deletes
+                    const editor = users.find((u) => u.ws === ws);
+                    if (editor && may(editor.nick, 'post')) {
+                        broadcast(
+                            JSON.stringify({
+                                messageType: 'edit',
+                                data: JSON.stringify({ ...JSON.parse(parsed_data.data as string), from: editor.nick }),
+                            })
+                        );
+                    }
+                    break;
+                }
+                case 'reaction':
+                case 'delete': {
+                    // The sender is always taken from the connection, never trusted from the payload
+                    const actor = users.find((u) => u.ws === ws);
+                    if (actor) {
                         broadcast(
                             JSON.stringify({
-                                messageType: 'message',
-                                data: JSON.stringify({
-                                    from: sender.nick,
-                                    message: parsed_data.data,
-                                    time: Date.now(),
-                                }),
+                                messageType: parsed_data.messageType,
+                                data: JSON.stringify({ ...JSON.parse(parsed_data.data as string), from: actor.nick }),
                             })
                         );
                     }
+                    break;
+                }
             }
         } catch (e) {
             console.log('Error in message', e);
@@ -56,13 +1446,23 @@ const interval = setInterval(function ping() {
     const updated_users = users.filter((u) => current_clients.includes(u.ws));
     if (updated_users.length !== users.length) {
         users = updated_users;
-        broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+        broadcast(usersPayload());
+    }
+    const inVoice = voiceMembers.size;
+    voiceMembers.forEach((_, client) => {
+        if (!users.some((u) => u.ws === client)) {
+            voiceMembers.delete(client);
+        }
+    });
+    if (voiceMembers.size !== inVoice) {
+        broadcast(voicePayload());
     }
 }, 5000);
 
 const broadcast = (data: any) => {
     wss.clients.forEach((client) => {
-        if (client.readyState === WebSocket.OPEN) {
+        // Sockets that haven't entered the room password don't get to see the room
+        if (client.readyState === WebSocket.OPEN && canRead(client)) {
             client.send(data);
         }
     });
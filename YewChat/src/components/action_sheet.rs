@@ -0,0 +1,79 @@
+use yew::prelude::*;
+
+pub const QUICK_REACTIONS: [&str; 6] = ["👍", "❤️", "😂", "😮", "😢", "🙏"];
+
+#[derive(Properties, PartialEq)]
+pub struct ActionSheetProps {
+    pub is_own: bool,
+    /// Delivered, so the server knows the message and it can be edited
+    #[prop_or(true)]
+    pub editable: bool,
+    /// Why replying and editing aren't allowed here, shown as a tooltip on the disabled actions
+    #[prop_or_default]
+    pub post_denied: Option<String>,
+    pub on_react: Callback<String>,
+    pub on_reply: Callback<()>,
+    pub on_edit: Callback<()>,
+    pub on_copy: Callback<()>,
+    /// Only messages the server has given an ID can be linked to
+    #[prop_or_default]
+    pub on_copy_link: Option<Callback<()>>,
+    pub on_delete: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(ActionSheet)]
+pub fn action_sheet(props: &ActionSheetProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let action = |cb: &Callback<()>| cb.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 z-50 flex flex-col justify-end">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative bg-white rounded-t-2xl shadow-lg pb-4">
+                <div class="flex justify-around p-4 border-b">
+                    {
+                        QUICK_REACTIONS.iter().map(|emoji| {
+                            let emoji_val = emoji.to_string();
+                            let on_react = props.on_react.clone();
+                            let onclick = Callback::from(move |_: MouseEvent| on_react.emit(emoji_val.clone()));
+                            html! {
+                                <button {onclick} class="text-2xl w-10 h-10 rounded-full hover:bg-gray-100 flex items-center justify-center">
+                                    {emoji}
+                                </button>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+                <button
+                    onclick={action(&props.on_reply)}
+                    disabled={props.post_denied.is_some()}
+                    title={props.post_denied.clone()}
+                    class="w-full text-left px-6 py-3 hover:bg-gray-100 disabled:opacity-40 disabled:cursor-not-allowed disabled:hover:bg-transparent"
+                >
+                    {"↩️  Reply"}
+                </button>
+                <button onclick={action(&props.on_copy)} class="w-full text-left px-6 py-3 hover:bg-gray-100">{"📋  Copy text"}</button>
+                if let Some(on_copy_link) = &props.on_copy_link {
+                    <button onclick={action(on_copy_link)} class="w-full text-left px-6 py-3 hover:bg-gray-100">{"🔗  Copy link"}</button>
+                }
+                if props.is_own && props.editable {
+                    <button
+                        onclick={action(&props.on_edit)}
+                        disabled={props.post_denied.is_some()}
+                        title={props.post_denied.clone()}
+                        class="w-full text-left px-6 py-3 hover:bg-gray-100 disabled:opacity-40 disabled:cursor-not-allowed disabled:hover:bg-transparent"
+                    >
+                        {"✏️  Edit"}
+                    </button>
+                }
+                if props.is_own {
+                    <button onclick={action(&props.on_delete)} class="w-full text-left px-6 py-3 text-red-600 hover:bg-red-50">{"🗑️  Delete"}</button>
+                }
+                <button onclick={close} class="w-full px-6 py-3 mt-2 font-medium text-gray-500 border-t">{"Cancel"}</button>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+use crate::services::storage;
+
+// Ids of banners put away on this device
+const DISMISSED_KEY: &str = "dismissed_announcements";
+// Old ids are of no use once their banner is gone from the server
+const DISMISSED_LIMIT: usize = 50;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[default]
+    Info,
+    Warning,
+}
+
+/// A notice from the server or a room owner, e.g. planned maintenance or the
+/// message of the day. An empty `text` withdraws the one with that `id`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Announcement {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub level: Level,
+    /// The owner who sent it; `None` for the server's own
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub time: i64,
+}
+
+// What `/announce` and `/alert` send; only owners are listened to
+#[derive(Serialize)]
+pub struct AnnounceRequest {
+    pub text: String,
+    pub level: Level,
+}
+
+pub fn is_dismissed(id: &str) -> bool {
+    storage::load::<Vec<String>>(DISMISSED_KEY).unwrap_or_default().iter().any(|d| d == id)
+}
+
+pub fn dismiss(id: &str) {
+    let mut dismissed: Vec<String> = storage::load(DISMISSED_KEY).unwrap_or_default();
+    if !dismissed.iter().any(|d| d == id) {
+        dismissed.push(id.to_string());
+    }
+    let excess = dismissed.len().saturating_sub(DISMISSED_LIMIT);
+    dismissed.drain(..excess);
+    storage::save(DISMISSED_KEY, &dismissed);
+}
+
+#[derive(Properties, PartialEq)]
+pub struct AnnouncementBannerProps {
+    pub announcement: Announcement,
+    /// Given the announcement's id
+    pub on_dismiss: Callback<String>,
+}
+
+/// A strip across the top of the chat that stays until it's dismissed.
+#[function_component(AnnouncementBanner)]
+pub fn announcement_banner(props: &AnnouncementBannerProps) -> Html {
+    let announcement = &props.announcement;
+    let (icon, colors) = match announcement.level {
+        Level::Info => ("📣", "bg-blue-50 border-blue-200 text-blue-900"),
+        Level::Warning => ("⚠️", "bg-amber-50 border-amber-300 text-amber-900"),
+    };
+    let dismiss = {
+        let id = announcement.id.clone();
+        props.on_dismiss.reform(move |_: MouseEvent| id.clone())
+    };
+    html! {
+        <div
+            class={classes!("flex", "items-start", "gap-3", "px-4", "py-2", "text-sm", "border-b", colors)}
+            role={if announcement.level == Level::Warning { "alert" } else { "status" }}
+        >
+            <span aria-hidden="true">{icon}</span>
+            <div class="grow whitespace-pre-wrap">
+                {&announcement.text}
+                if let Some(from) = &announcement.from {
+                    <span class="ml-2 text-xs opacity-70">{format!("— {}", from)}</span>
+                }
+            </div>
+            <button onclick={dismiss} class="px-1 opacity-60 hover:opacity-100" aria-label="Dismiss announcement">
+                {"✕"}
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+use crate::components::name_color::name_bg_color;
+
+/// The picture generated from a username, used when nothing else is available.
+pub fn identicon_url(name: &str) -> String {
+    format!("https://avatars.dicebear.com/api/adventurer-neutral/{}.svg", name)
+}
+
+/// Where a member's picture comes from, chosen in their profile.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum AvatarProvider {
+    #[default]
+    Identicon,
+    /// Gravatar's picture for an email address; only the SHA-256 of the
+    /// trimmed, lowercased address is ever shared
+    Gravatar { hash: String },
+}
+
+impl AvatarProvider {
+    pub fn url(&self, name: &str) -> String {
+        match self {
+            AvatarProvider::Identicon => identicon_url(name),
+            // `d=404` makes a missing Gravatar fail, so `Avatar` falls back to the identicon
+            AvatarProvider::Gravatar { hash } => format!("https://www.gravatar.com/avatar/{}?s=128&d=404", hash),
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct AvatarProps {
+    pub name: String,
+    pub src: String,
+    /// Size and shape classes, applied to both the image and the fallback
+    #[prop_or_else(|| classes!("w-10", "h-10"))]
+    pub class: Classes,
+}
+
+/// Up to two initials taken from the words of a username ("jane_doe" -> "JD").
+pub fn initials(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || "_-.".contains(c))
+        .filter_map(|w| w.chars().next())
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// User avatar that falls back to the identicon, then to a colored circle with
+/// initials, when the image fails to load (provider down, 404, offline).
+#[function_component(Avatar)]
+pub fn avatar(props: &AvatarProps) -> Html {
+    // Sources that failed so far: the given one, then the identicon
+    let failures = use_state(|| 0);
+
+    {
+        // A new URL deserves a fresh attempt
+        let failures = failures.clone();
+        use_effect_with_deps(
+            move |_| {
+                failures.set(0);
+                || ()
+            },
+            props.src.clone(),
+        );
+    }
+
+    let identicon = identicon_url(&props.name);
+    let src = match *failures {
+        0 => Some(props.src.clone()),
+        1 if props.src != identicon => Some(identicon),
+        _ => None,
+    };
+    let src = match src {
+        Some(src) => src,
+        None => {
+            return html! {
+                <div
+                    class={classes!(
+                        props.class.clone(),
+                        "rounded-full", "flex", "flex-none", "items-center", "justify-center",
+                        "text-white", "text-sm", "font-semibold", "select-none",
+                        name_bg_color(&props.name)
+                    )}
+                    title={props.name.clone()}
+                >
+                    {initials(&props.name)}
+                </div>
+            };
+        }
+    };
+
+    let onerror = {
+        let failures = failures.clone();
+        Callback::from(move |_: Event| failures.set(*failures + 1))
+    };
+
+    html! {
+        <img
+            class={classes!(props.class.clone(), "rounded-full")}
+            {src}
+            alt={props.name.clone()}
+            {onerror}
+        />
+    }
+}
@@ -0,0 +1,121 @@
+use js_sys::{Function, Object, Reflect};
+use serde::Deserialize;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+// Global the provider script calls once it has loaded
+const ONLOAD_CALLBACK: &str = "yewchatCaptchaLoaded";
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Hcaptcha,
+    Recaptcha,
+    Turnstile,
+}
+
+impl Provider {
+    fn script_url(self) -> &'static str {
+        match self {
+            Provider::Hcaptcha => "https://js.hcaptcha.com/1/api.js",
+            Provider::Recaptcha => "https://www.google.com/recaptcha/api.js",
+            Provider::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/api.js",
+        }
+    }
+
+    /// The global each provider's script defines; all three share a `render` API.
+    fn global(self) -> &'static str {
+        match self {
+            Provider::Hcaptcha => "hcaptcha",
+            Provider::Recaptcha => "grecaptcha",
+            Provider::Turnstile => "turnstile",
+        }
+    }
+}
+
+/// The server wants proof that a human is registering.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Challenge {
+    pub provider: Provider,
+    pub site_key: String,
+    /// Why the previous attempt was turned down, if there was one
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Draws the provider's widget into `container`, reporting solved tokens to `on_solve`.
+fn render(challenge: &Challenge, container: &HtmlElement, on_solve: &Callback<String>) -> Option<()> {
+    let window = web_sys::window()?;
+    let api = Reflect::get(&window, &challenge.provider.global().into()).ok()?;
+    let render: Function = Reflect::get(&api, &"render".into()).ok()?.dyn_into().ok()?;
+
+    let on_solve = on_solve.clone();
+    let callback = Closure::wrap(Box::new(move |token: JsValue| {
+        on_solve.emit(token.as_string().unwrap_or_default());
+    }) as Box<dyn Fn(JsValue)>);
+    let options = Object::new();
+    Reflect::set(&options, &"sitekey".into(), &challenge.site_key.clone().into()).ok()?;
+    // The widget outlives this call, so the callback is handed over to the JS side
+    Reflect::set(&options, &"callback".into(), &callback.into_js_value()).ok()?;
+    render.call2(&api, container, &options).ok()?;
+    Some(())
+}
+
+/// Loads the provider's script with explicit rendering, then calls `then`.
+fn load_script(provider: Provider, then: impl FnOnce() + 'static) -> Option<()> {
+    let window = web_sys::window()?;
+    let onload = Closure::once_into_js(then);
+    Reflect::set(&window, &ONLOAD_CALLBACK.into(), &onload).ok()?;
+    let document = window.document()?;
+    let script = document.create_element("script").ok()?;
+    script
+        .set_attribute("src", &format!("{}?render=explicit&onload={}", provider.script_url(), ONLOAD_CALLBACK))
+        .ok()?;
+    script.set_attribute("async", "").ok()?;
+    document.body()?.append_child(&script).ok()?;
+    Some(())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CaptchaWidgetProps {
+    pub challenge: Challenge,
+    pub on_solve: Callback<String>,
+}
+
+/// The provider's CAPTCHA widget, loading its script the first time it's needed.
+#[function_component(CaptchaWidget)]
+pub fn captcha_widget(props: &CaptchaWidgetProps) -> Html {
+    let container = use_node_ref();
+    {
+        let container = container.clone();
+        let on_solve = props.on_solve.clone();
+        use_effect_with_deps(
+            move |challenge: &Challenge| {
+                let loaded = web_sys::window()
+                    .and_then(|w| Reflect::get(&w, &challenge.provider.global().into()).ok())
+                    .filter(|api| !api.is_undefined())
+                    .is_some();
+                let provider = challenge.provider;
+                let challenge = challenge.clone();
+                let draw = move || {
+                    if let Some(container) = container.cast::<HtmlElement>() {
+                        if render(&challenge, &container, &on_solve).is_none() {
+                            log::error!("Couldn't show the {:?} CAPTCHA", challenge.provider);
+                        }
+                    }
+                };
+                if loaded {
+                    draw();
+                } else if load_script(provider, draw).is_none() {
+                    log::error!("Couldn't load the {:?} CAPTCHA script", provider);
+                }
+                || ()
+            },
+            props.challenge.clone(),
+        );
+    }
+
+    html! { <div ref={container} class="flex justify-center"></div> }
+}
@@ -1,49 +1,869 @@
 use serde::{Deserialize, Serialize};
-use web_sys::{HtmlInputElement, KeyboardEvent, HtmlElement};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::ops::Range;
+use std::rc::Rc;
+use web_sys::{HtmlInputElement, KeyboardEvent, HtmlElement, TouchEvent};
 use wasm_bindgen::JsCast;
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
+use yew_router::{history::Location as _, prelude::*, scope_ext::HistoryHandle};
+use gloo_timers::callback::{Interval, Timeout};
+use wasm_bindgen_futures::spawn_local;
 
-use crate::{services::{event_bus::EventBus, websocket::WebsocketService}, User};
+use crate::{
+    Route,
+    components::{action_sheet::ActionSheet, announcement::{self, AnnounceRequest, Announcement, AnnouncementBanner}, avatar::identicon_url, captcha::Challenge, command_palette::{Command, CommandPalette}, confirm::{Confirm, ConfirmDialog}, connection_error::ConnectionError, edit_history::EditHistory, emoji_picker::EmojiPicker, format_toolbar::{self, Format, FormatToolbar}, highlight::{Highlight, Highlighter}, latency::{LatencyMeter, LinkQuality}, invite::{invite_url, InviteDialog}, login::validate_username, message::MessageBubble, motion::{self, animate}, name_color::name_color, onboarding::{self, EmptyRoom, MessageSkeleton, TipsCard, UserListSkeleton}, permalink::permalink_url, settings::{Contrast, Layout, Settings, SettingsPanel}, transcript::Transcript, update_toast::UpdateToast, lightbox::Lightbox, link_warning::{is_trusted, ExternalLink, LinkWarning}, media::{is_image_url, is_video_url}, media_gallery::{MediaGallery, MediaItem}, links::{extract_urls, LinkItem, LinksPanel}, live_region::{Announcer, LiveRegion}, location::Location, mentions::{MentionIndex, MentionsPanel}, members::MembersPanel, presence::Presence, profile::{Profile, ProfileCard}, renderers, room_password::RoomPasswordPrompt, server_info::{ServerInfo, ServerInfoDialog}, sessions::{DeviceSession, SessionsDialog}, shortcuts::ShortcutSheet, stats::{StatsPanel, UsageStats}, user_list::{UserList, UserSection}, video_call::{CallWindow, IncomingCallDialog}, voice_strip::VoiceStrip},
+    services::{
+        account::{self, RecentIdentity, Session},
+        endpoints::Endpoints,
+        clipboard,
+        event_bus::EventBus,
+        hotkeys::{self, HotkeyListener},
+        irc::IrcConfig,
+        matrix::MatrixConfig,
+        metadata,
+        notify,
+        rejoin::LastConversation,
+        tracking,
+        version,
+        storage,
+        upload::{Limits, Upload, UploadEvent},
+        viewport::ViewportListener,
+        call::{format_duration, Call, CallPayload, CallSignal, IncomingCall},
+        geolocation,
+        rtc,
+        voice::{Participant, Roster, Signal, Voice, VoiceUpdate},
+        websocket::{self, ConnectionStatus, WebsocketService},
+    },
+    User,
+};
 
 pub enum Msg {
     HandleMsg(String),
     SubmitMessage,
     InputKeyPress(KeyboardEvent),
+    InputKeyDown(KeyboardEvent),
     ToggleEmojiPicker,
+    CloseEmojiPicker,
     InsertEmoji(String),
+    StartReply(usize),
+    CancelReply,
+    OpenActions(usize),
+    CloseActions,
+    React(usize, String),
+    CopyMessage(usize),
+    CopyLink(usize),
+    DeleteMessage(usize),
+    LoadOlder,
+    MessagesScrolled,
+    PullStart(TouchEvent),
+    PullMove(TouchEvent),
+    PullEnd,
+    ViewportResized(f64),
+    ToggleSection(SidebarSection),
+    FilterUsers(String),
+    ToggleFavorite(String),
+    RetrySend(usize),
+    RetryFailed,
+    ScheduleRetry,
+    DiscardFailed(usize),
+    ConnectionChanged(ConnectionStatus),
+    ToggleSettings,
+    Navigated,
+    ResetSettings,
+    UpdateSettings(Settings),
+    TogglePrintView,
+    ShowPanel(Option<PanelTab>),
+    OpenLightbox(Option<usize>),
+    JumpToMessage(usize),
+    ClearFlash,
+    OpenMention(MessageRef),
+    MarkMentionsRead,
+    JumpToLatest,
+    MentionUser(String),
+    CopyName(String),
+    ToggleInvite,
+    ToggleSessions,
+    ToggleServerInfo,
+    SignOutSession(String),
+    ShowProfile(Option<String>),
+    SaveProfile(Profile),
+    LogOut,
+    ShowRoomPassword(bool),
+    SubmitRoomPassword(String),
+    ToggleIconPicker,
+    SetRoomIcon(Option<String>),
+    Confirmed,
+    CancelConfirm,
+    SendScheduled(String),
+    UndoSend(usize),
+    StartEdit(usize),
+    CancelEdit,
+    ShowEdits(Option<usize>),
+    MessageListKey(KeyboardEvent),
+    FlushAnnouncements,
+    Hotkey(KeyboardEvent),
+    ShowPalette(bool),
+    ShowShortcuts(bool),
+    ShowStats(bool),
+    ClearStats,
+    JoinVoice,
+    MicrophoneReady(Result<web_sys::MediaStream, String>),
+    LeaveVoice,
+    ToggleVoiceMute,
+    SendSignal(Signal),
+    MeasureVoice,
+    StartCall(String),
+    AcceptCall,
+    CallMedia(Result<web_sys::MediaStream, String>),
+    DeclineCall,
+    HangUp,
+    CallTimeout,
+    SendCallSignal(CallSignal),
+    RemoteStream(web_sys::MediaStream),
+    ToggleCallMute,
+    ToggleCamera,
+    Ping,
+    CooldownTick,
+    ToggleBurst(MessageRef),
+    DraftChanged,
+    SyncDraft,
+    WarnLink(Option<ExternalLink>),
+    ComposerPaste(Event),
+    ToggleSensitive,
+    AttachFiles(Event),
+    UploadEvent(u32, UploadEvent),
+    CancelUpload(u32),
+    RetryUpload(u32),
+    UploadLimits(Limits),
+    ShareLocation,
+    LocationReady(Result<Location, String>),
+    AttachmentPrepared(u32, Option<web_sys::File>),
+    Format(Format),
+    TogglePreview,
+    DismissTips,
+    RetryConnection,
+    DismissAnnouncement(String),
+    CheckForUpdate,
+    UpdateAvailable,
+    DismissUpdate,
 }
 
-#[derive(Deserialize)]
-struct MessageData {
+/// Whether a newer client has been deployed since this tab loaded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UpdateState {
+    Current,
+    Available,
+    // Put off for this tab; it isn't offered again until the next load
+    Dismissed,
+}
+
+/// A destructive action held back until the user confirms it
+#[derive(Clone, Copy)]
+enum PendingAction {
+    DeleteMessage(usize),
+    DiscardFailed(usize),
+    ResetSettings,
+}
+
+impl PendingAction {
+    fn confirm(self) -> Confirm {
+        match self {
+            PendingAction::DeleteMessage(_) => Confirm {
+                title: "Delete message?",
+                message: "It will be removed for everyone in the room. This can't be undone.",
+                confirm_label: "Delete",
+                skip_key: None,
+            },
+            PendingAction::DiscardFailed(_) => Confirm {
+                title: "Discard message?",
+                message: "It was never delivered, so nobody else has seen it.",
+                confirm_label: "Discard",
+                skip_key: Some("discard_failed"),
+            },
+            PendingAction::ResetSettings => Confirm {
+                title: "Reset settings?",
+                message: "Every setting goes back to how it was on a fresh install. Export them first to keep a copy.",
+                confirm_label: "Reset",
+                skip_key: None,
+            },
+        }
+    }
+
+    fn map_index(self, shift: impl Fn(usize) -> Option<usize>) -> Option<Self> {
+        match self {
+            PendingAction::DeleteMessage(i) => shift(i).map(PendingAction::DeleteMessage),
+            PendingAction::DiscardFailed(i) => shift(i).map(PendingAction::DiscardFailed),
+            PendingAction::ResetSettings => Some(self),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SidebarSection {
+    Favorites,
+    Online,
+    Offline,
+}
+
+/// What the message area has to show for the conversation so far.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RoomState {
+    /// Still connecting, or waiting on the first page of history
+    Loading,
+    /// Joined, and nothing has been said yet
+    Empty,
+    Ready,
+}
+
+/// Tabs of the panel on the right of the conversation
+#[derive(Clone, Copy, PartialEq)]
+pub enum PanelTab {
+    Media,
+    Links,
+    Mentions,
+    Members,
+}
+
+impl PanelTab {
+    fn label(self) -> &'static str {
+        match self {
+            PanelTab::Media => "Media",
+            PanelTab::Links => "Links",
+            PanelTab::Mentions => "Mentions",
+            PanelTab::Members => "Members",
+        }
+    }
+}
+
+const FAVORITES_KEY: &str = "favorites";
+// Token for reclaiming our name after reconnecting, kept for the tab so a reload works too
+const RECLAIM_KEY: &str = "reclaim";
+// The only conversation for now; scroll state is kept per conversation so more can follow
+const ROOM: &str = "main";
+
+/// The dialog open over the chat, kept in the query string, e.g.
+/// `/chat?profile=bob`, so Back closes it and Forward opens it again.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Overlay {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    settings: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+}
+
+/// Where the reader left a conversation, restored when they come back to it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct ScrollState {
+    // Message at the top of the viewport and its offset from it
+    anchor: Option<(MessageRef, i32)>,
+    at_bottom: bool,
+    // Newest message at the time, where the "new messages" divider goes on return
+    last_seen: Option<MessageRef>,
+}
+
+impl ScrollState {
+    fn storage_key(room: &str) -> String {
+        format!("scroll.{}", room)
+    }
+}
+
+/// What's typed but not sent yet in a conversation. Kept locally, and with
+/// [`Settings::sync_drafts`] also passed between our devices, where the most
+/// recently typed draft wins.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Draft {
+    room: String,
+    text: String,
+    // When it was last typed in, on the typing device's clock
+    time: i64,
+}
+
+impl Draft {
+    fn storage_key(room: &str) -> String {
+        format!("draft.{}", room)
+    }
+}
+
+/// A file picked in the composer. It's posted as a link once the server has it.
+struct Attachment {
+    id: u32,
+    file: web_sys::File,
+    sent: f64, // Bytes the server has taken so far
+    upload: Option<Upload>, // While in flight; dropping it cancels
+    error: Option<String>, // Why the last attempt failed, until it's retried
+    rejected: bool, // Turned down before sending, so there's nothing to retry
+    preparing: bool, // Being re-encoded without its metadata before it's uploaded
+    metadata_removed: bool,
+    sensitive: bool,
+}
+
+impl Attachment {
+    fn progress(&self) -> f64 {
+        let size = self.file.size();
+        if size > 0.0 { (self.sent / size).min(1.0) } else { 0.0 }
+    }
+}
+
+/// How far we've read in a conversation, shared with our other devices through
+/// the server. Markers only ever move forward: whichever points at the later
+/// message wins, so devices agree again after being offline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct ReadMarker {
+    room: String,
+    // Of the last message read; servers without sequence numbers leave it out
+    #[serde(default)]
+    seq: Option<u64>,
+    time: i64,
+}
+
+impl ReadMarker {
+    fn storage_key(room: &str) -> String {
+        format!("read.{}", room)
+    }
+
+    fn at(m: &MessageData) -> Option<Self> {
+        Some(Self {
+            room: ROOM.to_string(),
+            seq: m.seq,
+            time: m.timestamp?,
+        })
+    }
+
+    fn is_after(&self, other: &ReadMarker) -> bool {
+        match (self.seq, other.seq) {
+            (Some(a), Some(b)) => a > b,
+            _ => self.time > other.time,
+        }
+    }
+
+    fn covers(&self, m: &MessageData) -> bool {
+        match (self.seq, m.seq) {
+            (Some(a), Some(b)) => b <= a,
+            _ => matches!(m.timestamp, Some(t) if t <= self.time),
+        }
+    }
+}
+// How long a message stays outlined after jumping to it
+const FLASH_MS: u32 = 1_500;
+// Pause in typing before a draft goes to our other devices
+const DRAFT_SYNC_DELAY_MS: u32 = 1_000;
+// Messages skipped by PageUp/PageDown in the message list
+const FOCUS_PAGE: isize = 10;
+// How often the connection's round-trip time is measured
+const PING_INTERVAL_MS: u32 = 5_000;
+// How often the voice channel's speaking indicators are refreshed
+const VOICE_METER_INTERVAL_MS: u32 = 150;
+// How long an unanswered video call rings before giving up
+const CALL_RING_TIMEOUT_MS: u32 = 30_000;
+// How often an open tab looks for a newer deploy, besides after each reconnect
+const UPDATE_CHECK_INTERVAL_MS: u32 = 15 * 60 * 1000;
+// Sent messages ArrowUp/ArrowDown can bring back into the composer
+const RECALL_LIMIT: usize = 50;
+
+// Collapsed sidebar sections, persisted across sessions
+#[derive(Default, Serialize, Deserialize)]
+struct SidebarState {
+    #[serde(default)]
+    favorites_collapsed: bool,
+    online_collapsed: bool,
+    offline_collapsed: bool,
+}
+
+impl SidebarState {
+    const STORAGE_KEY: &'static str = "sidebar";
+
+    fn collapsed(&self, section: SidebarSection) -> bool {
+        match section {
+            SidebarSection::Favorites => self.favorites_collapsed,
+            SidebarSection::Online => self.online_collapsed,
+            SidebarSection::Offline => self.offline_collapsed,
+        }
+    }
+
+    fn toggle(&mut self, section: SidebarSection) {
+        match section {
+            SidebarSection::Favorites => self.favorites_collapsed = !self.favorites_collapsed,
+            SidebarSection::Online => self.online_collapsed = !self.online_collapsed,
+            SidebarSection::Offline => self.offline_collapsed = !self.offline_collapsed,
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageData {
+    // Assigned by the server; absent on optimistic local copies
+    #[serde(default)]
+    pub id: Option<String>,
+    // Per-room, gapless and increasing; lets us notice dropped messages
+    #[serde(default)]
+    pub seq: Option<u64>,
+    pub from: String,
+    pub message: String,
+    #[serde(alias = "time")]
+    pub timestamp: Option<i64>, // Add timestamp field
+    #[serde(default)]
+    pub reply_to: Option<ReplyPreview>,
+    // Random ID we attach to our own sends; the server echoes it back so the
+    // optimistic local copy can be matched with the real one
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub media: Option<MediaMeta>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    // Emoji -> usernames who reacted with it, maintained locally from `reaction` events
+    #[serde(skip)]
+    pub reactions: BTreeMap<String, BTreeSet<String>>,
+    #[serde(skip)]
+    pub deleted: bool,
+    // Locally generated notices (renames etc.) rendered as a centered line, not a bubble
+    #[serde(skip)]
+    pub system: bool,
+    #[serde(skip)]
+    pub status: DeliveryStatus,
+    #[serde(skip)]
+    pub send_attempts: u32,
+    // When a `Scheduled` message goes out, in ms since the epoch
+    #[serde(skip)]
+    pub send_at: Option<f64>,
+    // Earlier versions, oldest first, as seen by this client
+    #[serde(skip)]
+    pub revisions: Vec<Revision>,
+    #[serde(skip)]
+    pub edited_at: Option<i64>,
+    // Worked out once on arrival, see `Chat::classify`
+    #[serde(skip)]
+    pub highlight: Highlight,
+}
+
+/// A version of a message that has since been edited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revision {
+    pub message: String,
+    /// When this version was written
+    pub timestamp: Option<i64>,
+}
+
+/// Delivery state of messages we sent. Anything received from the server is `Sent`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DeliveryStatus {
+    #[default]
+    Sent,
+    // Held back for the undo-send window
+    Scheduled,
+    Sending,
+    Failed,
+}
+
+// Automatic retries of failed sends back off 2s, 4s, 8s... before giving up
+const MAX_SEND_ATTEMPTS: u32 = 4;
+const RETRY_BASE_DELAY_MS: u32 = 2_000;
+// Someone sending at least this many messages in a row, each within
+// BURST_GAP_MS of the last, is flooding; the run is collapsed in the list
+const BURST_MIN: usize = 8;
+const BURST_GAP_MS: i64 = 3_000;
+
+/// Sliding-window cap on outgoing messages, so a bouncing Enter key or a held
+/// paste can't flood the room.
+struct SendLimiter {
+    max: usize,
+    window_ms: f64,
+    sent: VecDeque<f64>,
+}
+
+impl SendLimiter {
+    /// Allows `settings.send_limit` messages in any `settings.send_limit_secs` window.
+    fn new(settings: &Settings) -> Self {
+        let mut limiter = Self { max: 0, window_ms: 0.0, sent: VecDeque::new() };
+        limiter.configure(settings);
+        limiter
+    }
+
+    /// Picks up a changed limit, still counting what was sent under the old one.
+    fn configure(&mut self, settings: &Settings) {
+        self.max = settings.send_limit.max(1);
+        self.window_ms = f64::from(settings.send_limit_secs) * 1_000.0;
+    }
+
+    /// Counts a send at `now`, or returns how many milliseconds to wait first.
+    fn acquire(&mut self, now: f64) -> Result<(), f64> {
+        while let Some(oldest) = self.sent.front() {
+            if now - oldest < self.window_ms {
+                break;
+            }
+            self.sent.pop_front();
+        }
+        match self.sent.front() {
+            Some(oldest) if self.sent.len() >= self.max => Err(oldest + self.window_ms - now),
+            _ => {
+                self.sent.push_back(now);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl MessageData {
+    fn system(text: String) -> Self {
+        Self {
+            message: text,
+            system: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn to_ref(&self) -> MessageRef {
+        MessageRef {
+            id: self.id.clone(),
+            from: self.from.clone(),
+            timestamp: self.timestamp,
+        }
+    }
+
+    /// Whether `other` is another copy of this message (replay, retry, history overlap).
+    fn same_as(&self, other: &MessageData) -> bool {
+        // A resend after reconnecting gets a fresh server ID but keeps its client ID
+        if self.client_id.is_some() && self.client_id == other.client_id {
+            return true;
+        }
+        match (&self.id, &other.id) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.timestamp.is_some() && self.from == other.from && self.timestamp == other.timestamp,
+        }
+    }
+}
+
+// Identifies a message by its server ID, falling back to sender + timestamp
+// for messages from servers that don't assign IDs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MessageRef {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub from: String,
+    pub timestamp: Option<i64>,
+}
+
+impl MessageRef {
+    pub fn matches(&self, m: &MessageData) -> bool {
+        match (&self.id, &m.id) {
+            (Some(a), Some(b)) => a == b,
+            _ => m.from == self.from && m.timestamp == self.timestamp,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReactionData {
+    #[serde(default)]
+    from: String,
+    target: MessageRef,
+    emoji: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeleteData {
+    #[serde(default)]
+    from: String,
+    target: MessageRef,
+}
+
+// Enough of the original message to render the quote above a reply
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReplyPreview {
+    pub from: String,
+    pub message: String,
+}
+
+// Layout hints for an image/GIF attachment so its space can be reserved before it loads
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaMeta {
+    pub width: u32,
+    pub height: u32,
+    // Tiny inline preview (usually a data: URL) shown blurred while the real image loads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placeholder: Option<String>,
+    // Still first frame of an animated image, shown when autoplay is off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poster: Option<String>,
+    // The sender asked for it to stay blurred until the reader chooses to see it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EditData {
+    #[serde(default)]
     from: String,
+    target: MessageRef,
     message: String,
-    timestamp: Option<i64>, // Add timestamp field
 }
 
+#[derive(Serialize, Deserialize)]
+struct RenameData {
+    #[serde(default)]
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ResyncRequest {
+    from: u64,
+    to: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CatchupRequest {
+    after: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UnfurlRequest {
+    url: String,
+}
+
+// Page title the server found for a shared link, `None` if it had none or couldn't be fetched
+#[derive(Serialize, Deserialize)]
+struct UnfurlData {
+    url: String,
+    title: Option<String>,
+}
+
+// A fresh invite token for the room
+#[derive(Deserialize)]
+struct InviteData {
+    token: String,
+}
+
+// Outcome of redeeming an invite token or entering a room password
+#[derive(Deserialize)]
+struct JoinResult {
+    room: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JoinRoomRequest {
+    room: String,
+    // Hex SHA-256 of "room:password"
+    passphrase: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryRequest {
+    before: Option<i64>,
+    limit: usize,
+}
+
+// Number of older messages requested per page
+const HISTORY_PAGE_SIZE: usize = 50;
+// Pull distance (px) past which releasing triggers a history load
+const PULL_TRIGGER: i32 = 60;
+const PULL_MAX: i32 = 90;
+// At most this many messages are in the DOM; the rest stay in memory only
+const WINDOW_SIZE: usize = 150;
+// Messages added or dropped at an end of the window each time it moves
+const WINDOW_STEP: usize = 50;
+// Distance (px) from either end of the list at which the window moves
+const WINDOW_EDGE: i32 = 300;
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MsgTypes {
     Users,
     Register,
+    RegisterError,
     Message,
+    Rename,
+    RenameError,
+    Reaction,
+    Delete,
+    History,
+    Resync,
+    Catchup,
+    Unfurl,
+    Permissions,
+    CreateInvite,
+    Invite,
+    JoinByToken,
+    Joined,
+    JoinRoom,
+    JoinedRoom,
+    Room,
+    RoomIcon,
+    Edit,
+    Ping,
+    Pong,
+    Challenge,
+    Solve,
+    ProfileUpdate,
+    Registered,
+    Sessions,
+    SignOut,
+    SignedOut,
+    ReadMarker,
+    Draft,
+    Announce,
+    Announcement,
+    ServerInfo,
+    Voice,
+    Signal,
+    Call,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WebSocketMessage {
     message_type: MsgTypes,
     data_array: Option<Vec<String>>,
     data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reply_to: Option<ReplyPreview>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media: Option<MediaMeta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    // Sent with `register`: the token from our last registration, which lets us
+    // take the name back from a connection of ours the server hasn't noticed is dead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reclaim: Option<String>,
+}
+
+// The server's answer to a successful `register`
+#[derive(Deserialize)]
+struct Registered {
+    nick: String,
+    token: String,
+}
+
+// Where our account is connected from, in answer to `sessions`
+#[derive(Deserialize)]
+struct SessionList {
+    sessions: Vec<DeviceSession>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct UserProfile {
+    pub name: String,
+    pub avatar: String,
+    pub presence: Presence,
+    pub role: Role,
+    pub profile: Profile,
+}
+
+impl UserProfile {
+    /// Someone we know only by name, e.g. from an old message.
+    fn offline(name: &str) -> Self {
+        UserProfile {
+            name: name.to_string(),
+            avatar: identicon_url(name),
+            presence: Presence::Offline,
+            role: Role::default(),
+            profile: Profile::default(),
+        }
+    }
+
+
+    pub fn online(&self) -> bool {
+        self.presence != Presence::Offline
+    }
+}
+
+/// A member's standing in the room, as assigned by the server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Owner,
+    Moderator,
+    #[default]
+    Member,
+}
+
+impl Role {
+    pub fn label(self) -> &'static str {
+        match self {
+            Role::Owner => "Owner",
+            Role::Moderator => "Moderator",
+            Role::Member => "Member",
+        }
+    }
+}
+
+// Room-scoped detail sent alongside the plain nickname list of a `users` message.
+// It also names the room, which we can ignore while there's only one.
+#[derive(Deserialize)]
+struct RoomMembers {
+    members: Vec<RoomMember>,
+}
+
+#[derive(Deserialize)]
+struct RoomMember {
+    nick: String,
+    #[serde(default)]
+    role: Role,
+    #[serde(default)]
+    status: Presence,
+    // Absent until they fill it in
+    #[serde(default)]
+    profile: Option<Profile>,
+}
+
+/// Who in the room a permission is granted to.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    Everyone,
+    Moderators,
+    Owners,
+}
+
+/// Whether the current user may do something, and who may if they can't.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub struct Permission {
+    pub allowed: bool,
+    pub audience: Audience,
+}
+
+impl Permission {
+    /// Servers that don't send permissions let everyone do everything.
+    const GRANTED: Permission = Permission { allowed: true, audience: Audience::Everyone };
+
+    /// Tooltip explaining why the action is unavailable, or `None` if it isn't.
+    pub fn denied_reason(self, action: &str) -> Option<String> {
+        let who = match self.audience {
+            Audience::Everyone => "members",
+            Audience::Moderators => "moderators and owners",
+            Audience::Owners => "owners",
+        };
+        (!self.allowed).then(|| format!("Only {} can {} in this room", who, action))
+    }
+}
+
+// What the current user may do in the room, sent after registering and renaming.
+// The pinning flag comes along too but has nothing to gate yet.
+#[derive(Deserialize)]
+struct RoomPermissions {
+    post: Permission,
+    // Covers the room's appearance as well as its topic
+    topic: Permission,
 }
 
-#[derive(Clone)]
-struct UserProfile {
-    name: String,
-    avatar: String,
-    online: bool, // Add online status
+// Room appearance shared by everyone in it
+#[derive(Deserialize)]
+struct RoomInfo {
+    icon: Option<String>,
+    // Password protected, and we haven't entered it yet
+    #[serde(default)]
+    locked: bool,
 }
 
+// Choices offered when changing the room icon
+const ROOM_ICONS: [&str; 12] = ["💬", "🏠", "🎉", "💼", "🎮", "📚", "🎵", "☕", "🚀", "🐛", "📢", "🌱"];
+const DEFAULT_ROOM_ICON: &str = "💬";
+
 pub struct Chat {
     users: Vec<UserProfile>,
     chat_input: NodeRef,
@@ -52,6 +872,109 @@ pub struct Chat {
     _producer: Box<dyn Bridge<EventBus>>,
     username: String, // Store current username to differentiate sent/received messages
     show_emoji_picker: bool, // State for emoji picker
+    replying_to: Option<usize>, // Index into `messages` of the message being replied to
+    action_sheet: Option<usize>, // Message whose long-press actions are open
+    loading_history: bool,
+    history_exhausted: bool,
+    pull_start: Option<i32>, // Touch y where a pull-to-refresh began
+    pull_distance: i32,
+    // Message that must stay put on screen through the next render: (index, px from the top of the list)
+    scroll_anchor: Option<(usize, i32)>,
+    window_start: usize, // First rendered message when not following the tail
+    follow_tail: bool, // Rendering the newest messages, moving along as more arrive
+    saved_scroll: Option<ScrollState>, // Position to return to once its messages are loaded
+    unread_after: Option<MessageRef>, // Messages after this one get a "new messages" divider
+    read_marker: Option<ReadMarker>, // Last message read on any of our devices
+    // Whether the list was scrolled to the bottom before the last layout change
+    pinned_to_bottom: bool,
+    scroll_to_bottom: bool,
+    // Visible height while the mobile keyboard shrinks the viewport
+    viewport_height: Option<f64>,
+    _viewport: Option<ViewportListener>,
+    _hotkeys: Option<HotkeyListener>,
+    sidebar: SidebarState,
+    user_filter: String,
+    favorites: BTreeSet<String>, // Starred usernames, persisted locally
+    retry_timer: Option<Timeout>,
+    last_seq: Option<u64>, // Highest sequence number received so far
+    connection: ConnectionStatus,
+    was_connected: bool,
+    settings: Settings,
+    show_settings: bool,
+    highlighter: Highlighter, // Username and keywords that make a message stand out
+    print_view: bool, // Showing the printable transcript instead of the chat
+    signed_out: bool, // On the way back to login, with nothing left to save
+    side_panel: Option<PanelTab>,
+    mentions: MentionIndex,
+    link_titles: HashMap<String, Option<String>>, // Unfurled link titles; `None` while pending or untitled
+    lightbox: Option<usize>, // Media message shown full screen
+    jump_to: Option<usize>, // Message to scroll into view after the next render
+    flash: Option<usize>, // Message briefly outlined after a jump
+    flash_timer: Option<Timeout>,
+    seeking: Option<String>, // ID of a permalinked message we're loading history to find
+    can_post: Permission,
+    show_invite: bool,
+    signed_in: bool, // Connected with an account's session token
+    show_sessions: bool,
+    sessions: Option<Vec<DeviceSession>>, // Last list the server sent
+    show_server_info: bool,
+    server_info: Option<ServerInfo>, // Refreshed each time the panel opens
+    invite_token: Option<String>, // Issued once per session and reused
+    room_icon: Option<String>,
+    room_locked: bool,
+    room_key: Option<String>, // Accepted room password hash, resent when rejoining after a reconnect
+    show_room_password: bool,
+    room_password_error: Option<String>,
+    joining_room: bool, // Waiting to hear whether the password was right
+    can_edit_room: Permission,
+    show_icon_picker: bool,
+    confirming: Option<PendingAction>,
+    send_timers: HashMap<String, Timeout>, // Messages waiting out the undo window, by client ID
+    editing: Option<usize>, // Own message being rewritten in the composer
+    edit_history: Option<usize>, // Message whose revisions are open
+    focused: Option<usize>, // Message selected with the keyboard
+    reveal: Option<usize>, // Message to scroll just far enough to be fully visible after the next render
+    announcer: Announcer, // Feeds the screen-reader live region
+    sent_history: Vec<String>, // What this session sent, oldest first
+    recall: Option<(usize, String)>, // Position in `sent_history`, and the draft it replaced
+    show_palette: bool,
+    show_shortcuts: bool, // The keyboard cheat sheet
+    stats: UsageStats,
+    show_stats: bool,
+    voice: Option<Voice>, // While we're in the room's voice channel
+    voice_roster: Vec<Participant>,
+    voice_speaking: BTreeSet<String>,
+    voice_muted: bool,
+    joining_voice: bool, // Waiting on the microphone prompt
+    _voice_meter: Option<Interval>,
+    call: Option<Call>, // A video call, ringing or connected
+    incoming_call: Option<IncomingCall>,
+    placing_call: Option<String>, // Who we're calling or answering, while the camera prompt is up
+    call_timeout: Option<Timeout>, // Gives up on an outgoing call nobody answers
+    link_quality: LinkQuality,
+    _ping_timer: Interval,
+    _update_timer: Interval,
+    _history_listener: Option<HistoryHandle>, // Brings dialogs in line with the URL on Back and Forward
+    update: UpdateState,
+    send_limiter: SendLimiter,
+    cooldown_until: Option<f64>, // Sending is paused until then after hitting the rate limit
+    cooldown_timer: Option<Interval>,
+    expanded_bursts: Vec<MessageRef>, // Floods opened by the reader, by their first message
+    profile: Profile, // Ours, shared with the room once registered
+    profile_card: Option<String>, // Member whose profile is open
+    draft: Draft,
+    draft_timer: Option<Timeout>, // Sends the draft once typing pauses
+    leaving_to: Option<ExternalLink>, // Link waiting for the user to confirm leaving
+    paste_cleaned: Option<Vec<String>>, // Tracking parameters taken out of the last paste
+    composing_image: bool, // The composer holds an image link, which can be marked sensitive
+    mark_sensitive: bool,
+    attachments: Vec<Attachment>, // Uploading or failed, in the order they were picked
+    next_attachment: u32,
+    upload_limits: Limits, // What the server takes, checked before uploading
+    locating: bool, // Waiting on the browser for our position
+    show_preview: bool, // The composer's text rendered as it will be sent
+    show_tips: bool, // First-run tips, until they're dismissed on this device
+    announcements: Vec<Announcement>, // Banners not yet dismissed here, oldest first
 }
 
 impl Component for Chat {
@@ -64,22 +987,31 @@ impl Component for Chat {
             .context::<User>(Callback::noop())
             .expect("Context to be set");
         
-        let wss = WebsocketService::new();
+        let token = user.session.borrow().as_ref().map(|s| s.token.clone());
+        let wss = WebsocketService::new(token.as_deref(), ctx.link().callback(Msg::ConnectionChanged));
         let username = user.username.borrow().clone();
 
-        let message = WebSocketMessage {
-            message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
-            data_array: None,
-        };
+        // The server checks a solved CAPTCHA before it looks at the registration
+        if let Some(token) = user.captcha_token.borrow_mut().take() {
+            let solve = WebSocketMessage {
+                message_type: MsgTypes::Solve,
+                data: Some(token),
+                data_array: None,
+                reply_to: None,
+                client_id: None,
+                media: None,
+                location: None,
+                reclaim: None,
+            };
+            let _ = wss.tx.clone().try_send(serde_json::to_string(&solve).unwrap());
+        }
 
         log::debug!("Create function");
 
-        if let Ok(_) = wss.tx.clone().try_send(serde_json::to_string(&message).unwrap()) {
-            log::debug!("Message sent successfully!");
-        }
-
-        Self {
+        let settings: Settings = storage::load(Settings::STORAGE_KEY).unwrap_or_default();
+        motion::configure(settings.motion);
+        let send_limiter = SendLimiter::new(&settings);
+        let mut chat = Self {
             users: vec![],
             messages: vec![],
             chat_input: NodeRef::default(),
@@ -87,82 +1019,338 @@ impl Component for Chat {
             _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
             username: username.clone(),
             show_emoji_picker: false,
+            replying_to: None,
+            action_sheet: None,
+            loading_history: false,
+            history_exhausted: false,
+            pull_start: None,
+            pull_distance: 0,
+            scroll_anchor: None,
+            window_start: 0,
+            follow_tail: true,
+            saved_scroll: storage::load(&ScrollState::storage_key(ROOM)),
+            unread_after: None,
+            read_marker: None,
+            pinned_to_bottom: true,
+            scroll_to_bottom: false,
+            viewport_height: None,
+            _viewport: ViewportListener::new(ctx.link().callback(Msg::ViewportResized)),
+            _hotkeys: HotkeyListener::new(ctx.link().callback(Msg::Hotkey)),
+            sidebar: storage::load(SidebarState::STORAGE_KEY).unwrap_or_default(),
+            user_filter: String::new(),
+            favorites: storage::load(FAVORITES_KEY).unwrap_or_default(),
+            retry_timer: None,
+            last_seq: None,
+            connection: ConnectionStatus::Connecting,
+            was_connected: false,
+            highlighter: Highlighter::new(&username, &settings.keywords),
+            settings,
+            show_settings: false,
+            print_view: false,
+            signed_out: false,
+            side_panel: None,
+            mentions: MentionIndex::load(),
+            link_titles: HashMap::new(),
+            lightbox: None,
+            jump_to: None,
+            flash: None,
+            flash_timer: None,
+            seeking: None,
+            can_post: Permission::GRANTED,
+            show_invite: false,
+            signed_in: token.is_some(),
+            show_sessions: false,
+            show_server_info: false,
+            server_info: None,
+            sessions: None,
+            invite_token: None,
+            room_icon: None,
+            room_locked: false,
+            // Sent again if the room turns out to be locked, so a reload doesn't ask twice
+            room_key: LastConversation::load().and_then(|last| last.key_for(ROOM)),
+            show_room_password: false,
+            room_password_error: None,
+            joining_room: false,
+            // Until the server says otherwise, leave room settings to the staff
+            can_edit_room: Permission { allowed: false, audience: Audience::Moderators },
+            show_icon_picker: false,
+            confirming: None,
+            send_timers: HashMap::new(),
+            editing: None,
+            edit_history: None,
+            focused: None,
+            reveal: None,
+            announcer: Announcer::default(),
+            sent_history: Vec::new(),
+            recall: None,
+            show_palette: false,
+            show_shortcuts: false,
+            stats: UsageStats::load(),
+            show_stats: false,
+            voice: None,
+            voice_roster: Vec::new(),
+            voice_speaking: BTreeSet::new(),
+            voice_muted: false,
+            joining_voice: false,
+            _voice_meter: None,
+            call: None,
+            incoming_call: None,
+            placing_call: None,
+            call_timeout: None,
+            link_quality: LinkQuality::default(),
+            _ping_timer: {
+                let link = ctx.link().clone();
+                Interval::new(PING_INTERVAL_MS, move || link.send_message(Msg::Ping))
+            },
+            _update_timer: {
+                let link = ctx.link().clone();
+                Interval::new(UPDATE_CHECK_INTERVAL_MS, move || link.send_message(Msg::CheckForUpdate))
+            },
+            _history_listener: ctx.link().add_history_listener(ctx.link().callback(|_| Msg::Navigated)),
+            update: UpdateState::Current,
+            send_limiter,
+            cooldown_until: None,
+            cooldown_timer: None,
+            expanded_bursts: Vec::new(),
+            profile: storage::load(Profile::STORAGE_KEY).unwrap_or_default(),
+            profile_card: None,
+            draft: storage::load(&Draft::storage_key(ROOM)).unwrap_or_default(),
+            draft_timer: None,
+            leaving_to: None,
+            paste_cleaned: None,
+            composing_image: false,
+            mark_sensitive: false,
+            attachments: Vec::new(),
+            next_attachment: 0,
+            upload_limits: Limits::default(),
+            locating: false,
+            show_preview: false,
+            show_tips: onboarding::tips_pending(),
+            announcements: Vec::new(),
+        };
+
+        if let Some(marker) = storage::load::<ReadMarker>(&ReadMarker::storage_key(ROOM)) {
+            chat.mentions.mark_read_until(marker.time);
+            chat.read_marker = Some(marker);
+        }
+        chat.register();
+        {
+            let link = ctx.link().clone();
+            spawn_local(async move {
+                if let Some(limits) = Limits::fetch().await {
+                    link.send_message(Msg::UploadLimits(limits));
+                }
+            });
+        }
+        // Fill the room with recent conversation instead of starting empty
+        chat.request_history();
+        // Opened from a permalink; history is paged back until the message turns up
+        if let Some(link) = user.pending_permalink.borrow_mut().take() {
+            if link.room == ROOM {
+                chat.seeking = Some(link.id);
+            } else {
+                chat.messages.push(MessageData::system(format!("That link is to #{}, which isn't on this server", link.room)));
+            }
+        }
+        // Arrived through an invite link; the server checks it once we're registered
+        if let Some(token) = user.pending_invite.borrow_mut().take() {
+            chat.send(MsgTypes::JoinByToken, token);
         }
+        // Reloaded with a dialog open
+        chat.apply_overlay(ctx);
+        chat
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::HandleMsg(s) => {
-                let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
-                match msg.message_type {
-                    MsgTypes::Users => {
-                        let users_from_message = msg.data_array.unwrap_or_default();
-                        self.users = users_from_message
-                            .iter()
-                            .map(|u| UserProfile {
-                                name: u.into(),
-                                avatar: format!(
-                                    "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
-                                    u
-                                )
-                                .into(),
-                                online: true, // Assume all users are online for now
-                            })
-                            .collect();
+                if !self.pinned_to_bottom {
+                    // Whatever arrives, the message being read shouldn't move
+                    self.scroll_anchor = self.capture_anchor();
+                }
+                match self.handle_frame(ctx, &s) {
+                    Some(render) => render,
+                    None => {
+                        log::warn!("Ignoring malformed frame: {}", s);
+                        false
+                    }
+                }
+            }
+            Msg::SubmitMessage => {
+                let input = self.chat_input.cast::<HtmlInputElement>();
+                if let Some(input) = input {
+                    let message_text = input.value();
+                    if let Some(index) = self.editing.take() {
+                        let text = message_text.trim();
+                        if let Some(m) = self.messages.get(index).filter(|m| !text.is_empty() && m.message != text) {
+                            let edit = EditData {
+                                from: self.username.clone(),
+                                target: m.to_ref(),
+                                message: text.to_string(),
+                            };
+                            self.send(MsgTypes::Edit, serde_json::to_string(&edit).unwrap());
+                        }
+                        input.set_value("");
                         return true;
                     }
-                    MsgTypes::Message => {
-                        let message_data: MessageData = serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
-                        
-                        // Auto-scroll to bottom when new message arrives
-                        // Using web_sys directly instead of gloo_utils
-                        if let Some(window) = web_sys::window() {
-                            if let Some(document) = window.document() {
-                                if let Some(element) = document.get_element_by_id("message-container") {
-                                    if let Ok(element) = element.dyn_into::<web_sys::HtmlElement>() {
-                                        element.set_scroll_top(element.scroll_height());
-                                    }
-                                }
+                    let command = message_text.trim();
+                    if let Some(new_name) = command.strip_prefix("/nick ").or_else(|| (command == "/nick").then_some("")) {
+                        let new_name = new_name.trim();
+                        self.remember_sent(command);
+                        match validate_username(new_name) {
+                            Ok(()) if new_name != self.username => {
+                                let rename = RenameData {
+                                    from: self.username.clone(),
+                                    to: new_name.to_string(),
+                                };
+                                self.send(MsgTypes::Rename, serde_json::to_string(&rename).unwrap());
                             }
+                            Ok(()) => {}
+                            Err(e) => self.messages.push(MessageData::system(format!("Can't rename: {}", e))),
                         }
-                            
+                        input.set_value("");
+                        self.set_draft(ctx, String::new());
                         return true;
                     }
-                    _ => {
+                    if let Some((level, text)) = announce_command(command) {
+                        self.remember_sent(command);
+                        if self.own_role() == Role::Owner {
+                            let request = AnnounceRequest { text: text.to_string(), level };
+                            self.send(MsgTypes::Announce, serde_json::to_string(&request).unwrap());
+                        } else {
+                            self.messages.push(MessageData::system("Only room owners can make announcements".into()));
+                        }
+                        input.set_value("");
+                        self.set_draft(ctx, String::new());
+                        return true;
+                    }
+                    if !self.can_post.allowed {
                         return false;
                     }
-                }
-            }
-            Msg::SubmitMessage => {
-                let input = self.chat_input.cast::<HtmlInputElement>();
-                if let Some(input) = input {
-                    let message_text = input.value();
                     if !message_text.trim().is_empty() {
-                        let message = WebSocketMessage {
-                            message_type: MsgTypes::Message,
-                            data: Some(message_text),
-                            data_array: None,
+                        let now = js_sys::Date::now();
+                        if let Err(wait) = self.send_limiter.acquire(now) {
+                            // Keep the text so it can go out once the cooldown is over
+                            self.cooldown_until = Some(now + wait);
+                            let link = ctx.link().clone();
+                            self.cooldown_timer = Some(Interval::new(250, move || link.send_message(Msg::CooldownTick)));
+                            return true;
+                        }
+                        self.remember_sent(&message_text);
+                        // Sending is a user gesture, which is what the permission prompt needs
+                        notify::request_permission();
+                        let reply_to = self
+                            .replying_to
+                            .take()
+                            .and_then(|i| self.messages.get(i))
+                            .map(|m| ReplyPreview {
+                                from: m.from.clone(),
+                                message: m.message.chars().take(100).collect(),
+                            });
+                        let media = (self.mark_sensitive && is_image_url(&message_text)).then(|| MediaMeta {
+                            sensitive: true,
+                            ..Default::default()
+                        });
+                        self.mark_sensitive = false;
+                        self.composing_image = false;
+                        // Show the message right away; it's reconciled when the server echoes it
+                        let mut message = MessageData {
+                            from: self.username.clone(),
+                            message: message_text,
+                            reply_to,
+                            client_id: Some(new_client_id()),
+                            media,
+                            status: DeliveryStatus::Sending,
+                            send_attempts: 1,
+                            ..Default::default()
                         };
-                        if let Err(e) = self.wss.tx.clone().try_send(serde_json::to_string(&message).unwrap()) {
-                            log::debug!("Error sending to channel: {:?}", e);
+                        let delay_ms = self.settings.send_delay_secs * 1_000;
+                        if delay_ms > 0 {
+                            message.status = DeliveryStatus::Scheduled;
+                            message.send_at = Some(js_sys::Date::now() + f64::from(delay_ms));
+                            let client_id = message.client_id.clone().unwrap_or_default();
+                            let link = ctx.link().clone();
+                            let id = client_id.clone();
+                            let timer = Timeout::new(delay_ms, move || link.send_message(Msg::SendScheduled(id)));
+                            self.send_timers.insert(client_id, timer);
+                            self.messages.push(message);
+                        } else if !self.send_chat(&message) {
+                            // Keep the text around so it can be retried instead of silently vanishing
+                            message.status = DeliveryStatus::Failed;
+                            self.messages.push(message);
+                            self.schedule_retry(ctx);
+                        } else {
+                            self.messages.push(message);
                         }
+                        self.follow_tail = true;
+                        self.scroll_to_bottom = true;
+                        self.unread_after = None;
                         input.set_value("");
+                        self.set_draft(ctx, String::new());
+                        return true;
                     }
                 }
                 false
             }
             Msg::InputKeyPress(event) => {
-                if event.key() == "Enter" && !event.shift_key() {
+                if hotkeys::SEND.matches(&event) && !event.shift_key() {
                     event.prevent_default();
                     ctx.link().send_message(Msg::SubmitMessage);
                 }
                 false
             }
+            Msg::InputKeyDown(event) => {
+                let input = match self.chat_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                if self.editing.is_some() || event.shift_key() || event.ctrl_key() || event.alt_key() || event.meta_key() {
+                    return false;
+                }
+                if hotkeys::EDIT_LAST.matches(&event) && self.recall.is_none() && input.value().is_empty() {
+                    // An empty composer edits the last message instead
+                    let last_own = self
+                        .messages
+                        .iter()
+                        .rposition(|m| m.from == self.username && m.status == DeliveryStatus::Sent && !m.deleted && !m.system);
+                    if let Some(index) = last_own {
+                        event.prevent_default();
+                        ctx.link().send_message(Msg::StartEdit(index));
+                    }
+                } else if hotkeys::OLDER_SENT.matches(&event) && !self.sent_history.is_empty() {
+                    event.prevent_default();
+                    let (position, draft) = match self.recall.take() {
+                        Some((position, draft)) => (position.saturating_sub(1), draft),
+                        None => (self.sent_history.len() - 1, input.value()),
+                    };
+                    input.set_value(&self.sent_history[position]);
+                    self.recall = Some((position, draft));
+                } else if hotkeys::NEWER_SENT.matches(&event) {
+                    if let Some((position, draft)) = self.recall.take() {
+                        event.prevent_default();
+                        if position + 1 < self.sent_history.len() {
+                            input.set_value(&self.sent_history[position + 1]);
+                            self.recall = Some((position + 1, draft));
+                        } else {
+                            // Past the newest entry: back to what was being typed
+                            input.set_value(&draft);
+                        }
+                    }
+                }
+                false
+            }
             Msg::ToggleEmojiPicker => {
                 self.show_emoji_picker = !self.show_emoji_picker;
                 true
             }
+            Msg::CloseEmojiPicker => {
+                self.show_emoji_picker = false;
+                // Hand focus back to where the user was typing
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    input.focus().ok();
+                }
+                true
+            }
             Msg::InsertEmoji(emoji) => {
                 if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
                     let current_value = input.value();
@@ -172,26 +1360,1137 @@ impl Component for Chat {
                 self.show_emoji_picker = false;
                 true
             }
-        }
-    }
-
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
-        let on_keypress = ctx.link().callback(Msg::InputKeyPress);
-        let toggle_emoji = ctx.link().callback(|_| Msg::ToggleEmojiPicker);
-        
-        // Group users by online status
-        let online_users: Vec<_> = self.users.iter().filter(|u| u.online).collect();
-        let offline_users: Vec<_> = self.users.iter().filter(|u| !u.online).collect();
-
-        html! {
-            <div class="flex w-screen h-screen bg-gray-50">
-                // Sidebar with users
-                <div class="flex-none w-64 h-screen bg-white shadow-md">
-                    <div class="flex items-center justify-between p-4 border-b">
+            Msg::StartReply(index) => {
+                self.action_sheet = None;
+                if !self.can_post.allowed {
+                    return true;
+                }
+                self.replying_to = Some(index);
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    input.focus().ok();
+                }
+                true
+            }
+            Msg::StartEdit(index) => {
+                self.action_sheet = None;
+                let editable = self
+                    .messages
+                    .get(index)
+                    .map_or(false, |m| m.from == self.username && m.status == DeliveryStatus::Sent && !m.deleted);
+                if !editable || !self.can_post.allowed {
+                    return true;
+                }
+                self.editing = Some(index);
+                self.replying_to = None;
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    input.set_value(&self.messages[index].message);
+                    input.focus().ok();
+                }
+                true
+            }
+            Msg::CancelEdit => {
+                self.editing = None;
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    input.set_value("");
+                }
+                true
+            }
+            Msg::ShowEdits(index) => {
+                self.edit_history = index;
+                true
+            }
+            Msg::FlushAnnouncements => {
+                self.announcer.flush();
+                true
+            }
+            Msg::Hotkey(e) => {
+                if self.print_view {
+                    return false;
+                }
+                if hotkeys::COMMAND_PALETTE.matches(&e) {
+                    // Browsers bind Ctrl+K to the address bar search
+                    e.prevent_default();
+                    self.show_palette = !self.show_palette;
+                    return true;
+                }
+                if hotkeys::CHEAT_SHEET.matches(&e) && !hotkeys::is_typing(&e) {
+                    e.prevent_default();
+                    self.show_shortcuts = !self.show_shortcuts;
+                    return true;
+                }
+                false
+            }
+            Msg::ShowShortcuts(show) => {
+                self.show_shortcuts = show;
+                true
+            }
+            Msg::ShowStats(show) => {
+                self.show_stats = show;
+                true
+            }
+            Msg::ClearStats => {
+                UsageStats::forget();
+                self.stats = UsageStats::default();
+                true
+            }
+            Msg::JoinVoice => {
+                if self.voice.is_some() || self.joining_voice {
+                    return false;
+                }
+                self.joining_voice = true;
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    link.send_message(Msg::MicrophoneReady(rtc::user_media(false).await));
+                });
+                true
+            }
+            Msg::MicrophoneReady(microphone) => {
+                self.joining_voice = false;
+                match microphone {
+                    Ok(microphone) => {
+                        let mut voice = Voice::new(&self.username, microphone, ctx.link().callback(Msg::SendSignal));
+                        voice.set_muted(self.voice_muted);
+                        voice.sync(&self.voice_roster);
+                        self.voice = Some(voice);
+                        self.send_voice_update();
+                        let link = ctx.link().clone();
+                        self._voice_meter = Some(Interval::new(VOICE_METER_INTERVAL_MS, move || link.send_message(Msg::MeasureVoice)));
+                        self.announce(ctx, "Joined voice".into());
+                    }
+                    Err(e) => self.messages.push(MessageData::system(format!("Can't join voice: {}", e))),
+                }
+                true
+            }
+            Msg::LeaveVoice => {
+                if self.voice.take().is_some() {
+                    self._voice_meter = None;
+                    self.voice_speaking.clear();
+                    self.send_voice_update();
+                    self.announce(ctx, "Left voice".into());
+                }
+                true
+            }
+            Msg::ToggleVoiceMute => {
+                self.voice_muted = !self.voice_muted;
+                if let Some(voice) = &self.voice {
+                    voice.set_muted(self.voice_muted);
+                    self.send_voice_update();
+                }
+                self.announce(ctx, if self.voice_muted { "Microphone muted" } else { "Microphone on" }.into());
+                true
+            }
+            Msg::SendSignal(signal) => {
+                self.send(MsgTypes::Signal, serde_json::to_string(&signal).unwrap());
+                false
+            }
+            Msg::MeasureVoice => {
+                let speaking = self.voice.as_ref().map(Voice::speaking).unwrap_or_default();
+                if speaking == self.voice_speaking {
+                    return false;
+                }
+                self.voice_speaking = speaking;
+                true
+            }
+            Msg::StartCall(peer) => {
+                self.show_palette = false;
+                if self.profile_card.is_some() {
+                    self.navigate(ctx, Overlay::default());
+                }
+                if self.call.is_some() || self.placing_call.is_some() || peer == self.username {
+                    return true;
+                }
+                self.placing_call = Some(peer);
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    link.send_message(Msg::CallMedia(rtc::user_media(true).await));
+                });
+                true
+            }
+            Msg::AcceptCall => {
+                if let Some(incoming) = &self.incoming_call {
+                    self.placing_call = Some(incoming.from.clone());
+                    let link = ctx.link().clone();
+                    spawn_local(async move {
+                        link.send_message(Msg::CallMedia(rtc::user_media(true).await));
+                    });
+                }
+                true
+            }
+            Msg::CallMedia(media) => {
+                // The other side may have hung up while the camera prompt was open
+                let peer = match self.placing_call.take() {
+                    Some(peer) => peer,
+                    None => {
+                        if let Ok(stream) = &media {
+                            rtc::stop(stream);
+                        }
+                        return true;
+                    }
+                };
+                let local = match media {
+                    Ok(local) => local,
+                    Err(e) => {
+                        if let Some(incoming) = self.incoming_call.take() {
+                            self.send_call_signal(&incoming.from, CallPayload::Decline);
+                        }
+                        self.messages.push(MessageData::system(format!("Can't start a video call: {}", e)));
+                        return true;
+                    }
+                };
+                let on_signal = ctx.link().callback(Msg::SendCallSignal);
+                let on_remote = ctx.link().callback(Msg::RemoteStream);
+                let call = match self.incoming_call.take() {
+                    Some(incoming) if incoming.from == peer => Call::accept(incoming, local, on_signal, on_remote),
+                    _ => {
+                        let link = ctx.link().clone();
+                        self.call_timeout = Some(Timeout::new(CALL_RING_TIMEOUT_MS, move || link.send_message(Msg::CallTimeout)));
+                        Call::start(&peer, local, on_signal, on_remote)
+                    }
+                };
+                match call {
+                    Ok(call) => {
+                        self.announce(ctx, format!("Video call with {}", peer));
+                        self.call = Some(call);
+                    }
+                    Err(e) => {
+                        self.call_timeout = None;
+                        self.messages.push(MessageData::system(format!("Can't start a video call: {}", e)));
+                    }
+                }
+                true
+            }
+            Msg::DeclineCall => {
+                if let Some(incoming) = self.incoming_call.take() {
+                    self.send_call_signal(&incoming.from, CallPayload::Decline);
+                    self.messages.push(MessageData::system(format!("Declined a video call from {}", incoming.from)));
+                }
+                true
+            }
+            Msg::HangUp => {
+                if let Some(call) = &self.call {
+                    let peer = call.peer.clone();
+                    self.send_call_signal(&peer, CallPayload::HangUp);
+                    self.end_call(ctx, format!("Cancelled video call to {}", peer));
+                }
+                true
+            }
+            Msg::CallTimeout => {
+                if let Some(call) = self.call.as_ref().filter(|call| call.started.is_none()) {
+                    let peer = call.peer.clone();
+                    self.send_call_signal(&peer, CallPayload::HangUp);
+                    self.end_call(ctx, format!("{} didn't answer", peer));
+                }
+                true
+            }
+            Msg::SendCallSignal(signal) => {
+                self.send(MsgTypes::Call, serde_json::to_string(&signal).unwrap());
+                false
+            }
+            Msg::RemoteStream(stream) => {
+                if let Some(call) = &mut self.call {
+                    call.remote = Some(stream);
+                }
+                true
+            }
+            Msg::ToggleCallMute => {
+                if let Some(call) = &mut self.call {
+                    call.set_muted(!call.muted);
+                }
+                true
+            }
+            Msg::ToggleCamera => {
+                if let Some(call) = &mut self.call {
+                    call.set_camera_off(!call.camera_off);
+                }
+                true
+            }
+            Msg::ShowPalette(show) => {
+                self.show_palette = show;
+                true
+            }
+            Msg::ToggleBurst(start) => {
+                match self.expanded_bursts.iter().position(|r| *r == start) {
+                    Some(i) => {
+                        self.expanded_bursts.remove(i);
+                    }
+                    None => self.expanded_bursts.push(start),
+                }
+                true
+            }
+            Msg::CooldownTick => {
+                if self.cooldown_until.map_or(true, |until| js_sys::Date::now() >= until) {
+                    self.cooldown_until = None;
+                    self.cooldown_timer = None;
+                }
+                true
+            }
+            Msg::Ping => {
+                let now = js_sys::Date::now();
+                let changed = self.link_quality.expire(now);
+                if self.connection == ConnectionStatus::Connected {
+                    let id = self.link_quality.ping(now);
+                    self.send(MsgTypes::Ping, id);
+                }
+                changed
+            }
+            Msg::MessageListKey(e) => {
+                if e.ctrl_key() || e.meta_key() || e.alt_key() {
+                    return false;
+                }
+                let steps = [
+                    (hotkeys::PREVIOUS_MESSAGE, -1),
+                    (hotkeys::NEXT_MESSAGE, 1),
+                    (hotkeys::PAGE_UP, -FOCUS_PAGE),
+                    (hotkeys::PAGE_DOWN, FOCUS_PAGE),
+                    (hotkeys::FIRST_MESSAGE, isize::MIN),
+                    (hotkeys::LAST_MESSAGE, isize::MAX),
+                ];
+                let step = steps.iter().find(|(shortcut, _)| shortcut.matches(&e)).map(|(_, step)| *step);
+                if let Some(step) = step {
+                    e.prevent_default();
+                    if let Some(index) = self.step_focus(step) {
+                        self.focus_message(index);
+                    }
+                    return true;
+                }
+                let index = match self.focused {
+                    Some(index) => index,
+                    None => return false,
+                };
+                let action = if hotkeys::REPLY.matches(&e) {
+                    Msg::StartReply(index)
+                } else if hotkeys::REACT.matches(&e) {
+                    Msg::OpenActions(index)
+                } else if hotkeys::COPY.matches(&e) {
+                    Msg::CopyMessage(index)
+                } else if hotkeys::DESELECT.matches(&e) {
+                    self.focused = None;
+                    return true;
+                } else {
+                    return false;
+                };
+                e.prevent_default();
+                ctx.link().send_message(action);
+                false
+            }
+            Msg::CancelReply => {
+                self.replying_to = None;
+                true
+            }
+            Msg::OpenActions(index) => {
+                self.action_sheet = Some(index);
+                true
+            }
+            Msg::CloseActions => {
+                self.action_sheet = None;
+                true
+            }
+            Msg::React(index, emoji) => {
+                self.action_sheet = None;
+                if let Some(m) = self.messages.get(index) {
+                    let reaction = ReactionData {
+                        from: self.username.clone(),
+                        target: m.to_ref(),
+                        emoji,
+                    };
+                    self.send(MsgTypes::Reaction, serde_json::to_string(&reaction).unwrap());
+                }
+                true
+            }
+            Msg::MentionUser(name) => {
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    let current = input.value();
+                    let separator = if current.is_empty() || current.ends_with(' ') { "" } else { " " };
+                    input.set_value(&format!("{}{}@{} ", current, separator, name));
+                    input.focus().ok();
+                }
+                false
+            }
+            Msg::CopyName(name) => {
+                clipboard::copy_text(&name);
+                false
+            }
+            Msg::CopyMessage(index) => {
+                self.action_sheet = None;
+                if let Some(m) = self.messages.get(index) {
+                    clipboard::copy_text(&m.message);
+                }
+                true
+            }
+            Msg::CopyLink(index) => {
+                self.action_sheet = None;
+                if let Some(id) = self.messages.get(index).and_then(|m| m.id.as_deref()) {
+                    clipboard::copy_text(&permalink_url(ROOM, id));
+                    self.announce(ctx, "Link copied".into());
+                }
+                true
+            }
+            Msg::DeleteMessage(index) => {
+                self.action_sheet = None;
+                self.ask(ctx, PendingAction::DeleteMessage(index));
+                true
+            }
+            Msg::LoadOlder => {
+                if self.loading_history || self.history_exhausted {
+                    return false;
+                }
+                self.request_history();
+                true
+            }
+            Msg::MessagesScrolled => {
+                let c = match Self::message_container() {
+                    Some(c) => c,
+                    None => return false,
+                };
+                let range = self.visible_range();
+                let len = self.messages.len();
+                let top = c.scroll_top();
+                let bottom_gap = c.scroll_height() - top - c.client_height();
+                self.pinned_to_bottom = bottom_gap <= 20 && range.end == len;
+                if self.pinned_to_bottom {
+                    self.follow_tail = true;
+                    self.mark_read();
+                }
+
+                if top < WINDOW_EDGE && range.start > 0 {
+                    // Bring back older messages kept in memory and drop some from the bottom
+                    self.scroll_anchor = self.capture_anchor();
+                    self.follow_tail = false;
+                    self.window_start = range.start.saturating_sub(WINDOW_STEP);
+                    return true;
+                }
+                if bottom_gap < WINDOW_EDGE && range.end < len {
+                    self.scroll_anchor = self.capture_anchor();
+                    self.window_start = range.start + WINDOW_STEP;
+                    self.follow_tail = self.window_start + WINDOW_SIZE >= len;
+                    return true;
+                }
+                // Desktop pagination: reaching the top of the list pulls in the previous page
+                if top <= 0 {
+                    ctx.link().send_message(Msg::LoadOlder);
+                }
+                false
+            }
+            Msg::JumpToLatest => {
+                self.follow_tail = true;
+                self.scroll_to_bottom = true;
+                true
+            }
+            Msg::PullStart(e) => {
+                let at_top = Self::message_container().map_or(false, |c| c.scroll_top() <= 0);
+                self.pull_start = e.touches().get(0).filter(|_| at_top).map(|t| t.client_y());
+                false
+            }
+            Msg::PullMove(e) => {
+                if let (Some(start), Some(touch)) = (self.pull_start, e.touches().get(0)) {
+                    // Dampen the pull so it feels elastic rather than 1:1 with the finger
+                    self.pull_distance = ((touch.client_y() - start) / 2).clamp(0, PULL_MAX);
+                    return true;
+                }
+                false
+            }
+            Msg::PullEnd => {
+                if self.pull_distance >= PULL_TRIGGER {
+                    ctx.link().send_message(Msg::LoadOlder);
+                }
+                self.pull_start = None;
+                self.pull_distance = 0;
+                true
+            }
+            Msg::ViewportResized(height) => {
+                let full_height = web_sys::window()
+                    .and_then(|w| w.inner_height().ok())
+                    .and_then(|h| h.as_f64())
+                    .unwrap_or(height);
+                // Only constrain the layout while something (the keyboard) covers part of the page
+                let viewport_height = if height < full_height - 1.0 { Some(height) } else { None };
+                if viewport_height == self.viewport_height {
+                    return false;
+                }
+                self.viewport_height = viewport_height;
+                // Keep the latest messages in view when the keyboard opens or closes
+                self.scroll_to_bottom = self.pinned_to_bottom;
+                true
+            }
+            Msg::ToggleSection(section) => {
+                self.sidebar.toggle(section);
+                storage::save(SidebarState::STORAGE_KEY, &self.sidebar);
+                true
+            }
+            Msg::FilterUsers(filter) => {
+                self.user_filter = filter;
+                true
+            }
+            Msg::ToggleFavorite(name) => {
+                if !self.favorites.remove(&name) {
+                    self.favorites.insert(name);
+                }
+                storage::save(FAVORITES_KEY, &self.favorites);
+                true
+            }
+            Msg::RetrySend(index) => {
+                let sent = match self.messages.get(index) {
+                    Some(m) if m.status == DeliveryStatus::Failed => self.send_chat(m),
+                    _ => return false,
+                };
+                let m = &mut self.messages[index];
+                m.send_attempts += 1;
+                if sent {
+                    m.status = DeliveryStatus::Sending;
+                }
+                true
+            }
+            Msg::RetryFailed => {
+                self.retry_timer = None;
+                let pending: Vec<usize> = self
+                    .messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| m.status == DeliveryStatus::Failed && m.send_attempts < MAX_SEND_ATTEMPTS)
+                    .map(|(i, _)| i)
+                    .collect();
+                for index in pending {
+                    ctx.link().send_message(Msg::RetrySend(index));
+                }
+                ctx.link().send_message(Msg::ScheduleRetry);
+                false
+            }
+            Msg::ScheduleRetry => {
+                self.schedule_retry(ctx);
+                false
+            }
+            Msg::ConnectionChanged(status) => {
+                let reconnected = status == ConnectionStatus::Connected && self.was_connected;
+                let dropped = status != ConnectionStatus::Connected && self.connection == ConnectionStatus::Connected;
+                self.connection = status;
+                match status {
+                    ConnectionStatus::Connected => {
+                        if reconnected {
+                            self.announce(ctx, "Reconnected".into());
+                            // A dropped connection is often the server being redeployed, clients included
+                            ctx.link().send_message(Msg::CheckForUpdate);
+                            // The server forgot us with the old socket: register again and
+                            // ask only for what we missed
+                            self.register();
+                            self.catch_up();
+                        }
+                        self.was_connected = true;
+                        self.schedule_retry(ctx);
+                    }
+                    ConnectionStatus::Connecting | ConnectionStatus::Reconnecting | ConnectionStatus::Unreachable => {
+                        if dropped {
+                            self.announce(ctx, "Connection lost, reconnecting".into());
+                        }
+                        // Round trips on the old socket say nothing about the next one
+                        self.link_quality.reset();
+                        // Anything still in flight may have been lost with the old socket
+                        for m in self.messages.iter_mut().filter(|m| m.status == DeliveryStatus::Sending) {
+                            m.status = DeliveryStatus::Failed;
+                        }
+                    }
+                }
+                true
+            }
+            Msg::DiscardFailed(index) => {
+                self.ask(ctx, PendingAction::DiscardFailed(index));
+                true
+            }
+            Msg::SendScheduled(client_id) => {
+                self.send_timers.remove(&client_id);
+                let index = match self
+                    .messages
+                    .iter()
+                    .position(|m| m.status == DeliveryStatus::Scheduled && m.client_id.as_ref() == Some(&client_id))
+                {
+                    Some(index) => index,
+                    // Undone in the meantime
+                    None => return false,
+                };
+                let sent = self.send_chat(&self.messages[index]);
+                let m = &mut self.messages[index];
+                m.send_at = None;
+                if sent {
+                    m.status = DeliveryStatus::Sending;
+                } else {
+                    m.status = DeliveryStatus::Failed;
+                    self.schedule_retry(ctx);
+                }
+                true
+            }
+            Msg::UndoSend(index) => {
+                if self.messages.get(index).map_or(true, |m| m.status != DeliveryStatus::Scheduled) {
+                    return false;
+                }
+                let m = self.remove_message(index);
+                if let Some(id) = &m.client_id {
+                    self.send_timers.remove(id);
+                }
+                // Back into the composer for another go
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    input.set_value(&m.message);
+                    input.focus().ok();
+                }
+                true
+            }
+            Msg::Confirmed => {
+                if let Some(action) = self.confirming.take() {
+                    self.perform(ctx, action);
+                }
+                true
+            }
+            Msg::CancelConfirm => {
+                self.confirming = None;
+                true
+            }
+            Msg::ToggleIconPicker => {
+                self.show_icon_picker = !self.show_icon_picker && self.can_edit_room.allowed;
+                true
+            }
+            Msg::SetRoomIcon(icon) => {
+                self.show_icon_picker = false;
+                self.send(MsgTypes::RoomIcon, icon.unwrap_or_default());
+                true
+            }
+            Msg::ToggleInvite => {
+                self.show_invite = !self.show_invite;
+                if self.show_invite && self.invite_token.is_none() {
+                    self.send(MsgTypes::CreateInvite, String::new());
+                }
+                true
+            }
+            Msg::ToggleSessions => {
+                self.show_sessions = !self.show_sessions;
+                if self.show_sessions {
+                    self.send(MsgTypes::Sessions, String::new());
+                }
+                true
+            }
+            Msg::ToggleServerInfo => {
+                self.show_server_info = !self.show_server_info;
+                if self.show_server_info {
+                    self.send(MsgTypes::ServerInfo, String::new());
+                }
+                true
+            }
+            Msg::SignOutSession(id) => {
+                self.send(MsgTypes::SignOut, id);
+                false
+            }
+            Msg::ShowRoomPassword(show) => {
+                self.show_room_password = show;
+                true
+            }
+            Msg::SubmitRoomPassword(key) => {
+                self.join_room(key);
+                true
+            }
+            Msg::LogOut => {
+                if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+                    if let Some(session) = user.session.borrow_mut().take() {
+                        spawn_local(account::revoke(session.token));
+                    }
+                }
+                self.sign_out(ctx);
+                false
+            }
+            Msg::ShowProfile(name) => {
+                self.navigate(ctx, Overlay { profile: name, ..Default::default() });
+                true
+            }
+            Msg::SaveProfile(profile) => {
+                storage::save(Profile::STORAGE_KEY, &profile);
+                self.profile = profile;
+                self.share_profile();
+                true
+            }
+            Msg::ToggleSettings => {
+                self.navigate(ctx, Overlay { settings: !self.show_settings, ..Default::default() });
+                true
+            }
+            Msg::Navigated => self.apply_overlay(ctx),
+            Msg::ResetSettings => {
+                self.ask(ctx, PendingAction::ResetSettings);
+                true
+            }
+            Msg::TogglePrintView => {
+                if self.print_view {
+                    // The message list is re-created, so put the reader back where they were
+                    self.print_view = false;
+                    match self.saved_scroll.take() {
+                        Some(state) => self.restore_scroll(state),
+                        None => {
+                            self.follow_tail = true;
+                            self.scroll_to_bottom = true;
+                        }
+                    }
+                } else {
+                    self.saved_scroll = Some(self.save_scroll());
+                    self.print_view = true;
+                }
+                true
+            }
+            Msg::ShowPanel(tab) => {
+                self.side_panel = tab;
+                if tab == Some(PanelTab::Links) {
+                    self.request_link_titles();
+                }
+                true
+            }
+            Msg::OpenLightbox(index) => {
+                self.lightbox = index;
+                true
+            }
+            Msg::JumpToMessage(index) => {
+                if !self.visible_range().contains(&index) {
+                    self.follow_tail = false;
+                    self.window_start = index.saturating_sub(WINDOW_SIZE / 2);
+                }
+                self.lightbox = None;
+                self.jump_to = Some(index);
+                self.flash = Some(index);
+                let link = ctx.link().clone();
+                self.flash_timer = Some(Timeout::new(FLASH_MS, move || link.send_message(Msg::ClearFlash)));
+                true
+            }
+            Msg::OpenMention(target) => {
+                self.mentions.mark_read(&target);
+                match self.messages.iter().rposition(|m| target.matches(m)) {
+                    Some(index) => ctx.link().send_message(Msg::JumpToMessage(index)),
+                    None => log::debug!("Mentioned message is no longer loaded"),
+                }
+                true
+            }
+            Msg::MarkMentionsRead => {
+                self.mentions.mark_all_read();
+                true
+            }
+            Msg::ClearFlash => {
+                self.flash = None;
+                self.flash_timer = None;
+                true
+            }
+            Msg::DraftChanged => {
+                let input = match self.chat_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                if self.editing.is_none() {
+                    self.set_draft(ctx, input.value());
+                }
+                let composing_image = is_image_url(input.value().trim());
+                let changed = composing_image != self.composing_image;
+                self.composing_image = composing_image;
+                // The note about a cleaned paste goes once the user types on
+                self.paste_cleaned.take().is_some() || changed || self.show_preview
+            }
+            Msg::ToggleSensitive => {
+                self.mark_sensitive = !self.mark_sensitive;
+                for attachment in &mut self.attachments {
+                    attachment.sensitive = self.mark_sensitive;
+                }
+                true
+            }
+            Msg::AttachFiles(event) => {
+                let input: HtmlInputElement = event.target_unchecked_into();
+                let files = input.files();
+                // Picking the same file again should still count as a change
+                input.set_value("");
+                if !self.can_post.allowed {
+                    return false;
+                }
+                if let Some(files) = files {
+                    for file in (0..files.length()).filter_map(|i| files.get(i)) {
+                        let id = self.next_attachment;
+                        self.next_attachment += 1;
+                        // Shown in place of the progress bar, with nothing sent
+                        let refused = self.upload_limits.check(&file).err();
+                        let rejected = refused.is_some();
+                        let strip = !rejected && self.settings.strip_metadata && metadata::strippable(&file);
+                        if strip {
+                            let link = ctx.link().clone();
+                            let file = file.clone();
+                            spawn_local(async move {
+                                let stripped = metadata::strip(&file).await;
+                                link.send_message(Msg::AttachmentPrepared(id, stripped));
+                            });
+                        }
+                        self.attachments.push(Attachment {
+                            id,
+                            file,
+                            sent: 0.0,
+                            upload: None,
+                            error: refused,
+                            rejected,
+                            preparing: strip,
+                            metadata_removed: false,
+                            sensitive: self.mark_sensitive,
+                        });
+                        if !rejected && !strip {
+                            self.start_upload(ctx, id);
+                        }
+                    }
+                }
+                true
+            }
+            Msg::UploadEvent(id, event) => {
+                let index = match self.attachments.iter().position(|a| a.id == id) {
+                    Some(index) => index,
+                    // Cancelled while the event was on its way
+                    None => return false,
+                };
+                match event {
+                    UploadEvent::Progress { loaded, .. } => self.attachments[index].sent = loaded,
+                    UploadEvent::Done(url) => {
+                        let attachment = self.attachments.remove(index);
+                        self.post_attachment(ctx, url, attachment.sensitive);
+                        if self.attachments.is_empty() && !self.composing_image {
+                            self.mark_sensitive = false;
+                        }
+                    }
+                    UploadEvent::Failed(reason) => {
+                        let attachment = &mut self.attachments[index];
+                        attachment.upload = None;
+                        attachment.error = Some(reason);
+                    }
+                }
+                true
+            }
+            Msg::CancelUpload(id) => {
+                self.attachments.retain(|a| a.id != id);
+                true
+            }
+            Msg::RetryUpload(id) => {
+                self.start_upload(ctx, id);
+                true
+            }
+            Msg::AttachmentPrepared(id, stripped) => {
+                let limits = &self.upload_limits;
+                let attachment = match self.attachments.iter_mut().find(|a| a.id == id) {
+                    Some(attachment) => attachment,
+                    None => return false,
+                };
+                attachment.preparing = false;
+                // Never sent with its metadata when that was asked to go
+                let checked = stripped
+                    .ok_or_else(|| "Couldn't remove the photo's metadata".to_string())
+                    .and_then(|file| limits.check(&file).map(|()| file));
+                match checked {
+                    Ok(file) => {
+                        attachment.file = file;
+                        attachment.metadata_removed = true;
+                        self.start_upload(ctx, id);
+                    }
+                    Err(reason) => {
+                        attachment.error = Some(reason);
+                        attachment.rejected = true;
+                    }
+                }
+                true
+            }
+            Msg::Format(format) => {
+                let input = match self.chat_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                let start = input.selection_start().ok().flatten().unwrap_or(0);
+                let end = input.selection_end().ok().flatten().unwrap_or(start);
+                let (text, start, end) = format_toolbar::apply(format, &input.value(), start, end);
+                input.set_value(&text);
+                let _ = input.focus();
+                let _ = input.set_selection_range(start, end);
+                // Same as typing it
+                ctx.link().send_message(Msg::DraftChanged);
+                false
+            }
+            Msg::RetryConnection => {
+                let (user, _) = ctx
+                    .link()
+                    .context::<User>(Callback::noop())
+                    .expect("Context to be set");
+                let token = user.session.borrow().as_ref().map(|s| s.token.clone());
+                // Websockets get another chance, and the old connection stops once it's dropped
+                websocket::forget_fallback();
+                self.wss = WebsocketService::new(token.as_deref(), ctx.link().callback(Msg::ConnectionChanged));
+                self.connection = ConnectionStatus::Connecting;
+                // What was queued went with the old connection
+                self.register();
+                self.request_history();
+                true
+            }
+            Msg::DismissTips => {
+                self.show_tips = false;
+                true
+            }
+            Msg::CheckForUpdate => {
+                if self.update == UpdateState::Current {
+                    let link = ctx.link().clone();
+                    spawn_local(async move {
+                        if version::update_available().await {
+                            link.send_message(Msg::UpdateAvailable);
+                        }
+                    });
+                }
+                false
+            }
+            Msg::UpdateAvailable => {
+                if self.update != UpdateState::Current {
+                    return false;
+                }
+                self.update = UpdateState::Available;
+                self.announce(ctx, "A new version is available".into());
+                true
+            }
+            Msg::DismissUpdate => {
+                self.update = UpdateState::Dismissed;
+                true
+            }
+            Msg::DismissAnnouncement(id) => {
+                announcement::dismiss(&id);
+                self.announcements.retain(|a| a.id != id);
+                true
+            }
+            Msg::TogglePreview => {
+                self.show_preview = !self.show_preview;
+                true
+            }
+            Msg::ShareLocation => {
+                self.show_palette = false;
+                if self.locating || self.post_denied().is_some() {
+                    return true;
+                }
+                self.locating = true;
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    link.send_message(Msg::LocationReady(geolocation::current_position().await));
+                });
+                true
+            }
+            Msg::LocationReady(position) => {
+                self.locating = false;
+                match position {
+                    Ok(location) => {
+                        let message = MessageData {
+                            from: self.username.clone(),
+                            message: location.message_text(),
+                            client_id: Some(new_client_id()),
+                            location: Some(location),
+                            status: DeliveryStatus::Sending,
+                            send_attempts: 1,
+                            ..Default::default()
+                        };
+                        self.post_now(ctx, message);
+                        self.announce(ctx, "Location shared".into());
+                    }
+                    Err(e) => self.messages.push(MessageData::system(format!("Can't share your location: {}", e))),
+                }
+                true
+            }
+            Msg::UploadLimits(limits) => {
+                self.upload_limits = limits;
+                true
+            }
+            Msg::ComposerPaste(event) => {
+                let pasted = event
+                    .dyn_ref::<web_sys::ClipboardEvent>()
+                    .and_then(|e| e.clipboard_data())
+                    .and_then(|data| data.get_data("text").ok())
+                    .unwrap_or_default();
+                let (cleaned, removed) = match tracking::clean_text(&pasted, &self.settings.tracking_params) {
+                    Some(cleaned) => cleaned,
+                    None => return false,
+                };
+                let input = match self.chat_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                event.prevent_default();
+                let start = input.selection_start().ok().flatten().unwrap_or(0);
+                if input.set_range_text(&cleaned).is_ok() {
+                    let caret = start + cleaned.encode_utf16().count() as u32;
+                    let _ = input.set_selection_range(caret, caret);
+                }
+                if self.editing.is_none() {
+                    self.set_draft(ctx, input.value());
+                }
+                self.paste_cleaned = Some(removed);
+                true
+            }
+            Msg::SyncDraft => {
+                self.draft_timer = None;
+                self.send(MsgTypes::Draft, serde_json::to_string(&self.draft).unwrap());
+                false
+            }
+            Msg::WarnLink(link) => {
+                self.leaving_to = link;
+                true
+            }
+            Msg::UpdateSettings(settings) => {
+                storage::save(Settings::STORAGE_KEY, &settings);
+                if settings.sync_drafts && !self.settings.sync_drafts && self.draft.time > 0 {
+                    ctx.link().send_message(Msg::SyncDraft);
+                }
+                motion::configure(settings.motion);
+                self.send_limiter.configure(&settings);
+                if settings.keywords != self.settings.keywords {
+                    self.settings = settings;
+                    self.reclassify();
+                } else {
+                    self.settings = settings;
+                }
+                true
+            }
+        }
+    }
+
+    fn destroy(&mut self, _ctx: &Context<Self>) {
+        // Leaving the chat (e.g. back to login); remember the spot for next time
+        if !self.print_view && !self.signed_out {
+            storage::save(&ScrollState::storage_key(ROOM), &self.save_scroll());
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render && !self.draft.text.is_empty() {
+            if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                input.set_value(&self.draft.text);
+            }
+        }
+        if let Some(anchor) = self.scroll_anchor.take() {
+            self.restore_anchor(anchor);
+        }
+        if self.scroll_to_bottom {
+            self.scroll_to_bottom = false;
+            if let Some(container) = Self::message_container() {
+                container.set_scroll_top(container.scroll_height());
+            }
+        }
+        if let Some(el) = self.jump_to.take().and_then(message_element) {
+            el.scroll_into_view_with_bool(false);
+        }
+        if let Some(index) = self.reveal.take() {
+            Self::reveal_message(index);
+        }
+        self.mark_read();
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.connection == ConnectionStatus::Unreachable && !self.was_connected {
+            return html! {
+                <ConnectionError
+                    endpoint={Endpoints::current().websocket}
+                    on_retry={ctx.link().callback(|_| Msg::RetryConnection)}
+                />
+            };
+        }
+        if self.print_view {
+            return html! {
+                <Transcript
+                    title={format!("{} Chat Room", self.room_icon.as_deref().unwrap_or(DEFAULT_ROOM_ICON))}
+                    messages={self.messages.clone()}
+                    on_close={ctx.link().callback(|_| Msg::TogglePrintView)}
+                />
+            };
+        }
+
+        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
+        let high_contrast = self.settings.contrast.is_high();
+        let post_denied = self.post_denied();
+        let range = self.visible_range();
+        let unread_divider = self.unread_divider();
+        let bursts = self.bursts(range.clone());
+        // Header buttons open their tab, or close the panel if it's already showing
+        let panel_button = |tab: PanelTab, icon: &'static str, title: &'static str, badge: usize| {
+            let open = self.side_panel == Some(tab);
+            html! {
+                <button
+                    onclick={ctx.link().callback(move |_| Msg::ShowPanel(if open { None } else { Some(tab) }))}
+                    class={classes!(
+                        "relative", "p-2", "hover:text-gray-700", "focus:outline-none",
+                        if open { "text-blue-600" } else { "text-gray-500" }
+                    )}
+                    {title}
+                >
+                    {icon}
+                    if badge > 0 {
+                        <span class="absolute top-0 right-0 min-w-[1rem] h-4 px-1 rounded-full bg-red-500 text-white text-[10px] leading-4 text-center">
+                            {badge}
+                        </span>
+                    }
+                </button>
+            }
+        };
+        let on_keypress = ctx.link().callback(Msg::InputKeyPress);
+        let on_keydown = ctx.link().callback(Msg::InputKeyDown);
+        let on_input = ctx.link().callback(|_: InputEvent| Msg::DraftChanged);
+        let on_paste = ctx.link().callback(Msg::ComposerPaste);
+        let tracking_rules: Rc<[String]> = self.settings.tracking_params.clone().into();
+        let room_state = self.room_state();
+        // The composer isn't a controlled input, so what's in it is read back for the preview
+        let preview = MessageData {
+            from: self.username.clone(),
+            message: self.chat_input.cast::<HtmlInputElement>().map(|i| i.value()).unwrap_or_default(),
+            ..Default::default()
+        };
+        let toggle_emoji = ctx.link().callback(|_| Msg::ToggleEmojiPicker);
+        let viewport_style = self.viewport_height.map(|h| format!("height: {}px;", h));
+        
+        // Group users: favorites first (regardless of presence), then online, then offline
+        let filter = self.user_filter.trim().to_lowercase();
+        let matches = |name: &str| filter.is_empty() || name.to_lowercase().contains(&filter);
+        let online_count = self.users.iter().filter(|u| u.online()).count();
+        let favorite_users: Vec<UserProfile> = self
+            .favorites
+            .iter()
+            .filter(|name| matches(name))
+            .map(|name| {
+                self.users.iter().find(|u| &u.name == name).cloned().unwrap_or_else(|| UserProfile::offline(name))
+            })
+            .collect();
+        let listed = |u: &&UserProfile| !self.favorites.contains(&u.name) && matches(&u.name);
+        let mut online_users: Vec<UserProfile> = self.users.iter().filter(|u| u.online()).filter(listed).cloned().collect();
+        let mut offline_users: Vec<UserProfile> = self.users.iter().filter(|u| !u.online()).filter(listed).cloned().collect();
+        // Alphabetical order is what makes the letter jumps in the list meaningful
+        online_users.sort_by_key(|u| u.name.to_lowercase());
+        offline_users.sort_by_key(|u| u.name.to_lowercase());
+        let sections = vec![
+            (SidebarSection::Favorites, "FAVORITES", favorite_users),
+            (SidebarSection::Online, "ONLINE", online_users),
+            (SidebarSection::Offline, "OFFLINE", offline_users),
+        ]
+        .into_iter()
+        .map(|(section, label, users)| UserSection {
+            section,
+            label,
+            users,
+            collapsed: self.sidebar.collapsed(section),
+        })
+        .collect::<Vec<_>>();
+        let on_filter = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::FilterUsers(input.value())
+        });
+
+        // Links anywhere in the chat go through here, so untrusted ones can be confirmed first
+        let on_link = ctx.link().batch_callback(|e: MouseEvent| {
+            let link = ExternalLink::clicked(&e).filter(|link| !is_trusted(&link.host))?;
+            e.prevent_default();
+            Some(Msg::WarnLink(Some(link)))
+        });
+        let on_aux_link = on_link.clone();
+
+        html! {
+            <div
+                class={classes!("flex", "w-screen", "h-screen", "bg-gray-50", high_contrast.then(|| "high-contrast"))}
+                style={viewport_style.clone()}
+                onclick={on_link}
+                onauxclick={on_aux_link}
+            >
+                // Sidebar with users
+                <div class="flex-none w-64 h-screen bg-white shadow-md flex flex-col">
+                    <div class="flex items-center justify-between p-4 border-b">
                         <div class="text-xl font-semibold text-gray-800">{"Users"}</div>
                         <div class="bg-green-500 text-white rounded-full w-6 h-6 flex items-center justify-center">
-                            {online_users.len()}
+                            {online_count}
                         </div>
                     </div>
                     
@@ -201,196 +2500,2091 @@ impl Component for Chat {
                             type="text" 
                             placeholder="Search users..." 
                             class="w-full p-2 text-sm bg-gray-100 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-400" 
+                            value={self.user_filter.clone()}
+                            oninput={on_filter}
+                        />
+                    </div>
+
+                    // We're on the list ourselves once registered, so empty means it hasn't come yet
+                    if self.users.is_empty() {
+                        <UserListSkeleton />
+                    } else {
+                        <UserList
+                            {sections}
+                            favorites={self.favorites.clone()}
+                            on_toggle_section={ctx.link().callback(Msg::ToggleSection)}
+                            on_toggle_favorite={ctx.link().callback(Msg::ToggleFavorite)}
+                            on_open_profile={ctx.link().callback(|name| Msg::ShowProfile(Some(name)))}
+                            presence_shapes={self.settings.presence_shapes}
                         />
+                    }
+                </div>
+                
+                // Main chat area
+                <div class="grow h-screen flex flex-col" style={viewport_style.clone()}>
+                    // Chat header
+                    <div class="w-full h-16 bg-white shadow-sm flex items-center px-6">
+                        <div class="relative">
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleIconPicker)}
+                                disabled={!self.can_edit_room.allowed}
+                                title={self.can_edit_room.denied_reason("change the room icon").unwrap_or_else(|| "Change room icon".into())}
+                                class="w-9 h-9 mr-1 text-xl rounded-lg hover:bg-gray-100 focus:outline-none disabled:hover:bg-transparent disabled:cursor-default"
+                            >
+                                {self.room_icon.clone().unwrap_or_else(|| DEFAULT_ROOM_ICON.into())}
+                            </button>
+                            if self.show_icon_picker {
+                                <div class="absolute z-10 top-11 left-0 p-2 bg-white rounded-lg shadow-lg">
+                                    <div class="grid grid-cols-6 gap-1">
+                                        {
+                                            ROOM_ICONS.iter().map(|icon| {
+                                                let onclick = ctx.link().callback(move |_| Msg::SetRoomIcon(Some(icon.to_string())));
+                                                html! {
+                                                    <button {onclick} class="w-8 h-8 text-lg rounded hover:bg-gray-100">{icon}</button>
+                                                }
+                                            }).collect::<Html>()
+                                        }
+                                    </div>
+                                    <button
+                                        onclick={ctx.link().callback(|_| Msg::SetRoomIcon(None))}
+                                        class="w-full mt-1 py-1 text-xs text-gray-500 rounded hover:bg-gray-100"
+                                    >
+                                        {"Use default"}
+                                    </button>
+                                </div>
+                            }
+                        </div>
+                        <div class="text-xl font-semibold">{"Chat Room"}</div>
+                        <div class="ml-3 text-sm text-gray-500">{format!("{} participants", self.users.len())}</div>
+                        if let Some(summary) = self.link_quality.summary().filter(|_| self.connection == ConnectionStatus::Connected) {
+                            <div class="ml-auto">
+                                <LatencyMeter {summary} />
+                            </div>
+                        }
+                        if self.connection == ConnectionStatus::Reconnecting {
+                            <div class="ml-auto flex items-center text-sm text-amber-600">
+                                <div class={classes!("w-3", "h-3", "mr-2", "rounded-full", "border-2", "border-amber-300", "border-t-amber-600", animate("animate-spin"))}></div>
+                                {"Reconnecting…"}
+                            </div>
+                        }
+                        <div class={classes!(
+                            "flex", "items-center",
+                            if self.connection == ConnectionStatus::Reconnecting || (self.connection == ConnectionStatus::Connected && self.link_quality.summary().is_some()) {
+                                "ml-3"
+                            } else {
+                                "ml-auto"
+                            }
+                        )}>
+                            {panel_button(PanelTab::Members, "👥", "Members", 0)}
+                            {panel_button(PanelTab::Mentions, "@", "Mentions", self.mentions.unread())}
+                            {panel_button(PanelTab::Media, "🖼️", "Shared media", 0)}
+                            {panel_button(PanelTab::Links, "🔗", "Shared links", 0)}
+                            if self.voice_roster.is_empty() && self.voice.is_none() {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::JoinVoice)}
+                                    class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                    title={if self.joining_voice { "Joining voice…" } else { "Join voice" }}
+                                    disabled={self.joining_voice}
+                                >
+                                    {"🎙️"}
+                                </button>
+                            }
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleInvite)}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                title="Invite people"
+                            >
+                                {"✉️"}
+                            </button>
+                            if self.signed_in {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleSessions)}
+                                    class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                    title="Your sessions"
+                                >
+                                    {"💻"}
+                                </button>
+                            }
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::TogglePrintView)}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                title="Print / Save transcript"
+                            >
+                                {"🖨️"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleServerInfo)}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                title="Server info"
+                            >
+                                {"ℹ️"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ToggleSettings)}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                title="Settings"
+                            >
+                                {"⚙️"}
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::LogOut)}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
+                                title="Log out"
+                            >
+                                {"🚪"}
+                            </button>
+                        </div>
                     </div>
+                    {
+                        self.announcements.iter().map(|a| html! {
+                            <AnnouncementBanner
+                                key={a.id.clone()}
+                                announcement={a.clone()}
+                                on_dismiss={ctx.link().callback(Msg::DismissAnnouncement)}
+                            />
+                        }).collect::<Html>()
+                    }
+                    if !self.voice_roster.is_empty() || self.voice.is_some() {
+                        <VoiceStrip
+                            participants={self.voice_roster.clone()}
+                            speaking={self.voice_speaking.clone()}
+                            joined={self.voice.is_some()}
+                            muted={self.voice_muted}
+                            on_join={ctx.link().callback(|_| Msg::JoinVoice)}
+                            on_leave={ctx.link().callback(|_| Msg::LeaveVoice)}
+                            on_toggle_mute={ctx.link().callback(|_| Msg::ToggleVoiceMute)}
+                        />
+                    }
                     
-                    // Online users
-                    <div class="p-2 text-xs font-medium text-gray-500">{"ONLINE"}</div>
-                    <div class="overflow-y-auto max-h-64">
+                    // Messages container
+                    <div
+                        id="message-container"
+                        class={classes!(
+                            "relative", "w-full", "grow", "overflow-auto",
+                            "focus:outline-none", "focus-visible:ring-2", "focus-visible:ring-inset", "focus-visible:ring-blue-300",
+                            if self.settings.layout == Layout::Compact { "p-2 space-y-0.5" } else { "p-6 space-y-4" }
+                        )}
+                        tabindex="0"
+                        aria-label="Messages. Use the arrow keys to select one, then r to reply, e to react or c to copy."
+                        aria-activedescendant={self.focused.map(message_dom_id)}
+                        onkeydown={ctx.link().callback(Msg::MessageListKey)}
+                        onscroll={ctx.link().callback(|_| Msg::MessagesScrolled)}
+                        ontouchstart={ctx.link().callback(Msg::PullStart)}
+                        ontouchmove={ctx.link().callback(Msg::PullMove)}
+                        ontouchend={ctx.link().callback(|_| Msg::PullEnd)}
+                    >
+                        if self.room_locked {
+                            <div class="flex flex-col items-center justify-center h-full text-gray-500">
+                                <div class="text-3xl" aria-hidden="true">{"🔒"}</div>
+                                <div class="mt-2 text-sm">{"This room is password protected"}</div>
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ShowRoomPassword(true))}
+                                    class="mt-3 px-4 py-2 text-sm font-medium rounded-md text-white bg-violet-600 hover:bg-violet-700"
+                                >
+                                    {"Enter password"}
+                                </button>
+                            </div>
+                        }
+                        if !self.room_locked {
+                            if self.show_tips && room_state != RoomState::Loading {
+                                <TipsCard on_dismiss={ctx.link().callback(|_| Msg::DismissTips)} />
+                            }
+                            {
+                                match room_state {
+                                    RoomState::Loading => html! { <MessageSkeleton /> },
+                                    RoomState::Empty => html! { <EmptyRoom /> },
+                                    RoomState::Ready => html! {},
+                                }
+                            }
+                        }
+                        // Pull-to-refresh / pagination spinner
+                        if !self.room_locked && range.start == 0 && (self.loading_history || self.pull_distance > 0) {
+                            <div
+                                class="flex items-end justify-center overflow-hidden text-gray-400"
+                                style={format!("height: {}px;", if self.loading_history { PULL_TRIGGER } else { self.pull_distance })}
+                            >
+                                <div class={classes!(
+                                    "w-6", "h-6", "mb-2", "rounded-full", "border-2", "border-gray-300", "border-t-blue-500",
+                                    if self.loading_history || self.pull_distance >= PULL_TRIGGER { animate("animate-spin") } else { "" }
+                                )}></div>
+                            </div>
+                        }
                         {
-                            online_users.iter().map(|u| {
-                                html!{
-                                    <div class="flex items-center p-3 hover:bg-gray-100 rounded-lg cursor-pointer transition-colors">
-                                        <div class="relative">
-                                            <img class="w-10 h-10 rounded-full" src={u.avatar.clone()} alt="avatar"/>
-                                            <div class="absolute bottom-0 right-0 w-3 h-3 bg-green-500 rounded-full border-2 border-white"></div>
-                                        </div>
-                                        <div class="ml-3">
-                                            <div class="font-medium">{u.name.clone()}</div>
-                                            <div class="text-xs text-gray-500">{"Active now"}</div>
+                            self.messages[range.clone()].iter().zip(range.clone()).map(|(m, i)| {
+                                if m.system {
+                                    return html! {
+                                        <div id={message_dom_id(i)} class="text-center text-xs text-gray-400 italic">{m.message.clone()}</div>
+                                    };
+                                }
+                                let mut burst_toggle = html! {};
+                                if let Some(burst) = bursts.iter().find(|b| b.contains(&i)) {
+                                    let start = self.messages[burst.start].to_ref();
+                                    // Where it shows, if it started above the window
+                                    let first = burst.start.max(range.start);
+                                    let expanded = self.expanded_bursts.contains(&start);
+                                    // Jumping or arrowing to a hidden message opens its burst
+                                    let targeted = [self.flash, self.focused].iter().flatten().any(|t| burst.contains(t));
+                                    let toggle = ctx.link().callback(move |_| Msg::ToggleBurst(start.clone()));
+                                    if !expanded && !targeted {
+                                        if i != first {
+                                            return html! {};
+                                        }
+                                        let unread = unread_divider.map_or(false, |u| burst.contains(&u));
+                                        return html! {
+                                            <div id={message_dom_id(i)}>
+                                                if unread {
+                                                    <div class="flex items-center mb-4 text-xs font-medium text-red-500">
+                                                        <div class="grow border-t border-red-300"></div>
+                                                        <span class="px-2">{"New messages"}</span>
+                                                        <div class="grow border-t border-red-300"></div>
+                                                    </div>
+                                                }
+                                                <button
+                                                    onclick={toggle}
+                                                    aria-expanded="false"
+                                                    class="w-full px-4 py-2 text-sm text-left text-gray-600 bg-gray-100 rounded-lg hover:bg-gray-200 focus:outline-none"
+                                                >
+                                                    {"⚡ "}{format!("{} rapid messages from ", burst.len())}
+                                                    <span class={classes!("font-medium", name_color(&m.from))}>{m.from.clone()}</span>
+                                                    <span class="ml-2 text-blue-600">{"Show"}</span>
+                                                </button>
+                                            </div>
+                                        };
+                                    }
+                                    if expanded && i == first {
+                                        burst_toggle = html! {
+                                            <button onclick={toggle} aria-expanded="true" class="mb-2 text-xs text-blue-600 hover:underline focus:outline-none">
+                                                {format!("Hide {} rapid messages from {}", burst.len(), m.from)}
+                                            </button>
+                                        };
+                                    }
+                                }
+                                let divider = if unread_divider == Some(i) {
+                                    html! {
+                                        <div class="flex items-center mb-4 text-xs font-medium text-red-500">
+                                            <div class="grow border-t border-red-300"></div>
+                                            <span class="px-2">{"New messages"}</span>
+                                            <div class="grow border-t border-red-300"></div>
                                         </div>
+                                    }
+                                } else {
+                                    html! {}
+                                };
+                                let is_self = m.from == self.username;
+                                let highlight = if m.deleted { Highlight::default() } else { m.highlight };
+                                let sender = self.users.iter().find(|u| u.name == m.from);
+                                let avatar = sender.map(|u| u.avatar.clone());
+                                let sender_timezone = sender
+                                    .map(|u| u.profile.timezone.clone())
+                                    .filter(|zone| self.settings.sender_time && !is_self && !zone.is_empty());
+                                let username = self.username.clone();
+                                let on_reply = ctx.link().callback(move |_| Msg::StartReply(i));
+                                let on_long_press = ctx.link().callback(move |_| Msg::OpenActions(i));
+                                let on_react = ctx.link().callback(move |emoji| Msg::React(i, emoji));
+                                let on_retry = ctx.link().callback(move |_| Msg::RetrySend(i));
+                                let on_discard = ctx.link().callback(move |_| Msg::DiscardFailed(i));
+                                let on_undo = ctx.link().callback(move |_| Msg::UndoSend(i));
+                                let on_show_edits = ctx.link().callback(move |_| Msg::ShowEdits(Some(i)));
+
+                                html!{
+                                    <div
+                                        id={message_dom_id(i)}
+                                        class={classes!(
+                                            "rounded-lg", animate("transition-shadow"),
+                                            if self.flash == Some(i) {
+                                                "ring-2 ring-blue-400 ring-offset-2"
+                                            } else if self.focused == Some(i) {
+                                                "ring-2 ring-gray-400 ring-offset-2"
+                                            } else {
+                                                ""
+                                            }
+                                        )}
+                                    >
+                                        {divider}
+                                        {burst_toggle}
+                                        <MessageBubble
+                                            message={m.clone()}
+                                            {is_self}
+                                            {avatar}
+                                            {username}
+                                            {on_reply}
+                                            {on_long_press}
+                                            {on_react}
+                                            {on_retry}
+                                            {on_discard}
+                                            {on_undo}
+                                            {on_show_edits}
+                                            can_reply={self.can_post.allowed}
+                                            autoplay_gifs={self.settings.autoplay_gifs}
+                                            {highlight}
+                                            {high_contrast}
+                                            {sender_timezone}
+                                            tracking_rules={tracking_rules.clone()}
+                                            blur_media={self.settings.blur_media}
+                                            map_tiles={self.settings.map_tiles.clone()}
+                                            layout={self.settings.layout}
+                                        />
                                     </div>
                                 }
                             }).collect::<Html>()
                         }
                     </div>
+
+                    // The newest messages aren't rendered while reading far back
+                    if range.end < self.messages.len() {
+                        <div class="relative h-0">
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::JumpToLatest)}
+                                class="absolute bottom-4 right-6 px-4 py-2 rounded-full bg-blue-600 text-white text-sm shadow-lg hover:bg-blue-700"
+                            >
+                                {"↓ Latest messages"}
+                            </button>
+                        </div>
+                    }
                     
-                    // Offline users (if any)
-                    if !offline_users.is_empty() {
-                        <>
-                            <div class="p-2 text-xs font-medium text-gray-500">{"OFFLINE"}</div>
-                            <div class="overflow-y-auto max-h-48">
-                                {
-                                    offline_users.iter().map(|u| {
-                                        html!{
-                                            <div class="flex items-center p-3 hover:bg-gray-100 rounded-lg cursor-pointer opacity-60">
-                                                <div class="relative">
-                                                    <img class="w-10 h-10 rounded-full grayscale" src={u.avatar.clone()} alt="avatar"/>
-                                                </div>
-                                                <div class="ml-3">
-                                                    <div class="font-medium">{u.name.clone()}</div>
-                                                    <div class="text-xs text-gray-500">{"Offline"}</div>
-                                                </div>
+                    // Input area
+                    <div class="w-full bg-white p-4 shadow-lg">
+                        if let Some(editing) = self.editing.and_then(|i| self.messages.get(i)) {
+                            <div class="flex items-center mb-2 pl-3 border-l-4 border-amber-400 text-sm">
+                                <div class="grow min-w-0">
+                                    <div class="font-medium text-gray-600">{"Editing message"}</div>
+                                    <div class="truncate text-gray-500">{editing.message.clone()}</div>
+                                </div>
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::CancelEdit)}
+                                    class="p-2 text-gray-400 hover:text-gray-600 focus:outline-none"
+                                >
+                                    {"✕"}
+                                </button>
+                            </div>
+                        }
+                        // Reply preview
+                        if let Some(reply) = self.replying_to.and_then(|i| self.messages.get(i)) {
+                            <div class="flex items-center mb-2 pl-3 border-l-4 border-blue-400 text-sm">
+                                <div class="grow min-w-0">
+                                    <div class="font-medium text-gray-600">
+                                        {"Replying to "}<span class={name_color(&reply.from)}>{reply.from.clone()}</span>
+                                    </div>
+                                    <div class="truncate text-gray-500">{reply.message.clone()}</div>
+                                </div>
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::CancelReply)}
+                                    class="p-2 text-gray-400 hover:text-gray-600 focus:outline-none"
+                                >
+                                    {"✕"}
+                                </button>
+                            </div>
+                        }
+                        <FormatToolbar
+                            on_format={ctx.link().callback(Msg::Format)}
+                            preview={self.show_preview}
+                            on_toggle_preview={ctx.link().callback(|_| Msg::TogglePreview)}
+                            disabled={post_denied.is_some()}
+                        />
+                        if self.show_preview {
+                            <div class="mb-2 max-h-48 overflow-auto px-4 py-2 text-gray-800 border border-dashed border-gray-300 rounded-lg" aria-label="Preview">
+                                if preview.message.trim().is_empty() {
+                                    <span class="text-sm text-gray-400">{"Nothing to preview"}</span>
+                                } else {
+                                    {
+                                        renderers::render(&renderers::Context {
+                                            message: &preview,
+                                            autoplay_gifs: self.settings.autoplay_gifs,
+                                            tracking_rules: &tracking_rules,
+                                            blur_media: self.settings.blur_media,
+                                            map_tiles: &self.settings.map_tiles,
+                                        })
+                                    }
+                                }
+                            </div>
+                        }
+                        if let Some(removed) = &self.paste_cleaned {
+                            <div class="mb-2 px-3 text-xs text-gray-500" role="status">
+                                {format!("🧹 Removed tracking from the pasted link: {}", removed.join(", "))}
+                            </div>
+                        }
+                        {
+                            self.attachments.iter().map(|attachment| {
+                                let id = attachment.id;
+                                let percent = (attachment.progress() * 100.0).round();
+                                html! {
+                                    <div class="flex items-center mb-2 px-3 text-sm" key={id}>
+                                        <div class="grow min-w-0">
+                                            <div class="flex justify-between text-gray-600">
+                                                <span class="truncate">{attachment.file.name()}</span>
+                                                if let Some(error) = &attachment.error {
+                                                    <span class="ml-2 flex-none text-red-600">{error.clone()}</span>
+                                                } else if attachment.preparing {
+                                                    <span class="ml-2 flex-none text-gray-400">{"Removing metadata…"}</span>
+                                                } else {
+                                                    <span class="ml-2 flex-none text-gray-400">{format!("{}%", percent)}</span>
+                                                }
                                             </div>
+                                            if !attachment.rejected {
+                                                <div
+                                                    class="h-1 mt-1 bg-gray-200 rounded-full overflow-hidden"
+                                                    role="progressbar"
+                                                    aria-label={format!("Uploading {}", attachment.file.name())}
+                                                    aria-valuemin="0"
+                                                    aria-valuemax="100"
+                                                    aria-valuenow={percent.to_string()}
+                                                >
+                                                    <div
+                                                        class={classes!("h-full", if attachment.error.is_some() { "bg-red-400" } else { "bg-blue-500" }, animate("transition-all"))}
+                                                        style={format!("width: {}%", percent)}
+                                                    ></div>
+                                                </div>
+                                            }
+                                            if attachment.metadata_removed {
+                                                <div class="mt-1 text-xs text-green-700">{"Location, device and time taken removed"}</div>
+                                            }
+                                        </div>
+                                        if attachment.error.is_some() && !attachment.rejected {
+                                            <button
+                                                onclick={ctx.link().callback(move |_| Msg::RetryUpload(id))}
+                                                class="ml-2 px-2 py-1 text-xs rounded-md text-blue-600 hover:bg-blue-50 focus:outline-none"
+                                            >
+                                                {"Retry"}
+                                            </button>
                                         }
-                                    }).collect::<Html>()
+                                        <button
+                                            onclick={ctx.link().callback(move |_| Msg::CancelUpload(id))}
+                                            class="p-2 text-gray-400 hover:text-gray-600 focus:outline-none"
+                                            title={if attachment.error.is_some() { "Remove" } else { "Cancel upload" }}
+                                        >
+                                            {"✕"}
+                                        </button>
+                                    </div>
                                 }
+                            }).collect::<Html>()
+                        }
+                        if let Some(until) = self.cooldown_until {
+                            <div class="mb-2 px-3 text-sm text-amber-600" role="status">
+                                {format!("Sending too fast. Try again in {} s.", ((until - js_sys::Date::now()) / 1_000.0).ceil().max(1.0))}
                             </div>
-                        </>
+                        }
+                        <div class="flex items-center">
+                            // Emoji picker button
+                            <button 
+                                onclick={toggle_emoji}
+                                disabled={post_denied.is_some()}
+                                title={post_denied.clone()}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none disabled:opacity-40 disabled:cursor-not-allowed"
+                            >
+                                {"😀"}
+                            </button>
+                            
+                            <label
+                                class={classes!(
+                                    "p-2", "text-gray-500", "hover:text-gray-700",
+                                    if post_denied.is_some() { "opacity-40 cursor-not-allowed" } else { "cursor-pointer" }
+                                )}
+                                title={post_denied.clone().unwrap_or_else(|| "Attach files".into())}
+                            >
+                                {"📎"}
+                                <input
+                                    type="file"
+                                    multiple=true
+                                    accept={self.upload_limits.accept()}
+                                    class="sr-only"
+                                    disabled={post_denied.is_some()}
+                                    onchange={ctx.link().callback(Msg::AttachFiles)}
+                                />
+                            </label>
+
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ShareLocation)}
+                                disabled={post_denied.is_some() || self.locating}
+                                title={post_denied.clone().unwrap_or_else(|| if self.locating { "Finding your location…" } else { "Share your location" }.into())}
+                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none disabled:opacity-40 disabled:cursor-not-allowed"
+                            >
+                                {"📍"}
+                            </button>
+
+                            if self.composing_image || !self.attachments.is_empty() {
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleSensitive)}
+                                    class={classes!(
+                                        "p-2", "rounded-full", "focus:outline-none",
+                                        if self.mark_sensitive { "bg-amber-100 text-amber-700" } else { "text-gray-500 hover:text-gray-700" }
+                                    )}
+                                    title={if self.mark_sensitive { "Marked sensitive: sent blurred" } else { "Mark images as sensitive" }}
+                                    aria-pressed={self.mark_sensitive.to_string()}
+                                >
+                                    {"🙈"}
+                                </button>
+                            }
+
+                            // Message input
+                            <input 
+                                ref={self.chat_input.clone()} 
+                                type="text" 
+                                placeholder={post_denied.clone().unwrap_or_else(|| "Type a message...".into())}
+                                class="block w-full py-3 px-4 mx-3 bg-gray-100 rounded-full outline-none focus:ring-2 focus:ring-blue-400 focus:bg-white disabled:cursor-not-allowed" 
+                                name="message" 
+                                required=true 
+                                disabled={post_denied.is_some()}
+                                title={post_denied.clone()}
+                                onkeypress={on_keypress}
+                                onkeydown={on_keydown}
+                                oninput={on_input}
+                                onpaste={on_paste}
+                            />
+                            
+                            // Send button
+                            <button 
+                                onclick={submit} 
+                                disabled={post_denied.is_some() || self.cooldown_until.is_some()}
+                                title={post_denied.clone()}
+                                class={classes!("p-3", "bg-blue-600", "hover:bg-blue-700", "w-12", "h-12", "rounded-full", "flex", "justify-center", "items-center", "text-white", animate("transition-colors"), "disabled:bg-gray-300", "disabled:cursor-not-allowed")}
+                            >
+                                <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-6 h-6 fill-current">
+                                    <path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"></path>
+                                </svg>
+                            </button>
+                        </div>
+                        
+                        if self.show_emoji_picker {
+                            <EmojiPicker
+                                on_pick={ctx.link().callback(Msg::InsertEmoji)}
+                                on_close={ctx.link().callback(|_| Msg::CloseEmojiPicker)}
+                            />
+                        }
+                    </div>
+                </div>
+
+                if let Some(tab) = self.side_panel {
+                    <div class="flex-none w-80 h-screen bg-white border-l flex flex-col" style={viewport_style}>
+                        <div class="flex items-center h-16 px-4 border-b">
+                            {
+                                [PanelTab::Members, PanelTab::Mentions, PanelTab::Media, PanelTab::Links].iter().map(|t| {
+                                    let t = *t;
+                                    html! {
+                                        <button
+                                            onclick={ctx.link().callback(move |_| Msg::ShowPanel(Some(t)))}
+                                            class={classes!(
+                                                "mr-4", "py-1", "font-medium", "border-b-2", "focus:outline-none",
+                                                if t == tab { "text-gray-800 border-blue-500" } else { "text-gray-400 border-transparent hover:text-gray-600" }
+                                            )}
+                                        >
+                                            {t.label()}
+                                        </button>
+                                    }
+                                }).collect::<Html>()
+                            }
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::ShowPanel(None))}
+                                class="ml-auto p-2 text-gray-400 hover:text-gray-600 focus:outline-none"
+                            >
+                                {"✕"}
+                            </button>
+                        </div>
+                        <div class="grow overflow-y-auto">
+                            {match tab {
+                                PanelTab::Media => html! {
+                                    <MediaGallery
+                                        items={MediaItem::collect(&self.messages)}
+                                        on_open={ctx.link().callback(|i| Msg::OpenLightbox(Some(i)))}
+                                    />
+                                },
+                                PanelTab::Members => html! {
+                                    <MembersPanel
+                                        members={self.room_members()}
+                                        username={self.username.clone()}
+                                        favorites={self.favorites.clone()}
+                                        on_mention={ctx.link().callback(Msg::MentionUser)}
+                                        on_toggle_favorite={ctx.link().callback(Msg::ToggleFavorite)}
+                                        on_copy={ctx.link().callback(Msg::CopyName)}
+                                        on_open_profile={ctx.link().callback(|name| Msg::ShowProfile(Some(name)))}
+                                        presence_shapes={self.settings.presence_shapes}
+                                    />
+                                },
+                                PanelTab::Mentions => html! {
+                                    <MentionsPanel
+                                        mentions={self.mentions.entries().to_vec()}
+                                        on_open={ctx.link().callback(Msg::OpenMention)}
+                                        on_mark_all_read={ctx.link().callback(|_| Msg::MarkMentionsRead)}
+                                    />
+                                },
+                                PanelTab::Links => html! {
+                                    <LinksPanel
+                                        items={LinkItem::collect(&self.messages, &self.link_titles)}
+                                        on_jump={ctx.link().callback(Msg::JumpToMessage)}
+                                    />
+                                },
+                            }}
+                        </div>
+                    </div>
+                }
+
+                if let Some((i, m)) = self.lightbox.and_then(|i| self.messages.get(i).map(|m| (i, m))) {
+                    <Lightbox
+                        url={m.message.clone()}
+                        from={m.from.clone()}
+                        video={is_video_url(&m.message)}
+                        on_jump={ctx.link().callback(move |_| Msg::JumpToMessage(i))}
+                        on_close={ctx.link().callback(|_| Msg::OpenLightbox(None))}
+                    />
+                }
+
+                // Long-press actions (touch devices)
+                if let Some(i) = self.action_sheet.filter(|i| self.messages.get(*i).map_or(false, |m| !m.deleted)) {
+                    <ActionSheet
+                        is_own={self.messages[i].from == self.username}
+                        editable={self.messages[i].status == DeliveryStatus::Sent}
+                        post_denied={self.post_denied()}
+                        on_edit={ctx.link().callback(move |_| Msg::StartEdit(i))}
+                        on_react={ctx.link().callback(move |emoji| Msg::React(i, emoji))}
+                        on_reply={ctx.link().callback(move |_| Msg::StartReply(i))}
+                        on_copy={ctx.link().callback(move |_| Msg::CopyMessage(i))}
+                        on_copy_link={self.messages[i].id.is_some().then(|| ctx.link().callback(move |_| Msg::CopyLink(i)))}
+                        on_delete={ctx.link().callback(move |_| Msg::DeleteMessage(i))}
+                        on_close={ctx.link().callback(|_| Msg::CloseActions)}
+                    />
+                }
+
+                if self.show_invite {
+                    <InviteDialog
+                        url={self.invite_token.as_deref().map(invite_url)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleInvite)}
+                    />
+                }
+
+                if self.show_sessions {
+                    <SessionsDialog
+                        sessions={self.sessions.clone()}
+                        on_sign_out={ctx.link().callback(Msg::SignOutSession)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleSessions)}
+                    />
+                }
+
+                if self.show_server_info {
+                    <ServerInfoDialog
+                        info={self.server_info.clone()}
+                        endpoint={Endpoints::current().websocket}
+                        on_close={ctx.link().callback(|_| Msg::ToggleServerInfo)}
+                    />
+                }
+
+                if self.update == UpdateState::Available {
+                    <UpdateToast on_dismiss={ctx.link().callback(|_| Msg::DismissUpdate)} />
+                }
+
+                <LiveRegion text={self.announcer.text()} />
+
+                if let Some(m) = self.edit_history.and_then(|i| self.messages.get(i)) {
+                    <EditHistory
+                        versions={
+                            m.revisions
+                                .iter()
+                                .cloned()
+                                .chain(std::iter::once(Revision { message: m.message.clone(), timestamp: m.edited_at }))
+                                .collect::<Vec<_>>()
+                        }
+                        on_close={ctx.link().callback(|_| Msg::ShowEdits(None))}
+                    />
+                }
+
+                if let Some(link) = self.leaving_to.clone() {
+                    <LinkWarning
+                        {link}
+                        on_open={ctx.link().callback(|_| Msg::WarnLink(None))}
+                        on_cancel={ctx.link().callback(|_| Msg::WarnLink(None))}
+                    />
+                }
+
+                if let Some(user) = self.profile_card.as_ref().map(|name| self.profile_of(name)) {
+                    <ProfileCard
+                        is_self={user.name == self.username}
+                        {user}
+                        on_save={ctx.link().callback(Msg::SaveProfile)}
+                        on_close={ctx.link().callback(|_| Msg::ShowProfile(None))}
+                        on_call={(user.name != self.username && self.call.is_none() && self.users.iter().any(|u| u.name == user.name)).then(|| {
+                            let name = user.name.clone();
+                            ctx.link().callback(move |_| Msg::StartCall(name.clone()))
+                        })}
+                    />
+                }
+
+                if let Some(call) = &self.call {
+                    <CallWindow
+                        peer={call.peer.clone()}
+                        local={call.local.clone()}
+                        remote={call.remote.clone()}
+                        started={call.started}
+                        muted={call.muted}
+                        camera_off={call.camera_off}
+                        on_toggle_mute={ctx.link().callback(|_| Msg::ToggleCallMute)}
+                        on_toggle_camera={ctx.link().callback(|_| Msg::ToggleCamera)}
+                        on_hang_up={ctx.link().callback(|_| Msg::HangUp)}
+                    />
+                }
+                if let Some(incoming) = self.incoming_call.as_ref().filter(|_| self.placing_call.is_none()) {
+                    <IncomingCallDialog
+                        from={incoming.from.clone()}
+                        on_accept={ctx.link().callback(|_| Msg::AcceptCall)}
+                        on_decline={ctx.link().callback(|_| Msg::DeclineCall)}
+                    />
+                }
+
+                if self.show_room_password {
+                    <RoomPasswordPrompt
+                        room={ROOM}
+                        error={self.room_password_error.clone()}
+                        pending={self.joining_room}
+                        on_submit={ctx.link().callback(Msg::SubmitRoomPassword)}
+                        on_cancel={ctx.link().callback(|_| Msg::ShowRoomPassword(false))}
+                    />
+                }
+
+                if self.show_palette {
+                    <CommandPalette
+                        commands={self.palette_commands(ctx)}
+                        on_close={ctx.link().callback(|_| Msg::ShowPalette(false))}
+                    />
+                }
+
+                if self.show_shortcuts {
+                    <ShortcutSheet on_close={ctx.link().callback(|_| Msg::ShowShortcuts(false))} />
+                }
+
+                if self.show_stats {
+                    <StatsPanel
+                        stats={self.stats.clone()}
+                        on_clear={ctx.link().callback(|_| Msg::ClearStats)}
+                        on_close={ctx.link().callback(|_| Msg::ShowStats(false))}
+                    />
+                }
+
+                if self.show_settings {
+                    <SettingsPanel
+                        settings={self.settings.clone()}
+                        on_change={ctx.link().callback(Msg::UpdateSettings)}
+                        on_reset={ctx.link().callback(|_| Msg::ResetSettings)}
+                        on_close={ctx.link().callback(|_| Msg::ToggleSettings)}
+                    />
+                }
+
+                // Last, so it's on top of whichever dialog asked
+                if let Some(action) = self.confirming {
+                    <ConfirmDialog
+                        confirm={action.confirm()}
+                        on_confirm={ctx.link().callback(|_| Msg::Confirmed)}
+                        on_cancel={ctx.link().callback(|_| Msg::CancelConfirm)}
+                    />
+                }
+            </div>
+        }
+    }
+}
+
+/// `/announce <text>` or `/alert <text>` for a warning; either alone takes the banner down.
+fn announce_command(command: &str) -> Option<(announcement::Level, &str)> {
+    [("/announce", announcement::Level::Info), ("/alert", announcement::Level::Warning)]
+        .into_iter()
+        .find_map(|(name, level)| {
+            let rest = command.strip_prefix(name)?;
+            (rest.is_empty() || rest.starts_with(' ')).then(|| (level, rest.trim()))
+        })
+}
+
+fn new_client_id() -> String {
+    format!(
+        "{:x}-{:08x}",
+        js_sys::Date::now() as u64,
+        (js_sys::Math::random() * u32::MAX as f64) as u32
+    )
+}
+
+// Whether `next` came from the same sender hot on the heels of `previous`
+fn rapid_followup(previous: &MessageData, next: &MessageData) -> bool {
+    if previous.system || next.system || previous.from != next.from {
+        return false;
+    }
+    match (previous.timestamp, next.timestamp) {
+        (Some(a), Some(b)) => matches!(b.checked_sub(a), Some(gap) if (0..=BURST_GAP_MS).contains(&gap)),
+        _ => false,
+    }
+}
+
+// DOM id of a message's row in the list, used to scroll to it
+fn message_dom_id(index: usize) -> String {
+    format!("message-{}", index)
+}
+
+// The rendered row of a message, if it's inside the window
+fn message_element(index: usize) -> Option<HtmlElement> {
+    web_sys::window()?
+        .document()?
+        .get_element_by_id(&message_dom_id(index))?
+        .dyn_into::<HtmlElement>()
+        .ok()
+}
+
+impl Chat {
+    /// Applies one frame from the server, returning whether to re-render, or
+    /// `None` if it couldn't be made sense of. Nothing the server sends may panic.
+    fn handle_frame(&mut self, ctx: &Context<Self>, frame: &str) -> Option<bool> {
+        let msg: WebSocketMessage = serde_json::from_str(frame).ok()?;
+        match msg.message_type {
+            MsgTypes::Users => {
+                let users_from_message = msg.data_array.unwrap_or_default();
+                // Servers that predate roles only send the nickname list
+                let members: Vec<RoomMember> = msg
+                    .data
+                    .and_then(|data| serde_json::from_str::<RoomMembers>(&data).ok())
+                    .map(|room| room.members)
+                    .unwrap_or_default();
+                self.users = users_from_message
+                    .iter()
+                    .map(|u| {
+                        let member = members.iter().find(|m| &m.nick == u);
+                        let profile: Profile = member.and_then(|m| m.profile.clone()).unwrap_or_default();
+                        UserProfile {
+                            name: u.into(),
+                            avatar: profile.avatar.url(u),
+                            presence: member.map(|m| m.status).unwrap_or_default(),
+                            role: member.map(|m| m.role).unwrap_or_default(),
+                            profile,
+                        }
+                    })
+                    .collect();
+                // Nobody tells us when the other side of a call drops off
+                let gone = |name: &String| !self.users.iter().any(|u| &u.name == name);
+                let call_gone = self.call.as_ref().map_or(false, |call| gone(&call.peer));
+                if self.incoming_call.as_ref().map_or(false, |i| gone(&i.from)) {
+                    self.incoming_call = None;
+                    self.placing_call = None;
+                }
+                if call_gone {
+                    self.end_call(ctx, "The other side went offline".into());
+                }
+                Some(true)
+            }
+            MsgTypes::Message => {
+                let message_data = self.classify(serde_json::from_str(&msg.data?).ok()?);
+                // Reconnect replays and server retries can deliver the same message twice
+                if self.messages.iter().any(|m| m.status == DeliveryStatus::Sent && m.same_as(&message_data)) {
+                    return Some(false);
+                }
+                // Our own echo takes the place of the optimistic bubble
+                let pending = message_data.client_id.as_ref().and_then(|id| {
+                    self.messages
+                        .iter()
+                        .rposition(|m| m.status != DeliveryStatus::Sent && m.client_id.as_ref() == Some(id))
+                });
+                if let (Some(seq), Some(last)) = (message_data.seq, self.last_seq) {
+                    if seq > last.saturating_add(1) {
+                        log::debug!("Missed messages {}..={}, resyncing", last + 1, seq - 1);
+                        let request = ResyncRequest { from: last + 1, to: seq - 1 };
+                        self.send(MsgTypes::Resync, serde_json::to_string(&request).unwrap());
+                    }
+                }
+                self.last_seq = self.last_seq.max(message_data.seq);
+                let highlight = message_data.highlight;
+                // Someone flooding the room doesn't get to ring the bell each time
+                let quiet = self.settings.quiet_bursts && self.floods(&message_data);
+                // Mentions always alert; keywords only when the user asked for it
+                if !quiet && (highlight.mention || (highlight.keyword && self.settings.notify_keywords)) {
+                    notify::chime();
+                    let title = if highlight.mention {
+                        format!("{} mentioned you", message_data.from)
+                    } else {
+                        format!("{} in chat", message_data.from)
+                    };
+                    notify::desktop(&title, &message_data.message);
+                }
+                if pending.is_none() && message_data.from != self.username && !quiet {
+                    let text = if is_image_url(&message_data.message) || is_video_url(&message_data.message) {
+                        format!("{} shared media", message_data.from)
+                    } else {
+                        format!("{}: {}", message_data.from, message_data.message)
+                    };
+                    self.announce(ctx, text);
+                }
+                match pending {
+                    Some(i) => {
+                        self.stats.record(&message_data, &self.username, ROOM);
+                        self.messages[i] = message_data;
+                    }
+                    None => self.insert_message(message_data),
+                }
+                if self.side_panel == Some(PanelTab::Links) {
+                    self.request_link_titles();
+                }
+
+                // Follow new messages only if the reader is already at the bottom
+                self.scroll_to_bottom = self.pinned_to_bottom;
+                Some(true)
+            }
+            MsgTypes::Catchup => {
+                let missed: Vec<MessageData> = msg
+                    .data_array
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|m| serde_json::from_str(m).ok())
+                    .map(|m| self.classify(m))
+                    .collect();
+                let mut recovered = 0;
+                for m in missed {
+                    if let Some(i) = self.messages.iter().position(|e| e.status != DeliveryStatus::Sent && e.same_as(&m)) {
+                        // One of ours that made it out before the connection dropped
+                        self.stats.record(&m, &self.username, ROOM);
+                        self.messages[i] = m;
+                    } else if !self.messages.iter().any(|e| e.same_as(&m)) {
+                        self.last_seq = self.last_seq.max(m.seq);
+                        self.insert_message(m);
+                        recovered += 1;
+                    }
+                }
+                let notice = match recovered {
+                    0 => "Reconnected".to_string(),
+                    1 => "Reconnected, 1 message recovered".to_string(),
+                    n => format!("Reconnected, {} messages recovered", n),
+                };
+                self.messages.push(MessageData::system(notice));
+                self.scroll_to_bottom = self.pinned_to_bottom;
+                Some(true)
+            }
+            MsgTypes::Resync => {
+                let missing: Vec<MessageData> = msg
+                    .data_array
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|m| serde_json::from_str(m).ok())
+                    .map(|m| self.classify(m))
+                    .collect();
+                for m in missing {
+                    if !self.messages.iter().any(|e| e.same_as(&m)) {
+                        self.insert_message(m);
+                    }
+                }
+                Some(true)
+            }
+            MsgTypes::Unfurl => {
+                let unfurl: UnfurlData = serde_json::from_str(&msg.data?).ok()?;
+                self.link_titles.insert(unfurl.url, unfurl.title);
+                Some(self.side_panel == Some(PanelTab::Links))
+            }
+            MsgTypes::Permissions => {
+                let permissions: RoomPermissions = serde_json::from_str(&msg.data?).ok()?;
+                // Only sent once we're registered, which is when the server takes a profile
+                self.share_profile();
+                self.can_post = permissions.post;
+                self.can_edit_room = permissions.topic;
+                self.show_icon_picker &= self.can_edit_room.allowed;
+                if !self.can_post.allowed {
+                    self.replying_to = None;
+                    self.show_emoji_picker = false;
+                }
+                Some(true)
+            }
+            MsgTypes::ServerInfo => {
+                self.server_info = Some(serde_json::from_str(&msg.data?).ok()?);
+                Some(self.show_server_info)
+            }
+            MsgTypes::Voice => {
+                let roster: Roster = serde_json::from_str(&msg.data?).ok()?;
+                if roster.room != ROOM {
+                    return Some(false);
+                }
+                if let Some(voice) = &mut self.voice {
+                    voice.sync(&roster.participants);
+                }
+                self.voice_roster = roster.participants;
+                Some(true)
+            }
+            MsgTypes::Signal => {
+                let signal: Signal = serde_json::from_str(&msg.data?).ok()?;
+                if let Some(voice) = &mut self.voice {
+                    voice.handle(signal);
+                }
+                Some(false)
+            }
+            MsgTypes::Call => {
+                let signal: CallSignal = serde_json::from_str(&msg.data?).ok()?;
+                let from = signal.from;
+                let ours = self.call.as_ref().map_or(false, |call| call.peer == from);
+                match signal.payload {
+                    CallPayload::Offer { sdp } => {
+                        if self.call.is_some() || self.incoming_call.is_some() || self.placing_call.is_some() {
+                            self.send_call_signal(&from, CallPayload::Busy);
+                            return Some(false);
+                        }
+                        notify::chime();
+                        notify::desktop(&format!("{} is video calling you", from), "Open the chat to answer");
+                        self.announce(ctx, format!("Incoming video call from {}", from));
+                        self.incoming_call = Some(IncomingCall::new(from, sdp));
+                    }
+                    CallPayload::Answer { sdp } => {
+                        if let Some(call) = self.call.as_mut().filter(|call| ours && call.outgoing) {
+                            call.answered(sdp);
+                            self.call_timeout = None;
+                            self.announce(ctx, format!("{} answered", from));
+                        }
+                    }
+                    CallPayload::Candidate(candidate) => {
+                        if let Some(call) = self.call.as_ref().filter(|_| ours) {
+                            call.add_candidate(&candidate);
+                        } else if let Some(incoming) = self.incoming_call.as_mut().filter(|i| i.from == from) {
+                            incoming.add_candidate(candidate);
+                        }
+                        return Some(false);
+                    }
+                    CallPayload::Decline if ours => self.end_call(ctx, format!("{} declined the video call", from)),
+                    CallPayload::Busy if ours => self.end_call(ctx, format!("{} is in another call", from)),
+                    CallPayload::HangUp if ours => self.end_call(ctx, format!("{} hung up", from)),
+                    CallPayload::HangUp => {
+                        // They gave up before we answered
+                        if self.incoming_call.as_ref().map_or(false, |i| i.from == from) {
+                            self.incoming_call = None;
+                            if self.placing_call.as_ref() == Some(&from) {
+                                self.placing_call = None;
+                            }
+                            self.messages.push(MessageData::system(format!("Missed video call from {}", from)));
+                        }
+                    }
+                    CallPayload::Decline | CallPayload::Busy => return Some(false),
+                }
+                Some(true)
+            }
+            MsgTypes::Announcement => {
+                let announcement: Announcement = serde_json::from_str(&msg.data?).ok()?;
+                self.announcements.retain(|a| a.id != announcement.id);
+                if !announcement.text.is_empty() && !announcement::is_dismissed(&announcement.id) {
+                    self.announce(ctx, format!("Announcement: {}", announcement.text));
+                    self.announcements.push(announcement);
+                }
+                Some(true)
+            }
+            MsgTypes::Room => {
+                let room: RoomInfo = serde_json::from_str(&msg.data?).ok()?;
+                self.room_icon = room.icon;
+                if room.locked && !self.room_locked {
+                    match self.room_key.clone() {
+                        // A new socket after reconnecting; the password we entered still works
+                        Some(key) => self.join_room(key),
+                        None => self.show_room_password = true,
+                    }
+                }
+                self.room_locked = room.locked;
+                Some(true)
+            }
+            MsgTypes::Invite => {
+                let invite: InviteData = serde_json::from_str(&msg.data?).ok()?;
+                self.invite_token = Some(invite.token);
+                Some(self.show_invite)
+            }
+            MsgTypes::Joined => {
+                let result: JoinResult = serde_json::from_str(&msg.data?).ok()?;
+                let notice = match (result.room, result.error) {
+                    (_, Some(error)) => error,
+                    (Some(room), None) => format!("You joined #{} with an invite link", room),
+                    (None, None) => return Some(false),
+                };
+                self.messages.push(MessageData::system(notice));
+                self.scroll_to_bottom = self.pinned_to_bottom;
+                Some(true)
+            }
+            MsgTypes::JoinedRoom => {
+                let result: JoinResult = serde_json::from_str(&msg.data?).ok()?;
+                self.joining_room = false;
+                match result.error {
+                    Some(error) => {
+                        self.room_key = None;
+                        LastConversation::set_key(ROOM, None);
+                        self.room_password_error = Some(error);
+                        self.show_room_password = true;
+                    }
+                    None => {
+                        self.room_locked = false;
+                        self.show_room_password = false;
+                        self.room_password_error = None;
+                        LastConversation::set_key(ROOM, self.room_key.clone());
+                        // Anything asked for while locked went unanswered
+                        self.catch_up();
+                    }
+                }
+                Some(true)
+            }
+            MsgTypes::Registered => {
+                let registered: Registered = serde_json::from_str(&msg.data?).ok()?;
+                // A shared socket can pass on another tab's registration; its token isn't ours
+                if registered.nick != self.username {
+                    return Some(false);
+                }
+                storage::save_for_tab(RECLAIM_KEY, &registered.token);
+                LastConversation::entered(&self.username, ROOM);
+                RecentIdentity::remember(&self.username, self.signed_in);
+                // The server forgot we were in voice when the connection dropped
+                if self.voice.is_some() {
+                    self.send_voice_update();
+                }
+                Some(false)
+            }
+            MsgTypes::Draft => {
+                let draft: Draft = serde_json::from_str(&msg.data?).ok()?;
+                // Last writer wins, and a message being edited isn't a draft to replace
+                if !self.settings.sync_drafts || draft.time <= self.draft.time || self.editing.is_some() {
+                    return Some(false);
+                }
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    input.set_value(&draft.text);
+                }
+                storage::save(&Draft::storage_key(ROOM), &draft);
+                self.draft = draft;
+                // Whatever we were about to send is older now
+                self.draft_timer = None;
+                Some(false)
+            }
+            MsgTypes::ReadMarker => {
+                let marker: ReadMarker = serde_json::from_str(&msg.data?).ok()?;
+                Some(self.apply_read_marker(marker))
+            }
+            MsgTypes::Sessions => {
+                let list: SessionList = serde_json::from_str(&msg.data?).ok()?;
+                self.sessions = Some(list.sessions);
+                Some(self.show_sessions)
+            }
+            MsgTypes::SignedOut => {
+                // Ended from another device; the token is no good anymore
+                if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+                    user.session.borrow_mut().take();
+                    *user.register_error.borrow_mut() = Some("You were signed out from another device".into());
+                }
+                self.sign_out(ctx);
+                Some(false)
+            }
+            MsgTypes::RegisterError => {
+                // Send the user back to the login form with the server's reason
+                let reason = msg.data.unwrap_or_else(|| "Registration was rejected".into());
+                if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+                    *user.register_error.borrow_mut() = Some(reason);
+                }
+                if let Some(history) = ctx.link().history() {
+                    history.push(Route::Login);
+                }
+                Some(false)
+            }
+            MsgTypes::Challenge => {
+                // Back to the login form, which shows the CAPTCHA
+                let challenge: Challenge = serde_json::from_str(&msg.data?).ok()?;
+                if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+                    *user.register_error.borrow_mut() = challenge.error.clone();
+                    *user.challenge.borrow_mut() = Some(challenge);
+                }
+                if let Some(history) = ctx.link().history() {
+                    history.push(Route::Login);
+                }
+                Some(false)
+            }
+            MsgTypes::Rename => {
+                let rename: RenameData = serde_json::from_str(&msg.data?).ok()?;
+                self.apply_rename(ctx, &rename.from, &rename.to);
+                Some(true)
+            }
+            MsgTypes::RenameError => {
+                let reason = msg.data.unwrap_or_else(|| "Rename was rejected".into());
+                self.messages.push(MessageData::system(reason));
+                Some(true)
+            }
+            MsgTypes::Reaction => {
+                let reaction: ReactionData = serde_json::from_str(&msg.data?).ok()?;
+                let ours = reaction.from == self.username;
+                if let Some(m) = self.find_message(&reaction.target) {
+                    let users = m.reactions.entry(reaction.emoji.clone()).or_default();
+                    // Reacting twice with the same emoji takes it back
+                    let added = !users.remove(&reaction.from);
+                    if added {
+                        users.insert(reaction.from);
+                    }
+                    if users.is_empty() {
+                        m.reactions.remove(&reaction.emoji);
+                    }
+                    if ours && added {
+                        self.stats.reacted(&reaction.emoji);
+                    }
+                    return Some(true);
+                }
+                Some(false)
+            }
+            MsgTypes::Delete => {
+                let delete: DeleteData = serde_json::from_str(&msg.data?).ok()?;
+                if delete.from != delete.target.from {
+                    return Some(false);
+                }
+                self.mentions.remove(&delete.target);
+                if let Some(m) = self.find_message(&delete.target) {
+                    m.deleted = true;
+                    m.reactions.clear();
+                    return Some(true);
+                }
+                Some(false)
+            }
+            MsgTypes::Edit => {
+                let edit: EditData = serde_json::from_str(&msg.data?).ok()?;
+                if edit.from != edit.target.from {
+                    return Some(false);
+                }
+                let index = match self.messages.iter().position(|m| !m.deleted && edit.target.matches(m)) {
+                    Some(index) => index,
+                    None => return Some(false),
+                };
+                let mut m = std::mem::take(&mut self.messages[index]);
+                if m.message != edit.message {
+                    m.revisions.push(Revision {
+                        message: std::mem::replace(&mut m.message, edit.message),
+                        timestamp: m.edited_at.or(m.timestamp),
+                    });
+                    m.edited_at = Some(js_sys::Date::now() as i64);
+                }
+                // The new text may add or drop a mention
+                self.messages[index] = self.classify(m);
+                self.mentions.remove(&edit.target);
+                self.mentions.add(&self.messages[index]);
+                Some(true)
+            }
+            MsgTypes::Pong => {
+                self.link_quality.pong(&msg.data.unwrap_or_default(), js_sys::Date::now());
+                Some(true)
+            }
+            MsgTypes::History => {
+                let page = msg.data_array.unwrap_or_default();
+                self.loading_history = false;
+                self.history_exhausted = page.len() < HISTORY_PAGE_SIZE;
+                let older: Vec<MessageData> = page
+                    .iter()
+                    .filter_map(|m| serde_json::from_str(m).ok())
+                    .filter(|m: &MessageData| !self.messages.iter().any(|e| e.same_as(m)))
+                    .map(|m| self.classify(m))
+                    .collect();
+
+                let first_page = self.messages.iter().all(|m| m.system);
+                if first_page {
+                    // First page after joining: start at the newest message
+                    self.scroll_to_bottom = true;
+                }
+                let start = self.visible_range().start;
+                self.last_seq = self.last_seq.max(older.iter().filter_map(|m| m.seq).max());
+                let added = older.len();
+                for m in older.iter() {
+                    self.mentions.add(m);
+                }
+                self.messages.splice(0..0, older);
+                self.shift_indices(|i| Some(i + added));
+                if first_page {
+                    if let Some(state) = self.saved_scroll.take() {
+                        self.restore_scroll(state);
+                    }
+                } else {
+                    // Reveal part of the new page above what's on screen; the scroll
+                    // anchor keeps the viewport on the message being read
+                    self.follow_tail = false;
+                    self.window_start = (start + added).saturating_sub(WINDOW_STEP);
+                }
+                self.seek(ctx);
+                Some(true)
+            }
+            _ => Some(false),
+        }
+    }
+
+    /// Re-attributes everything we hold locally from `from` to `to` and posts a notice.
+    fn apply_rename(&mut self, ctx: &Context<Self>, from: &str, to: &str) {
+        if from == self.username {
+            self.username = to.to_string();
+            LastConversation::renamed(to);
+            if let Some(voice) = &mut self.voice {
+                voice.rename(to);
+            }
+            if let Some((user, _)) = ctx.link().context::<User>(Callback::noop()) {
+                *user.username.borrow_mut() = to.to_string();
+            }
+        }
+        if self.favorites.remove(from) {
+            self.favorites.insert(to.to_string());
+            storage::save(FAVORITES_KEY, &self.favorites);
+        }
+        for u in self.users.iter_mut().filter(|u| u.name == from) {
+            u.name = to.to_string();
+            u.avatar = u.profile.avatar.url(to);
+        }
+        for m in self.messages.iter_mut() {
+            if m.from == from {
+                m.from = to.to_string();
+            }
+            if let Some(reply) = m.reply_to.as_mut().filter(|r| r.from == from) {
+                reply.from = to.to_string();
+            }
+            for users in m.reactions.values_mut() {
+                if users.remove(from) {
+                    users.insert(to.to_string());
+                }
+            }
+        }
+        if to == self.username {
+            self.reclassify();
+        } else {
+            // Entries still carry the old sender name
+            self.mentions.rebuild(self.messages.iter());
+        }
+        self.messages
+            .push(MessageData::system(format!("{} is now known as {}", from, to)));
+    }
+
+    /// Flags messages that mention us or contain a highlight keyword. Our own
+    /// messages never count.
+    fn classify(&self, mut m: MessageData) -> MessageData {
+        m.highlight = if m.from == self.username {
+            Highlight::default()
+        } else {
+            self.highlighter.scan(&m.message)
+        };
+        m
+    }
+
+    /// Re-runs [`Chat::classify`] over everything after our name or keywords change.
+    fn reclassify(&mut self) {
+        self.highlighter = Highlighter::new(&self.username, &self.settings.keywords);
+        for m in std::mem::take(&mut self.messages) {
+            let m = self.classify(m);
+            self.messages.push(m);
+        }
+        self.mentions.rebuild(self.messages.iter());
+    }
+
+    fn message_container() -> Option<HtmlElement> {
+        web_sys::window()?
+            .document()?
+            .get_element_by_id("message-container")?
+            .dyn_into::<HtmlElement>()
+            .ok()
+    }
+
+    /// Loads what was missed while away: the gap if we've seen messages before, else the latest page.
+    fn catch_up(&mut self) {
+        match self.last_seq {
+            Some(after) => self.send(MsgTypes::Catchup, serde_json::to_string(&CatchupRequest { after }).unwrap()),
+            None => self.request_history(),
+        }
+    }
+
+    fn join_room(&mut self, key: String) {
+        self.joining_room = true;
+        self.room_key = Some(key.clone());
+        let request = JoinRoomRequest { room: ROOM.to_string(), passphrase: key };
+        self.send(MsgTypes::JoinRoom, serde_json::to_string(&request).unwrap());
+    }
+
+    fn share_profile(&mut self) {
+        if !self.profile.is_empty() {
+            self.send(MsgTypes::ProfileUpdate, serde_json::to_string(&self.profile).unwrap());
+        }
+    }
+
+    /// Why the composer is disabled, if it is.
+    fn post_denied(&self) -> Option<String> {
+        if self.room_locked {
+            return Some("Enter the room password to post".into());
+        }
+        self.can_post.denied_reason("post")
+    }
+
+    fn overlay(&self) -> Overlay {
+        Overlay {
+            settings: self.show_settings,
+            profile: self.profile_card.clone(),
+        }
+    }
+
+    /// Opens or closes dialogs through the router, so each one is a history
+    /// entry. Without a router, as in tests, they just open and close.
+    fn navigate(&mut self, ctx: &Context<Self>, overlay: Overlay) {
+        let history = match ctx.link().history() {
+            Some(history) => history,
+            None => {
+                self.show_settings = overlay.settings;
+                self.profile_card = overlay.profile;
+                return;
+            }
+        };
+        // Entries we pushed are marked, so closing can step back to the one before
+        let pushed = history.state::<bool>().unwrap_or(false);
+        let result = if overlay == Overlay::default() {
+            if pushed {
+                history.back();
+                return;
+            }
+            history.replace(Route::Chat);
+            Ok(())
+        } else if self.overlay() == Overlay::default() {
+            history.push_with_query_and_state(Route::Chat, &overlay, true)
+        } else {
+            // One dialog leading to another takes its place rather than stacking up
+            history.replace_with_query_and_state(Route::Chat, &overlay, pushed)
+        };
+        if let Err(e) = result {
+            log::error!("Can't update the address: {:?}", e);
+        }
+    }
+
+    /// Shows whichever dialog the address asks for, returning whether that changed anything.
+    fn apply_overlay(&mut self, ctx: &Context<Self>) -> bool {
+        let overlay = match ctx.link().location() {
+            Some(location) if location.route::<Route>() == Some(Route::Chat) => location.query::<Overlay>().unwrap_or_default(),
+            _ => return false,
+        };
+        if overlay == self.overlay() {
+            return false;
+        }
+        self.show_settings = overlay.settings;
+        self.profile_card = overlay.profile;
+        true
+    }
+
+    /// Jumps to the permalinked message once it's loaded, paging further back until it is.
+    fn seek(&mut self, ctx: &Context<Self>) {
+        let id = match &self.seeking {
+            Some(id) => id,
+            None => return,
+        };
+        if let Some(index) = self.messages.iter().position(|m| m.id.as_ref() == Some(id)) {
+            self.seeking = None;
+            ctx.link().send_message(Msg::JumpToMessage(index));
+        } else if self.history_exhausted {
+            self.seeking = None;
+            self.messages.push(MessageData::system("The linked message is no longer in the history".into()));
+        } else if !self.loading_history {
+            self.request_history();
+        }
+    }
+
+    /// Asks for the page of messages before the oldest one we have (or the latest page if none).
+    fn request_history(&mut self) {
+        self.loading_history = true;
+        let request = HistoryRequest {
+            before: self.messages.iter().find_map(|m| m.timestamp),
+            limit: HISTORY_PAGE_SIZE,
+        };
+        self.send(MsgTypes::History, serde_json::to_string(&request).unwrap());
+    }
+
+    /// Sends one of our chat messages, returning whether it was handed to the socket.
+    fn send_chat(&self, m: &MessageData) -> bool {
+        if self.connection != ConnectionStatus::Connected {
+            return false;
+        }
+        let message = WebSocketMessage {
+            message_type: MsgTypes::Message,
+            data: Some(m.message.clone()),
+            data_array: None,
+            reply_to: m.reply_to.clone(),
+            client_id: m.client_id.clone(),
+            media: m.media.clone(),
+            location: m.location,
+            reclaim: None,
+        };
+        match self.wss.tx.clone().try_send(serde_json::to_string(&message).unwrap()) {
+            Ok(()) => true,
+            Err(e) => {
+                log::debug!("Error sending to channel: {:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Adds `text` to the composer's recall buffer and leaves recall mode.
+    fn remember_sent(&mut self, text: &str) {
+        self.recall = None;
+        if self.sent_history.last().map(String::as_str) != Some(text) {
+            self.sent_history.push(text.to_string());
+        }
+        if self.sent_history.len() > RECALL_LIMIT {
+            self.sent_history.remove(0);
+        }
+    }
+
+    /// Runs of at least [`BURST_MIN`] rapid messages from one other person that reach
+    /// into `range`. They're found across the whole list, so a flood crossing the
+    /// edge of the window is kept whole rather than split.
+    fn bursts(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut bursts = Vec::new();
+        let mut start = 0;
+        while start < range.end {
+            let mut end = start + 1;
+            while end < self.messages.len() && rapid_followup(&self.messages[end - 1], &self.messages[end]) {
+                end += 1;
+            }
+            if end > range.start && end - start >= BURST_MIN && self.messages[start].from != self.username {
+                bursts.push(start..end);
+            }
+            start = end;
+        }
+        bursts
+    }
+
+    /// Whether `m` continues a flood that's already at the end of the list.
+    fn floods(&self, m: &MessageData) -> bool {
+        let mut run = 1;
+        let mut next = m;
+        for previous in self.messages.iter().rev() {
+            if !rapid_followup(previous, next) {
+                break;
+            }
+            run += 1;
+            next = previous;
+        }
+        run >= BURST_MIN
+    }
+
+    /// What the command palette offers: the room, the people in it and app actions.
+    fn palette_commands(&self, ctx: &Context<Self>) -> Vec<Command> {
+        let command = |label: String, group: &'static str, msg: fn() -> Msg| Command {
+            label,
+            group,
+            on_run: ctx.link().callback(move |_| msg()),
+        };
+        let mut commands = vec![command(format!("#{}", ROOM), "Rooms", || Msg::JumpToLatest)];
+        commands.extend(self.room_members().into_iter().filter(|m| m.name != self.username).map(|m| Command {
+            label: format!("@{}", m.name),
+            group: "People",
+            on_run: ctx.link().callback(move |_| Msg::MentionUser(m.name.clone())),
+        }));
+        commands.push(command("Jump to latest message".into(), "Commands", || Msg::JumpToLatest));
+        commands.push(command("Invite people".into(), "Commands", || Msg::ToggleInvite));
+        if self.can_post.allowed {
+            commands.push(command("Share location".into(), "Commands", || Msg::ShareLocation));
+        }
+        let me = self.username.clone();
+        commands.push(Command {
+            label: "Edit profile".into(),
+            group: "Commands",
+            on_run: ctx.link().callback(move |_| Msg::ShowProfile(Some(me.clone()))),
+        });
+        if !self.show_settings {
+            commands.push(command("Open settings".into(), "Commands", || Msg::ToggleSettings));
+        }
+        let mut themed = self.settings.clone();
+        themed.contrast = if themed.contrast.is_high() { Contrast::Standard } else { Contrast::High };
+        commands.push(Command {
+            label: if themed.contrast == Contrast::High { "Switch to high contrast" } else { "Switch to standard contrast" }.into(),
+            group: "Commands",
+            on_run: ctx.link().callback(move |_| Msg::UpdateSettings(themed.clone())),
+        });
+        for tab in [PanelTab::Members, PanelTab::Mentions, PanelTab::Media, PanelTab::Links] {
+            commands.push(Command {
+                label: format!("Show {}", tab.label().to_lowercase()),
+                group: "Commands",
+                on_run: ctx.link().callback(move |_| Msg::ShowPanel(Some(tab))),
+            });
+        }
+        commands.push(command("Print transcript".into(), "Commands", || Msg::TogglePrintView));
+        if self.signed_in {
+            commands.push(command("Your sessions".into(), "Commands", || Msg::ToggleSessions));
+        }
+        commands.push(command("Keyboard shortcuts".into(), "Commands", || Msg::ShowShortcuts(true)));
+        commands.push(command("Your stats".into(), "Commands", || Msg::ShowStats(true)));
+        if self.voice.is_some() {
+            commands.push(command(if self.voice_muted { "Unmute microphone" } else { "Mute microphone" }.into(), "Voice", || Msg::ToggleVoiceMute));
+            commands.push(command("Leave voice".into(), "Voice", || Msg::LeaveVoice));
+        } else {
+            commands.push(command("Join voice".into(), "Voice", || Msg::JoinVoice));
+        }
+        if let Some(call) = &self.call {
+            commands.push(command(if call.muted { "Unmute call" } else { "Mute call" }.into(), "Call", || Msg::ToggleCallMute));
+            commands.push(command(if call.camera_off { "Turn camera on" } else { "Turn camera off" }.into(), "Call", || Msg::ToggleCamera));
+            commands.push(command("Hang up".into(), "Call", || Msg::HangUp));
+        } else {
+            commands.extend(self.users.iter().filter(|u| u.name != self.username).map(|u| {
+                let name = u.name.clone();
+                Command {
+                    label: format!("Video call @{}", name),
+                    group: "Call",
+                    on_run: ctx.link().callback(move |_| Msg::StartCall(name.clone())),
+                }
+            }));
+        }
+        commands.push(command("Server info".into(), "Commands", || Msg::ToggleServerInfo));
+        commands.push(command("Log out".into(), "Commands", || Msg::LogOut));
+        // Logging out lands on the login form, which lists who else used this device
+        commands.push(command("Switch account".into(), "Commands", || Msg::LogOut));
+        commands
+    }
+
+    /// What the profile card shows for `name`; our own edits show up before the server echoes them.
+    fn profile_of(&self, name: &str) -> UserProfile {
+        let mut user = self
+            .room_members()
+            .into_iter()
+            .find(|m| m.name == name)
+            .unwrap_or_else(|| UserProfile::offline(name));
+        if name == self.username {
+            user.avatar = self.profile.avatar.url(name);
+            user.profile = self.profile.clone();
+        }
+        user
+    }
+
+    /// Everyone online plus anyone who has posted in the loaded conversation.
+    fn room_members(&self) -> Vec<UserProfile> {
+        let mut members = self.users.clone();
+        for m in self.messages.iter().filter(|m| !m.system) {
+            if !members.iter().any(|u| u.name == m.from) {
+                members.push(UserProfile::offline(&m.from));
+            }
+        }
+        members
+    }
+
+    /// Asks the server for the titles of links we haven't looked up yet.
+    fn request_link_titles(&mut self) {
+        let urls: Vec<String> = self
+            .messages
+            .iter()
+            .filter(|m| !m.system && !m.deleted)
+            .flat_map(|m| extract_urls(&m.message))
+            .filter(|url| !self.link_titles.contains_key(*url))
+            .map(String::from)
+            .collect();
+        for url in urls {
+            if self.link_titles.contains_key(&url) {
+                continue;
+            }
+            self.send(MsgTypes::Unfurl, serde_json::to_string(&UnfurlRequest { url: url.clone() }).unwrap());
+            self.link_titles.insert(url, None);
+        }
+    }
+
+    /// Arms the backoff timer for the next automatic retry of failed messages.
+    fn schedule_retry(&mut self, ctx: &Context<Self>) {
+        let attempts = self
+            .messages
+            .iter()
+            .filter(|m| m.status == DeliveryStatus::Failed && m.send_attempts < MAX_SEND_ATTEMPTS)
+            .map(|m| m.send_attempts)
+            .min();
+        self.retry_timer = attempts.map(|attempts| {
+            let link = ctx.link().clone();
+            let delay = RETRY_BASE_DELAY_MS * 2u32.pow(attempts.saturating_sub(1));
+            Timeout::new(delay, move || link.send_message(Msg::RetryFailed))
+        });
+    }
+
+    /// Inserts a message in sequence order, so resynced gaps land where they belong.
+    fn insert_message(&mut self, m: MessageData) {
+        self.mentions.add(&m);
+        self.stats.record(&m, &self.username, ROOM);
+        let index = m
+            .seq
+            .and_then(|seq| self.messages.iter().position(|e| e.seq.map_or(false, |s| s > seq)))
+            .unwrap_or(self.messages.len());
+        self.messages.insert(index, m);
+        self.shift_indices(|i| Some(if i >= index { i + 1 } else { i }));
+    }
+
+    fn remove_message(&mut self, index: usize) -> MessageData {
+        let removed = self.messages.remove(index);
+        self.shift_indices(|i| match i {
+            i if i == index => None,
+            i if i > index => Some(i - 1),
+            i => Some(i),
+        });
+        removed
+    }
+
+    /// Keeps everything that refers to messages by index pointing at the same
+    /// message after the list changed. `shift` returns `None` for a removed one.
+    fn shift_indices(&mut self, shift: impl Fn(usize) -> Option<usize>) {
+        self.replying_to = self.replying_to.and_then(&shift);
+        self.action_sheet = self.action_sheet.and_then(&shift);
+        self.lightbox = self.lightbox.and_then(&shift);
+        self.flash = self.flash.and_then(&shift);
+        self.scroll_anchor = self.scroll_anchor.and_then(|(i, offset)| shift(i).map(|i| (i, offset)));
+        self.window_start = shift(self.window_start).unwrap_or(self.window_start);
+        self.confirming = self.confirming.and_then(|action| action.map_index(&shift));
+        self.editing = self.editing.and_then(&shift);
+        self.edit_history = self.edit_history.and_then(&shift);
+        self.focused = self.focused.and_then(&shift);
+        self.reveal = self.reveal.and_then(&shift);
+    }
+
+    fn own_role(&self) -> Role {
+        self.users.iter().find(|u| u.name == self.username).map(|u| u.role).unwrap_or_default()
+    }
+
+    fn announce(&mut self, ctx: &Context<Self>, text: String) {
+        let link = ctx.link().clone();
+        self.announcer.announce(text, move || link.send_message(Msg::FlushAnnouncements));
+    }
+
+    /// The message `step` places from the keyboard-selected one, skipping notices.
+    /// With nothing selected yet, the newest message on screen is picked.
+    fn step_focus(&self, step: isize) -> Option<usize> {
+        let selectable: Vec<usize> = (0..self.messages.len()).filter(|&i| !self.messages[i].system).collect();
+        let last = selectable.len().checked_sub(1)?;
+        let position = match self.focused.and_then(|f| selectable.iter().position(|&i| i == f)) {
+            Some(current) => (current as isize).saturating_add(step).clamp(0, last as isize) as usize,
+            None => {
+                let end = self.visible_range().end;
+                selectable.iter().rposition(|&i| i < end).unwrap_or(last)
+            }
+        };
+        Some(selectable[position])
+    }
+
+    fn focus_message(&mut self, index: usize) {
+        if !self.visible_range().contains(&index) {
+            self.follow_tail = false;
+            self.window_start = index.saturating_sub(WINDOW_SIZE / 2);
+        }
+        self.focused = Some(index);
+        self.reveal = Some(index);
+    }
+
+    /// Scrolls the least needed to show the whole of a message.
+    fn reveal_message(index: usize) {
+        if let (Some(container), Some(el)) = (Self::message_container(), message_element(index)) {
+            let top = container.scroll_top();
+            let height = container.client_height();
+            if el.offset_top() < top {
+                container.set_scroll_top(el.offset_top());
+            } else if el.offset_top() + el.offset_height() > top + height {
+                container.set_scroll_top(el.offset_top() + el.offset_height() - height);
+            }
+        }
+    }
+
+    /// Runs `action` straight away if the user opted out of confirming it,
+    /// otherwise opens the confirmation dialog.
+    fn ask(&mut self, ctx: &Context<Self>, action: PendingAction) {
+        if action.confirm().skipped() {
+            self.perform(ctx, action);
+        } else {
+            self.confirming = Some(action);
+        }
+    }
+
+    fn perform(&mut self, ctx: &Context<Self>, action: PendingAction) {
+        match action {
+            PendingAction::DeleteMessage(index) => {
+                if let Some(m) = self.messages.get(index) {
+                    if m.from == self.username {
+                        let delete = DeleteData {
+                            from: self.username.clone(),
+                            target: m.to_ref(),
+                        };
+                        self.send(MsgTypes::Delete, serde_json::to_string(&delete).unwrap());
                     }
-                </div>
-                
-                // Main chat area
-                <div class="grow h-screen flex flex-col">
-                    // Chat header
-                    <div class="w-full h-16 bg-white shadow-sm flex items-center px-6">
-                        <div class="text-xl font-semibold">{"💬 Chat Room"}</div>
-                        <div class="ml-3 text-sm text-gray-500">{format!("{} participants", self.users.len())}</div>
-                    </div>
-                    
-                    // Messages container
-                    <div id="message-container" class="w-full grow overflow-auto p-6 space-y-4">
-                        {
-                            self.messages.iter().map(|m| {
-                                let is_self = m.from == self.username;
-                                let user = self.users.iter().find(|u| u.name == m.from);
-                                
-                                html!{
-                                    <div class={classes!(
-                                        "flex", 
-                                        "max-w-md",
-                                        if is_self { "ml-auto flex-row-reverse" } else { "" }
-                                    )}>
-                                        if let Some(user) = user {
-                                            <img 
-                                                class="w-8 h-8 rounded-full mt-1" 
-                                                src={user.avatar.clone()} 
-                                                alt="avatar"
-                                            />
-                                        }
-                                        
-                                        <div class={classes!(
-                                            "mx-3", 
-                                            "p-3", 
-                                            "rounded-lg", 
-                                            if is_self { 
-                                                "bg-blue-500 text-white rounded-br-none" 
-                                            } else { 
-                                                "bg-gray-100 text-gray-800 rounded-bl-none" 
-                                            }
-                                        )}>
-                                            if !is_self {
-                                                <div class="text-sm font-medium mb-1">
-                                                    {m.from.clone()}
-                                                </div>
-                                            }
-                                            
-                                            <div class={if is_self { "text-white" } else { "text-gray-800" }}>
-                                                if m.message.ends_with(".gif") {
-                                                    <div class="mt-1 relative">
-                                                        <div class="absolute inset-0 flex items-center justify-center bg-gray-200 bg-opacity-50">
-                                                            {"Loading GIF..."}
-                                                        </div>
-                                                        <img 
-                                                            class="max-w-xs rounded" 
-                                                            src={m.message.clone()} 
-                                                            alt="GIF" 
-                                                            onload={Callback::from(|_| {
-                                                                // Handle image load event
-                                                            })}
-                                                        />
-                                                    </div>
-                                                } else {
-                                                    {m.message.clone()}
-                                                }
-                                            </div>
-                                            
-                                            // Time stamp
-                                            <div class={classes!(
-                                                "text-xs", 
-                                                "mt-1",
-                                                if is_self { "text-blue-100" } else { "text-gray-500" }
-                                            )}>
-                                                {
-                                                    m.timestamp.map_or_else(
-                                                        || "Just now".to_string(),
-                                                        |ts| format!("{}", ts) // Format timestamp properly in production
-                                                    )
-                                                }
-                                            </div>
-                                        </div>
-                                    </div>
-                                }
-                            }).collect::<Html>()
-                        }
-                    </div>
-                    
-                    // Input area
-                    <div class="w-full bg-white p-4 shadow-lg">
-                        <div class="flex items-center">
-                            // Emoji picker button
-                            <button 
-                                onclick={toggle_emoji}
-                                class="p-2 text-gray-500 hover:text-gray-700 focus:outline-none"
-                            >
-                                {"😀"}
-                            </button>
-                            
-                            // Message input
-                            <input 
-                                ref={self.chat_input.clone()} 
-                                type="text" 
-                                placeholder="Type a message..." 
-                                class="block w-full py-3 px-4 mx-3 bg-gray-100 rounded-full outline-none focus:ring-2 focus:ring-blue-400 focus:bg-white" 
-                                name="message" 
-                                required=true 
-                                onkeypress={on_keypress}
-                            />
-                            
-                            // Send button
-                            <button 
-                                onclick={submit} 
-                                class="p-3 bg-blue-600 hover:bg-blue-700 w-12 h-12 rounded-full flex justify-center items-center text-white transition-colors"
-                            >
-                                <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-6 h-6 fill-current">
-                                    <path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"></path>
-                                </svg>
-                            </button>
-                        </div>
-                        
-                        // Emoji picker popup (simplified version)
-                        if self.show_emoji_picker {
-                            <div class="absolute bottom-16 left-4 bg-white p-2 rounded-lg shadow-lg grid grid-cols-8 gap-1">
-                                {
-                                    ["😀", "😁", "😂", "🤣", "😃", "😄", "😅", "😆", 
-                                     "😉", "😊", "😋", "😎", "😍", "😘", "🥰", "😗"].iter().map(|emoji| {
-                                        let emoji_val = emoji.to_string();
-                                        let on_click = ctx.link().callback(move |_| Msg::InsertEmoji(emoji_val.clone()));
-                                        html! {
-                                            <button 
-                                                onclick={on_click} 
-                                                class="w-8 h-8 hover:bg-gray-100 rounded cursor-pointer flex items-center justify-center"
-                                            >
-                                                {emoji}
-                                            </button>
-                                        }
-                                    }).collect::<Html>()
-                                }
-                            </div>
-                        }
-                    </div>
-                </div>
-            </div>
+                }
+                if self.replying_to == Some(index) {
+                    self.replying_to = None;
+                }
+            }
+            PendingAction::DiscardFailed(index) => {
+                if self.messages.get(index).map_or(false, |m| m.status == DeliveryStatus::Failed) {
+                    self.remove_message(index);
+                }
+            }
+            PendingAction::ResetSettings => {
+                ctx.link().send_message(Msg::UpdateSettings(Settings::default()));
+            }
+        }
+    }
+
+    /// Messages currently rendered: a window of at most [`WINDOW_SIZE`] that either
+    /// follows the newest message or sits where the reader scrolled to.
+    fn visible_range(&self) -> Range<usize> {
+        let len = self.messages.len();
+        let start = if self.follow_tail {
+            len.saturating_sub(WINDOW_SIZE)
+        } else {
+            self.window_start.min(len.saturating_sub(WINDOW_SIZE))
+        };
+        start..(start + WINDOW_SIZE).min(len)
+    }
+
+    /// The first message at least partly on screen and its distance from the top
+    /// of the viewport, to be restored with [`Chat::restore_anchor`] after a render.
+    fn capture_anchor(&self) -> Option<(usize, i32)> {
+        let container = Self::message_container()?;
+        let top = container.scroll_top();
+        self.visible_range().find_map(|i| {
+            let el = message_element(i)?;
+            (el.offset_top() + el.offset_height() > top).then(|| (i, el.offset_top() - top))
+        })
+    }
+
+    fn save_scroll(&self) -> ScrollState {
+        ScrollState {
+            anchor: self
+                .capture_anchor()
+                .map(|(i, offset)| (self.messages[i].to_ref(), offset)),
+            at_bottom: self.pinned_to_bottom,
+            last_seen: self.messages.iter().rev().find(|m| !m.system && m.status == DeliveryStatus::Sent).map(MessageData::to_ref),
+        }
+    }
+
+    /// Returns to a saved position, or the bottom if it's no longer loaded.
+    fn restore_scroll(&mut self, state: ScrollState) {
+        self.unread_after = state.last_seen;
+        let anchor = state
+            .anchor
+            .filter(|_| !state.at_bottom)
+            .and_then(|(target, offset)| Some((self.messages.iter().rposition(|m| target.matches(m))?, offset)));
+        match anchor {
+            Some((index, offset)) => {
+                self.follow_tail = false;
+                self.window_start = index.saturating_sub(WINDOW_SIZE / 2);
+                self.scroll_anchor = Some((index, offset));
+            }
+            None => {
+                self.follow_tail = true;
+                self.scroll_to_bottom = true;
+            }
+        }
+    }
+
+    /// Index of the first message that arrived after `unread_after`, where the divider goes.
+    fn unread_divider(&self) -> Option<usize> {
+        let last_seen = self.unread_after.as_ref()?;
+        let seen = self.messages.iter().rposition(|m| last_seen.matches(m))?;
+        (seen + 1..self.messages.len()).find(|i| {
+            let m = &self.messages[*i];
+            !m.system && m.from != self.username
+        })
+    }
+
+    fn restore_anchor(&self, (index, offset): (usize, i32)) {
+        if let (Some(container), Some(el)) = (Self::message_container(), message_element(index)) {
+            container.set_scroll_top(el.offset_top() - offset);
+        }
+    }
+
+    /// Closes the connection, clears what this device kept for whoever was
+    /// signed in, and goes back to the login form. Device settings stay.
+    fn sign_out(&mut self, ctx: &Context<Self>) {
+        self.voice = None;
+        if let Some(call) = self.call.take() {
+            self.send_call_signal(&call.peer, CallPayload::HangUp);
+        }
+        self.incoming_call = None;
+        self.wss.close();
+        self.signed_out = true;
+        Session::forget();
+        LastConversation::forget();
+        MentionIndex::forget();
+        UsageStats::forget();
+        for key in [
+            RECLAIM_KEY.to_string(),
+            FAVORITES_KEY.to_string(),
+            Profile::STORAGE_KEY.to_string(),
+            Draft::storage_key(ROOM),
+            ScrollState::storage_key(ROOM),
+            ReadMarker::storage_key(ROOM),
+            // Bridge credentials belong to whoever set them up
+            MatrixConfig::STORAGE_KEY.to_string(),
+            IrcConfig::STORAGE_KEY.to_string(),
+        ] {
+            storage::remove(&key);
+        }
+        if let Some(history) = ctx.link().history() {
+            history.push(Route::Login);
+        }
+    }
+
+    /// Joins the room under our name, reclaiming it if we held it before.
+    fn register(&self) {
+        let message = WebSocketMessage {
+            message_type: MsgTypes::Register,
+            data: Some(self.username.clone()),
+            data_array: None,
+            reply_to: None,
+            client_id: None,
+            media: None,
+            location: None,
+            reclaim: storage::load_for_tab(RECLAIM_KEY),
+        };
+        if let Err(e) = self.wss.tx.clone().try_send(serde_json::to_string(&message).unwrap()) {
+            log::debug!("Error sending to channel: {:?}", e);
+        }
+        // Our devices may have read on without us, or we without them
+        if let Some(marker) = &self.read_marker {
+            self.send(MsgTypes::ReadMarker, serde_json::to_string(marker).unwrap());
+        }
+        if self.settings.sync_drafts && self.draft.time > 0 {
+            self.send(MsgTypes::Draft, serde_json::to_string(&self.draft).unwrap());
+        }
+    }
+
+    /// Keeps the draft in step with the composer, sending it on once typing pauses.
+    fn set_draft(&mut self, ctx: &Context<Self>, text: String) {
+        if text == self.draft.text {
+            return;
+        }
+        self.draft = Draft {
+            room: ROOM.to_string(),
+            text,
+            time: js_sys::Date::now() as i64,
+        };
+        storage::save(&Draft::storage_key(ROOM), &self.draft);
+        if self.settings.sync_drafts {
+            let link = ctx.link().clone();
+            self.draft_timer = Some(Timeout::new(DRAFT_SYNC_DELAY_MS, move || link.send_message(Msg::SyncDraft)));
+        }
+    }
+
+    fn room_state(&self) -> RoomState {
+        if self.messages.iter().any(|m| !m.system) {
+            RoomState::Ready
+        } else if !self.was_connected || self.loading_history {
+            RoomState::Loading
+        } else {
+            RoomState::Empty
+        }
+    }
+
+    /// Sends an attachment to the server, from the start if it was tried before.
+    fn start_upload(&mut self, ctx: &Context<Self>, id: u32) {
+        if let Some(attachment) = self.attachments.iter_mut().find(|a| a.id == id) {
+            // Any earlier attempt is dropped, and with it cancelled
+            let token = storage::load_for_tab::<String>(RECLAIM_KEY).unwrap_or_default();
+            attachment.upload =
+                Upload::start(&attachment.file, &token, ctx.link().callback(move |e| Msg::UploadEvent(id, e)));
+            attachment.sent = 0.0;
+            attachment.error = attachment.upload.is_none().then(|| "Couldn't start the upload".to_string());
+        }
+    }
+
+    /// Posts the link to an uploaded file like a message typed into the composer.
+    fn post_attachment(&mut self, ctx: &Context<Self>, url: String, sensitive: bool) {
+        let media = (sensitive && is_image_url(&url)).then(|| MediaMeta {
+            sensitive: true,
+            ..Default::default()
+        });
+        let message = MessageData {
+            from: self.username.clone(),
+            message: url,
+            client_id: Some(new_client_id()),
+            media,
+            status: DeliveryStatus::Sending,
+            send_attempts: 1,
+            ..Default::default()
+        };
+        self.post_now(ctx, message);
+    }
+
+    /// Sends a message that skips the composer, keeping it to retry if that fails.
+    fn post_now(&mut self, ctx: &Context<Self>, mut message: MessageData) {
+        if !self.send_chat(&message) {
+            message.status = DeliveryStatus::Failed;
+            self.messages.push(message);
+            self.schedule_retry(ctx);
+        } else {
+            self.messages.push(message);
+        }
+        self.follow_tail = true;
+        self.scroll_to_bottom = true;
+        self.unread_after = None;
+    }
+
+    /// Moves the read marker up to the newest message once the reader can see it.
+    fn mark_read(&mut self) {
+        let visible = matches!(web_sys::window().and_then(|w| w.document()), Some(d) if !d.hidden());
+        if !self.pinned_to_bottom || !visible {
+            return;
+        }
+        let newest = self
+            .messages
+            .iter()
+            .rev()
+            .find(|m| !m.system && m.status == DeliveryStatus::Sent)
+            .and_then(ReadMarker::at);
+        let newest = match newest {
+            Some(newest) => newest,
+            None => return,
+        };
+        if matches!(&self.read_marker, Some(marker) if !newest.is_after(marker)) {
+            return;
+        }
+        self.mentions.mark_read_until(newest.time);
+        storage::save(&ReadMarker::storage_key(ROOM), &newest);
+        self.send(MsgTypes::ReadMarker, serde_json::to_string(&newest).unwrap());
+        self.read_marker = Some(newest);
+    }
+
+    /// Takes on a read marker from another of our devices, if it's ahead of ours.
+    fn apply_read_marker(&mut self, marker: ReadMarker) -> bool {
+        if matches!(&self.read_marker, Some(ours) if !marker.is_after(ours)) {
+            return false;
+        }
+        self.mentions.mark_read_until(marker.time);
+        // The "new messages" divider moves past what was read elsewhere
+        if self.unread_after.is_some() {
+            if let Some(m) = self.messages.iter().rev().find(|m| !m.system && marker.covers(m)) {
+                self.unread_after = Some(m.to_ref());
+            }
+        }
+        storage::save(&ReadMarker::storage_key(ROOM), &marker);
+        self.read_marker = Some(marker);
+        true
+    }
+
+    /// Tells the server whether we're in the room's voice channel, and muted.
+    fn send_voice_update(&self) {
+        let update = VoiceUpdate {
+            room: ROOM.to_string(),
+            joined: self.voice.is_some(),
+            muted: self.voice_muted,
+        };
+        self.send(MsgTypes::Voice, serde_json::to_string(&update).unwrap());
+    }
+
+    fn send_call_signal(&self, to: &str, payload: CallPayload) {
+        let signal = CallSignal { from: String::new(), to: to.to_string(), payload };
+        self.send(MsgTypes::Call, serde_json::to_string(&signal).unwrap());
+    }
+
+    /// Hangs up without telling the other side and notes the call in the
+    /// conversation: how long it lasted, or `unanswered` if it never connected.
+    fn end_call(&mut self, ctx: &Context<Self>, unanswered: String) {
+        let call = match self.call.take() {
+            Some(call) => call,
+            None => return,
+        };
+        self.call_timeout = None;
+        let note = match call.duration() {
+            Some(ms) => format!("Video call with {} · {}", call.peer, format_duration(ms)),
+            None => unanswered,
+        };
+        self.announce(ctx, note.clone());
+        self.messages.push(MessageData::system(note));
+    }
+
+    fn send(&self, message_type: MsgTypes, data: String) {
+        let message = WebSocketMessage {
+            message_type,
+            data: Some(data),
+            data_array: None,
+            reply_to: None,
+            client_id: None,
+            media: None,
+            location: None,
+            reclaim: None,
+        };
+        if let Err(e) = self.wss.tx.clone().try_send(serde_json::to_string(&message).unwrap()) {
+            log::debug!("Error sending to channel: {:?}", e);
         }
     }
-}
\ No newline at end of file
+
+    fn find_message(&mut self, target: &MessageRef) -> Option<&mut MessageData> {
+        self.messages
+            .iter_mut()
+            .rev()
+            .find(|m| target.matches(m))
+    }
+}
+
+#[cfg(test)]
+mod proptests;
+#[cfg(test)]
+mod tests;
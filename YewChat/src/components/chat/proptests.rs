@@ -0,0 +1,265 @@
+use proptest::{
+    collection::{btree_map, vec},
+    option,
+    prelude::*,
+    sample::select,
+    strategy::ValueTree,
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use wasm_bindgen_test::*;
+
+use super::{
+    tests::Session, CatchupRequest, DeleteData, EditData, HistoryRequest, MediaMeta, MessageData, MessageRef,
+    MsgTypes, ReactionData, RenameData, ReplyPreview, ResyncRequest, UnfurlData, UnfurlRequest, WebSocketMessage,
+};
+use crate::components::{avatar::AvatarProvider, location::Location, profile::Profile, testing::settle};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+// Every `messageType` on the wire, as `MsgTypes` spells them
+const MESSAGE_TYPES: [&str; 33] = [
+    "users", "register", "registererror", "message", "rename", "renameerror", "reaction", "delete", "history",
+    "resync", "catchup", "unfurl", "permissions", "createinvite", "invite", "joinbytoken", "joined", "joinroom",
+    "joinedroom", "room", "roomicon", "edit", "ping", "pong", "challenge", "solve", "profileupdate",
+    "registered", "sessions", "signout", "signedout",
+    "readmarker", "draft",
+];
+
+// Field names the client looks for, so made-up objects often get past the first field
+const KEYS: [&str; 38] = [
+    "id", "seq", "from", "message", "time", "clientId", "replyTo", "media", "width", "height", "target",
+    "emoji", "to", "url", "title", "token", "room", "error", "members", "nick", "role", "status", "profile",
+    "post", "topic", "allowed", "audience", "icon", "locked", "after", "before", "limit", "provider",
+    "sessions", "device", "since", "current", "sensitive",
+];
+
+// Frames fed to a mounted chat in `hostile_frames_never_break_the_chat`
+const HOSTILE_FRAMES: usize = 400;
+// Frames pushed between letting the chat catch up
+const HOSTILE_BATCH: usize = 25;
+
+fn config() -> Config {
+    Config {
+        cases: 64,
+        // There's no file system to record failing cases in
+        failure_persistence: None,
+        ..Config::default()
+    }
+}
+
+fn round_trips<T: Serialize + DeserializeOwned>(value: &T) -> Result<(), TestCaseError> {
+    let json = serde_json::to_string(value).unwrap();
+    let back: T = serde_json::from_str(&json).map_err(|e| TestCaseError::fail(format!("{}: {}", e, json)))?;
+    prop_assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    Ok(())
+}
+
+fn message_type() -> impl Strategy<Value = MsgTypes> {
+    select(&MESSAGE_TYPES[..]).prop_map(|name| serde_json::from_value(json!(name)).unwrap())
+}
+
+fn message_ref() -> impl Strategy<Value = MessageRef> {
+    (option::of(any::<String>()), any::<String>(), option::of(any::<i64>()))
+        .prop_map(|(id, from, timestamp)| MessageRef { id, from, timestamp })
+}
+
+fn reply_preview() -> impl Strategy<Value = ReplyPreview> {
+    (any::<String>(), any::<String>()).prop_map(|(from, message)| ReplyPreview { from, message })
+}
+
+fn media_meta() -> impl Strategy<Value = MediaMeta> {
+    (any::<u32>(), any::<u32>(), option::of(any::<String>()), option::of(any::<String>()), any::<bool>()).prop_map(
+        |(width, height, placeholder, poster, sensitive)| MediaMeta { width, height, placeholder, poster, sensitive },
+    )
+}
+
+// Finite only: JSON has no NaN or infinity
+fn location() -> impl Strategy<Value = Location> {
+    (-90.0..=90.0f64, -180.0..=180.0f64, 0.0..1e6f64).prop_map(|(lat, lon, accuracy)| Location { lat, lon, accuracy })
+}
+
+fn websocket_message() -> impl Strategy<Value = WebSocketMessage> {
+    (
+        message_type(),
+        option::of(vec(any::<String>(), 0..4)),
+        option::of(any::<String>()),
+        option::of(reply_preview()),
+        option::of(any::<String>()),
+        option::of(media_meta()),
+        option::of(location()),
+        option::of(any::<String>()),
+    )
+        .prop_map(|(message_type, data_array, data, reply_to, client_id, media, location, reclaim)| WebSocketMessage {
+            message_type,
+            data_array,
+            data,
+            reply_to,
+            client_id,
+            media,
+            location,
+            reclaim,
+        })
+}
+
+fn profile() -> impl Strategy<Value = Profile> {
+    let avatar = prop_oneof![
+        Just(AvatarProvider::Identicon),
+        any::<String>().prop_map(|hash| AvatarProvider::Gravatar { hash }),
+    ];
+    (any::<String>(), any::<String>(), any::<String>(), vec(any::<String>(), 0..4), avatar).prop_map(
+        |(bio, pronouns, timezone, links, avatar)| Profile { bio, pronouns, timezone, links, avatar },
+    )
+}
+
+/// Any JSON at all, with objects leaning towards the field names the client reads.
+fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        any::<String>().prop_map(Value::from),
+    ];
+    let key = prop_oneof![3 => select(&KEYS[..]).prop_map(String::from), 1 => any::<String>()];
+    leaf.prop_recursive(4, 64, 8, move |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(Value::from),
+            btree_map(key.clone(), inner, 0..8).prop_map(|fields| Value::Object(fields.into_iter().collect())),
+        ]
+    })
+}
+
+/// Chat messages that parse but carry extreme or inconsistent values.
+fn message_json() -> impl Strategy<Value = Value> {
+    (
+        option::of(any::<String>()),
+        any::<u64>(),
+        prop_oneof![Just("alice".to_string()), Just("bob".to_string()), any::<String>()],
+        any::<String>(),
+        any::<i64>(),
+        option::of(any::<String>()),
+        option::of(media_meta()),
+    )
+        .prop_map(|(id, seq, from, message, time, client_id, media)| {
+            json!({
+                "id": id, "seq": seq, "from": from, "message": message, "time": time,
+                "clientId": client_id, "media": media,
+            })
+        })
+}
+
+/// Something a broken or hostile server might send.
+fn hostile_frame() -> impl Strategy<Value = String> {
+    let bytes = vec(any::<u8>(), 0..256).prop_map(|b| String::from_utf8_lossy(&b).into_owned());
+    let payload = prop_oneof![
+        any::<String>(),
+        json_value().prop_map(|v| v.to_string()),
+        message_json().prop_map(|v| v.to_string()),
+    ];
+    let framed = (
+        select(&MESSAGE_TYPES[..]),
+        option::of(payload.clone()),
+        option::of(vec(payload, 0..8)),
+        json_value(),
+    )
+        .prop_map(|(message_type, data, data_array, extra)| {
+            json!({ "messageType": message_type, "data": data, "dataArray": data_array, "replyTo": extra })
+                .to_string()
+        });
+    prop_oneof![
+        1 => bytes,
+        1 => json_value().prop_map(|v| v.to_string()),
+        4 => framed,
+    ]
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    #[wasm_bindgen_test]
+    fn frames_round_trip(frame in websocket_message()) {
+        round_trips(&frame)?;
+    }
+
+    #[wasm_bindgen_test]
+    fn payloads_round_trip(
+        target in message_ref(),
+        from in any::<String>(),
+        text in any::<String>(),
+        seq in any::<u64>(),
+        time in option::of(any::<i64>()),
+        title in option::of(any::<String>()),
+    ) {
+        round_trips(&target)?;
+        round_trips(&ReactionData { from: from.clone(), target: target.clone(), emoji: text.clone() })?;
+        round_trips(&DeleteData { from: from.clone(), target: target.clone() })?;
+        round_trips(&EditData { from: from.clone(), target, message: text.clone() })?;
+        round_trips(&RenameData { from, to: text.clone() })?;
+        round_trips(&ResyncRequest { from: seq / 2, to: seq })?;
+        round_trips(&CatchupRequest { after: seq })?;
+        round_trips(&HistoryRequest { before: time, limit: seq as usize })?;
+        round_trips(&UnfurlRequest { url: text.clone() })?;
+        round_trips(&UnfurlData { url: text, title })?;
+    }
+
+    #[wasm_bindgen_test]
+    fn attachments_and_profiles_round_trip(reply in reply_preview(), media in media_meta(), profile in profile()) {
+        round_trips(&reply)?;
+        round_trips(&media)?;
+        round_trips(&profile)?;
+    }
+
+    #[wasm_bindgen_test]
+    fn server_messages_parse(
+        id in option::of(any::<String>()),
+        seq in option::of(any::<u64>()),
+        from in any::<String>(),
+        message in any::<String>(),
+        timestamp in option::of(any::<i64>()),
+        reply_to in option::of(reply_preview()),
+        client_id in option::of(any::<String>()),
+        media in option::of(media_meta()),
+    ) {
+        let wire = json!({
+            "id": id, "seq": seq, "from": from, "message": message, "time": timestamp,
+            "replyTo": reply_to, "clientId": client_id, "media": media,
+        });
+        let expected = MessageData { id, seq, from, message, timestamp, reply_to, client_id, media, ..Default::default() };
+        let parsed: MessageData = serde_json::from_str(&wire.to_string()).unwrap();
+        prop_assert!(parsed == expected, "{} parsed differently", wire);
+    }
+
+    #[wasm_bindgen_test]
+    fn garbage_never_panics_the_parsers(frame in hostile_frame()) {
+        // Errors are fine, panics are not
+        let _ = serde_json::from_str::<WebSocketMessage>(&frame);
+        let _ = serde_json::from_str::<MessageData>(&frame);
+    }
+}
+
+// A fixed seed, so a failure here reproduces on every run
+#[wasm_bindgen_test]
+async fn hostile_frames_never_break_the_chat() {
+    let session = Session::new().await;
+    let mut runner = TestRunner::new_with_rng(config(), TestRng::deterministic_rng(RngAlgorithm::ChaCha));
+    let strategy = hostile_frame();
+    for batch in 0..HOSTILE_FRAMES / HOSTILE_BATCH {
+        for _ in 0..HOSTILE_BATCH {
+            let frame = strategy.new_tree(&mut runner).unwrap().current();
+            // Shown if the chat panics, as the last lines before the failure
+            console_log!("frame: {}", frame);
+            session.mock.push_raw(frame);
+        }
+        settle().await;
+        assert!(session.chat.find("input[name=message]").is_some(), "chat gone after batch {}", batch);
+    }
+
+    // Still listening after all that
+    session.mock.push(json!({ "messageType": "users", "dataArray": ["alice", "still-here"] }));
+    settle().await;
+    assert!(session.chat.text().contains("still-here"));
+    session.chat.unmount();
+}
@@ -0,0 +1,180 @@
+use std::{cell::RefCell, rc::Rc};
+
+use serde_json::{json, Value};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use web_sys::{HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+use super::{Chat, MediaMeta, MessageData, MsgTypes, ReplyPreview, WebSocketMessage};
+use crate::{
+    components::testing::{settle, Mounted},
+    services::{transport::mock::MockTransport, websocket::MOCK},
+    User, UserInner,
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn outgoing_frames_round_trip() {
+    let frame = WebSocketMessage {
+        message_type: MsgTypes::Message,
+        data: Some("hi".into()),
+        data_array: None,
+        reply_to: Some(ReplyPreview { from: "bob".into(), message: "hello".into() }),
+        client_id: Some("c1".into()),
+        media: Some(MediaMeta { width: 320, height: 240, ..Default::default() }),
+        location: None,
+        reclaim: None,
+    };
+    let json = serde_json::to_string(&frame).unwrap();
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["messageType"], "message");
+    assert_eq!(value["clientId"], "c1");
+    assert_eq!(value["replyTo"]["from"], "bob");
+    assert_eq!(value["media"]["width"], 320);
+
+    let back: WebSocketMessage = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&back).unwrap(), json);
+}
+
+#[wasm_bindgen_test]
+fn unset_extras_stay_off_the_wire() {
+    let frame = WebSocketMessage {
+        message_type: MsgTypes::Register,
+        data: Some("alice".into()),
+        data_array: None,
+        reply_to: None,
+        client_id: None,
+        media: None,
+        location: None,
+        reclaim: None,
+    };
+    let value: Value = serde_json::to_value(&frame).unwrap();
+    let keys: Vec<_> = value.as_object().unwrap().keys().cloned().collect();
+    assert_eq!(keys, vec!["data", "dataArray", "messageType"]);
+}
+
+#[wasm_bindgen_test]
+fn server_frames_parse() {
+    let frame: WebSocketMessage =
+        serde_json::from_str(r#"{"messageType":"users","dataArray":["alice","bob"],"data":null}"#).unwrap();
+    assert!(matches!(frame.message_type, MsgTypes::Users));
+    assert_eq!(frame.data_array, Some(vec!["alice".to_string(), "bob".to_string()]));
+    assert!(serde_json::from_str::<WebSocketMessage>(r#"{"messageType":"nonsense"}"#).is_err());
+}
+
+#[wasm_bindgen_test]
+fn message_data_reads_server_json() {
+    let m: MessageData = serde_json::from_str(
+        r#"{"id":"7","seq":7,"from":"bob","message":"hi","time":1700000000000,"clientId":"c1",
+            "replyTo":{"from":"alice","message":"hey"},"media":{"width":10,"height":20},"extra":true}"#,
+    )
+    .unwrap();
+    assert_eq!(m.id.as_deref(), Some("7"));
+    assert_eq!(m.seq, Some(7));
+    assert_eq!(m.timestamp, Some(1_700_000_000_000));
+    assert_eq!(m.client_id.as_deref(), Some("c1"));
+    assert_eq!(m.reply_to.map(|r| r.from), Some("alice".to_string()));
+    assert_eq!(m.media.map(|m| (m.width, m.height)), Some((10, 20)));
+    // What the client tracks itself never comes off the wire
+    assert!(m.reactions.is_empty() && !m.deleted && !m.system);
+}
+
+#[wasm_bindgen_test]
+fn message_data_needs_only_sender_and_text() {
+    let m: MessageData = serde_json::from_str(r#"{"from":"bob","message":"hi","time":null}"#).unwrap();
+    assert!(m.id.is_none() && m.seq.is_none() && m.timestamp.is_none());
+    assert!(serde_json::from_str::<MessageData>(r#"{"from":"bob"}"#).is_err());
+}
+
+#[function_component(Harness)]
+pub(super) fn harness() -> Html {
+    let user = use_state(|| {
+        Rc::new(UserInner {
+            username: RefCell::new("alice".into()),
+            register_error: RefCell::new(None),
+            pending_invite: RefCell::new(None),
+            pending_permalink: RefCell::new(None),
+            challenge: RefCell::new(None),
+            captcha_token: RefCell::new(None),
+            session: RefCell::new(None),
+        })
+    });
+    html! {
+        <ContextProvider<User> context={(*user).clone()}>
+            <Chat />
+        </ContextProvider<User>>
+    }
+}
+
+// A chat for "alice" talking to a mock server
+pub(super) struct Session {
+    pub(super) mock: MockTransport,
+    pub(super) chat: Mounted<Harness>,
+}
+
+impl Session {
+    pub(super) async fn new() -> Self {
+        let mock = MockTransport::default();
+        MOCK.with(|m| *m.borrow_mut() = Some(mock.clone()));
+        let chat = Mounted::new(());
+        settle().await;
+        Self { mock, chat }
+    }
+
+    fn sent(&self, message_type: &str) -> Vec<Value> {
+        self.mock.sent().into_iter().filter(|f| f["messageType"] == message_type).collect()
+    }
+}
+
+#[wasm_bindgen_test]
+async fn registers_and_asks_for_history() {
+    let session = Session::new().await;
+    let register = session.sent("register");
+    assert_eq!(register.len(), 1);
+    assert_eq!(register[0]["data"], "alice");
+    assert_eq!(session.sent("history").len(), 1);
+    session.chat.unmount();
+}
+
+#[wasm_bindgen_test]
+async fn user_list_follows_the_server() {
+    let session = Session::new().await;
+    session.mock.push(json!({ "messageType": "users", "dataArray": ["alice", "bob"] }));
+    settle().await;
+    assert!(session.chat.text().contains("bob"));
+
+    session.mock.push(json!({ "messageType": "rename", "data": json!({ "from": "bob", "to": "robert" }).to_string() }));
+    settle().await;
+    assert!(session.chat.text().contains("robert"));
+    session.chat.unmount();
+}
+
+#[wasm_bindgen_test]
+async fn incoming_messages_are_shown() {
+    let session = Session::new().await;
+    let message = json!({ "id": "1", "seq": 1, "from": "bob", "message": "Hello from bob", "time": 0 });
+    session.mock.push(json!({ "messageType": "message", "data": message.to_string() }));
+    settle().await;
+    assert!(session.chat.text().contains("Hello from bob"));
+    session.chat.unmount();
+}
+
+#[wasm_bindgen_test]
+async fn sent_messages_are_confirmed_by_the_echo() {
+    let session = Session::new().await;
+    let input: HtmlInputElement = session.chat.find("input[name=message]").unwrap().unchecked_into();
+    input.set_value("Hi everyone");
+    let send: HtmlElement = input.next_element_sibling().unwrap().unchecked_into();
+    send.click();
+    settle().await;
+
+    let sent = session.sent("message");
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["data"], "Hi everyone");
+    assert!(sent[0]["clientId"].is_string());
+    assert!(session.chat.text().contains("Hi everyone"));
+    assert!(!session.chat.text().contains("Sending…"));
+    session.chat.unmount();
+}
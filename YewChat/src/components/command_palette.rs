@@ -0,0 +1,190 @@
+use web_sys::{HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+/// One entry in the palette.
+#[derive(Clone, PartialEq)]
+pub struct Command {
+    pub label: String,
+    /// Shown beside the label, e.g. "People" or "Commands"
+    pub group: &'static str,
+    pub on_run: Callback<()>,
+}
+
+/// How well `query` matches `label`, or `None` if its characters don't all
+/// appear in order. Consecutive runs and matches at word starts score higher.
+fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut chars = label.char_indices();
+    for q in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let (i, _) = chars.find(|(_, c)| c.to_lowercase().eq(std::iter::once(q)))?;
+        let word_start = i == 0 || label[..i].ends_with(|c: char| !c.is_alphanumeric());
+        score += 1;
+        if previous.map_or(false, |p| p + 1 == i) {
+            score += 3;
+        }
+        if word_start {
+            score += 2;
+        }
+        previous = Some(i);
+    }
+    Some(score)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CommandPaletteProps {
+    pub commands: Vec<Command>,
+    pub on_close: Callback<()>,
+}
+
+/// Ctrl+K overlay for reaching people, the room and app actions from the keyboard.
+#[function_component(CommandPalette)]
+pub fn command_palette(props: &CommandPaletteProps) -> Html {
+    let query = use_state(String::new);
+    let selected = use_state(|| 0usize);
+    let search = use_node_ref();
+    {
+        let search = search.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(input) = search.cast::<HtmlElement>() {
+                    let _ = input.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+
+    {
+        // Keep the highlighted entry in view while moving through a long list
+        let current = *selected;
+        use_effect_with_deps(
+            move |current| {
+                if let Some(option) = web_sys::window()
+                    .and_then(|w| w.document())
+                    .and_then(|d| d.get_element_by_id(&format!("palette-{}", current)))
+                {
+                    option.scroll_into_view_with_bool(false);
+                }
+                || ()
+            },
+            current,
+        );
+    }
+
+    let mut matches: Vec<(i32, &Command)> = props
+        .commands
+        .iter()
+        .filter_map(|c| fuzzy_score(&query, &c.label).map(|score| (score, c)))
+        .collect();
+    // Stable, so equally good matches keep the order they were given in
+    matches.sort_by_key(|(score, _)| -score);
+    let matches: Vec<Command> = matches.into_iter().map(|(_, c)| c.clone()).collect();
+    let current = (*selected).min(matches.len().saturating_sub(1));
+
+    let run = {
+        let on_close = props.on_close.clone();
+        move |command: &Command| {
+            on_close.emit(());
+            command.on_run.emit(());
+        }
+    };
+    let onkeydown = {
+        let selected = selected.clone();
+        let on_close = props.on_close.clone();
+        let matches = matches.clone();
+        let run = run.clone();
+        Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+            "ArrowDown" => {
+                e.prevent_default();
+                if !matches.is_empty() {
+                    selected.set((current + 1) % matches.len());
+                }
+            }
+            "ArrowUp" => {
+                e.prevent_default();
+                if !matches.is_empty() {
+                    selected.set((current + matches.len() - 1) % matches.len());
+                }
+            }
+            "Enter" => {
+                e.prevent_default();
+                if let Some(command) = matches.get(current) {
+                    run(command);
+                }
+            }
+            "Escape" => {
+                e.prevent_default();
+                on_close.emit(());
+            }
+            _ => {}
+        })
+    };
+    let oninput = {
+        let query = query.clone();
+        let selected = selected.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+            selected.set(0);
+        })
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    html! {
+        <div class="fixed inset-0 z-50 flex justify-center pt-24" {onkeydown}>
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close}></div>
+
+            <div class="relative w-[32rem] max-h-[60vh] flex flex-col self-start bg-white rounded-lg shadow-lg" role="dialog" aria-label="Command palette">
+                <input
+                    ref={search}
+                    type="text"
+                    placeholder="Search people, rooms and commands"
+                    class="w-full px-4 py-3 text-sm border-b rounded-t-lg focus:outline-none"
+                    role="combobox"
+                    aria-expanded="true"
+                    aria-controls="palette-results"
+                    aria-activedescendant={(!matches.is_empty()).then(|| format!("palette-{}", current))}
+                    value={(*query).clone()}
+                    {oninput}
+                />
+                <div id="palette-results" role="listbox" class="overflow-y-auto py-1">
+                    if matches.is_empty() {
+                        <div class="px-4 py-3 text-sm text-gray-400">{"Nothing matches"}</div>
+                    }
+                    {
+                        matches.iter().enumerate().map(|(i, command)| {
+                            let onclick = {
+                                let run = run.clone();
+                                let command = command.clone();
+                                Callback::from(move |_: MouseEvent| run(&command))
+                            };
+                            let onmouseenter = {
+                                let selected = selected.clone();
+                                Callback::from(move |_: MouseEvent| selected.set(i))
+                            };
+                            html! {
+                                <div
+                                    id={format!("palette-{}", i)}
+                                    role="option"
+                                    aria-selected={(i == current).to_string()}
+                                    {onclick}
+                                    {onmouseenter}
+                                    class={classes!(
+                                        "flex", "items-center", "justify-between", "px-4", "py-2", "text-sm", "cursor-pointer",
+                                        if i == current { "bg-blue-50 text-blue-800" } else { "text-gray-700" }
+                                    )}
+                                >
+                                    <span class="truncate">{command.label.clone()}</span>
+                                    <span class="ml-4 text-xs text-gray-400">{command.group}</span>
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
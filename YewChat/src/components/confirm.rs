@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+
+use web_sys::{HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::services::{hotkeys, storage};
+
+// Confirmations the user asked not to see again
+const SKIPPED_KEY: &str = "confirm_skipped";
+
+/// A question put to the user before an action that can't be taken back.
+#[derive(Clone, PartialEq)]
+pub struct Confirm {
+    pub title: &'static str,
+    pub message: &'static str,
+    pub confirm_label: &'static str,
+    /// Low-risk actions offer "Don't ask again", remembered under this key
+    pub skip_key: Option<&'static str>,
+}
+
+impl Confirm {
+    /// Whether the user opted out of this confirmation earlier.
+    pub fn skipped(&self) -> bool {
+        self.skip_key.map_or(false, |key| {
+            storage::load::<BTreeSet<String>>(SKIPPED_KEY)
+                .unwrap_or_default()
+                .contains(key)
+        })
+    }
+
+    fn skip_from_now_on(&self) {
+        if let Some(key) = self.skip_key {
+            let mut skipped: BTreeSet<String> = storage::load(SKIPPED_KEY).unwrap_or_default();
+            skipped.insert(key.to_string());
+            storage::save(SKIPPED_KEY, &skipped);
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ConfirmDialogProps {
+    pub confirm: Confirm,
+    pub on_confirm: Callback<()>,
+    pub on_cancel: Callback<()>,
+}
+
+#[function_component(ConfirmDialog)]
+pub fn confirm_dialog(props: &ConfirmDialogProps) -> Html {
+    let dont_ask = use_state(|| false);
+    let cancel_button = use_node_ref();
+    {
+        // Cancel is the safe choice, so that's what Enter hits by default
+        let cancel_button = cancel_button.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(button) = cancel_button.cast::<HtmlElement>() {
+                    let _ = button.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+    let cancel = props.on_cancel.reform(|_: MouseEvent| ());
+    let onkeydown = {
+        let on_cancel = props.on_cancel.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if hotkeys::CLOSE.matches(&e) {
+                on_cancel.emit(());
+            }
+        })
+    };
+    let on_dont_ask = {
+        let dont_ask = dont_ask.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            dont_ask.set(input.checked());
+        })
+    };
+    let on_confirm = {
+        let confirm = props.confirm.clone();
+        let dont_ask = *dont_ask;
+        props.on_confirm.reform(move |_: MouseEvent| {
+            if dont_ask {
+                confirm.skip_from_now_on();
+            }
+        })
+    };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center" role="alertdialog" aria-modal="true" {onkeydown}>
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={cancel.clone()}></div>
+
+            <div class="relative w-80 bg-white rounded-lg shadow-lg">
+                <div class="px-6 pt-5">
+                    <div class="text-lg font-semibold text-gray-800">{props.confirm.title}</div>
+                    <div class="mt-2 text-sm text-gray-600">{props.confirm.message}</div>
+                    if props.confirm.skip_key.is_some() {
+                        <label class="flex items-center mt-4 text-sm text-gray-600 cursor-pointer">
+                            <input type="checkbox" class="w-4 h-4 mr-2" checked={*dont_ask} onchange={on_dont_ask} />
+                            {"Don't ask again"}
+                        </label>
+                    }
+                </div>
+                <div class="flex justify-end gap-2 px-6 py-4">
+                    <button ref={cancel_button} onclick={cancel} class="px-4 py-2 text-sm rounded-md text-gray-600 hover:bg-gray-100">
+                        {"Cancel"}
+                    </button>
+                    <button onclick={on_confirm} class="px-4 py-2 text-sm font-medium rounded-md text-white bg-red-600 hover:bg-red-700">
+                        {props.confirm.confirm_label}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
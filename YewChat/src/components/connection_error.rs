@@ -0,0 +1,46 @@
+use yew::prelude::*;
+
+/// The best guess at why `endpoint` can't be reached. Browsers don't say why a
+/// websocket failed, so this only goes by what can be seen from here.
+fn likely_cause(endpoint: &str) -> String {
+    let protocol = web_sys::window().and_then(|w| w.location().protocol().ok());
+    let secure_page = protocol.as_deref() == Some("https:");
+    if !endpoint.starts_with("ws://") && !endpoint.starts_with("wss://") {
+        "That isn't a ws:// or wss:// address.".to_string()
+    } else if secure_page && endpoint.starts_with("ws://") {
+        "This page was loaded over HTTPS, so the browser blocks the unencrypted ws:// address. The server needs a wss:// address.".to_string()
+    } else {
+        "The server didn't answer over websockets or HTTP. It may be down, or the address may be wrong.".to_string()
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ConnectionErrorProps {
+    /// Where we tried to connect
+    pub endpoint: String,
+    pub on_retry: Callback<()>,
+}
+
+/// Shown instead of the chat when it never managed to connect at all.
+#[function_component(ConnectionError)]
+pub fn connection_error(props: &ConnectionErrorProps) -> Html {
+    html! {
+        <div class="flex items-center justify-center w-screen h-screen bg-gray-50" role="alert">
+            <div class="w-96 p-8 text-center bg-white rounded-lg shadow-lg">
+                <div class="text-4xl" aria-hidden="true">{"🔌"}</div>
+                <div class="mt-3 text-lg font-semibold text-gray-800">{"Can't reach the chat server"}</div>
+                <div class="mt-2 text-sm text-gray-600">{likely_cause(&props.endpoint)}</div>
+                <div class="mt-4 p-2 text-xs text-left text-gray-700 bg-gray-100 rounded-md break-all">
+                    <span class="font-medium">{"Tried: "}</span>{&props.endpoint}
+                </div>
+                <button
+                    onclick={props.on_retry.reform(|_: MouseEvent| ())}
+                    class="mt-6 px-6 py-2 text-sm font-medium rounded-md text-white bg-blue-600 hover:bg-blue-700"
+                >
+                    {"Retry"}
+                </button>
+                <div class="mt-3 text-xs text-gray-400">{"Still trying in the background too"}</div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,112 @@
+use yew::prelude::*;
+
+use crate::components::chat::Revision;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Same,
+    Added,
+    Removed,
+}
+
+/// Word-level diff from `old` to `new`, built from their longest common subsequence.
+fn diff<'a>(old: &'a str, new: &'a str) -> Vec<(Change, &'a str)> {
+    let a: Vec<&str> = old.split_whitespace().collect();
+    let b: Vec<&str> = new.split_whitespace().collect();
+    // common[i][j] is the number of words a[i..] and b[j..] have in common
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            changes.push((Change::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            changes.push((Change::Removed, a[i]));
+            i += 1;
+        } else {
+            changes.push((Change::Added, b[j]));
+            j += 1;
+        }
+    }
+    changes.extend(a[i..].iter().map(|w| (Change::Removed, *w)));
+    changes.extend(b[j..].iter().map(|w| (Change::Added, *w)));
+    changes
+}
+
+fn format_time(ts: i64) -> String {
+    let date = js_sys::Date::new(&(ts as f64).into());
+    format!("{:02}/{:02} {:02}:{:02}", date.get_date(), date.get_month() + 1, date.get_hours(), date.get_minutes())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct EditHistoryProps {
+    /// Every version of the message, oldest first; the last one is what's shown now
+    pub versions: Vec<Revision>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(EditHistory)]
+pub fn edit_history(props: &EditHistoryProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let versions = &props.versions;
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-96 max-h-[80vh] flex flex-col bg-white rounded-lg shadow-lg">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Edit history"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="overflow-y-auto px-6 py-2">
+                    {
+                        versions.iter().enumerate().rev().map(|(k, version)| {
+                            let label = match k {
+                                0 => "Original",
+                                k if k + 1 == versions.len() => "Current",
+                                _ => "Edited",
+                            };
+                            let body = if k == 0 {
+                                html! { {version.message.clone()} }
+                            } else {
+                                diff(&versions[k - 1].message, &version.message).into_iter().map(|(change, word)| {
+                                    let class = match change {
+                                        Change::Same => "",
+                                        Change::Added => "bg-green-100 text-green-800",
+                                        Change::Removed => "bg-red-100 text-red-700 line-through",
+                                    };
+                                    html! { <><span class={class}>{word}</span>{" "}</> }
+                                }).collect::<Html>()
+                            };
+                            html! {
+                                <div class="py-3 border-b last:border-b-0">
+                                    <div class="flex justify-between text-xs text-gray-500">
+                                        <span class="font-medium">{label}</span>
+                                        if let Some(ts) = version.timestamp {
+                                            <span>{format_time(ts)}</span>
+                                        }
+                                    </div>
+                                    <div class="mt-1 text-sm text-gray-800 break-words">{body}</div>
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
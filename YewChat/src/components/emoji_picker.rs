@@ -0,0 +1,176 @@
+use std::rc::Rc;
+
+use serde::Deserialize;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::services::assets;
+
+// The full set is fetched the first time the picker opens
+const EMOJI_ASSET: &str = "/emoji.json";
+
+// Offered until the full set arrives, or if it can't be fetched
+const BUILT_IN: [(&str, &str); 16] = [
+    ("😀", "grinning smile happy"),
+    ("😁", "beaming grin teeth"),
+    ("😂", "joy tears laugh"),
+    ("🤣", "rofl rolling laugh"),
+    ("😃", "smiley happy"),
+    ("😄", "smile happy"),
+    ("😅", "sweat smile nervous"),
+    ("😆", "laughing squint"),
+    ("😉", "wink"),
+    ("😊", "blush smile"),
+    ("😋", "yum tasty tongue"),
+    ("😎", "cool sunglasses"),
+    ("😍", "heart eyes love"),
+    ("😘", "kiss blow love"),
+    ("🥰", "hearts love adore"),
+    ("😗", "kissing"),
+];
+
+/// An emoji with the words it can be found by.
+#[derive(Clone, PartialEq, Deserialize)]
+struct Emoji {
+    emoji: String,
+    keywords: String,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct EmojiPickerProps {
+    pub on_pick: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Popup above the composer. It keeps keyboard focus to itself while open and
+/// goes away on Escape or a click anywhere else.
+#[function_component(EmojiPicker)]
+pub fn emoji_picker(props: &EmojiPickerProps) -> Html {
+    let query = use_state(String::new);
+    let emojis = use_state(|| None::<Rc<Vec<Emoji>>>);
+    let search = use_node_ref();
+    let grid = use_node_ref();
+    {
+        let emojis = emojis.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    if let Some(loaded) = assets::load::<Vec<Emoji>>(EMOJI_ASSET).await {
+                        emojis.set(Some(Rc::new(loaded)));
+                    }
+                });
+                || ()
+            },
+            (),
+        );
+    }
+    {
+        let search = search.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(input) = search.cast::<HtmlElement>() {
+                    let _ = input.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+
+    let onkeydown = {
+        let on_close = props.on_close.clone();
+        let search = search.clone();
+        let grid = grid.clone();
+        Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+            "Escape" => {
+                e.prevent_default();
+                on_close.emit(());
+            }
+            "Tab" => {
+                // Wrap between the search field and the last emoji
+                let first = search.cast::<HtmlElement>();
+                let last = grid
+                    .cast::<HtmlElement>()
+                    .and_then(|g| g.last_element_child())
+                    .and_then(|el| el.dyn_into::<HtmlElement>().ok());
+                let active = web_sys::window()
+                    .and_then(|w| w.document())
+                    .and_then(|d| d.active_element());
+                let (from, to) = if e.shift_key() { (&first, &last) } else { (&last, &first) };
+                let at_edge = match (from, &active) {
+                    (Some(from), Some(active)) => from.is_same_node(Some(active)),
+                    _ => false,
+                };
+                if at_edge || last.is_none() {
+                    e.prevent_default();
+                    if let Some(to) = to.as_ref().or(first.as_ref()) {
+                        let _ = to.focus();
+                    }
+                }
+            }
+            _ => {}
+        })
+    };
+    let oninput = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+        })
+    };
+    let close = props.on_close.reform(|_: MouseEvent| ());
+
+    let needle = query.trim().to_lowercase();
+    let found = |keywords: &str| needle.is_empty() || keywords.split(' ').any(|w| w.starts_with(&needle));
+    let matches: Vec<String> = match &*emojis {
+        Some(emojis) => emojis.iter().filter(|e| found(&e.keywords)).map(|e| e.emoji.clone()).collect(),
+        None => BUILT_IN.iter().filter(|(_, words)| found(words)).map(|(emoji, _)| emoji.to_string()).collect(),
+    };
+
+    html! {
+        <>
+            // Invisible backdrop so a click anywhere else dismisses the picker
+            <div class="fixed inset-0 z-10" onclick={close}></div>
+
+            <div
+                class="absolute bottom-16 left-4 z-20 w-72 bg-white p-2 rounded-lg shadow-lg"
+                role="dialog"
+                aria-label="Emoji picker"
+                {onkeydown}
+            >
+                <input
+                    ref={search}
+                    type="text"
+                    placeholder="Search emoji"
+                    class="w-full mb-2 p-2 text-sm bg-gray-100 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-400"
+                    value={(*query).clone()}
+                    {oninput}
+                />
+                if matches.is_empty() {
+                    <div class="py-4 text-sm text-center text-gray-400">{"No emoji found"}</div>
+                } else {
+                    <div ref={grid} class="grid grid-cols-8 gap-1">
+                        {
+                            matches.into_iter().map(|emoji| {
+                                let onclick = {
+                                    let emoji = emoji.clone();
+                                    props.on_pick.reform(move |_: MouseEvent| emoji.clone())
+                                };
+                                html! {
+                                    <button
+                                        {onclick}
+                                        class="w-8 h-8 hover:bg-gray-100 focus:bg-gray-100 rounded cursor-pointer flex items-center justify-center focus:outline-none focus:ring-2 focus:ring-blue-400"
+                                    >
+                                        {emoji}
+                                    </button>
+                                }
+                            }).collect::<Html>()
+                        }
+                    </div>
+                }
+            </div>
+        </>
+    }
+}
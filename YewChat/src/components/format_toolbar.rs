@@ -0,0 +1,137 @@
+use yew::prelude::*;
+
+// Put in for the address when a link is made, selected so it can be typed over
+const LINK_PLACEHOLDER: &str = "https://";
+
+/// Markdown the toolbar can wrap the composer's selection in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Bold,
+    Italic,
+    Code,
+    Quote,
+    Link,
+}
+
+impl Format {
+    const ALL: [Format; 5] = [Format::Bold, Format::Italic, Format::Code, Format::Quote, Format::Link];
+
+    fn icon(self) -> &'static str {
+        match self {
+            Format::Bold => "B",
+            Format::Italic => "I",
+            Format::Code => "</>",
+            Format::Quote => "❝",
+            Format::Link => "🔗",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Format::Bold => "Bold",
+            Format::Italic => "Italic",
+            Format::Code => "Code",
+            Format::Quote => "Quote",
+            Format::Link => "Link",
+        }
+    }
+
+    fn icon_class(self) -> &'static str {
+        match self {
+            Format::Bold => "font-bold",
+            Format::Italic => "italic font-serif",
+            Format::Code => "font-mono text-xs",
+            _ => "",
+        }
+    }
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+/// `text` with `format` applied to the selection from `start` to `end`, and
+/// the selection to leave behind. Offsets count UTF-16 units, as inputs do.
+pub fn apply(format: Format, text: &str, start: u32, end: u32) -> (String, u32, u32) {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let end = (end as usize).min(units.len());
+    let start = (start as usize).min(end);
+    let before = String::from_utf16_lossy(&units[..start]);
+    let selected = String::from_utf16_lossy(&units[start..end]);
+    let after = String::from_utf16_lossy(&units[end..]);
+    let (start, end) = (start as u32, end as u32);
+
+    let wrap = |marker: &str| {
+        let shift = utf16_len(marker);
+        (format!("{}{}{}{}{}", before, marker, selected, marker, after), start + shift, end + shift)
+    };
+    match format {
+        Format::Bold => wrap("**"),
+        Format::Italic => wrap("_"),
+        Format::Code => wrap("`"),
+        // The composer is one line, so the whole message is the quote; quoting again takes it off
+        Format::Quote => match text.strip_prefix("> ") {
+            Some(rest) => (rest.to_string(), start.saturating_sub(2), end.saturating_sub(2)),
+            None => (format!("> {}", text), start + 2, end + 2),
+        },
+        Format::Link => {
+            let label = if selected.is_empty() { "link" } else { selected.as_str() };
+            let url_start = start + utf16_len(label) + 3;
+            (
+                format!("{}[{}]({}){}", before, label, LINK_PLACEHOLDER, after),
+                url_start,
+                url_start + utf16_len(LINK_PLACEHOLDER),
+            )
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct FormatToolbarProps {
+    pub on_format: Callback<Format>,
+    /// Whether the preview pane is open
+    pub preview: bool,
+    pub on_toggle_preview: Callback<()>,
+    #[prop_or_default]
+    pub disabled: bool,
+}
+
+/// Buttons above the composer that mark up the selected text, and the preview switch.
+#[function_component(FormatToolbar)]
+pub fn format_toolbar(props: &FormatToolbarProps) -> Html {
+    html! {
+        <div class="flex items-center gap-1 mb-2 px-2 text-gray-500" role="toolbar" aria-label="Formatting">
+            {
+                Format::ALL.iter().map(|format| {
+                    let format = *format;
+                    html! {
+                        <button
+                            // Keeps the selection in the composer, which a click would otherwise take away
+                            onmousedown={Callback::from(|e: MouseEvent| e.prevent_default())}
+                            onclick={props.on_format.reform(move |_: MouseEvent| format)}
+                            disabled={props.disabled}
+                            title={format.label()}
+                            aria-label={format.label()}
+                            class={classes!(
+                                "w-8", "h-8", "rounded-md", "hover:bg-gray-100", "hover:text-gray-700", "focus:outline-none",
+                                "disabled:opacity-40", "disabled:cursor-not-allowed", format.icon_class()
+                            )}
+                        >
+                            {format.icon()}
+                        </button>
+                    }
+                }).collect::<Html>()
+            }
+            <button
+                onclick={props.on_toggle_preview.reform(|_: MouseEvent| ())}
+                aria-pressed={props.preview.to_string()}
+                class={classes!(
+                    "ml-auto", "px-2", "py-1", "text-xs", "rounded-md", "focus:outline-none",
+                    if props.preview { "bg-blue-100 text-blue-700" } else { "hover:bg-gray-100 hover:text-gray-700" }
+                )}
+            >
+                {"Preview"}
+            </button>
+        </div>
+    }
+}
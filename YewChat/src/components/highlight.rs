@@ -0,0 +1,74 @@
+// Characters that can continue a username, so "@bob_smith" doesn't count as a mention of "bob"
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Why a message stands out for the current user.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Highlight {
+    /// `@username` appears in the text
+    pub mention: bool,
+    /// One of the user's highlight keywords appears as a whole word
+    pub keyword: bool,
+}
+
+/// Matches message text against the user's name and highlight keywords,
+/// case-insensitively and on word boundaries ("email@bob.com" and "@bobby"
+/// don't mention "bob", "lunchbox" doesn't match "lunch").
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Highlighter {
+    username: String,
+    keywords: Vec<String>,
+}
+
+impl Highlighter {
+    pub fn new(username: &str, keywords: &[String]) -> Self {
+        Self {
+            username: username.to_lowercase(),
+            keywords: keywords
+                .iter()
+                .map(|k| k.trim().trim_start_matches('@').to_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Checks everything in one walk over the words of `text`.
+    pub fn scan(&self, text: &str) -> Highlight {
+        let mut result = Highlight::default();
+        let text = text.to_lowercase();
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if !is_name_char(chars[i]) {
+                i += 1;
+                continue;
+            }
+            // `@` only starts a mention when it isn't glued to a previous word
+            let at = i > 0 && chars[i - 1] == '@' && !(i > 1 && is_name_char(chars[i - 2]));
+            let start = i;
+            while i < chars.len() {
+                if is_name_char(chars[i]) {
+                    i += 1;
+                } else if chars[i] == '.' && chars.get(i + 1).map_or(false, |c| is_name_char(*c)) {
+                    // A dot ends the sentence unless another name character follows ("@bob." vs "@bob.smith")
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            let word: String = chars[start..i].iter().collect();
+
+            if at && !self.username.is_empty() && word == self.username {
+                result.mention = true;
+            }
+            if self.keywords.iter().any(|k| *k == word) {
+                result.keyword = true;
+            }
+            if result.mention && (result.keyword || self.keywords.is_empty()) {
+                break;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,122 @@
+use qrcode::{Color, QrCode};
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::{services::clipboard, Route, User};
+
+// Blank modules around the code that scanners rely on to find its edges
+const QR_QUIET_ZONE: usize = 4;
+
+/// Shareable link that redeems `token`, rooted at wherever the app is served from.
+pub fn invite_url(token: &str) -> String {
+    let origin = web_sys::window()
+        .and_then(|w| w.location().origin().ok())
+        .unwrap_or_default();
+    format!("{}/join/{}", origin, token)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct JoinInviteProps {
+    pub token: String,
+}
+
+/// Target of a `/join/<token>` link. The username only lives in memory, so the
+/// token is parked until the visitor has picked a name and the chat connects.
+#[function_component(JoinInvite)]
+pub fn join_invite(props: &JoinInviteProps) -> Html {
+    let user = use_context::<User>().expect("No context found.");
+    *user.pending_invite.borrow_mut() = Some(props.token.clone());
+
+    html! { <Redirect<Route> to={Route::Login} /> }
+}
+
+/// `data` drawn as a QR code, or nothing if it's too long to encode.
+fn qr_code(data: &str) -> Html {
+    let code = match QrCode::new(data) {
+        Ok(code) => code,
+        Err(e) => {
+            log::error!("Can't encode invite link as a QR code: {:?}", e);
+            return html! {};
+        }
+    };
+    let width = code.width();
+    let size = width + 2 * QR_QUIET_ZONE;
+    // A single path with one unit square per dark module keeps the DOM small
+    let path: String = code
+        .to_colors()
+        .iter()
+        .enumerate()
+        .filter(|(_, color)| **color == Color::Dark)
+        .map(|(i, _)| format!("M{},{}h1v1h-1z", i % width + QR_QUIET_ZONE, i / width + QR_QUIET_ZONE))
+        .collect();
+
+    html! {
+        <svg
+            viewBox={format!("0 0 {0} {0}", size)}
+            class="w-56 h-56 mx-auto"
+            shape-rendering="crispEdges"
+            role="img"
+            aria-label="QR code of the invite link"
+        >
+            <rect width="100%" height="100%" fill="#fff" />
+            <path d={path} fill="#000" />
+        </svg>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct InviteDialogProps {
+    /// `None` until the server has issued a token
+    pub url: Option<String>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(InviteDialog)]
+pub fn invite_dialog(props: &InviteDialogProps) -> Html {
+    let copied = use_state(|| false);
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let on_copy = {
+        let url = props.url.clone();
+        let copied = copied.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(url) = &url {
+                clipboard::copy_text(url);
+                copied.set(true);
+            }
+        })
+    };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-96 bg-white rounded-lg shadow-lg">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Invite people"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="px-6 py-4">
+                    <div class="text-sm text-gray-500">{"Anyone with this link can join the room. It expires after a week."}</div>
+                    if let Some(url) = &props.url {
+                        <div class="mt-4">{qr_code(url)}</div>
+                        <div class="text-xs text-center text-gray-400">{"Scan with a phone camera to join"}</div>
+                        <div class="flex mt-3">
+                            <input
+                                type="text"
+                                readonly=true
+                                class="grow min-w-0 p-2 text-sm bg-gray-100 rounded-l-md focus:outline-none"
+                                value={url.clone()}
+                            />
+                            <button onclick={on_copy} class="px-4 text-sm font-medium text-white bg-blue-600 rounded-r-md hover:bg-blue-700">
+                                {if *copied { "Copied" } else { "Copy" }}
+                            </button>
+                        </div>
+                    } else {
+                        <div class="mt-3 p-2 text-sm text-gray-400 bg-gray-100 rounded-md">{"Creating link…"}</div>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::{HashMap, VecDeque};
+
+use yew::prelude::*;
+
+// Round trips remembered when judging the connection
+const SAMPLES: usize = 10;
+// A ping with no pong after this long counts as lost
+const PONG_TIMEOUT_MS: f64 = 5_000.0;
+// Latency (ms) beyond which the connection is flagged as slow
+const SLOW_RTT_MS: f64 = 500.0;
+// Share of lost pings beyond which the connection is flagged as lossy
+const LOSSY: f64 = 0.2;
+
+/// Round-trip times measured with `ping`/`pong` messages. Browsers don't expose
+/// websocket control frames, so the pings are ordinary application messages.
+#[derive(Default)]
+pub struct LinkQuality {
+    next_id: u64,
+    in_flight: HashMap<String, f64>,
+    // Most recent last; `None` for a ping that was never answered
+    samples: VecDeque<Option<f64>>,
+    // Kept for when every recent ping was lost
+    last_rtt: Option<f64>,
+}
+
+impl LinkQuality {
+    /// Records a ping sent at `now` and returns the ID to send with it.
+    pub fn ping(&mut self, now: f64) -> String {
+        self.next_id += 1;
+        let id = self.next_id.to_string();
+        self.in_flight.insert(id.clone(), now);
+        id
+    }
+
+    pub fn pong(&mut self, id: &str, now: f64) {
+        if let Some(sent) = self.in_flight.remove(id) {
+            self.last_rtt = Some(now - sent);
+            self.push(Some(now - sent));
+        }
+    }
+
+    /// Counts pings that have waited too long as lost; returns whether any were.
+    pub fn expire(&mut self, now: f64) -> bool {
+        let lost: Vec<String> = self
+            .in_flight
+            .iter()
+            .filter(|(_, sent)| now - **sent > PONG_TIMEOUT_MS)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in lost.iter() {
+            self.in_flight.remove(id);
+            self.push(None);
+        }
+        !lost.is_empty()
+    }
+
+    /// Forgets everything, e.g. after the connection dropped.
+    pub fn reset(&mut self) {
+        self.in_flight.clear();
+        self.samples.clear();
+        self.last_rtt = None;
+    }
+
+    fn push(&mut self, sample: Option<f64>) {
+        self.samples.push_back(sample);
+        if self.samples.len() > SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Average of the last few answered round trips, in milliseconds.
+    fn rtt(&self) -> Option<f64> {
+        let recent: Vec<f64> = self.samples.iter().rev().flatten().take(3).copied().collect();
+        if recent.is_empty() {
+            return self.last_rtt;
+        }
+        Some(recent.iter().sum::<f64>() / recent.len() as f64)
+    }
+
+    fn loss(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().filter(|s| s.is_none()).count() as f64 / self.samples.len() as f64
+    }
+
+    /// What the header shows, or `None` until the server has answered a ping;
+    /// servers that predate pings would otherwise look like a dead connection.
+    pub fn summary(&self) -> Option<LinkSummary> {
+        let rtt = self.rtt()?;
+        let loss = self.loss();
+        let mut bars = match rtt {
+            r if r < 100.0 => 4,
+            r if r < 250.0 => 3,
+            r if r < SLOW_RTT_MS => 2,
+            _ => 1,
+        };
+        if loss >= LOSSY {
+            bars = (bars - 1).max(1);
+        }
+        Some(LinkSummary {
+            rtt_ms: rtt.round() as u32,
+            loss_percent: (loss * 100.0).round() as u32,
+            bars,
+            degraded: rtt >= SLOW_RTT_MS || loss >= LOSSY,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct LinkSummary {
+    pub rtt_ms: u32,
+    pub loss_percent: u32,
+    /// Signal strength out of 4
+    pub bars: u32,
+    pub degraded: bool,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LatencyMeterProps {
+    pub summary: LinkSummary,
+}
+
+/// Signal bars and round-trip time for the header.
+#[function_component(LatencyMeter)]
+pub fn latency_meter(props: &LatencyMeterProps) -> Html {
+    let s = props.summary;
+    let color = match (s.degraded, s.bars) {
+        (true, 1) => "bg-red-500",
+        (true, _) => "bg-amber-500",
+        _ => "bg-green-500",
+    };
+    let title = if s.loss_percent > 0 {
+        format!("Latency {} ms, {}% of pings lost", s.rtt_ms, s.loss_percent)
+    } else {
+        format!("Latency {} ms", s.rtt_ms)
+    };
+
+    html! {
+        <div class="flex items-center text-xs text-gray-500" title={title.clone()} role="img" aria-label={title.clone()}>
+            <div class="flex items-end h-3 gap-px">
+                {
+                    (1..=4).map(|bar| html! {
+                        <div
+                            class={classes!("w-1", "rounded-sm", if bar <= s.bars { color } else { "bg-gray-300" })}
+                            style={format!("height: {}%;", bar * 25)}
+                        ></div>
+                    }).collect::<Html>()
+                }
+            </div>
+            <span class={classes!("ml-1", "tabular-nums", s.degraded.then(|| "text-amber-600"))}>
+                {format!("{} ms", s.rtt_ms)}
+            </span>
+            if s.degraded {
+                <span class="ml-1 text-amber-600">{"· Slow connection"}</span>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,66 @@
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+use crate::services::hotkeys;
+
+#[derive(Properties, PartialEq)]
+pub struct LightboxProps {
+    pub url: String,
+    pub from: String,
+    pub video: bool,
+    pub on_jump: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+/// Full-screen view of a shared image or video.
+#[function_component(Lightbox)]
+pub fn lightbox(props: &LightboxProps) -> Html {
+    let root = use_node_ref();
+    {
+        // Take focus so Escape closes it straight away
+        let root = root.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(el) = root.cast::<HtmlElement>() {
+                    let _ = el.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let onkeydown = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if hotkeys::CLOSE.matches(&e) {
+                on_close.emit(());
+            }
+        })
+    };
+
+    html! {
+        <div ref={root} class="fixed inset-0 z-50 flex flex-col bg-black bg-opacity-90 outline-none" tabindex="-1" {onkeydown}>
+            <div class="flex items-center justify-between p-4 text-white">
+                <div class="text-sm">{format!("Shared by {}", props.from)}</div>
+                <div class="flex items-center gap-2">
+                    <button onclick={props.on_jump.reform(|_: MouseEvent| ())} class="px-3 py-1 text-sm rounded hover:bg-white hover:bg-opacity-10">
+                        {"Jump to message"}
+                    </button>
+                    <a href={props.url.clone()} target="_blank" rel="noopener noreferrer" class="px-3 py-1 text-sm rounded hover:bg-white hover:bg-opacity-10">
+                        {"Open original"}
+                    </a>
+                    <button onclick={close.clone()} class="px-3 py-1 text-lg rounded hover:bg-white hover:bg-opacity-10">{"✕"}</button>
+                </div>
+            </div>
+            <div class="relative grow min-h-0 flex items-center justify-center p-4">
+                <div class="absolute inset-0" onclick={close}></div>
+                if props.video {
+                    <video class="relative max-w-full max-h-full" src={props.url.clone()} controls=true autoplay=true />
+                } else {
+                    <img class="relative max-w-full max-h-full object-contain" src={props.url.clone()} alt={format!("Shared by {}", props.from)} />
+                }
+            </div>
+        </div>
+    }
+}
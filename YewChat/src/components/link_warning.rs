@@ -0,0 +1,145 @@
+use std::collections::BTreeSet;
+
+use js_sys::Reflect;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::services::{hotkeys, storage};
+
+// Domains the user chose to always open without asking
+const TRUSTED_KEY: &str = "trusted_domains";
+
+fn trusted_domains() -> BTreeSet<String> {
+    storage::load(TRUSTED_KEY).unwrap_or_default()
+}
+
+/// Whether links to `host` open straight away: our own site, or a domain the
+/// user trusts, including its subdomains.
+pub fn is_trusted(host: &str) -> bool {
+    let own = web_sys::window().and_then(|w| w.location().hostname().ok());
+    if own.as_deref() == Some(host) {
+        return true;
+    }
+    trusted_domains()
+        .iter()
+        .any(|domain| host == domain || matches!(host.strip_suffix(domain.as_str()), Some(sub) if sub.ends_with('.')))
+}
+
+fn trust(host: &str) {
+    let mut domains = trusted_domains();
+    domains.insert(host.to_string());
+    storage::save(TRUSTED_KEY, &domains);
+}
+
+/// A link leaving the app, as clicked somewhere in the chat.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalLink {
+    pub url: String,
+    pub host: String,
+}
+
+impl ExternalLink {
+    /// The web link a click landed on, if any. Links are found by walking up
+    /// from the click target, so this works for links rendered anywhere.
+    pub fn clicked(event: &MouseEvent) -> Option<Self> {
+        let target: Element = event.target()?.dyn_into().ok()?;
+        let anchor = target.closest("a[href]").ok()??;
+        // The anchor's own properties give the resolved URL and its parts
+        let property = |name: &str| Reflect::get(&anchor, &name.into()).ok()?.as_string();
+        if !matches!(property("protocol")?.as_str(), "http:" | "https:") {
+            return None;
+        }
+        Some(Self {
+            url: property("href")?,
+            host: property("hostname")?,
+        })
+    }
+
+    /// Opens the link in a new tab, like the anchor would have.
+    pub fn open(&self) {
+        if let Some(window) = web_sys::window() {
+            let _ = window.open_with_url_and_target_and_features(&self.url, "_blank", "noopener,noreferrer");
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LinkWarningProps {
+    pub link: ExternalLink,
+    pub on_open: Callback<()>,
+    pub on_cancel: Callback<()>,
+}
+
+/// Asks before following a link someone posted to a domain the user hasn't trusted yet.
+#[function_component(LinkWarning)]
+pub fn link_warning(props: &LinkWarningProps) -> Html {
+    let always = use_state(|| false);
+    let cancel_button = use_node_ref();
+    {
+        // Staying is the safe choice, so that's what Enter hits by default
+        let cancel_button = cancel_button.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(button) = cancel_button.cast::<HtmlElement>() {
+                    let _ = button.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+    let cancel = props.on_cancel.reform(|_: MouseEvent| ());
+    let onkeydown = {
+        let on_cancel = props.on_cancel.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if hotkeys::CLOSE.matches(&e) {
+                on_cancel.emit(());
+            }
+        })
+    };
+    let on_always = {
+        let always = always.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            always.set(input.checked());
+        })
+    };
+    let on_open = {
+        let link = props.link.clone();
+        let always = *always;
+        props.on_open.reform(move |_: MouseEvent| {
+            if always {
+                trust(&link.host);
+            }
+            link.open();
+        })
+    };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center" role="alertdialog" aria-modal="true" {onkeydown}>
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={cancel.clone()}></div>
+
+            <div class="relative w-96 bg-white rounded-lg shadow-lg">
+                <div class="px-6 pt-5">
+                    <div class="text-lg font-semibold text-gray-800">{format!("You're leaving to {}", props.link.host)}</div>
+                    <div class="mt-2 text-sm text-gray-600">{"Anyone in the room can post links. Only continue if you trust where this one goes:"}</div>
+                    <div class="mt-2 p-2 text-xs text-gray-700 bg-gray-100 rounded-md break-all">{&props.link.url}</div>
+                    <label class="flex items-center mt-4 text-sm text-gray-600 cursor-pointer">
+                        <input type="checkbox" class="w-4 h-4 mr-2" checked={*always} onchange={on_always} />
+                        {format!("Always trust {}", props.link.host)}
+                    </label>
+                </div>
+                <div class="flex justify-end gap-2 px-6 py-4">
+                    <button ref={cancel_button} onclick={cancel} class="px-4 py-2 text-sm rounded-md text-gray-600 hover:bg-gray-100">
+                        {"Stay here"}
+                    </button>
+                    <button onclick={on_open} class="px-4 py-2 text-sm font-medium rounded-md text-white bg-blue-600 hover:bg-blue-700">
+                        {"Open link"}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::components::{chat::MessageData, name_color::name_color};
+
+// Punctuation that usually belongs to the sentence rather than the URL ("see https://x.org).")
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}', '\'', '"', '>'];
+
+/// Every http(s) URL in a message body, in order of appearance.
+pub fn extract_urls(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            let start = word.find("https://").or_else(|| word.find("http://"))?;
+            let url = word[start..].trim_end_matches(TRAILING_PUNCTUATION);
+            (url.len() > "https://".len()).then(|| url)
+        })
+        .collect()
+}
+
+/// A URL shared in the room, collapsed across everyone who posted it.
+#[derive(Clone, PartialEq)]
+pub struct LinkItem {
+    pub url: String,
+    pub title: Option<String>,
+    /// Who posted it most recently, and where
+    pub from: String,
+    pub index: usize,
+    pub times_shared: usize,
+}
+
+impl LinkItem {
+    /// Newest first, one entry per distinct URL.
+    pub fn collect(messages: &[MessageData], titles: &HashMap<String, Option<String>>) -> Vec<LinkItem> {
+        let mut items: Vec<LinkItem> = Vec::new();
+        for (index, m) in messages.iter().enumerate().rev().filter(|(_, m)| !m.system && !m.deleted) {
+            for url in extract_urls(&m.message) {
+                match items.iter_mut().find(|item| item.url == url) {
+                    Some(item) => item.times_shared += 1,
+                    None => items.push(LinkItem {
+                        url: url.to_string(),
+                        title: titles.get(url).cloned().flatten(),
+                        from: m.from.clone(),
+                        index,
+                        times_shared: 1,
+                    }),
+                }
+            }
+        }
+        items
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LinksPanelProps {
+    pub items: Vec<LinkItem>,
+    pub on_jump: Callback<usize>,
+}
+
+#[function_component(LinksPanel)]
+pub fn links_panel(props: &LinksPanelProps) -> Html {
+    let query = use_state(String::new);
+    let oninput = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+        })
+    };
+
+    let needle = query.trim().to_lowercase();
+    let matching: Vec<&LinkItem> = props
+        .items
+        .iter()
+        .filter(|item| {
+            needle.is_empty()
+                || item.url.to_lowercase().contains(&needle)
+                || item.from.to_lowercase().contains(&needle)
+                || item.title.as_ref().map_or(false, |t| t.to_lowercase().contains(&needle))
+        })
+        .collect();
+
+    html! {
+        <div class="flex flex-col">
+            <div class="p-2">
+                <input
+                    type="text"
+                    placeholder="Search links..."
+                    class="w-full p-2 text-sm bg-gray-100 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-400"
+                    value={(*query).clone()}
+                    {oninput}
+                />
+            </div>
+            if props.items.is_empty() {
+                <div class="p-6 text-center text-sm text-gray-400">{"Links shared here will show up in this tab"}</div>
+            } else if matching.is_empty() {
+                <div class="p-6 text-center text-sm text-gray-400">{"No links match your search"}</div>
+            }
+            {
+                matching.into_iter().map(|item| {
+                    let index = item.index;
+                    let on_jump = props.on_jump.reform(move |_: MouseEvent| index);
+                    html! {
+                        <div class="px-4 py-3 border-b hover:bg-gray-50">
+                            <a
+                                href={item.url.clone()}
+                                target="_blank"
+                                rel="noopener noreferrer"
+                                class="block text-sm font-medium text-blue-600 hover:underline break-words"
+                            >
+                                {item.title.clone().unwrap_or_else(|| item.url.clone())}
+                            </a>
+                            if item.title.is_some() {
+                                <div class="text-xs text-gray-400 truncate">{item.url.clone()}</div>
+                            }
+                            <div class="flex items-center mt-1 text-xs text-gray-500">
+                                <span class={name_color(&item.from)}>{item.from.clone()}</span>
+                                if item.times_shared > 1 {
+                                    <span class="ml-2">{format!("· shared {} times", item.times_shared)}</span>
+                                }
+                                <button onclick={on_jump} class="ml-auto hover:text-gray-700 hover:underline">{"Jump to message"}</button>
+                            </div>
+                        </div>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
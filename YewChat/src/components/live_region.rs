@@ -0,0 +1,55 @@
+use gloo_timers::callback::Timeout;
+use yew::prelude::*;
+
+// Announcements are gathered for this long and then read out together
+const THROTTLE_MS: u32 = 1_500;
+// More than this in one window is summarised instead of read out in full
+const MAX_SPOKEN: usize = 3;
+
+/// Collects screen-reader announcements so a burst of activity is read out as a
+/// short summary instead of a queue the listener can never catch up with.
+#[derive(Default)]
+pub struct Announcer {
+    queued: Vec<String>,
+    timer: Option<Timeout>,
+    spoken: String,
+}
+
+impl Announcer {
+    /// Queues `text`; `flush` fires once the current throttle window closes and
+    /// should end up calling [`Announcer::flush`].
+    pub fn announce(&mut self, text: String, flush: impl FnOnce() + 'static) {
+        self.queued.push(text);
+        if self.timer.is_none() {
+            self.timer = Some(Timeout::new(THROTTLE_MS, flush));
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.timer = None;
+        let queued = std::mem::take(&mut self.queued);
+        self.spoken = match queued.len() {
+            0 => return,
+            n if n <= MAX_SPOKEN => queued.join(". "),
+            n => format!("{} updates. Latest: {}", n, queued[n - 1]),
+        };
+    }
+
+    /// What the live region currently says.
+    pub fn text(&self) -> String {
+        self.spoken.clone()
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LiveRegionProps {
+    pub text: String,
+}
+
+/// Visually hidden region that screen readers read out whenever its text changes.
+#[function_component(LiveRegion)]
+pub fn live_region(props: &LiveRegionProps) -> Html {
+    html! {
+        <div class="sr-only" role="status" aria-live="polite" aria-atomic="true">{props.text.clone()}</div>
+    }
+}
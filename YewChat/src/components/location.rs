@@ -0,0 +1,205 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+/// OpenStreetMap's own tiles, fine for the odd thumbnail but not for heavy use;
+/// busier servers should point [`Settings::map_tiles`](crate::components::settings::Settings) elsewhere.
+pub const DEFAULT_TILES: &str = "https://tile.openstreetmap.org/{z}/{x}/{y}.png";
+
+const TILE_SIZE: f64 = 256.0;
+// Thumbnail size in CSS pixels
+const WIDTH: f64 = 256.0;
+const HEIGHT: f64 = 160.0;
+// The accuracy circle should fit in this many pixels across
+const ACCURACY_FIT: f64 = 120.0;
+const MIN_ZOOM: u32 = 3;
+const MAX_ZOOM: u32 = 17;
+// Where Web Mercator maps stop
+const MAX_LATITUDE: f64 = 85.051_128_78;
+const EARTH_CIRCUMFERENCE_M: f64 = 40_075_016.686;
+
+/// A shared position, sent along with a `message` frame. The message text
+/// is a map link, so clients that don't know about locations still show something useful.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub lat: f64,
+    pub lon: f64,
+    /// Radius in metres the real position is likely within
+    #[serde(default)]
+    pub accuracy: f64,
+}
+
+impl Location {
+    /// Where to see it in full, on openstreetmap.org.
+    pub fn open_url(&self) -> String {
+        let zoom = zoom_for(self);
+        format!(
+            "https://www.openstreetmap.org/?mlat={lat:.6}&mlon={lon:.6}#map={zoom}/{lat:.6}/{lon:.6}",
+            lat = self.lat,
+            lon = self.lon,
+            zoom = zoom
+        )
+    }
+
+    /// The text of a message sharing it.
+    pub fn message_text(&self) -> String {
+        format!("📍 {}", self.open_url())
+    }
+}
+
+/// The closest zoom that still fits the whole accuracy circle in the thumbnail.
+pub fn zoom_for(location: &Location) -> u32 {
+    (MIN_ZOOM..=MAX_ZOOM)
+        .rev()
+        .find(|zoom| location.accuracy * 2.0 / metres_per_pixel(location.lat, *zoom) <= ACCURACY_FIT)
+        .unwrap_or(MIN_ZOOM)
+}
+
+fn metres_per_pixel(lat: f64, zoom: u32) -> f64 {
+    EARTH_CIRCUMFERENCE_M * lat.to_radians().cos() / (TILE_SIZE * f64::from(1u32 << zoom))
+}
+
+// Position in pixels on the whole Web Mercator map at `zoom`
+fn world_pixel(lat: f64, lon: f64, zoom: u32) -> (f64, f64) {
+    let size = TILE_SIZE * f64::from(1u32 << zoom);
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (lon + 180.0) / 360.0 * size;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * size;
+    (x, y)
+}
+
+/// A map tile to draw, and where its top left corner goes in the thumbnail.
+#[derive(Debug, PartialEq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub left: f64,
+    pub top: f64,
+}
+
+/// The tiles covering a thumbnail centred on `location`, at most two by two.
+pub fn tiles(location: &Location, zoom: u32) -> Vec<Tile> {
+    let (x, y) = world_pixel(location.lat, location.lon, zoom);
+    let (left, top) = (x - WIDTH / 2.0, y - HEIGHT / 2.0);
+    let count = 1i64 << zoom;
+    let columns = (left / TILE_SIZE).floor() as i64..=((left + WIDTH - 1.0) / TILE_SIZE).floor() as i64;
+    let rows = (top / TILE_SIZE).floor() as i64..=((top + HEIGHT - 1.0) / TILE_SIZE).floor() as i64;
+    let mut tiles = Vec::new();
+    for row in rows.filter(|row| (0..count).contains(row)) {
+        for column in columns.clone() {
+            tiles.push(Tile {
+                // Across the antimeridian the map repeats
+                x: column.rem_euclid(count) as u32,
+                y: row as u32,
+                left: column as f64 * TILE_SIZE - left,
+                top: row as f64 * TILE_SIZE - top,
+            });
+        }
+    }
+    tiles
+}
+
+/// Fills in a provider's URL template. Tile servers take `{z}`, `{x}` and
+/// `{y}`; static map services take `{lat}`, `{lon}`, `{zoom}`, `{width}` and `{height}`.
+fn fill(template: &str, location: &Location, zoom: u32, tile: Option<&Tile>) -> String {
+    let mut url = template
+        .replace("{lat}", &format!("{:.6}", location.lat))
+        .replace("{lon}", &format!("{:.6}", location.lon))
+        .replace("{zoom}", &zoom.to_string())
+        .replace("{width}", &(WIDTH as u32).to_string())
+        .replace("{height}", &(HEIGHT as u32).to_string());
+    if let Some(tile) = tile {
+        url = url
+            .replace("{z}", &zoom.to_string())
+            .replace("{x}", &tile.x.to_string())
+            .replace("{y}", &tile.y.to_string());
+    }
+    url
+}
+
+/// Whether `template` can be used as a map provider.
+pub fn is_valid_template(template: &str) -> bool {
+    let tiled = ["{z}", "{x}", "{y}"].iter().all(|p| template.contains(p));
+    let single = ["{lat}", "{lon}"].iter().all(|p| template.contains(p));
+    template.starts_with("https://") && (tiled || single)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LocationCardProps {
+    pub location: Location,
+    /// Map provider URL template; an invalid one falls back to [`DEFAULT_TILES`]
+    #[prop_or_default]
+    pub map_tiles: String,
+}
+
+/// A shared location: a small map with the accuracy radius drawn around the
+/// pin, linking to a full map.
+#[function_component(LocationCard)]
+pub fn location_card(props: &LocationCardProps) -> Html {
+    let location = &props.location;
+    let template = if is_valid_template(&props.map_tiles) { props.map_tiles.as_str() } else { DEFAULT_TILES };
+    let zoom = zoom_for(location);
+    let radius = location.accuracy / metres_per_pixel(location.lat, zoom);
+    let map = if template.contains("{x}") {
+        tiles(location, zoom)
+            .iter()
+            .map(|tile| html! {
+                <img
+                    class="absolute max-w-none"
+                    style={format!("left: {}px; top: {}px; width: {}px; height: {}px", tile.left, tile.top, TILE_SIZE, TILE_SIZE)}
+                    src={fill(template, location, zoom, Some(tile))}
+                    alt=""
+                    loading="lazy"
+                />
+            })
+            .collect::<Html>()
+    } else {
+        html! { <img class="absolute inset-0 w-full h-full object-cover" src={fill(template, location, zoom, None)} alt="" loading="lazy" /> }
+    };
+    let open_url = location.open_url();
+    html! {
+        <div class="inline-block">
+            <a
+                href={open_url.clone()}
+                target="_blank"
+                rel="noopener noreferrer"
+                class="relative block overflow-hidden bg-gray-200 rounded-md"
+                style={format!("width: {}px; height: {}px", WIDTH, HEIGHT)}
+                aria-label={format!("Map of {:.5}, {:.5}", location.lat, location.lon)}
+            >
+                {map}
+                if radius >= 2.0 {
+                    <div
+                        class="absolute border-2 border-blue-500 rounded-full bg-blue-500/20"
+                        style={format!(
+                            "left: {}px; top: {}px; width: {}px; height: {}px",
+                            WIDTH / 2.0 - radius, HEIGHT / 2.0 - radius, radius * 2.0, radius * 2.0
+                        )}
+                    ></div>
+                }
+                <div class="absolute w-3 h-3 -translate-x-1/2 -translate-y-1/2 bg-blue-600 border-2 border-white rounded-full shadow left-1/2 top-1/2"></div>
+            </a>
+            <div class="flex items-center justify-between gap-3 mt-1 text-xs">
+                <span class="opacity-80">
+                    {"📍 Location"}
+                    if location.accuracy > 0.0 {
+                        {format!(" · within {}", format_distance(location.accuracy))}
+                    }
+                </span>
+                <a href={open_url} target="_blank" rel="noopener noreferrer" class="underline">{"Open in maps"}</a>
+            </div>
+        </div>
+    }
+}
+
+fn format_distance(metres: f64) -> String {
+    if metres >= 1_000.0 {
+        format!("{:.1} km", metres / 1_000.0)
+    } else {
+        format!("{} m", metres.round() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests;
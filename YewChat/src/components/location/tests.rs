@@ -0,0 +1,50 @@
+use wasm_bindgen_test::*;
+
+use super::{is_valid_template, tiles, zoom_for, Location, DEFAULT_TILES};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn at(lat: f64, lon: f64, accuracy: f64) -> Location {
+    Location { lat, lon, accuracy }
+}
+
+#[wasm_bindgen_test]
+fn zooms_out_for_vaguer_positions() {
+    let precise = zoom_for(&at(51.5, -0.12, 10.0));
+    let vague = zoom_for(&at(51.5, -0.12, 5_000.0));
+    assert_eq!(precise, 17);
+    assert!(vague < precise);
+    assert_eq!(zoom_for(&at(51.5, -0.12, 10_000_000.0)), 3);
+}
+
+#[wasm_bindgen_test]
+fn covers_the_thumbnail_with_at_most_four_tiles() {
+    let covering = tiles(&at(51.5, -0.12, 10.0), 15);
+    assert!(!covering.is_empty() && covering.len() <= 4);
+    // The tiles reach every corner of the thumbnail
+    assert!(covering.iter().any(|t| t.left <= 0.0 && t.top <= 0.0));
+    assert!(covering.iter().any(|t| t.left + 256.0 >= 256.0 && t.top + 256.0 >= 160.0));
+}
+
+#[wasm_bindgen_test]
+fn wraps_around_the_antimeridian() {
+    let covering = tiles(&at(0.0, 179.999, 0.0), 3);
+    assert!(covering.iter().any(|t| t.x == 0));
+    assert!(covering.iter().any(|t| t.x == 7));
+}
+
+#[wasm_bindgen_test]
+fn links_to_the_full_map() {
+    let url = at(48.858_37, 2.294_481, 10.0).open_url();
+    assert!(url.starts_with("https://www.openstreetmap.org/?mlat=48.858370&mlon=2.294481"));
+    assert!(at(1.0, 2.0, 0.0).message_text().starts_with("📍 https://"));
+}
+
+#[wasm_bindgen_test]
+fn accepts_tile_and_static_map_templates() {
+    assert!(is_valid_template(DEFAULT_TILES));
+    assert!(is_valid_template("https://maps.example.com/static?center={lat},{lon}&zoom={zoom}"));
+    assert!(!is_valid_template("http://tile.example.com/{z}/{x}/{y}.png"));
+    assert!(!is_valid_template("https://tile.example.com/{z}/{x}.png"));
+    assert!(!is_valid_template(""));
+}
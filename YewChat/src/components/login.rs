@@ -1,38 +1,357 @@
+use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::components::avatar::{identicon_url, Avatar};
+use crate::components::captcha::CaptchaWidget;
+use crate::services::account::{self, AuthMode, RecentIdentity, Session};
+use crate::services::rejoin::LastConversation;
 use crate::Route;
 use crate::User;
 
+pub const MAX_USERNAME_LEN: usize = 20;
+const MIN_PASSWORD_LEN: usize = 8;
+
+// Kept to six letters so any pair plus the number stays well inside the length limit
+const ADJECTIVES: [&str; 32] = [
+    "amber", "bold", "brave", "breezy", "bright", "calm", "clever", "cosmic", "crisp", "dapper", "eager",
+    "fuzzy", "gentle", "golden", "happy", "jolly", "lively", "lucky", "mellow", "merry", "misty", "nimble",
+    "plucky", "quiet", "rapid", "rosy", "silver", "sleepy", "snowy", "sunny", "swift", "witty",
+];
+const NOUNS: [&str; 32] = [
+    "badger", "beaver", "bison", "cactus", "comet", "coral", "falcon", "ferret", "gecko", "heron", "koala",
+    "lemon", "lotus", "maple", "meadow", "otter", "panda", "pebble", "puffin", "quokka", "raven", "robin",
+    "salmon", "tiger", "tulip", "turtle", "violet", "walnut", "walrus", "willow", "wombat", "zebra",
+];
+
+fn pick<'a>(words: &[&'a str]) -> &'a str {
+    words[(js_sys::Math::random() * words.len() as f64) as usize % words.len()]
+}
+
+/// A pronounceable name such as `sunny-otter-427`. With nearly a million
+/// combinations, two people picking one at the same time rarely collide.
+pub fn random_username() -> String {
+    let number = 100 + (js_sys::Math::random() * 900.0) as u32 % 900;
+    format!("{}-{}-{}", pick(&ADJECTIVES), pick(&NOUNS), number)
+}
+
+/// Mirrors the server-side rules so most mistakes are caught before connecting.
+pub fn validate_username(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Please enter a username".into());
+    }
+    if name != name.trim() {
+        return Err("Username can't start or end with spaces".into());
+    }
+    if name.chars().count() > MAX_USERNAME_LEN {
+        return Err(format!("Username can be at most {} characters", MAX_USERNAME_LEN));
+    }
+    if let Some(c) = name.chars().find(|c| !(c.is_ascii_alphanumeric() || "_-.".contains(*c))) {
+        return Err(format!("'{}' is not allowed, use letters, digits, '_', '-' or '.'", c));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Guest,
+    Account(AuthMode),
+}
+
+impl Tab {
+    fn label(self) -> &'static str {
+        match self {
+            Tab::Guest => "Guest",
+            Tab::Account(AuthMode::SignIn) => "Sign in",
+            Tab::Account(AuthMode::SignUp) => "Create account",
+        }
+    }
+}
+
 #[function_component(Login)]
 pub fn login() -> Html {
     let username = use_state(|| String::new());
+    let password = use_state(String::new);
+    let remember = use_state(|| false);
+    let tab = use_state(|| Tab::Guest);
+    let busy = use_state(|| false);
     let user = use_context::<User>().expect("No context found.");
+    let history = use_history();
+    let session = use_state(|| user.session.borrow().clone());
+    // Straight back into last visit's conversation, unless the server sent us here to sort something out
+    let rejoin = use_state(|| {
+        let sent_back = user.register_error.borrow().is_some() || user.challenge.borrow().is_some();
+        LastConversation::load().filter(|last| !sent_back && validate_username(&last.name).is_ok())
+    });
+    // A rejection from the server takes precedence until the name is edited
+    let server_error = use_state(|| user.register_error.borrow_mut().take());
+    let invited = user.pending_invite.borrow().is_some();
+    let challenge = user.challenge.borrow().clone();
+    let solved = use_state(|| false);
+    let recent = use_state(RecentIdentity::all);
+    let on_solve = {
+        let user = user.clone();
+        let solved = solved.clone();
+        Callback::from(move |token: String| {
+            *user.captcha_token.borrow_mut() = Some(token);
+            solved.set(true);
+        })
+    };
 
     let oninput = {
         let current_username = username.clone();
+        let server_error = server_error.clone();
         Callback::from(move |e: InputEvent| {
             let input: HtmlInputElement = e.target_unchecked_into();
             current_username.set(input.value());
+            server_error.set(None);
+        })
+    };
+    let on_surprise = {
+        let (username, server_error) = (username.clone(), server_error.clone());
+        Callback::from(move |_: MouseEvent| {
+            username.set(random_username());
+            server_error.set(None);
+        })
+    };
+    let on_password = {
+        let password = password.clone();
+        let server_error = server_error.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            password.set(input.value());
+            server_error.set(None);
+        })
+    };
+    let on_remember = {
+        let remember = remember.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            remember.set(input.checked());
+        })
+    };
+    let select_tab = |next: Tab| {
+        let tab = tab.clone();
+        let server_error = server_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            tab.set(next);
+            server_error.set(None);
         })
     };
 
     let onclick = {
         let username = username.clone();
+        let session = session.clone();
+        let user = user.clone();
+        Callback::from(move |_| {
+            *user.username.borrow_mut() = match &*session {
+                Some(session) => session.name.clone(),
+                None => (*username).clone(),
+            };
+            // Answered now; the server asks again if it still wants one
+            user.challenge.borrow_mut().take();
+        })
+    };
+    let on_authenticate = {
+        let (username, password, remember, busy) = (username.clone(), password.clone(), remember.clone(), busy.clone());
+        let (server_error, user, tab, history) = (server_error.clone(), user.clone(), *tab, history.clone());
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            let mode = match tab {
+                Tab::Account(mode) => mode,
+                Tab::Guest => return,
+            };
+            busy.set(true);
+            let (name, password, remember) = ((*username).clone(), (*password).clone(), *remember);
+            let (busy, server_error, user, history) = (busy.clone(), server_error.clone(), user.clone(), history.clone());
+            spawn_local(async move {
+                match account::authenticate(mode, &name, &password).await {
+                    Ok(session) => {
+                        session.save(remember);
+                        *user.username.borrow_mut() = session.name.clone();
+                        *user.session.borrow_mut() = Some(session);
+                        if let Some(history) = history {
+                            history.push(Route::Chat);
+                        }
+                    }
+                    Err(error) => {
+                        server_error.set(Some(error));
+                        busy.set(false);
+                    }
+                }
+            });
+        })
+    };
+    let on_sign_out = {
+        let session = session.clone();
         let user = user.clone();
-        Callback::from(move |_| *user.username.borrow_mut() = (*username).clone())
+        Callback::from(move |_: MouseEvent| {
+            if let Some(old) = user.session.borrow_mut().take() {
+                spawn_local(account::revoke(old.token));
+            }
+            Session::forget();
+            LastConversation::forget();
+            session.set(None);
+        })
+    };
+    // A guest name goes straight back in; an account still needs its password
+    let on_pick_recent = {
+        let (username, tab, server_error, user, history) =
+            (username.clone(), tab.clone(), server_error.clone(), user.clone(), history.clone());
+        Callback::from(move |identity: RecentIdentity| {
+            server_error.set(None);
+            if identity.account {
+                username.set(identity.name);
+                tab.set(Tab::Account(AuthMode::SignIn));
+            } else if let Some(history) = &history {
+                *user.username.borrow_mut() = identity.name;
+                user.challenge.borrow_mut().take();
+                history.push(Route::Chat);
+            }
+        })
+    };
+    let on_forget_recent = {
+        let recent = recent.clone();
+        Callback::from(move |name: String| {
+            RecentIdentity::forget(&name);
+            recent.set(RecentIdentity::all());
+        })
+    };
+
+    if let Some(last) = &*rejoin {
+        if session.is_none() {
+            *user.username.borrow_mut() = last.name.clone();
+        }
+        return html! { <Redirect<Route> to={Route::Chat} /> };
+    }
+
+    let validation = validate_username(&username);
+    let password_error = match *tab {
+        Tab::Account(AuthMode::SignUp) if password.chars().count() < MIN_PASSWORD_LEN => {
+            Err(format!("Passwords need at least {} characters", MIN_PASSWORD_LEN))
+        }
+        Tab::Account(_) if password.is_empty() => Err("Please enter your password".to_string()),
+        _ => Ok(()),
+    };
+    let blocked = validation.is_err() || (challenge.is_some() && !*solved);
+    // Don't nag about an empty field before the user has typed anything
+    let error = (*server_error)
+        .clone()
+        .or_else(|| validation.clone().err().filter(|_| !username.is_empty()))
+        .or_else(|| password_error.clone().err().filter(|_| !password.is_empty()));
+    let input_class = |invalid: bool| {
+        classes!("p-4", "border", "text-gray-800", "bg-white", if invalid { "border-red-500" } else { "border-gray-200" })
     };
 
     html! {
         <div class="bg-gray-800 flex w-screen">
             <div class="container mx-auto flex flex-col justify-center items-center">
-                <form class="m-4 flex">
-                    <input {oninput} class="rounded-l-lg p-4 border-t mr-0 border-b border-l text-gray-800 border-gray-200 bg-white" placeholder="Username" />
-                    <Link<Route> to={Route::Chat}><button {onclick} disabled={username.len() < 1} class="px-8 rounded-r-lg bg-violet-600 text-white font-bold p-4 uppercase border-violet-600 border-t border-b border-r">{"Go Chatting"}</button></Link<Route>>
-                </form>
+                if invited {
+                    <div class="text-gray-200">{"You've been invited to the chat — pick a username to join"}</div>
+                }
+                if let Some(current) = (*session).clone() {
+                    <div class="m-4 flex flex-col items-center text-gray-200">
+                        <div>{"Signed in as "}<span class="font-bold">{current.name}</span></div>
+                        <div class="mt-4 flex items-center gap-4">
+                            <Link<Route> to={Route::Chat}><button {onclick} disabled={challenge.is_some() && !*solved} class="px-8 rounded-lg bg-violet-600 text-white font-bold p-4 uppercase">{"Continue"}</button></Link<Route>>
+                            <button onclick={on_sign_out} class="text-sm text-gray-400 hover:text-gray-200 underline">{"Sign out"}</button>
+                        </div>
+                    </div>
+                } else {
+                    <div class="flex gap-2" role="tablist">
+                        {
+                            [Tab::Guest, Tab::Account(AuthMode::SignIn), Tab::Account(AuthMode::SignUp)].into_iter().map(|t| html! {
+                                <button
+                                    role="tab"
+                                    aria-selected={(*tab == t).to_string()}
+                                    onclick={select_tab(t)}
+                                    class={classes!("px-4", "py-2", "text-sm", "rounded-md", if *tab == t { "bg-gray-700 text-white" } else { "text-gray-400 hover:text-gray-200" })}
+                                >
+                                    {t.label()}
+                                </button>
+                            }).collect::<Html>()
+                        }
+                    </div>
+                    if *tab == Tab::Guest {
+                        if validation.is_ok() {
+                            <div class="mt-4 flex flex-col items-center text-sm text-gray-300">
+                                <Avatar name={(*username).clone()} src={identicon_url(&username)} class={classes!("w-16", "h-16", "rounded-full", "bg-white")} />
+                                <div class="mt-1">{"You'll appear as "}<span class="font-bold text-gray-100">{(*username).clone()}</span></div>
+                            </div>
+                        }
+                        <form class="m-4 flex">
+                            <input oninput={oninput.clone()} value={(*username).clone()} maxlength={(MAX_USERNAME_LEN * 2).to_string()} class={classes!("rounded-l-lg", "p-4", "border-t", "mr-0", "border-b", "border-l", "text-gray-800", "bg-white", if error.is_some() { "border-red-500" } else { "border-gray-200" })} placeholder="Username" />
+                            <button
+                                type="button"
+                                onclick={on_surprise}
+                                class="px-3 text-xl bg-white border-t border-b border-gray-200 hover:bg-gray-100"
+                                title="Surprise me"
+                                aria-label="Pick a random name"
+                            >
+                                {"🎲"}
+                            </button>
+                            <Link<Route> to={Route::Chat}><button {onclick} disabled={blocked} class="px-8 rounded-r-lg bg-violet-600 text-white font-bold p-4 uppercase border-violet-600 border-t border-b border-r">{"Go Chatting"}</button></Link<Route>>
+                        </form>
+                    } else {
+                        <form class="m-4 flex flex-col w-80" onsubmit={on_authenticate}>
+                            <input {oninput} value={(*username).clone()} autocomplete="username" maxlength={(MAX_USERNAME_LEN * 2).to_string()} class={classes!(input_class(validation.is_err() && !username.is_empty()), "rounded-t-lg")} placeholder="Username" />
+                            <input
+                                oninput={on_password}
+                                type="password"
+                                autocomplete={if *tab == Tab::Account(AuthMode::SignUp) { "new-password" } else { "current-password" }}
+                                class={classes!(input_class(password_error.is_err() && !password.is_empty()), "border-t-0")}
+                                placeholder="Password"
+                            />
+                            <label class="flex items-center mt-3 text-sm text-gray-300 cursor-pointer">
+                                <input type="checkbox" class="w-4 h-4 mr-2" checked={*remember} onchange={on_remember} />
+                                {"Remember me on this device"}
+                            </label>
+                            <button type="submit" disabled={validation.is_err() || password_error.is_err() || *busy} class="mt-3 rounded-lg bg-violet-600 text-white font-bold p-4 uppercase disabled:opacity-50">
+                                {tab.label()}
+                            </button>
+                        </form>
+                    }
+                    if !recent.is_empty() {
+                        <div class="mt-2 flex flex-col items-center">
+                            <div class="text-xs text-gray-400">{"Recently used on this device"}</div>
+                            <div class="mt-2 flex flex-wrap justify-center gap-2">
+                                {
+                                    recent.iter().map(|identity| {
+                                        let pick = { let identity = identity.clone(); on_pick_recent.reform(move |_: MouseEvent| identity.clone()) };
+                                        let name = identity.name.clone();
+                                        html! {
+                                            <div class="flex items-center rounded-full bg-gray-700 text-sm text-gray-200">
+                                                <button onclick={pick} class="pl-3 pr-2 py-1 hover:text-white" title={if identity.account { "Sign in" } else { "Join as guest" }}>
+                                                    {&identity.name}
+                                                    if identity.account {
+                                                        <span class="ml-1" aria-label="account">{"🔒"}</span>
+                                                    }
+                                                </button>
+                                                <button
+                                                    onclick={on_forget_recent.reform(move |_: MouseEvent| name.clone())}
+                                                    class="pr-3 text-gray-400 hover:text-gray-200"
+                                                    aria-label={format!("Forget {}", identity.name)}
+                                                >
+                                                    {"✕"}
+                                                </button>
+                                            </div>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                            </div>
+                        </div>
+                    }
+                }
+                if let Some(challenge) = challenge {
+                    <div class="mb-2 text-gray-200 text-sm">{"Please confirm you're not a robot to join"}</div>
+                    <CaptchaWidget {challenge} {on_solve} />
+                }
+                if let Some(error) = error {
+                    <div class="text-red-400 text-sm">{error}</div>
+                }
             </div>
         </div>
     }
-}
\ No newline at end of file
+}
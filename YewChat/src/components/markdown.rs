@@ -0,0 +1,343 @@
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, Options, Parser, Tag};
+use yew::prelude::*;
+
+use crate::components::{
+    links::extract_urls,
+    math::{self, Math, Segment},
+};
+use crate::services::tracking;
+
+// Formulas are swapped for these before parsing, so markdown never sees their TeX
+const FORMULA_START: char = '\u{E000}';
+const FORMULA_END: char = '\u{E001}';
+
+fn options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH
+}
+
+/// A formula as it was typed, for places where it can't be typeset such as code.
+fn formula_source(tex: &str, display: bool) -> String {
+    if display {
+        format!("$${}$$", tex)
+    } else {
+        format!("${}$", tex)
+    }
+}
+
+/// `text` with each formula replaced by a placeholder, and the formulas in order.
+fn protect_formulas(text: &str) -> (String, Vec<(&str, bool)>) {
+    let mut protected = String::with_capacity(text.len());
+    let mut formulas = Vec::new();
+    for segment in math::split(text) {
+        match segment {
+            // The markers are private-use characters, but nothing stops someone typing them
+            Segment::Text(text) => protected.extend(text.chars().filter(|c| *c != FORMULA_START && *c != FORMULA_END)),
+            Segment::Math { tex, display } => {
+                protected.push(FORMULA_START);
+                protected.push_str(&formulas.len().to_string());
+                protected.push(FORMULA_END);
+                formulas.push((tex, display));
+            }
+        }
+    }
+    (protected, formulas)
+}
+
+/// Whether `text` uses any markdown, math or links, as opposed to being one plain paragraph.
+pub fn has_markup(text: &str) -> bool {
+    math::has_math(text)
+        || !extract_urls(text).is_empty()
+        || Parser::new_ext(text, options())
+            .any(|e| !matches!(e, Event::Start(Tag::Paragraph) | Event::End(Tag::Paragraph) | Event::Text(_) | Event::SoftBreak))
+}
+
+/// Links only go to the web or mail; anything else, like `javascript:`, stays text.
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    ["http://", "https://", "mailto:"].iter().any(|scheme| url.starts_with(scheme))
+}
+
+fn alignment_style(alignment: Option<&Alignment>) -> Option<&'static str> {
+    match alignment {
+        Some(Alignment::Left) => Some("text-align: left"),
+        Some(Alignment::Center) => Some("text-align: center"),
+        Some(Alignment::Right) => Some("text-align: right"),
+        _ => None,
+    }
+}
+
+/// Turns parser events into Yew nodes; raw HTML in the message is shown as text.
+struct Builder<'a> {
+    formulas: Vec<(&'a str, bool)>,
+    // Query parameters taken out of links
+    tracking_rules: &'a [String],
+    // Open elements with the children collected so far; the bottom one is the message
+    stack: Vec<(Option<Tag<'a>>, Vec<Html>)>,
+    // Text is flushed as one node, since the parser often splits it up
+    text: String,
+    in_code_block: bool,
+    // Inside a markdown link, where URLs in the text aren't linked again
+    link_depth: usize,
+    alignments: Vec<Alignment>,
+    in_table_head: bool,
+    cell: usize,
+    task_item: bool,
+}
+
+impl<'a> Builder<'a> {
+    fn new(formulas: Vec<(&'a str, bool)>, tracking_rules: &'a [String]) -> Self {
+        Self {
+            formulas,
+            tracking_rules,
+            stack: vec![(None, Vec::new())],
+            text: String::new(),
+            in_code_block: false,
+            link_depth: 0,
+            alignments: Vec::new(),
+            in_table_head: false,
+            cell: 0,
+            task_item: false,
+        }
+    }
+
+    fn push(&mut self, html: Html) {
+        if let Some((_, children)) = self.stack.last_mut() {
+            children.push(html);
+        }
+    }
+
+    fn formula(&self, index: &str) -> Option<(&'a str, bool)> {
+        index.parse::<usize>().ok().and_then(|i| self.formulas.get(i).copied())
+    }
+
+    /// Pending text as nodes, typesetting formulas unless they're inside code.
+    fn flush(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.text);
+        let mut rest = text.as_str();
+        let mut plain = String::new();
+        while let Some(start) = rest.find(FORMULA_START) {
+            plain.push_str(&rest[..start]);
+            let after = &rest[start + FORMULA_START.len_utf8()..];
+            let end = match after.find(FORMULA_END) {
+                Some(end) => end,
+                None => {
+                    rest = &rest[start..];
+                    break;
+                }
+            };
+            if let Some((tex, display)) = self.formula(&after[..end]) {
+                if self.in_code_block {
+                    plain.push_str(&formula_source(tex, display));
+                } else {
+                    self.push_text(std::mem::take(&mut plain));
+                    self.push(html! { <Math tex={tex.to_string()} {display} /> });
+                }
+            }
+            rest = &after[end + FORMULA_END.len_utf8()..];
+        }
+        plain.push_str(rest);
+        self.push_text(plain);
+    }
+
+    /// Text with the web addresses in it made into links.
+    fn push_text(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        if self.in_code_block || self.link_depth > 0 {
+            self.push(html! { {text} });
+            return;
+        }
+        let mut rest = text.as_str();
+        for url in extract_urls(&text) {
+            let start = match rest.find(url) {
+                Some(start) => start,
+                None => continue,
+            };
+            if start > 0 {
+                self.push(html! { {rest[..start].to_string()} });
+            }
+            let shown = tracking::clean(url, self.tracking_rules).map_or_else(|| url.to_string(), |c| c.url);
+            let link = self.anchor(url, None, html! { {shown} });
+            self.push(link);
+            rest = &rest[start + url.len()..];
+        }
+        if !rest.is_empty() {
+            self.push(html! { {rest.to_string()} });
+        }
+    }
+
+    /// A link opening in a new tab, without tracking parameters and marked if it had any.
+    fn anchor(&self, url: &str, title: Option<String>, children: Html) -> Html {
+        let cleaned = tracking::clean(url, self.tracking_rules);
+        let href = cleaned.as_ref().map_or_else(|| url.to_string(), |c| c.url.clone());
+        html! {
+            <>
+                <a {href} {title} target="_blank" rel="noopener noreferrer" class="underline">{children}</a>
+                if let Some(cleaned) = cleaned {
+                    <span
+                        class="ml-0.5 text-xs opacity-60 select-none"
+                        title={format!("Tracking removed from this link: {}", cleaned.removed.join(", "))}
+                    >
+                        {"🧹"}
+                    </span>
+                }
+            </>
+        }
+    }
+
+    /// Code spans keep formulas as typed.
+    fn code_text(&self, code: &str) -> String {
+        let mut out = String::new();
+        let mut rest = code;
+        while let Some(start) = rest.find(FORMULA_START) {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + FORMULA_START.len_utf8()..];
+            match after.find(FORMULA_END) {
+                Some(end) => {
+                    if let Some((tex, display)) = self.formula(&after[..end]) {
+                        out.push_str(&formula_source(tex, display));
+                    }
+                    rest = &after[end + FORMULA_END.len_utf8()..];
+                }
+                None => {
+                    rest = &rest[start..];
+                    break;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn event(&mut self, event: Event<'a>) {
+        match event {
+            Event::Start(tag) => {
+                self.flush();
+                match &tag {
+                    Tag::CodeBlock(_) => self.in_code_block = true,
+                    Tag::Table(alignments) => self.alignments = alignments.clone(),
+                    Tag::TableHead => {
+                        self.in_table_head = true;
+                        self.cell = 0;
+                    }
+                    Tag::TableRow => self.cell = 0,
+                    Tag::Item => self.task_item = false,
+                    Tag::Link(..) | Tag::Image(..) => self.link_depth += 1,
+                    _ => {}
+                }
+                self.stack.push((Some(tag), Vec::new()));
+            }
+            Event::End(_) => {
+                self.flush();
+                if self.stack.len() < 2 {
+                    return;
+                }
+                if let Some((Some(tag), children)) = self.stack.pop() {
+                    if matches!(tag, Tag::Link(..) | Tag::Image(..)) {
+                        self.link_depth -= 1;
+                    }
+                    let html = self.element(tag, children);
+                    self.push(html);
+                }
+            }
+            Event::Text(text) | Event::Html(text) => self.text.push_str(&text),
+            Event::Code(code) => {
+                self.flush();
+                let code = self.code_text(&code);
+                self.push(html! { <code class="px-1 font-mono text-sm bg-black bg-opacity-10 rounded">{code}</code> });
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                self.flush();
+                // A newline in a chat message is meant as one
+                self.push(html! { <br /> });
+            }
+            Event::Rule => {
+                self.flush();
+                self.push(html! { <hr class="my-2 border-current opacity-30" /> });
+            }
+            Event::TaskListMarker(checked) => {
+                self.flush();
+                self.task_item = true;
+                self.push(html! { <input type="checkbox" class="mr-1 align-middle" disabled=true {checked} /> });
+            }
+            Event::FootnoteReference(_) => {}
+        }
+    }
+
+    fn element(&mut self, tag: Tag<'a>, children: Vec<Html>) -> Html {
+        let children: Html = children.into_iter().collect();
+        match tag {
+            Tag::Paragraph => html! { <p>{children}</p> },
+            Tag::Heading(..) => html! { <p class="font-semibold">{children}</p> },
+            Tag::BlockQuote => html! { <blockquote class="pl-2 border-l-2 border-current opacity-80">{children}</blockquote> },
+            Tag::CodeBlock(kind) => {
+                self.in_code_block = false;
+                let language = match kind {
+                    CodeBlockKind::Fenced(language) if !language.is_empty() => Some(format!("language-{}", language)),
+                    _ => None,
+                };
+                html! {
+                    <pre class="p-2 overflow-x-auto text-sm font-mono bg-black bg-opacity-10 rounded">
+                        <code class={classes!(language)}>{children}</code>
+                    </pre>
+                }
+            }
+            Tag::List(Some(start)) => html! { <ol class="pl-5 list-decimal" start={start.to_string()}>{children}</ol> },
+            Tag::List(None) => html! { <ul class="pl-5 list-disc">{children}</ul> },
+            Tag::Item => {
+                // Task items show their checkbox instead of a bullet
+                let task = std::mem::take(&mut self.task_item);
+                html! { <li class={classes!(task.then_some("list-none -ml-5"))}>{children}</li> }
+            }
+            // Wide tables scroll inside the bubble instead of stretching it
+            Tag::Table(_) => html! {
+                <div class="max-w-full overflow-x-auto">
+                    <table class="text-sm border-collapse">{children}</table>
+                </div>
+            },
+            Tag::TableHead => {
+                self.in_table_head = false;
+                html! { <thead><tr>{children}</tr></thead> }
+            }
+            Tag::TableRow => html! { <tr>{children}</tr> },
+            Tag::TableCell => {
+                let style = alignment_style(self.alignments.get(self.cell));
+                self.cell += 1;
+                if self.in_table_head {
+                    html! { <th class="px-2 py-1 font-semibold border border-gray-300" {style}>{children}</th> }
+                } else {
+                    html! { <td class="px-2 py-1 border border-gray-300" {style}>{children}</td> }
+                }
+            }
+            Tag::Emphasis => html! { <em>{children}</em> },
+            Tag::Strong => html! { <strong>{children}</strong> },
+            Tag::Strikethrough => html! { <del>{children}</del> },
+            // Images aren't loaded inline; they become links like any other
+            Tag::Link(_, url, title) | Tag::Image(_, url, title) if is_safe_url(&url) => {
+                self.anchor(&url, (!title.is_empty()).then(|| title.to_string()), children)
+            }
+            _ => children,
+        }
+    }
+
+    fn finish(mut self) -> Html {
+        self.flush();
+        let children = self.stack.pop().map(|(_, children)| children).unwrap_or_default();
+        html! { <div class="space-y-2 break-words">{for children}</div> }
+    }
+}
+
+/// Renders a message written in markdown, with GFM tables, task lists and
+/// strikethrough, `$…$` math typeset, and links cleaned with `tracking_rules`.
+pub fn render(text: &str, tracking_rules: &[String]) -> Html {
+    let (protected, formulas) = protect_formulas(text);
+    let mut builder = Builder::new(formulas, tracking_rules);
+    for event in Parser::new_ext(&protected, options()) {
+        builder.event(event);
+    }
+    builder.finish()
+}
@@ -0,0 +1,196 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::{
+    channel::oneshot,
+    future::{LocalBoxFuture, Shared},
+    FutureExt,
+};
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+// Only fetched once a message with math shows up; copied out of the katex package by webpack
+const KATEX_SCRIPT: &str = "/katex/katex.min.js";
+const KATEX_STYLESHEET: &str = "/katex/katex.min.css";
+
+thread_local! {
+    // Every formula waits on the same download
+    static KATEX: RefCell<Option<Shared<LocalBoxFuture<'static, bool>>>> = RefCell::new(None);
+}
+
+/// A piece of message text, either as typed or a TeX formula.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    /// `$$…$$` is `display` math on a line of its own, `$…$` sits inline
+    Math { tex: &'a str, display: bool },
+}
+
+/// Splits out `$…$` and `$$…$$` formulas. Like Pandoc, an inline formula can't
+/// start or end with a space and can't be followed by a digit, so prices such
+/// as "$5 or $10" stay text; `\$` is never a delimiter.
+pub fn split(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    // Start of the text not handed out yet
+    let mut plain = 0;
+    let mut i = 0;
+    // Both delimiters are ASCII, so every index sliced at is a char boundary
+    let bytes = text.as_bytes();
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => match formula_at(text, i) {
+                Some((tex, display, end)) => {
+                    if plain < i {
+                        segments.push(Segment::Text(&text[plain..i]));
+                    }
+                    segments.push(Segment::Math { tex, display });
+                    i = end;
+                    plain = end;
+                }
+                None => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+    if plain < text.len() {
+        segments.push(Segment::Text(&text[plain..]));
+    }
+    segments
+}
+
+/// Whether `text` has at least one formula in it.
+pub fn has_math(text: &str) -> bool {
+    text.contains('$') && split(text).iter().any(|s| matches!(s, Segment::Math { .. }))
+}
+
+/// The formula opening at byte `start`, whether it's display math, and where it ends.
+fn formula_at(text: &str, start: usize) -> Option<(&str, bool, usize)> {
+    let rest = &text[start..];
+    if let Some(body) = rest.strip_prefix("$$") {
+        let close = body.find("$$")?;
+        let tex = body[..close].trim();
+        return (!tex.is_empty()).then_some((tex, true, start + 2 + close + 2));
+    }
+    let body = &rest[1..];
+    if body.is_empty() || body.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut from = 0;
+    loop {
+        let close = from + body[from..].find('$')?;
+        let before = body[..close].chars().next_back();
+        let after = body[close + 1..].chars().next();
+        let closes = matches!(before, Some(c) if c != '\\' && !c.is_whitespace());
+        if closes && !matches!(after, Some(c) if c.is_ascii_digit()) {
+            return Some((&body[..close], false, start + 1 + close + 1));
+        }
+        from = close + 1;
+    }
+}
+
+fn katex() -> Option<JsValue> {
+    web_sys::window()
+        .and_then(|w| Reflect::get(&w, &"katex".into()).ok())
+        .filter(|k| !k.is_undefined())
+}
+
+/// Adds KaTeX's stylesheet and script to the page, resolving once the script ran.
+async fn inject() -> bool {
+    let (tx, rx) = oneshot::channel();
+    let added = (|| {
+        let document = web_sys::window()?.document()?;
+        let stylesheet = document.create_element("link").ok()?;
+        stylesheet.set_attribute("rel", "stylesheet").ok()?;
+        stylesheet.set_attribute("href", KATEX_STYLESHEET).ok()?;
+        document.head()?.append_child(&stylesheet).ok()?;
+
+        let script = document.create_element("script").ok()?.dyn_into::<HtmlElement>().ok()?;
+        script.set_attribute("src", KATEX_SCRIPT).ok()?;
+        script.set_attribute("async", "").ok()?;
+        // Whichever fires first answers; the other finds the sender gone
+        let tx = Rc::new(RefCell::new(Some(tx)));
+        let done = |loaded: bool| {
+            let tx = tx.clone();
+            Closure::once_into_js(move || {
+                if let Some(tx) = tx.borrow_mut().take() {
+                    let _ = tx.send(loaded);
+                }
+            })
+        };
+        script.set_onload(Some(done(true).unchecked_ref()));
+        script.set_onerror(Some(done(false).unchecked_ref()));
+        document.head()?.append_child(&script).ok()?;
+        Some(())
+    })();
+    if added.is_none() {
+        return false;
+    }
+    matches!(rx.await, Ok(true))
+}
+
+/// Makes sure KaTeX is there, downloading it the first time; `false` if it can't be had.
+async fn load_katex() -> bool {
+    if katex().is_some() {
+        return true;
+    }
+    let loading = KATEX.with(|k| k.borrow_mut().get_or_insert_with(|| inject().boxed_local().shared()).clone());
+    loading.await
+}
+
+/// Typesets `tex` into `element`, `false` if KaTeX isn't loaded.
+fn typeset(tex: &str, display: bool, element: &HtmlElement) -> Option<()> {
+    let katex = katex()?;
+    let render: Function = Reflect::get(&katex, &"render".into()).ok()?.dyn_into().ok()?;
+    let options = Object::new();
+    Reflect::set(&options, &"displayMode".into(), &display.into()).ok()?;
+    // Bad TeX is shown in red in place rather than thrown
+    Reflect::set(&options, &"throwOnError".into(), &false.into()).ok()?;
+    render.call3(&katex, &tex.into(), element, &options).ok()?;
+    Some(())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MathProps {
+    pub tex: String,
+    #[prop_or_default]
+    pub display: bool,
+}
+
+/// A formula, shown as its source until KaTeX has loaded and typeset it.
+#[function_component(Math)]
+pub fn math(props: &MathProps) -> Html {
+    let target = use_node_ref();
+    let typeset_ok = use_state(|| false);
+    {
+        let target = target.clone();
+        let typeset_ok = typeset_ok.clone();
+        use_effect_with_deps(
+            move |(tex, display): &(String, bool)| {
+                let (tex, display) = (tex.clone(), *display);
+                wasm_bindgen_futures::spawn_local(async move {
+                    if !load_katex().await {
+                        return;
+                    }
+                    if let Some(element) = target.cast::<HtmlElement>() {
+                        typeset_ok.set(typeset(&tex, display, &element).is_some());
+                    }
+                });
+                || ()
+            },
+            (props.tex.clone(), props.display),
+        );
+    }
+
+    let source = if props.display { format!("$${}$$", props.tex) } else { format!("${}$", props.tex) };
+    html! {
+        <span class={classes!(props.display.then_some("block my-1 overflow-x-auto"))} title={source.clone()}>
+            if !*typeset_ok {
+                <code class="font-mono text-sm">{source}</code>
+            }
+            // Left empty here so KaTeX can fill it without Yew touching its output
+            <span ref={target}></span>
+        </span>
+    }
+}
@@ -0,0 +1,215 @@
+use yew::prelude::*;
+
+use crate::components::{chat::MediaMeta, motion::animate};
+use crate::services::storage;
+
+// Matches the old `max-w-xs` cap on inline images
+const MAX_WIDTH: u32 = 320;
+// Box reserved when the sender didn't tell us the dimensions
+const FALLBACK_WIDTH: u32 = 320;
+const FALLBACK_HEIGHT: u32 = 240;
+
+const IMAGE_EXTENSIONS: [&str; 5] = [".gif", ".png", ".jpg", ".jpeg", ".webp"];
+const VIDEO_EXTENSIONS: [&str; 3] = [".mp4", ".webm", ".mov"];
+
+// Messages whose hidden image the reader chose to see, newest last
+const REVEALED_KEY: &str = "revealed_media";
+const MAX_REVEALED: usize = 500;
+
+fn was_revealed(id: &str) -> bool {
+    storage::load::<Vec<String>>(REVEALED_KEY).unwrap_or_default().iter().any(|r| r == id)
+}
+
+fn remember_revealed(id: &str) {
+    let mut revealed: Vec<String> = storage::load(REVEALED_KEY).unwrap_or_default();
+    if !revealed.iter().any(|r| r == id) {
+        revealed.push(id.to_string());
+        let excess = revealed.len().saturating_sub(MAX_REVEALED);
+        revealed.drain(..excess);
+        storage::save(REVEALED_KEY, &revealed);
+    }
+}
+
+// Lowercased URL without its query string or fragment, for extension checks
+fn url_path(url: &str) -> String {
+    url.split(['?', '#']).next().unwrap_or_default().to_ascii_lowercase()
+}
+
+/// Whether a message body is a bare link to an image we render inline.
+pub fn is_image_url(text: &str) -> bool {
+    let path = url_path(text);
+    !text.contains(char::is_whitespace) && IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+/// Whether a message body is a bare link to a video file.
+pub fn is_video_url(text: &str) -> bool {
+    let path = url_path(text);
+    !text.contains(char::is_whitespace) && VIDEO_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+pub fn is_gif(url: &str) -> bool {
+    url_path(url).ends_with(".gif")
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MediaImageProps {
+    pub src: String,
+    pub alt: String,
+    #[prop_or_default]
+    pub media: Option<MediaMeta>,
+    /// When false, GIFs show a still poster and are only fetched once clicked
+    #[prop_or(true)]
+    pub autoplay: bool,
+    /// Blurred behind a "Show" button until clicked
+    #[prop_or_default]
+    pub hidden: bool,
+    /// Message the image belongs to, so showing it is remembered across reloads
+    #[prop_or_default]
+    pub reveal_id: Option<String>,
+}
+
+/// Inline image that occupies its final size from the first render, so the
+/// message list doesn't jump while it downloads.
+#[function_component(MediaImage)]
+pub fn media_image(props: &MediaImageProps) -> Html {
+    let loaded = use_state(|| false);
+    let failed = use_state(|| false);
+    let playing = use_state(|| false);
+    let revealed = use_state(|| matches!(&props.reveal_id, Some(id) if was_revealed(id)));
+
+    {
+        let loaded = loaded.clone();
+        let failed = failed.clone();
+        let playing = playing.clone();
+        use_effect_with_deps(
+            move |_| {
+                loaded.set(false);
+                failed.set(false);
+                playing.set(false);
+                || ()
+            },
+            props.src.clone(),
+        );
+    }
+
+    let (width, height) = match &props.media {
+        Some(m) if m.width > 0 && m.height > 0 => (m.width, m.height),
+        _ => (FALLBACK_WIDTH, FALLBACK_HEIGHT),
+    };
+    let box_style = format!(
+        "width: {}px; max-width: 100%; aspect-ratio: {} / {};",
+        width.min(MAX_WIDTH),
+        width,
+        height
+    );
+    let placeholder = props.media.as_ref().and_then(|m| m.placeholder.clone());
+    let click_to_play = !props.autoplay && is_gif(&props.src);
+
+    if props.hidden && !*revealed {
+        let onclick = {
+            let revealed = revealed.clone();
+            let reveal_id = props.reveal_id.clone();
+            Callback::from(move |_: MouseEvent| {
+                if let Some(id) = &reveal_id {
+                    remember_revealed(id);
+                }
+                revealed.set(true);
+            })
+        };
+        // Blurred past recognition; the tiny preview does when there is one, saving the download
+        let preview = props.media.as_ref().and_then(|m| m.placeholder.clone().or_else(|| m.poster.clone()));
+        return html! {
+            <button
+                {onclick}
+                class="mt-1 relative block overflow-hidden rounded bg-gray-400 focus:outline-none focus:ring-2 focus:ring-blue-400"
+                style={box_style}
+                title="Show image"
+            >
+                <img
+                    class="absolute inset-0 w-full h-full object-cover filter blur-2xl scale-110"
+                    src={preview.unwrap_or_else(|| props.src.clone())}
+                    alt=""
+                    aria-hidden="true"
+                />
+                <span class="absolute inset-0 flex flex-col items-center justify-center gap-1 bg-black bg-opacity-30 text-white">
+                    if matches!(&props.media, Some(m) if m.sensitive) {
+                        <span class="text-xs">{"Marked sensitive"}</span>
+                    }
+                    <span class="px-3 py-1 rounded-full bg-black bg-opacity-60 text-sm font-semibold">{"Show"}</span>
+                </span>
+            </button>
+        };
+    }
+
+    if click_to_play && !*playing {
+        let poster = props.media.as_ref().and_then(|m| m.poster.clone().or_else(|| m.placeholder.clone()));
+        let onclick = {
+            let playing = playing.clone();
+            Callback::from(move |_: MouseEvent| playing.set(true))
+        };
+        return html! {
+            <button
+                {onclick}
+                class="mt-1 relative block overflow-hidden rounded bg-gray-300 focus:outline-none focus:ring-2 focus:ring-blue-400"
+                style={box_style}
+                title="Play GIF"
+            >
+                if let Some(poster) = poster {
+                    <img class="absolute inset-0 w-full h-full object-contain" src={poster} alt={props.alt.clone()} />
+                }
+                <span class="absolute inset-0 flex items-center justify-center">
+                    <span class="px-3 py-1 rounded-full bg-black bg-opacity-60 text-white text-sm font-semibold">{"▶ GIF"}</span>
+                </span>
+            </button>
+        };
+    }
+    // Clicking a GIF the user started puts it back to its poster
+    let onclick = click_to_play.then(|| {
+        let playing = playing.clone();
+        Callback::from(move |_: MouseEvent| playing.set(false))
+    });
+
+    let onload = {
+        let loaded = loaded.clone();
+        Callback::from(move |_: Event| loaded.set(true))
+    };
+    let onerror = {
+        let failed = failed.clone();
+        Callback::from(move |_: Event| failed.set(true))
+    };
+
+    html! {
+        <div class="mt-1 relative overflow-hidden rounded bg-gray-200" style={box_style} {onclick}>
+            if *failed {
+                <div class="absolute inset-0 flex items-center justify-center text-xs text-gray-500">
+                    {"Image unavailable"}
+                </div>
+            } else {
+                if !*loaded {
+                    if let Some(placeholder) = placeholder {
+                        <img
+                            class="absolute inset-0 w-full h-full object-cover filter blur-md scale-110"
+                            src={placeholder}
+                            alt=""
+                            aria-hidden="true"
+                        />
+                    } else {
+                        <div class={classes!("absolute", "inset-0", "bg-gray-300", animate("animate-pulse"))}></div>
+                    }
+                }
+                <img
+                    class={classes!(
+                        "absolute", "inset-0", "w-full", "h-full", "object-contain",
+                        animate("transition-opacity duration-300"),
+                        if *loaded { "opacity-100" } else { "opacity-0" }
+                    )}
+                    src={props.src.clone()}
+                    alt={props.alt.clone()}
+                    loading="lazy"
+                    {onload}
+                    {onerror}
+                />
+            }
+        </div>
+    }
+}
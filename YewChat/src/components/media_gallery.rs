@@ -0,0 +1,78 @@
+use yew::prelude::*;
+
+use crate::components::{
+    chat::MessageData,
+    media::{is_image_url, is_video_url},
+};
+
+/// A picture or video shared in the room, pointing back at the message it came from.
+#[derive(Clone, PartialEq)]
+pub struct MediaItem {
+    pub index: usize,
+    pub url: String,
+    pub from: String,
+    pub video: bool,
+}
+
+impl MediaItem {
+    /// Collects every (non-deleted) media message, newest first.
+    pub fn collect(messages: &[MessageData]) -> Vec<MediaItem> {
+        messages
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, m)| !m.system && !m.deleted)
+            .filter_map(|(index, m)| {
+                let video = is_video_url(&m.message);
+                (video || is_image_url(&m.message)).then(|| MediaItem {
+                    index,
+                    url: m.message.clone(),
+                    from: m.from.clone(),
+                    video,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MediaGalleryProps {
+    pub items: Vec<MediaItem>,
+    /// Opens the lightbox for a message index
+    pub on_open: Callback<usize>,
+}
+
+#[function_component(MediaGallery)]
+pub fn media_gallery(props: &MediaGalleryProps) -> Html {
+    if props.items.is_empty() {
+        return html! {
+            <div class="p-6 text-center text-sm text-gray-400">{"Images and videos shared here will show up in this tab"}</div>
+        };
+    }
+
+    html! {
+        <div class="grid grid-cols-3 gap-1 p-2">
+            {
+                props.items.iter().map(|item| {
+                    let index = item.index;
+                    let onclick = props.on_open.reform(move |_: MouseEvent| index);
+                    html! {
+                        <button
+                            {onclick}
+                            title={format!("Shared by {}", item.from)}
+                            class="relative aspect-square overflow-hidden rounded bg-gray-200 focus:outline-none focus:ring-2 focus:ring-blue-400"
+                        >
+                            if item.video {
+                                // Only the metadata is fetched, enough for the first frame
+                                <video class="w-full h-full object-cover" src={item.url.clone()} preload="metadata" muted=true />
+                                <span class="absolute inset-0 flex items-center justify-center text-white text-xl drop-shadow">{"▶"}</span>
+                            } else {
+                                <img class="w-full h-full object-cover" src={item.url.clone()} alt="" loading="lazy" />
+                            }
+                        </button>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+
+use yew::prelude::*;
+
+use crate::components::{
+    avatar::Avatar,
+    chat::{Role, UserProfile},
+    name_color::name_color,
+    presence::PresenceDot,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct MembersPanelProps {
+    /// Everyone in the room, online or not
+    pub members: Vec<UserProfile>,
+    pub username: String,
+    pub favorites: BTreeSet<String>,
+    pub on_mention: Callback<String>,
+    pub on_toggle_favorite: Callback<String>,
+    pub on_copy: Callback<String>,
+    pub on_open_profile: Callback<String>,
+    pub presence_shapes: bool,
+}
+
+/// Members of the current room, staff first, then everyone online, then people
+/// who took part in the conversation but have left.
+#[function_component(MembersPanel)]
+pub fn members_panel(props: &MembersPanelProps) -> Html {
+    let mut members = props.members.clone();
+    members.sort_by_key(|m| m.name.to_lowercase());
+    let group = |label: &'static str, filter: &dyn Fn(&UserProfile) -> bool| {
+        let group: Vec<&UserProfile> = members.iter().filter(|m| filter(m)).collect();
+        if group.is_empty() {
+            return html! {};
+        }
+        html! {
+            <div class="mb-2">
+                <div class="px-4 py-1 text-xs font-medium text-gray-500">{format!("{} — {}", label, group.len())}</div>
+                { group.into_iter().map(|m| member_row(props, m)).collect::<Html>() }
+            </div>
+        }
+    };
+
+    html! {
+        <div class="py-2">
+            {group("OWNERS", &|m| m.online() && m.role == Role::Owner)}
+            {group("MODERATORS", &|m| m.online() && m.role == Role::Moderator)}
+            {group("ONLINE", &|m| m.online() && m.role == Role::Member)}
+            {group("OFFLINE", &|m| !m.online())}
+        </div>
+    }
+}
+
+fn member_row(props: &MembersPanelProps, m: &UserProfile) -> Html {
+    let is_self = m.name == props.username;
+    let favorite = props.favorites.contains(&m.name);
+    let action = |cb: &Callback<String>| {
+        let name = m.name.clone();
+        cb.reform(move |_: MouseEvent| name.clone())
+    };
+    let action_class = "px-2 py-1 text-xs rounded text-gray-500 hover:bg-gray-200 hover:text-gray-700 focus:outline-none";
+
+    html! {
+        <div class={classes!("group", "flex", "items-center", "px-4", "py-2", "hover:bg-gray-50", if m.online() { "" } else { "opacity-60" })}>
+            <div class="relative shrink-0">
+                <Avatar name={m.name.clone()} src={m.avatar.clone()} class={classes!("w-8", "h-8")} />
+                <PresenceDot presence={m.presence} shapes={props.presence_shapes} />
+            </div>
+            <div class="ml-3 min-w-0 grow">
+                <button
+                    onclick={action(&props.on_open_profile)}
+                    class={classes!("block", "max-w-full", "text-left", "text-sm", "font-medium", "truncate", "hover:underline", name_color(&m.name))}
+                    title="View profile"
+                >
+                    {m.name.clone()}
+                    if is_self {
+                        <span class="ml-1 text-xs font-normal text-gray-400">{"(you)"}</span>
+                    }
+                </button>
+                if !m.profile.pronouns.is_empty() {
+                    <div class="text-xs text-gray-500 truncate">{m.profile.pronouns.clone()}</div>
+                }
+                if m.role != Role::Member {
+                    <div class="text-xs text-gray-500">{m.role.label()}</div>
+                }
+            </div>
+            <div class="flex opacity-0 group-hover:opacity-100 focus-within:opacity-100">
+                if !is_self {
+                    <button onclick={action(&props.on_mention)} class={action_class} title="Mention">{"@"}</button>
+                    <button
+                        onclick={action(&props.on_toggle_favorite)}
+                        class={action_class}
+                        title={if favorite { "Remove from favorites" } else { "Add to favorites" }}
+                    >
+                        {if favorite { "★" } else { "☆" }}
+                    </button>
+                }
+                <button onclick={action(&props.on_copy)} class={action_class} title="Copy name">{"📋"}</button>
+            </div>
+        </div>
+    }
+}
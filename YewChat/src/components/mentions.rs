@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+
+use yew::prelude::*;
+
+use crate::{
+    components::{
+        chat::{MessageData, MessageRef},
+        name_color::name_color,
+    },
+    services::storage,
+};
+
+const READ_KEY: &str = "mentions_read";
+// Only the most recent read markers are kept so storage doesn't grow forever
+const MAX_READ_MARKERS: usize = 500;
+const EXCERPT_LEN: usize = 140;
+
+/// A message that mentions the current user.
+#[derive(Clone, PartialEq)]
+pub struct Mention {
+    pub target: MessageRef,
+    pub from: String,
+    pub excerpt: String,
+    pub timestamp: Option<i64>,
+    pub read: bool,
+}
+
+/// Mentions of the current user, oldest first, updated as messages arrive
+/// rather than by rescanning the conversation. Read state survives reloads
+/// for messages that carry a server ID.
+#[derive(Default)]
+pub struct MentionIndex {
+    entries: Vec<Mention>,
+    read_ids: BTreeSet<String>,
+    // Everything sent up to then has been read, here or on another device
+    read_until: Option<i64>,
+}
+
+impl MentionIndex {
+    pub fn load() -> Self {
+        Self {
+            entries: Vec::new(),
+            read_ids: storage::load(READ_KEY).unwrap_or_default(),
+            read_until: None,
+        }
+    }
+
+    /// Drops the read state kept on this device, e.g. when signing out.
+    pub fn forget() {
+        storage::remove(READ_KEY);
+    }
+
+    pub fn entries(&self) -> &[Mention] {
+        &self.entries
+    }
+
+    pub fn unread(&self) -> usize {
+        self.entries.iter().filter(|m| !m.read).count()
+    }
+
+    /// Records `m` if it mentions us and isn't indexed yet.
+    pub fn add(&mut self, m: &MessageData) {
+        if !m.highlight.mention || m.deleted || self.entries.iter().any(|e| e.target.matches(m)) {
+            return;
+        }
+        let mention = Mention {
+            target: m.to_ref(),
+            from: m.from.clone(),
+            excerpt: m.message.chars().take(EXCERPT_LEN).collect(),
+            timestamp: m.timestamp,
+            read: m.id.as_ref().map_or(false, |id| self.read_ids.contains(id)) || self.read_before(m.timestamp),
+        };
+        // History pages arrive out of order, so keep the list sorted by time
+        let at = self
+            .entries
+            .iter()
+            .position(|e| e.timestamp > m.timestamp)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(at, mention);
+    }
+
+    /// Drops the entry for a message that was deleted or no longer mentions us.
+    pub fn remove(&mut self, target: &MessageRef) {
+        self.entries.retain(|e| e.target != *target);
+    }
+
+    /// Rebuilds the index from scratch, e.g. after the username changed.
+    pub fn rebuild<'a>(&mut self, messages: impl Iterator<Item = &'a MessageData>) {
+        self.entries.clear();
+        for m in messages {
+            self.add(m);
+        }
+    }
+
+    pub fn mark_read(&mut self, target: &MessageRef) {
+        for e in self.entries.iter_mut().filter(|e| e.target == *target) {
+            e.read = true;
+        }
+        self.save();
+    }
+
+    /// Marks everything sent up to `time` as read, including mentions that only
+    /// turn up later in older history.
+    pub fn mark_read_until(&mut self, time: i64) {
+        if matches!(self.read_until, Some(until) if until >= time) {
+            return;
+        }
+        self.read_until = Some(time);
+        for e in self.entries.iter_mut() {
+            e.read |= matches!(e.timestamp, Some(t) if t <= time);
+        }
+        self.save();
+    }
+
+    fn read_before(&self, timestamp: Option<i64>) -> bool {
+        matches!((timestamp, self.read_until), (Some(t), Some(until)) if t <= until)
+    }
+
+    pub fn mark_all_read(&mut self) {
+        for e in self.entries.iter_mut() {
+            e.read = true;
+        }
+        self.save();
+    }
+
+    fn save(&mut self) {
+        for id in self.entries.iter().filter(|e| e.read).filter_map(|e| e.target.id.clone()) {
+            self.read_ids.insert(id);
+        }
+        // Server IDs are increasing numbers, so the smallest are the oldest
+        while self.read_ids.len() > MAX_READ_MARKERS {
+            let oldest = self
+                .read_ids
+                .iter()
+                .min_by_key(|id| id.parse::<u64>().unwrap_or(0))
+                .cloned();
+            match oldest {
+                Some(id) => self.read_ids.remove(&id),
+                None => break,
+            };
+        }
+        storage::save(READ_KEY, &self.read_ids);
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MentionsPanelProps {
+    pub mentions: Vec<Mention>,
+    pub on_open: Callback<MessageRef>,
+    pub on_mark_all_read: Callback<()>,
+}
+
+#[function_component(MentionsPanel)]
+pub fn mentions_panel(props: &MentionsPanelProps) -> Html {
+    if props.mentions.is_empty() {
+        return html! {
+            <div class="p-6 text-center text-sm text-gray-400">{"Messages that @mention you will show up here"}</div>
+        };
+    }
+    let any_unread = props.mentions.iter().any(|m| !m.read);
+
+    html! {
+        <div class="flex flex-col">
+            if any_unread {
+                <button
+                    onclick={props.on_mark_all_read.reform(|_: MouseEvent| ())}
+                    class="self-end m-2 text-xs text-blue-600 hover:underline"
+                >
+                    {"Mark all as read"}
+                </button>
+            }
+            {
+                props.mentions.iter().rev().map(|m| {
+                    let target = m.target.clone();
+                    let onclick = props.on_open.reform(move |_: MouseEvent| target.clone());
+                    html! {
+                        <button
+                            {onclick}
+                            class={classes!(
+                                "flex", "w-full", "text-left", "px-4", "py-3", "border-b", "hover:bg-gray-50", "focus:outline-none",
+                                if m.read { "" } else { "bg-amber-50" }
+                            )}
+                        >
+                            <div class={classes!("w-2", "h-2", "mt-1.5", "mr-2", "rounded-full", "flex-none", if m.read { "" } else { "bg-amber-500" })}></div>
+                            <div class="min-w-0">
+                                <div class="text-xs">
+                                    <span class={classes!("font-medium", name_color(&m.from))}>{m.from.clone()}</span>
+                                    if let Some(ts) = m.timestamp {
+                                        <span class="ml-2 text-gray-400">{format_time(ts)}</span>
+                                    }
+                                </div>
+                                <div class={classes!("text-sm", "break-words", if m.read { "text-gray-500" } else { "text-gray-800" })}>
+                                    {m.excerpt.clone()}
+                                </div>
+                            </div>
+                        </button>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
+
+fn format_time(ts: i64) -> String {
+    let date = js_sys::Date::new(&(ts as f64).into());
+    format!("{:02}/{:02} {:02}:{:02}", date.get_date(), date.get_month() + 1, date.get_hours(), date.get_minutes())
+}
@@ -0,0 +1,394 @@
+use std::rc::Rc;
+
+use gloo_timers::callback::{Interval, Timeout};
+use web_sys::TouchEvent;
+use yew::prelude::*;
+
+use crate::components::{
+    avatar::Avatar,
+    chat::{DeliveryStatus, MessageData},
+    highlight::Highlight,
+    motion::animate,
+    name_color::name_color,
+    renderers,
+    settings::Layout,
+};
+use crate::services::timezone;
+
+// How far (in px) a bubble has to be dragged before releasing it counts as a reply
+const SWIPE_REPLY_THRESHOLD: i32 = 64;
+// Cap the drag so the bubble never slides completely out of view
+const SWIPE_MAX_OFFSET: i32 = 96;
+// Hold duration before the action sheet opens
+const LONG_PRESS_MS: u32 = 500;
+// Movement tolerance so a slightly shaky finger still counts as a press
+const LONG_PRESS_SLOP: i32 = 10;
+
+#[derive(Properties, PartialEq)]
+pub struct MessageProps {
+    pub message: MessageData,
+    pub is_self: bool,
+    pub avatar: Option<String>,
+    pub username: String,
+    pub on_reply: Callback<()>,
+    pub on_long_press: Callback<()>,
+    pub on_react: Callback<String>,
+    pub on_retry: Callback<()>,
+    pub on_discard: Callback<()>,
+    pub on_undo: Callback<()>,
+    pub on_show_edits: Callback<()>,
+    /// Swiping to reply is turned off where the user can't post
+    #[prop_or(true)]
+    pub can_reply: bool,
+    #[prop_or(true)]
+    pub autoplay_gifs: bool,
+    #[prop_or_default]
+    pub highlight: Highlight,
+    /// Solid, bordered bubbles with AAA contrast instead of tinted ones
+    #[prop_or_default]
+    pub high_contrast: bool,
+    /// Sender's timezone, when they shared one and the reader wants their local time too
+    #[prop_or_default]
+    pub sender_timezone: Option<String>,
+    /// Query parameters stripped from links in the text
+    #[prop_or_default]
+    pub tracking_rules: Rc<[String]>,
+    /// Blur images until clicked even when the sender didn't mark them sensitive
+    #[prop_or_default]
+    pub blur_media: bool,
+    /// Map provider for location thumbnails
+    #[prop_or_default]
+    pub map_tiles: String,
+    #[prop_or_default]
+    pub layout: Layout,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Swipe {
+    start_x: i32,
+    start_y: i32,
+    offset: i32,
+    // Set once the gesture is recognised as vertical so we don't fight the list scroll
+    scrolling: bool,
+}
+
+fn first_touch(e: &TouchEvent) -> Option<(i32, i32)> {
+    e.touches().get(0).map(|t| (t.client_x(), t.client_y()))
+}
+
+#[function_component(MessageBubble)]
+pub fn message_bubble(props: &MessageProps) -> Html {
+    let swipe = use_state(|| None::<Swipe>);
+    let long_press = use_mut_ref(|| None::<Timeout>);
+    let m = &props.message;
+    let is_self = props.is_self;
+    let can_reply = props.can_reply;
+    // Only worth showing when their clock reads differently from ours
+    let sender_time = match (m.timestamp, props.sender_timezone.as_deref()) {
+        (Some(ts), Some(zone)) => timezone::time_in(ts as f64, zone)
+            .filter(|time| *time != format_time(ts))
+            .map(|time| (time, zone.to_string())),
+        _ => None,
+    };
+    // Ticks once a second while the undo countdown is showing
+    let now = use_state(js_sys::Date::now);
+    {
+        let now = now.clone();
+        use_effect_with_deps(
+            move |send_at: &Option<f64>| {
+                now.set(js_sys::Date::now());
+                let ticker = send_at.map(|_| Interval::new(1_000, move || now.set(js_sys::Date::now())));
+                move || drop(ticker)
+            },
+            m.send_at,
+        );
+    }
+
+    let ontouchstart = {
+        let swipe = swipe.clone();
+        let long_press = long_press.clone();
+        let on_long_press = props.on_long_press.clone();
+        Callback::from(move |e: TouchEvent| {
+            if let Some((x, y)) = first_touch(&e) {
+                swipe.set(Some(Swipe { start_x: x, start_y: y, offset: 0, scrolling: false }));
+
+                let swipe = swipe.clone();
+                let on_long_press = on_long_press.clone();
+                *long_press.borrow_mut() = Some(Timeout::new(LONG_PRESS_MS, move || {
+                    // Drop the gesture so releasing the finger doesn't also trigger a reply
+                    swipe.set(None);
+                    on_long_press.emit(());
+                }));
+            }
+        })
+    };
+
+    let ontouchmove = {
+        let swipe = swipe.clone();
+        let long_press = long_press.clone();
+        Callback::from(move |e: TouchEvent| {
+            if let (Some(mut s), Some((x, y))) = (*swipe, first_touch(&e)) {
+                if s.scrolling {
+                    return;
+                }
+                let dx = x - s.start_x;
+                let dy = y - s.start_y;
+                if dx.abs() > LONG_PRESS_SLOP || dy.abs() > LONG_PRESS_SLOP {
+                    long_press.borrow_mut().take();
+                }
+                if s.offset == 0 && dy.abs() > dx.abs() {
+                    s.scrolling = true;
+                } else {
+                    // Own messages are swiped to the left, everyone else's to the right
+                    let dx = if is_self { -dx } else { dx };
+                    s.offset = if can_reply { dx.clamp(0, SWIPE_MAX_OFFSET) } else { 0 };
+                }
+                swipe.set(Some(s));
+            }
+        })
+    };
+
+    let ontouchend = {
+        let swipe = swipe.clone();
+        let long_press = long_press.clone();
+        let on_reply = props.on_reply.clone();
+        Callback::from(move |_: TouchEvent| {
+            long_press.borrow_mut().take();
+            if let Some(s) = *swipe {
+                if !s.scrolling && s.offset >= SWIPE_REPLY_THRESHOLD {
+                    on_reply.emit(());
+                }
+            }
+            swipe.set(None);
+        })
+    };
+
+    // While the finger is down the bubble follows it directly; on release the
+    // transition classes spring it back into place.
+    let (offset, dragging) = match *swipe {
+        Some(s) if !s.scrolling => (s.offset, true),
+        _ => (0, false),
+    };
+    let translate = if is_self { -offset } else { offset };
+    let ontouchcancel = ontouchend.clone();
+    let failed = is_self && m.status == DeliveryStatus::Failed;
+    let bubble_class = match (props.high_contrast, is_self) {
+        (false, true) if failed => "bg-red-500 text-white rounded-br-none",
+        (false, true) => "bg-blue-500 text-white rounded-br-none",
+        (false, false) if props.highlight.mention => "bg-amber-50 text-gray-800 rounded-bl-none border-l-4 border-amber-400",
+        (false, false) if props.highlight.keyword => "bg-sky-50 text-gray-800 rounded-bl-none border-l-4 border-sky-400",
+        (false, false) => "bg-gray-100 text-gray-800 rounded-bl-none",
+        (true, true) if failed => "bg-red-800 text-white rounded-br-none border-2 border-black",
+        (true, true) => "bg-blue-900 text-white rounded-br-none border-2 border-black",
+        (true, false) if props.highlight.mention => "bg-yellow-200 text-black rounded-bl-none border-2 border-l-8 border-black",
+        (true, false) if props.highlight.keyword => "bg-white text-black rounded-bl-none border-2 border-l-8 border-black",
+        (true, false) => "bg-white text-black rounded-bl-none border-2 border-black",
+    };
+
+    if props.layout == Layout::Compact {
+        return compact_row(props, *now);
+    }
+
+    html! {
+        <div
+            class={classes!(
+                "flex",
+                "max-w-md",
+                if is_self { "ml-auto flex-row-reverse" } else { "" },
+                if dragging { "" } else { animate("transition-transform duration-200 ease-out") }
+            )}
+            style={format!("transform: translateX({}px); touch-action: pan-y;", translate)}
+            ontouchstart={ontouchstart}
+            ontouchmove={ontouchmove}
+            ontouchend={ontouchend}
+            ontouchcancel={ontouchcancel}
+        >
+            if let Some(avatar) = props.avatar.clone() {
+                <Avatar name={m.from.clone()} src={avatar} class={classes!("w-8", "h-8", "mt-1")} />
+            }
+
+            <div class={classes!(
+                "mx-3",
+                "p-3",
+                // Lets wide tables and code scroll instead of widening the bubble
+                "min-w-0",
+                "rounded-lg",
+                if matches!(m.status, DeliveryStatus::Sending | DeliveryStatus::Scheduled) { "opacity-70" } else { "" },
+                bubble_class
+            )}>
+                if !is_self {
+                    <div class={classes!("text-sm", "font-medium", "mb-1", name_color(&m.from))}>
+                        {m.from.clone()}
+                    </div>
+                }
+
+                if let Some(reply) = m.reply_to.as_ref() {
+                    <div class={classes!(
+                        "mb-2",
+                        "pl-2",
+                        "border-l-2",
+                        "text-xs",
+                        if is_self { "border-blue-200 text-blue-100" } else { "border-gray-400 text-gray-500" }
+                    )}>
+                        <div class={classes!("font-medium", if is_self { "" } else { name_color(&reply.from) })}>{reply.from.clone()}</div>
+                        <div class="truncate">{reply.message.clone()}</div>
+                    </div>
+                }
+
+                <div class={if is_self { "text-white" } else { "text-gray-800" }}>
+                    {content(props)}
+                </div>
+
+                // Time stamp
+                <div class={classes!(
+                    "text-xs",
+                    "mt-1",
+                    if is_self { "text-blue-100" } else { "text-gray-500" }
+                )}>
+                    if m.status == DeliveryStatus::Failed {
+                        <div class="flex items-center gap-2 text-red-200">
+                            <span title="Message not sent">{"⚠️ Not sent"}</span>
+                            <button onclick={props.on_retry.reform(|_: MouseEvent| ())} class="underline hover:text-white">{"Retry"}</button>
+                            <button onclick={props.on_discard.reform(|_: MouseEvent| ())} class="underline hover:text-white">{"Delete"}</button>
+                        </div>
+                    } else if let (DeliveryStatus::Scheduled, Some(send_at)) = (m.status, m.send_at) {
+                        <div class="flex items-center gap-2">
+                            <span>{format!("Sending in {}s", ((send_at - *now) / 1_000.0).ceil().max(0.0))}</span>
+                            <button onclick={props.on_undo.reform(|_: MouseEvent| ())} class="font-medium underline hover:text-white">{"Undo"}</button>
+                        </div>
+                    } else if m.status == DeliveryStatus::Sending {
+                        <span title="Sending">{"🕓 Sending…"}</span>
+                    } else {
+                        {
+                            m.timestamp.map_or_else(
+                                || "Just now".to_string(),
+                                format_time
+                            )
+                        }
+                        if let Some((time, zone)) = sender_time {
+                            <span title={zone}>{format!(" — {} for {}", time, m.from)}</span>
+                        }
+                        if !m.revisions.is_empty() && !m.deleted {
+                            <button
+                                onclick={props.on_show_edits.reform(|_: MouseEvent| ())}
+                                class="ml-1 hover:underline"
+                                title="Show edit history"
+                            >
+                                {"(edited)"}
+                            </button>
+                        }
+                    }
+                </div>
+
+                // Reactions
+                if !m.reactions.is_empty() {
+                    <div class="flex flex-wrap gap-1 mt-2">
+                        {reactions(props)}
+                    </div>
+                }
+            </div>
+        </div>
+    }
+}
+
+/// The message text as its renderer shows it, or a note that it's gone.
+fn content(props: &MessageProps) -> Html {
+    if props.message.deleted {
+        return html! { <span class="italic opacity-70">{"This message was deleted"}</span> };
+    }
+    renderers::render(&renderers::Context {
+        message: &props.message,
+        autoplay_gifs: props.autoplay_gifs,
+        tracking_rules: &props.tracking_rules,
+        blur_media: props.blur_media,
+        map_tiles: &props.map_tiles,
+    })
+}
+
+/// A button per emoji, showing who reacted and toggling our own reaction.
+fn reactions(props: &MessageProps) -> Html {
+    props.message.reactions.iter().map(|(emoji, users)| {
+        let mine = users.contains(&props.username);
+        let emoji_val = emoji.clone();
+        let on_react = props.on_react.clone();
+        let onclick = Callback::from(move |_: MouseEvent| on_react.emit(emoji_val.clone()));
+        html! {
+            <button
+                {onclick}
+                title={users.iter().cloned().collect::<Vec<_>>().join(", ")}
+                class={classes!(
+                    "px-2",
+                    "text-xs",
+                    "rounded-full",
+                    "border",
+                    if mine { "bg-blue-100 border-blue-400 text-blue-800" } else { "bg-white border-gray-200 text-gray-700" }
+                )}
+            >
+                {format!("{} {}", emoji, users.len())}
+            </button>
+        }
+    }).collect::<Html>()
+}
+
+/// The compact layout's single `[12:03] <alice> message` line, with what the
+/// bubble shows around the text squeezed in after it.
+fn compact_row(props: &MessageProps, now: f64) -> Html {
+    let m = &props.message;
+    let time = match m.status {
+        DeliveryStatus::Sending | DeliveryStatus::Scheduled => "--:--".to_string(),
+        _ => m.timestamp.map_or_else(|| "--:--".to_string(), format_time),
+    };
+    let row_class = match (props.high_contrast, props.highlight.mention, props.highlight.keyword) {
+        (false, true, _) => "bg-amber-50 border-amber-400",
+        (false, false, true) => "bg-sky-50 border-sky-400",
+        (true, true, _) => "bg-yellow-200 border-black",
+        (true, false, true) => "border-black",
+        _ => "border-transparent",
+    };
+    html! {
+        <div class={classes!(
+            "flex", "items-baseline", "gap-2", "px-2", "text-sm", "leading-6", "border-l-4", row_class,
+            matches!(m.status, DeliveryStatus::Sending | DeliveryStatus::Scheduled).then_some("opacity-70")
+        )}>
+            <span class="flex-none font-mono text-xs text-gray-400">{format!("[{}]", time)}</span>
+            <span class={classes!("flex-none", "font-medium", name_color(&m.from))}>{format!("<{}>", m.from)}</span>
+            <div class="grow min-w-0 text-gray-800">
+                if let Some(reply) = m.reply_to.as_ref() {
+                    <div class="truncate text-xs text-gray-500">
+                        {"↪ "}<span class={name_color(&reply.from)}>{reply.from.clone()}</span>{": "}{reply.message.clone()}
+                    </div>
+                }
+                {content(props)}
+                <div class="flex flex-wrap items-center gap-1 text-xs text-gray-500">
+                    if props.is_self && m.status == DeliveryStatus::Failed {
+                        <span class="text-red-600" title="Message not sent">{"⚠️ Not sent"}</span>
+                        <button onclick={props.on_retry.reform(|_: MouseEvent| ())} class="underline hover:text-gray-800">{"Retry"}</button>
+                        <button onclick={props.on_discard.reform(|_: MouseEvent| ())} class="underline hover:text-gray-800">{"Delete"}</button>
+                    } else if let (DeliveryStatus::Scheduled, Some(send_at)) = (m.status, m.send_at) {
+                        <span>{format!("Sending in {}s", ((send_at - now) / 1_000.0).ceil().max(0.0))}</span>
+                        <button onclick={props.on_undo.reform(|_: MouseEvent| ())} class="font-medium underline hover:text-gray-800">{"Undo"}</button>
+                    }
+                    if !m.revisions.is_empty() && !m.deleted {
+                        <button
+                            onclick={props.on_show_edits.reform(|_: MouseEvent| ())}
+                            class="hover:underline"
+                            title="Show edit history"
+                        >
+                            {"(edited)"}
+                        </button>
+                    }
+                    {reactions(props)}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+// Server timestamps are milliseconds since the epoch; show them as local HH:MM
+fn format_time(ts: i64) -> String {
+    let date = js_sys::Date::new(&(ts as f64).into());
+    format!("{:02}:{:02}", date.get_hours(), date.get_minutes())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,107 @@
+use wasm_bindgen_test::*;
+use yew::Callback;
+
+use super::{MessageBubble, MessageProps};
+use crate::components::{chat::MessageData, settings::Layout, testing::Mounted};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const GIF: &str = "https://media.example.org/cat.gif";
+
+fn props(from: &str, text: &str, is_self: bool) -> MessageProps {
+    MessageProps {
+        message: MessageData {
+            from: from.into(),
+            message: text.into(),
+            ..Default::default()
+        },
+        is_self,
+        avatar: None,
+        username: "alice".into(),
+        on_reply: Callback::noop(),
+        on_long_press: Callback::noop(),
+        on_react: Callback::noop(),
+        on_retry: Callback::noop(),
+        on_discard: Callback::noop(),
+        on_undo: Callback::noop(),
+        on_show_edits: Callback::noop(),
+        can_reply: true,
+        autoplay_gifs: true,
+        highlight: Default::default(),
+        high_contrast: false,
+        sender_timezone: None,
+        tracking_rules: Default::default(),
+        blur_media: false,
+        map_tiles: String::new(),
+        layout: Layout::Bubbles,
+    }
+}
+
+#[wasm_bindgen_test]
+fn own_bubble_sits_right_without_a_name() {
+    let bubble = Mounted::<MessageBubble>::new(props("alice", "hello", true));
+    assert!(bubble.find(":scope > .flex-row-reverse").is_some());
+    assert_eq!(bubble.count(".bg-blue-500"), 1);
+    assert_eq!(bubble.count(".bg-gray-100"), 0);
+    assert!(bubble.text().contains("hello"));
+    assert!(!bubble.text().contains("alice"));
+    bubble.unmount();
+}
+
+#[wasm_bindgen_test]
+fn others_bubble_sits_left_with_their_name() {
+    let bubble = Mounted::<MessageBubble>::new(props("bob", "hello", false));
+    assert!(bubble.find(":scope > .flex-row-reverse").is_none());
+    assert_eq!(bubble.count(".bg-gray-100"), 1);
+    assert_eq!(bubble.count(".bg-blue-500"), 0);
+    assert!(bubble.text().starts_with("bob"));
+    assert!(bubble.text().contains("Just now"));
+    bubble.unmount();
+}
+
+#[wasm_bindgen_test]
+fn gif_link_renders_inline() {
+    let bubble = Mounted::<MessageBubble>::new(props("bob", GIF, false));
+    // Checked before the download gets a chance to fail in the test browser
+    let image = bubble.find("img[alt=GIF]").expect("GIF rendered as an image");
+    assert_eq!(image.get_attribute("src").as_deref(), Some(GIF));
+    assert_eq!(image.get_attribute("loading").as_deref(), Some("lazy"));
+    // Shown instead of the link, not next to it
+    assert!(!bubble.text().contains(GIF));
+    bubble.unmount();
+}
+
+#[wasm_bindgen_test]
+fn gif_waits_for_a_click_without_autoplay() {
+    let mut props = props("bob", GIF, false);
+    props.autoplay_gifs = false;
+    let bubble = Mounted::<MessageBubble>::new(props);
+    assert!(bubble.find("button[title='Play GIF']").is_some());
+    // Not even fetched until it's asked for
+    assert_eq!(bubble.count(&format!("img[src='{}']", GIF)), 0);
+    bubble.unmount();
+}
+
+#[wasm_bindgen_test]
+fn deleted_message_hides_its_text() {
+    let mut props = props("bob", "oops", false);
+    props.message.deleted = true;
+    let bubble = Mounted::<MessageBubble>::new(props);
+    assert!(bubble.text().contains("This message was deleted"));
+    assert!(!bubble.text().contains("oops"));
+    bubble.unmount();
+}
+
+#[wasm_bindgen_test]
+fn compact_layout_is_one_line_without_bubble_or_avatar() {
+    let mut props = props("bob", "hello", false);
+    props.avatar = Some("https://example.org/bob.png".into());
+    props.message.timestamp = Some(0);
+    props.layout = Layout::Compact;
+    let row = Mounted::<MessageBubble>::new(props);
+    assert!(row.text().contains("<bob>"));
+    assert!(row.text().contains("hello"));
+    assert_eq!(row.count("img"), 0);
+    assert_eq!(row.count(".rounded-lg"), 0);
+    row.unmount();
+}
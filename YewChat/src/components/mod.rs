@@ -1,2 +1,47 @@
+pub mod action_sheet;
+pub mod announcement;
+pub mod avatar;
+pub mod captcha;
 pub mod chat;
-pub mod login;
\ No newline at end of file
+pub mod command_palette;
+pub mod confirm;
+pub mod connection_error;
+pub mod edit_history;
+pub mod emoji_picker;
+pub mod format_toolbar;
+pub mod highlight;
+pub mod invite;
+pub mod latency;
+pub mod lightbox;
+pub mod link_warning;
+pub mod links;
+pub mod live_region;
+pub mod location;
+pub mod login;
+pub mod markdown;
+pub mod math;
+pub mod media;
+pub mod media_gallery;
+pub mod members;
+pub mod mentions;
+pub mod message;
+pub mod motion;
+pub mod name_color;
+pub mod onboarding;
+pub mod permalink;
+pub mod presence;
+pub mod profile;
+pub mod renderers;
+pub mod room_password;
+pub mod server_info;
+pub mod sessions;
+pub mod settings;
+pub mod shortcuts;
+pub mod stats;
+#[cfg(test)]
+pub mod testing;
+pub mod transcript;
+pub mod update_toast;
+pub mod user_list;
+pub mod video_call;
+pub mod voice_strip;
\ No newline at end of file
@@ -0,0 +1,28 @@
+use std::cell::Cell;
+
+use crate::{components::settings::Motion, services::media_query};
+
+thread_local! {
+    static REDUCED: Cell<bool> = Cell::new(false);
+}
+
+/// Applies the user's motion setting; `Motion::Auto` follows the system's
+/// reduced-motion preference as it is right now.
+pub fn configure(motion: Motion) {
+    let reduced = match motion {
+        Motion::Auto => media_query::matches("(prefers-reduced-motion: reduce)"),
+        Motion::Full => false,
+        Motion::Reduced => true,
+    };
+    REDUCED.with(|r| r.set(reduced));
+}
+
+/// `class` if animations are allowed, otherwise nothing. Every animation or
+/// transition class goes through here so the motion setting covers them all.
+pub fn animate(class: &'static str) -> &'static str {
+    if REDUCED.with(Cell::get) {
+        ""
+    } else {
+        class
+    }
+}
@@ -0,0 +1,49 @@
+/// Text colors for usernames, all at least 4.5:1 against white and gray-100
+/// so they stay readable in both the sidebar and incoming bubbles.
+const NAME_COLORS: [&str; 10] = [
+    "text-red-700",
+    "text-orange-700",
+    "text-amber-700",
+    "text-lime-700",
+    "text-emerald-700",
+    "text-teal-700",
+    "text-sky-700",
+    "text-indigo-700",
+    "text-purple-700",
+    "text-pink-700",
+];
+
+/// Background counterparts of `NAME_COLORS`, dark enough for white text.
+const NAME_BG_COLORS: [&str; 10] = [
+    "bg-red-600",
+    "bg-orange-600",
+    "bg-amber-600",
+    "bg-lime-600",
+    "bg-emerald-600",
+    "bg-teal-600",
+    "bg-sky-600",
+    "bg-indigo-600",
+    "bg-purple-600",
+    "bg-pink-600",
+];
+
+/// Picks a stable color for `name`: the same user gets the same color on
+/// every client and across sessions.
+pub fn name_color(name: &str) -> &'static str {
+    NAME_COLORS[palette_index(name)]
+}
+
+/// Background color matching `name_color`, used for generated avatars.
+pub fn name_bg_color(name: &str) -> &'static str {
+    NAME_BG_COLORS[palette_index(name)]
+}
+
+fn palette_index(name: &str) -> usize {
+    (fnv1a(name) % NAME_COLORS.len() as u32) as usize
+}
+
+// FNV-1a is tiny and well distributed, and unlike `DefaultHasher` its output
+// is fixed, which is what keeps colors consistent between clients.
+fn fnv1a(s: &str) -> u32 {
+    s.bytes().fold(0x811c_9dc5, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
@@ -0,0 +1,94 @@
+use yew::prelude::*;
+
+use crate::components::motion::animate;
+use crate::services::storage;
+
+// Set once the first-run tips have been put away
+const TIPS_KEY: &str = "tips_dismissed";
+
+/// Whether the tips card should still be shown on this device.
+pub fn tips_pending() -> bool {
+    !storage::load::<bool>(TIPS_KEY).unwrap_or(false)
+}
+
+fn dismiss_tips() {
+    storage::save(TIPS_KEY, &true);
+}
+
+/// Grey bars in the shape of a few messages, while the room is loading.
+#[function_component(MessageSkeleton)]
+pub fn message_skeleton() -> Html {
+    // Widths vary so it reads as a conversation rather than a grid
+    let rows: [(bool, &str); 5] = [(false, "w-48"), (false, "w-64"), (true, "w-40"), (false, "w-56"), (true, "w-72")];
+    html! {
+        <div class="space-y-4" aria-busy="true" aria-label="Loading messages">
+            {
+                rows.iter().map(|(own, width)| html! {
+                    <div class={classes!("flex", "items-end", "gap-3", own.then_some("flex-row-reverse"), animate("animate-pulse"))}>
+                        if !own {
+                            <div class="w-8 h-8 rounded-full bg-gray-200"></div>
+                        }
+                        <div class={classes!("h-12", "rounded-lg", "bg-gray-200", *width)}></div>
+                    </div>
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
+
+/// Stands in for the member list until the server has sent it.
+#[function_component(UserListSkeleton)]
+pub fn user_list_skeleton() -> Html {
+    html! {
+        <div class="px-3 space-y-3" aria-busy="true" aria-label="Loading members">
+            {
+                ["w-24", "w-32", "w-20", "w-28"].iter().map(|width| html! {
+                    <div class={classes!("flex", "items-center", "gap-3", animate("animate-pulse"))}>
+                        <div class="w-10 h-10 rounded-full bg-gray-200"></div>
+                        <div class={classes!("h-3", "rounded", "bg-gray-200", *width)}></div>
+                    </div>
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
+
+/// What an empty room shows instead of nothing.
+#[function_component(EmptyRoom)]
+pub fn empty_room() -> Html {
+    html! {
+        <div class="flex flex-col items-center justify-center h-full py-12 text-gray-500">
+            <div class="text-4xl" aria-hidden="true">{"💬"}</div>
+            <div class="mt-3 font-medium text-gray-700">{"No messages yet — say hi 👋"}</div>
+            <div class="mt-1 text-sm">{"Everything sent here shows up for everyone in the room."}</div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct TipsCardProps {
+    pub on_dismiss: Callback<()>,
+}
+
+/// A few pointers for someone here for the first time, until they put it away.
+#[function_component(TipsCard)]
+pub fn tips_card(props: &TipsCardProps) -> Html {
+    let dismiss = props.on_dismiss.reform(|_: MouseEvent| dismiss_tips());
+    html! {
+        <div class="p-4 text-sm bg-blue-50 border border-blue-200 rounded-lg text-gray-700">
+            <div class="flex items-center justify-between">
+                <div class="font-semibold text-gray-800">{"Welcome! A few things to try"}</div>
+                <button onclick={dismiss} class="px-2 py-1 text-xs font-medium rounded-md text-blue-700 hover:bg-blue-100">
+                    {"Got it"}
+                </button>
+            </div>
+            <ul class="mt-2 space-y-1 list-disc pl-5">
+                <li>{"Swipe or long-press a message to reply and react"}</li>
+                <li>{"Format with the toolbar above the composer, or plain markdown"}</li>
+                <li>{"Attach photos and files with 📎"}</li>
+                <li>{"Type /nick and a name to change yours"}</li>
+                <li>{"Press Ctrl+K for every command, and ? for keyboard shortcuts"}</li>
+            </ul>
+        </div>
+    }
+}
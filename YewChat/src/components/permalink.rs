@@ -0,0 +1,51 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::{Route, User};
+
+// Messages' anchors in a permalink, `#msg-<id>`
+const ANCHOR_PREFIX: &str = "#msg-";
+
+/// A message someone linked to: the room, and the ID the server gave it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Permalink {
+    pub room: String,
+    pub id: String,
+}
+
+/// Where a message can be found again, e.g. `https://chat.example/chat/main#msg-42`.
+pub fn permalink_url(room: &str, id: &str) -> String {
+    let origin = web_sys::window()
+        .and_then(|w| w.location().origin().ok())
+        .unwrap_or_default();
+    format!("{}/chat/{}{}{}", origin, js_sys::encode_uri_component(room), ANCHOR_PREFIX, js_sys::encode_uri_component(id))
+}
+
+/// The message ID in a location hash, if it's a message anchor.
+pub fn parse_anchor(hash: &str) -> Option<String> {
+    let id = hash.strip_prefix(ANCHOR_PREFIX)?;
+    let id = js_sys::decode_uri_component(id).ok()?.as_string()?;
+    (!id.is_empty()).then_some(id)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct OpenPermalinkProps {
+    pub room: String,
+}
+
+/// Target of a `/chat/<room>#msg-<id>` link. Like an invite, the message is
+/// parked until the visitor is in the chat, which then looks it up.
+#[function_component(OpenPermalink)]
+pub fn open_permalink(props: &OpenPermalinkProps) -> Html {
+    let user = use_context::<User>().expect("No context found.");
+    // The router leaves the hash out of the route, so it's read off the page
+    let hash = web_sys::window().and_then(|w| w.location().hash().ok()).unwrap_or_default();
+    if let Some(id) = parse_anchor(&hash) {
+        *user.pending_permalink.borrow_mut() = Some(Permalink { room: props.room.clone(), id });
+    }
+
+    html! { <Redirect<Route> to={Route::Chat} /> }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,22 @@
+use wasm_bindgen_test::*;
+
+use super::{parse_anchor, permalink_url};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn reads_message_anchors() {
+    assert_eq!(parse_anchor("#msg-42"), Some("42".to_string()));
+    assert_eq!(parse_anchor("#msg-a%20b"), Some("a b".to_string()));
+    assert_eq!(parse_anchor("#msg-"), None);
+    assert_eq!(parse_anchor("#top"), None);
+    assert_eq!(parse_anchor(""), None);
+}
+
+#[wasm_bindgen_test]
+fn links_round_trip() {
+    let url = permalink_url("main", "42");
+    assert!(url.ends_with("/chat/main#msg-42"));
+    let hash = &url[url.find('#').unwrap()..];
+    assert_eq!(parse_anchor(hash), Some("42".to_string()));
+}
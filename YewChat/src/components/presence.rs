@@ -0,0 +1,58 @@
+use serde::Deserialize;
+use yew::prelude::*;
+
+/// Whether someone is around, as reported by the server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Presence {
+    #[default]
+    Online,
+    Away,
+    Dnd,
+    Offline,
+}
+
+impl Presence {
+    pub fn label(self) -> &'static str {
+        match self {
+            Presence::Online => "Active now",
+            Presence::Away => "Away",
+            Presence::Dnd => "Do not disturb",
+            Presence::Offline => "Offline",
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PresenceDotProps {
+    pub presence: Presence,
+    /// Tell states apart by shape as well as colour
+    #[prop_or_default]
+    pub shapes: bool,
+}
+
+/// Status badge for the corner of an avatar.
+#[function_component(PresenceDot)]
+pub fn presence_dot(props: &PresenceDotProps) -> Html {
+    let base = classes!(
+        "absolute", "bottom-0", "right-0", "w-3", "h-3", "rounded-full", "flex", "items-center", "justify-center"
+    );
+    let class = match (props.presence, props.shapes) {
+        // Without shapes, offline is told apart by the greyed-out avatar alone
+        (Presence::Offline, false) => return html! {},
+        (Presence::Online, _) => classes!(base, "bg-green-500", "border-2", "border-white"),
+        (Presence::Away, false) => classes!(base, "bg-yellow-500", "border-2", "border-white"),
+        (Presence::Away, true) => classes!(base, "bg-white", "border-[3px]", "border-yellow-500"),
+        (Presence::Dnd, _) => classes!(base, "bg-red-500", "border-2", "border-white"),
+        (Presence::Offline, true) => classes!(base, "bg-white", "border", "border-gray-500"),
+    };
+    let label = props.presence.label();
+
+    html! {
+        <div {class} title={label} role="img" aria-label={label}>
+            if props.shapes && props.presence == Presence::Dnd {
+                <div class="w-1.5 h-0.5 bg-white"></div>
+            }
+        </div>
+    }
+}
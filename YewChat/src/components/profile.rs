@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::components::{
+    avatar::{Avatar, AvatarProvider},
+    chat::{Role, UserProfile},
+    name_color::name_color,
+};
+use crate::services::{digest::sha256_hex, hotkeys, timezone};
+
+// Same limits the server trims to
+const MAX_BIO_LEN: usize = 300;
+const MAX_PRONOUNS_LEN: usize = 30;
+const MAX_TIMEZONE_LEN: usize = 64;
+const MAX_LINKS: usize = 5;
+
+/// What a member shares about themselves, edited by them and shown to everyone.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub bio: String,
+    pub pronouns: String,
+    /// IANA name such as "Asia/Jakarta"; empty if not shared
+    pub timezone: String,
+    pub links: Vec<String>,
+    pub avatar: AvatarProvider,
+}
+
+impl Profile {
+    pub const STORAGE_KEY: &'static str = "profile";
+
+    pub fn is_empty(&self) -> bool {
+        *self == Profile::default()
+    }
+}
+
+fn is_link(text: &str) -> bool {
+    text.starts_with("https://") || text.starts_with("http://")
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ProfileCardProps {
+    pub user: UserProfile,
+    /// Our own card can be edited
+    pub is_self: bool,
+    pub on_save: Callback<Profile>,
+    pub on_close: Callback<()>,
+    /// Offered on other members' cards while they're online
+    #[prop_or_default]
+    pub on_call: Option<Callback<()>>,
+}
+
+/// A member's profile, with an editor when it's our own.
+#[function_component(ProfileCard)]
+pub fn profile_card(props: &ProfileCardProps) -> Html {
+    let editing = use_state(|| false);
+    let draft = use_state(|| props.user.profile.clone());
+    // Only ever kept here; the profile gets its hash
+    let email = use_state(String::new);
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let onkeydown = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if hotkeys::CLOSE.matches(&e) {
+                on_close.emit(());
+            }
+        })
+    };
+    let profile = &props.user.profile;
+
+    let body = if *editing {
+        let edit = |apply: fn(&mut Profile, String)| {
+            let draft = draft.clone();
+            Callback::from(move |value: String| {
+                let mut next = (*draft).clone();
+                apply(&mut next, value);
+                draft.set(next);
+            })
+        };
+        let input = |cb: Callback<String>| {
+            cb.reform(|e: InputEvent| e.target_unchecked_into::<HtmlInputElement>().value())
+        };
+        let textarea = |cb: Callback<String>| {
+            cb.reform(|e: InputEvent| e.target_unchecked_into::<HtmlTextAreaElement>().value())
+        };
+        let use_local_zone = {
+            let draft = draft.clone();
+            Callback::from(move |_: MouseEvent| {
+                if let Some(zone) = timezone::local() {
+                    draft.set(Profile { timezone: zone, ..(*draft).clone() });
+                }
+            })
+        };
+        let choose_avatar = |gravatar: bool| {
+            let (draft, saved) = (draft.clone(), profile.avatar.clone());
+            Callback::from(move |_: Event| {
+                let avatar = match (gravatar, &saved) {
+                    (false, _) => AvatarProvider::Identicon,
+                    (true, AvatarProvider::Gravatar { .. }) => saved.clone(),
+                    (true, AvatarProvider::Identicon) => AvatarProvider::Gravatar { hash: String::new() },
+                };
+                draft.set(Profile { avatar, ..(*draft).clone() });
+            })
+        };
+        let on_email = {
+            let email = email.clone();
+            Callback::from(move |e: InputEvent| email.set(e.target_unchecked_into::<HtmlInputElement>().value()))
+        };
+        let save = {
+            let (draft, email, editing, on_save) = (draft.clone(), email.clone(), editing.clone(), props.on_save.clone());
+            Callback::from(move |_: MouseEvent| {
+                let mut profile = (*draft).clone();
+                profile.links.retain(|l| is_link(l));
+                profile.links.truncate(MAX_LINKS);
+                let address = email.trim().to_lowercase();
+                let (editing, on_save) = (editing.clone(), on_save.clone());
+                if !matches!(profile.avatar, AvatarProvider::Gravatar { .. }) || address.is_empty() {
+                    on_save.emit(profile);
+                    editing.set(false);
+                    return;
+                }
+                spawn_local(async move {
+                    match sha256_hex(&address).await {
+                        Some(hash) => {
+                            profile.avatar = AvatarProvider::Gravatar { hash };
+                            on_save.emit(profile);
+                            editing.set(false);
+                        }
+                        None => log::error!("Couldn't hash the email address"),
+                    }
+                });
+            })
+        };
+        let cancel = {
+            let (draft, editing, saved) = (draft.clone(), editing.clone(), profile.clone());
+            Callback::from(move |_: MouseEvent| {
+                draft.set(saved.clone());
+                editing.set(false);
+            })
+        };
+        let field_class = "w-full mt-1 px-3 py-2 text-sm rounded-md border border-gray-300 text-gray-800";
+        let zone_unknown = !draft.timezone.is_empty() && timezone::time_in(js_sys::Date::now(), &draft.timezone).is_none();
+        let gravatar = matches!(draft.avatar, AvatarProvider::Gravatar { .. });
+        // Picking Gravatar needs an address, unless one was given before
+        let needs_email = draft.avatar == (AvatarProvider::Gravatar { hash: String::new() }) && email.trim().is_empty();
+
+        html! {
+            <div class="px-6 py-4 space-y-3 text-sm text-gray-600">
+                <fieldset>
+                    <legend>{"Picture"}</legend>
+                    <div class="flex gap-4 mt-1">
+                        <label class="flex items-center cursor-pointer">
+                            <input type="radio" name="avatar" class="mr-1" checked={!gravatar} onchange={choose_avatar(false)} />
+                            {"Generated"}
+                        </label>
+                        <label class="flex items-center cursor-pointer">
+                            <input type="radio" name="avatar" class="mr-1" checked={gravatar} onchange={choose_avatar(true)} />
+                            {"Gravatar"}
+                        </label>
+                    </div>
+                    if gravatar {
+                        <input
+                            type="email"
+                            autocomplete="email"
+                            class={field_class}
+                            placeholder={if needs_email { "Email address" } else { "Email address (leave empty to keep)" }}
+                            value={(*email).clone()}
+                            oninput={on_email}
+                        />
+                        <div class="mt-1 text-xs text-gray-500">{"Only a hash of your address is shared with the room and Gravatar"}</div>
+                    }
+                </fieldset>
+                <label class="block">
+                    {"Pronouns"}
+                    <input
+                        class={field_class}
+                        maxlength={MAX_PRONOUNS_LEN.to_string()}
+                        placeholder="e.g. she/her"
+                        value={draft.pronouns.clone()}
+                        oninput={input(edit(|p, v| p.pronouns = v))}
+                    />
+                </label>
+                <label class="block">
+                    {"Timezone"}
+                    <div class="flex gap-2">
+                        <input
+                            class={field_class}
+                            maxlength={MAX_TIMEZONE_LEN.to_string()}
+                            placeholder="e.g. Asia/Jakarta"
+                            value={draft.timezone.clone()}
+                            oninput={input(edit(|p, v| p.timezone = v))}
+                        />
+                        <button type="button" onclick={use_local_zone} class="mt-1 px-2 text-xs rounded-md text-violet-600 hover:bg-violet-50 whitespace-nowrap">
+                            {"Use mine"}
+                        </button>
+                    </div>
+                    if zone_unknown {
+                        <div class="mt-1 text-xs text-red-600">{"Your browser doesn't recognise this timezone"}</div>
+                    }
+                </label>
+                <label class="block">
+                    {"Bio"}
+                    <textarea
+                        class={field_class}
+                        rows="3"
+                        maxlength={MAX_BIO_LEN.to_string()}
+                        value={draft.bio.clone()}
+                        oninput={textarea(edit(|p, v| p.bio = v))}
+                    />
+                </label>
+                <label class="block">
+                    {format!("Links (one per line, up to {})", MAX_LINKS)}
+                    <textarea
+                        class={field_class}
+                        rows="3"
+                        placeholder="https://"
+                        value={draft.links.join("\n")}
+                        oninput={textarea(edit(|p, v| {
+                            p.links = v.lines().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect()
+                        }))}
+                    />
+                </label>
+                <div class="flex justify-end gap-2 pt-2">
+                    <button onclick={cancel} class="px-4 py-2 rounded-md text-gray-600 hover:bg-gray-100">{"Cancel"}</button>
+                    <button onclick={save} disabled={zone_unknown || needs_email} class="px-4 py-2 font-medium rounded-md text-white bg-violet-600 hover:bg-violet-700 disabled:opacity-50">
+                        {"Save"}
+                    </button>
+                </div>
+            </div>
+        }
+    } else {
+        let local_time = (!profile.timezone.is_empty())
+            .then(|| timezone::time_in(js_sys::Date::now(), &profile.timezone))
+            .flatten();
+        html! {
+            <div class="px-6 py-4 space-y-3 text-sm text-gray-700">
+                if let Some(time) = local_time {
+                    <div class="text-gray-500">{format!("🕒 {} local time ({})", time, profile.timezone)}</div>
+                }
+                if !profile.bio.is_empty() {
+                    <p class="whitespace-pre-wrap break-words">{profile.bio.clone()}</p>
+                }
+                if !profile.links.is_empty() {
+                    <ul class="space-y-1">
+                        {
+                            profile.links.iter().filter(|l| is_link(l)).map(|link| html! {
+                                <li class="truncate">
+                                    <a href={link.clone()} target="_blank" rel="noopener noreferrer nofollow" class="text-blue-600 hover:underline">
+                                        {link.split_once("://").map_or(link.as_str(), |(_, rest)| rest).trim_end_matches('/').to_string()}
+                                    </a>
+                                </li>
+                            }).collect::<Html>()
+                        }
+                    </ul>
+                }
+                if profile.is_empty() {
+                    <div class="text-gray-400 italic">
+                        {if props.is_self { "You haven't filled in your profile yet" } else { "Nothing shared yet" }}
+                    </div>
+                }
+                if let Some(on_call) = &props.on_call {
+                    <div class="flex justify-end pt-2">
+                        <button
+                            onclick={on_call.reform(|_: MouseEvent| ())}
+                            class="px-4 py-2 font-medium rounded-md text-emerald-700 hover:bg-emerald-50"
+                        >
+                            {"📹 Video call"}
+                        </button>
+                    </div>
+                }
+                if props.is_self {
+                    <div class="flex justify-end pt-2">
+                        <button
+                            onclick={let editing = editing.clone(); move |_| editing.set(true)}
+                            class="px-4 py-2 font-medium rounded-md text-violet-600 hover:bg-violet-50"
+                        >
+                            {"Edit profile"}
+                        </button>
+                    </div>
+                }
+            </div>
+        }
+    };
+
+    let user = &props.user;
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center" role="dialog" aria-modal="true" aria-labelledby="profile-name" {onkeydown}>
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-96 max-h-[80vh] overflow-y-auto bg-white rounded-lg shadow-lg">
+                <div class="flex items-center px-6 pt-5">
+                    <Avatar name={user.name.clone()} src={user.avatar.clone()} class={classes!("w-16", "h-16")} />
+                    <div class="ml-4 min-w-0 grow">
+                        <div id="profile-name" class={classes!("text-lg", "font-semibold", "truncate", name_color(&user.name))}>{user.name.clone()}</div>
+                        if !profile.pronouns.is_empty() {
+                            <div class="text-sm text-gray-500">{profile.pronouns.clone()}</div>
+                        }
+                        <div class="text-xs text-gray-500">
+                            {user.presence.label()}
+                            if user.role != Role::Member {
+                                {format!(" · {}", user.role.label())}
+                            }
+                        </div>
+                    </div>
+                    <button onclick={close} class="self-start p-1 text-gray-400 hover:text-gray-600 focus:outline-none" aria-label="Close">{"✕"}</button>
+                </div>
+                {body}
+            </div>
+        </div>
+    }
+}
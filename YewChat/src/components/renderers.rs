@@ -0,0 +1,90 @@
+use yew::prelude::*;
+
+use crate::components::{
+    chat::MessageData,
+    location::LocationCard,
+    markdown,
+    media::{is_gif, is_image_url, MediaImage},
+};
+
+/// What a renderer gets to work with besides the message itself.
+pub struct Context<'a> {
+    pub message: &'a MessageData,
+    pub autoplay_gifs: bool,
+    /// Query parameters to strip from links, see [`tracking::clean`](crate::services::tracking::clean)
+    pub tracking_rules: &'a [String],
+    /// Blur every image, not only those marked sensitive
+    pub blur_media: bool,
+    /// Map provider for shared locations; empty means the default
+    pub map_tiles: &'a str,
+}
+
+/// Turns message content it recognises into something richer than plain text.
+pub struct Renderer {
+    /// Whether this renderer handles the message, e.g. by URL pattern or attachment type
+    pub claims: fn(&MessageData) -> bool,
+    pub render: fn(&Context) -> Html,
+}
+
+// Asked in order; the first one to claim a message renders it. New embeds go here.
+const RENDERERS: &[Renderer] = &[
+    Renderer {
+        claims: claims_location,
+        render: render_location,
+    },
+    Renderer {
+        claims: claims_image,
+        render: render_image,
+    },
+    Renderer {
+        claims: claims_markdown,
+        render: render_markdown,
+    },
+];
+
+/// A message body through the first renderer that claims it, or as text if none does.
+pub fn render(ctx: &Context) -> Html {
+    match RENDERERS.iter().find(|r| (r.claims)(ctx.message)) {
+        Some(renderer) => (renderer.render)(ctx),
+        None => html! { {ctx.message.message.clone()} },
+    }
+}
+
+fn claims_image(message: &MessageData) -> bool {
+    is_image_url(&message.message)
+}
+
+fn render_image(ctx: &Context) -> Html {
+    let m = ctx.message;
+    html! {
+        <MediaImage
+            src={m.message.clone()}
+            alt={if is_gif(&m.message) { "GIF" } else { "Image" }}
+            media={m.media.clone()}
+            autoplay={ctx.autoplay_gifs}
+            hidden={ctx.blur_media || matches!(&m.media, Some(media) if media.sensitive)}
+            reveal_id={m.id.clone().or_else(|| m.client_id.clone())}
+        />
+    }
+}
+
+// The text is only a map link for clients that don't know about locations
+fn claims_location(message: &MessageData) -> bool {
+    message.location.is_some()
+}
+
+fn render_location(ctx: &Context) -> Html {
+    match ctx.message.location {
+        Some(location) => html! { <LocationCard {location} map_tiles={ctx.map_tiles.to_string()} /> },
+        None => Html::default(),
+    }
+}
+
+// Plain sentences skip the markdown pass and render as text, like before
+fn claims_markdown(message: &MessageData) -> bool {
+    markdown::has_markup(&message.message)
+}
+
+fn render_markdown(ctx: &Context) -> Html {
+    markdown::render(&ctx.message.message, ctx.tracking_rules)
+}
@@ -0,0 +1,122 @@
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::{digest::sha256_hex, hotkeys};
+
+/// SHA-256 of `room:password` in hex, which is what the server compares against;
+/// the password itself is never sent.
+pub async fn room_key(room: &str, password: &str) -> Option<String> {
+    sha256_hex(&format!("{}:{}", room, password)).await
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RoomPasswordProps {
+    pub room: String,
+    /// Why the last attempt was turned down
+    #[prop_or_default]
+    pub error: Option<String>,
+    /// Waiting on the server's answer
+    #[prop_or_default]
+    pub pending: bool,
+    /// Called with the hashed password
+    pub on_submit: Callback<String>,
+    pub on_cancel: Callback<()>,
+}
+
+/// Asks for the password of a locked room before joining it.
+#[function_component(RoomPasswordPrompt)]
+pub fn room_password_prompt(props: &RoomPasswordProps) -> Html {
+    let password = use_state(String::new);
+    let hashing = use_state(|| false);
+    let input = use_node_ref();
+    {
+        // Focus again after a wrong password so the user can just retype it
+        let input = input.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(input) = input.cast::<HtmlInputElement>() {
+                    input.select();
+                    let _ = input.focus();
+                }
+                || ()
+            },
+            props.error.clone(),
+        );
+    }
+
+    let oninput = {
+        let password = password.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            password.set(input.value());
+        })
+    };
+    let onsubmit = {
+        let password = password.clone();
+        let hashing = hashing.clone();
+        let room = props.room.clone();
+        let on_submit = props.on_submit.clone();
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            if password.is_empty() || *hashing {
+                return;
+            }
+            hashing.set(true);
+            let password = (*password).clone();
+            let (hashing, room, on_submit) = (hashing.clone(), room.clone(), on_submit.clone());
+            spawn_local(async move {
+                match room_key(&room, &password).await {
+                    Some(key) => on_submit.emit(key),
+                    // Web Crypto is only available on https:// and localhost
+                    None => log::error!("Couldn't hash the room password"),
+                }
+                hashing.set(false);
+            });
+        })
+    };
+    let onkeydown = {
+        let on_cancel = props.on_cancel.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if hotkeys::CLOSE.matches(&e) {
+                on_cancel.emit(());
+            }
+        })
+    };
+    let cancel = props.on_cancel.reform(|_: MouseEvent| ());
+    let busy = props.pending || *hashing;
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center" role="dialog" aria-modal="true" aria-labelledby="room-password-title" {onkeydown}>
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={cancel.clone()}></div>
+
+            <form class="relative w-80 bg-white rounded-lg shadow-lg" {onsubmit}>
+                <div class="px-6 pt-5">
+                    <div id="room-password-title" class="text-lg font-semibold text-gray-800">{format!("🔒 #{}", props.room)}</div>
+                    <div class="mt-2 text-sm text-gray-600">{"This room is password protected."}</div>
+                    <input
+                        ref={input}
+                        type="password"
+                        autocomplete="current-password"
+                        aria-label="Room password"
+                        aria-invalid={props.error.is_some().to_string()}
+                        placeholder="Password"
+                        {oninput}
+                        class={classes!("mt-4", "w-full", "px-3", "py-2", "rounded-md", "border", "text-gray-800", if props.error.is_some() { "border-red-500" } else { "border-gray-300" })}
+                    />
+                    if let Some(error) = props.error.clone() {
+                        <div class="mt-2 text-sm text-red-600" role="alert">{error}</div>
+                    }
+                </div>
+                <div class="flex justify-end gap-2 px-6 py-4">
+                    <button type="button" onclick={cancel} class="px-4 py-2 text-sm rounded-md text-gray-600 hover:bg-gray-100">
+                        {"Cancel"}
+                    </button>
+                    <button type="submit" disabled={busy || password.is_empty()} class="px-4 py-2 text-sm font-medium rounded-md text-white bg-violet-600 hover:bg-violet-700 disabled:opacity-50">
+                        { if busy { "Joining…" } else { "Join" } }
+                    </button>
+                </div>
+            </form>
+        </div>
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Deserialize;
+use yew::prelude::*;
+
+use crate::services::{upload::format_size, version};
+
+/// What the server reports about itself, in answer to `serverinfo`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ServerInfo {
+    pub software: String,
+    pub version: String,
+    /// Seconds since it started
+    pub uptime: u64,
+    /// Registered right now, across every room
+    pub users: usize,
+    pub rooms: usize,
+    #[serde(default)]
+    pub limits: ServerLimits,
+}
+
+/// Only what the server chose to say; anything it leaves out isn't shown.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ServerLimits {
+    pub max_nick_length: Option<u32>,
+    /// Messages kept per room
+    pub history_size: Option<u32>,
+    pub upload_max_bytes: Option<f64>,
+}
+
+/// The two largest units, e.g. `3d 4h` or `12m 5s`.
+fn format_uptime(seconds: u64) -> String {
+    let units = [(seconds / 86400, "d"), (seconds / 3600 % 24, "h"), (seconds / 60 % 60, "m"), (seconds % 60, "s")];
+    let first = units.iter().position(|(n, _)| *n > 0).unwrap_or(units.len() - 1);
+    units[first..]
+        .iter()
+        .take(2)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ServerInfoDialogProps {
+    /// `None` until the server has answered
+    pub info: Option<ServerInfo>,
+    /// The address we're connected to
+    pub endpoint: String,
+    pub on_close: Callback<()>,
+}
+
+/// What the chat server is running and what it allows, for whoever hosts it.
+#[function_component(ServerInfoDialog)]
+pub fn server_info_dialog(props: &ServerInfoDialogProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let row = |label: &str, value: String| {
+        html! {
+            <div class="flex justify-between py-1.5">
+                <dt class="text-gray-500">{label}</dt>
+                <dd class="text-gray-800 font-medium">{value}</dd>
+            </div>
+        }
+    };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-96 bg-white rounded-lg shadow-lg">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Server info"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="px-6 py-4 text-sm">
+                    <div class="text-xs text-gray-500 break-all">{format!("Connected to {}", props.endpoint)}</div>
+                    <div class="text-xs text-gray-500">{format!("This client is YewChat {}", version::describe())}</div>
+                    if let Some(info) = &props.info {
+                        <dl class="mt-3 divide-y">
+                            {row("Software", format!("{} {}", info.software, info.version))}
+                            {row("Uptime", format_uptime(info.uptime))}
+                            {row("Users online", info.users.to_string())}
+                            {row("Rooms", info.rooms.to_string())}
+                            if let Some(length) = info.limits.max_nick_length {
+                                {row("Longest username", format!("{} characters", length))}
+                            }
+                            if let Some(size) = info.limits.history_size {
+                                {row("History kept", format!("{} messages per room", size))}
+                            }
+                            if let Some(bytes) = info.limits.upload_max_bytes {
+                                {row("Largest upload", format_size(bytes))}
+                            }
+                        </dl>
+                    } else {
+                        <div class="mt-3 p-2 text-gray-400 bg-gray-100 rounded-md">{"Waiting for the server… Servers that don't report their details never answer."}</div>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use yew::prelude::*;
+
+/// One connection signed in to our account, as listed by the server.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct DeviceSession {
+    pub id: String,
+    /// Browser and OS, e.g. "Firefox on Linux"
+    pub device: String,
+    /// When it connected, in ms since the epoch
+    pub since: i64,
+    /// Signed in with the same token as us, i.e. this browser
+    #[serde(default)]
+    pub current: bool,
+}
+
+fn format_since(ts: i64) -> String {
+    let date = js_sys::Date::new(&(ts as f64).into());
+    format!("{:02}/{:02} {:02}:{:02}", date.get_date(), date.get_month() + 1, date.get_hours(), date.get_minutes())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SessionsDialogProps {
+    /// `None` until the server has answered
+    pub sessions: Option<Vec<DeviceSession>>,
+    pub on_sign_out: Callback<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Where the account is signed in right now, with a way to end the other sessions.
+#[function_component(SessionsDialog)]
+pub fn sessions_dialog(props: &SessionsDialogProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let row = |session: &DeviceSession| {
+        let action = if session.current {
+            html! { <span class="text-xs text-gray-400">{"This browser"}</span> }
+        } else {
+            let id = session.id.clone();
+            html! {
+                <button
+                    onclick={props.on_sign_out.reform(move |_: MouseEvent| id.clone())}
+                    class="px-3 py-1 text-sm text-red-600 rounded-md hover:bg-red-50 focus:outline-none"
+                >
+                    {"Sign out"}
+                </button>
+            }
+        };
+        html! {
+            <li key={session.id.clone()} class="flex items-center justify-between py-2">
+                <div>
+                    <div class="text-sm text-gray-800">{&session.device}</div>
+                    <div class="text-xs text-gray-500">{format!("Connected since {}", format_since(session.since))}</div>
+                </div>
+                {action}
+            </li>
+        }
+    };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-96 bg-white rounded-lg shadow-lg">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Your sessions"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="px-6 py-4">
+                    <div class="text-sm text-gray-500">{"Devices connected to your account right now. Signing one out also ends its sign-in, so it has to enter the password again."}</div>
+                    if let Some(sessions) = &props.sessions {
+                        <ul class="mt-3 divide-y">{for sessions.iter().map(row)}</ul>
+                    } else {
+                        <div class="mt-3 p-2 text-sm text-gray-400 bg-gray-100 rounded-md">{"Loading…"}</div>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
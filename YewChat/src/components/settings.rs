@@ -0,0 +1,639 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::{
+    components::location,
+    services::{media_query, tracking},
+};
+
+/// User preferences, persisted locally under [`Settings::STORAGE_KEY`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub autoplay_gifs: bool,
+    /// Words that highlight a message like a mention does
+    pub keywords: Vec<String>,
+    /// Whether keyword matches also play the chime and send a notification
+    pub notify_keywords: bool,
+    /// Seconds a message waits, undoable, before it's actually sent; 0 sends right away
+    pub send_delay_secs: u32,
+    /// At most this many messages may be sent in any `send_limit_secs` window,
+    /// so a bouncing Enter key or a held paste can't flood the room
+    pub send_limit: usize,
+    pub send_limit_secs: u32,
+    pub contrast: Contrast,
+    pub motion: Motion,
+    /// Show presence as distinct shapes so it doesn't rely on colour
+    pub presence_shapes: bool,
+    /// Collapsed floods of messages don't chime, notify or get read out
+    pub quiet_bursts: bool,
+    /// Also show the sender's wall-clock time when their profile has a timezone
+    pub sender_time: bool,
+    /// Pass unsent drafts through the server to our other devices
+    pub sync_drafts: bool,
+    /// Query parameters taken out of links, see [`tracking::clean`]
+    pub tracking_params: Vec<String>,
+    /// Blur every image until it's clicked, not just ones marked sensitive
+    pub blur_media: bool,
+    /// Re-encode photos before uploading them, which drops their EXIF metadata
+    pub strip_metadata: bool,
+    /// Where shared locations' map thumbnails come from, see [`location::is_valid_template`]
+    pub map_tiles: String,
+    pub layout: Layout,
+}
+
+/// How messages are laid out; `Compact` fits the most on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Bubbles,
+    /// One `[12:03] <alice> message` line each, IRC style, without avatars or bubbles
+    Compact,
+}
+
+impl Layout {
+    const ALL: [Layout; 2] = [Layout::Bubbles, Layout::Compact];
+
+    fn label(self) -> &'static str {
+        match self {
+            Layout::Bubbles => "Bubbles",
+            Layout::Compact => "Compact",
+        }
+    }
+
+    fn value(self) -> &'static str {
+        match self {
+            Layout::Bubbles => "bubbles",
+            Layout::Compact => "compact",
+        }
+    }
+}
+
+/// Whether to animate; `Auto` follows the system's reduced-motion preference.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Motion {
+    #[default]
+    Auto,
+    Full,
+    Reduced,
+}
+
+impl Motion {
+    const ALL: [Motion; 3] = [Motion::Auto, Motion::Full, Motion::Reduced];
+
+    fn label(self) -> &'static str {
+        match self {
+            Motion::Auto => "Match system",
+            Motion::Full => "Full",
+            Motion::Reduced => "Reduced",
+        }
+    }
+
+    fn value(self) -> &'static str {
+        match self {
+            Motion::Auto => "auto",
+            Motion::Full => "full",
+            Motion::Reduced => "reduced",
+        }
+    }
+}
+
+/// Colour scheme strength; `Auto` follows the system's contrast preference.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Contrast {
+    #[default]
+    Auto,
+    Standard,
+    High,
+}
+
+impl Contrast {
+    const ALL: [Contrast; 3] = [Contrast::Auto, Contrast::Standard, Contrast::High];
+
+    fn label(self) -> &'static str {
+        match self {
+            Contrast::Auto => "Match system",
+            Contrast::Standard => "Standard",
+            Contrast::High => "High",
+        }
+    }
+
+    fn value(self) -> &'static str {
+        match self {
+            Contrast::Auto => "auto",
+            Contrast::Standard => "standard",
+            Contrast::High => "high",
+        }
+    }
+
+    /// Whether the high-contrast theme should be used right now.
+    pub fn is_high(self) -> bool {
+        match self {
+            Contrast::Auto => media_query::matches("(prefers-contrast: more), (forced-colors: active)"),
+            Contrast::Standard => false,
+            Contrast::High => true,
+        }
+    }
+}
+
+impl Settings {
+    pub const STORAGE_KEY: &'static str = "settings";
+
+    /// The settings as a file to take to another browser, see [`Settings::import`].
+    pub fn export(&self) -> String {
+        let export = Export {
+            format: EXPORT_FORMAT.to_string(),
+            version: EXPORT_VERSION,
+            settings: self.clone(),
+        };
+        serde_json::to_string_pretty(&export).unwrap_or_default()
+    }
+
+    /// Reads a file written by [`Settings::export`], bringing older versions up
+    /// to date. Anything the file leaves out keeps its default.
+    pub fn import(json: &str) -> Result<Settings, String> {
+        let mut file: Value = serde_json::from_str(json).map_err(|_| "That isn't a settings file".to_string())?;
+        let version = match file.get("format") {
+            Some(format) if format == EXPORT_FORMAT => file
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| "The settings file has no version".to_string())?,
+            Some(_) => return Err("That isn't a YewChat settings file".to_string()),
+            // A bare settings object, as kept in localStorage
+            None if file.is_object() => 0,
+            None => return Err("That isn't a settings file".to_string()),
+        };
+        if version > u64::from(EXPORT_VERSION) {
+            return Err("These settings are from a newer YewChat; reload to update it first".to_string());
+        }
+        for migrate in &MIGRATIONS[version as usize..] {
+            file = migrate(file);
+        }
+        serde_json::from_value::<Export>(file)
+            .map(|export| export.settings)
+            .map_err(|e| format!("The settings file is damaged: {}", e))
+    }
+}
+
+// Tells our exports apart from any other JSON file that gets picked
+const EXPORT_FORMAT: &str = "yewchat-settings";
+// Bump when a field is renamed or changes meaning, adding the step to `MIGRATIONS`
+const EXPORT_VERSION: u32 = 1;
+// Step `i` turns a version `i` file into version `i + 1`
+const MIGRATIONS: [fn(Value) -> Value; EXPORT_VERSION as usize] = [wrap_bare_settings];
+
+#[derive(Serialize, Deserialize)]
+struct Export {
+    format: String,
+    version: u32,
+    settings: Settings,
+}
+
+fn wrap_bare_settings(settings: Value) -> Value {
+    json!({ "format": EXPORT_FORMAT, "version": 1, "settings": settings })
+}
+
+// Choices offered for the undo-send window
+const SEND_DELAYS: [u32; 4] = [0, 5, 10, 30];
+// Choices offered for the send limit, as (messages, seconds)
+const SEND_LIMITS: [(usize, u32); 4] = [(3, 5), (5, 5), (10, 10), (20, 10)];
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            autoplay_gifs: true,
+            keywords: Vec::new(),
+            notify_keywords: false,
+            send_delay_secs: 0,
+            send_limit: 5,
+            send_limit_secs: 5,
+            contrast: Contrast::Auto,
+            motion: Motion::Auto,
+            presence_shapes: false,
+            quiet_bursts: true,
+            sender_time: false,
+            sync_drafts: false,
+            tracking_params: tracking::DEFAULT_RULES.iter().map(|rule| rule.to_string()).collect(),
+            blur_media: false,
+            strip_metadata: true,
+            map_tiles: location::DEFAULT_TILES.to_string(),
+            layout: Layout::Bubbles,
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SettingsPanelProps {
+    pub settings: Settings,
+    pub on_change: Callback<Settings>,
+    /// Asks before putting everything back to the defaults
+    pub on_reset: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(SettingsPanel)]
+pub fn settings_panel(props: &SettingsPanelProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    // Each control edits a copy of the current settings and hands the result back
+    let toggle = |apply: fn(&mut Settings, bool)| {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            apply(&mut settings, input.checked());
+            settings
+        })
+    };
+    let on_keywords = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.keywords = input
+                .value()
+                .split(',')
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect();
+            settings
+        })
+    };
+
+    let on_tracking_params = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.tracking_params = input
+                .value()
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+            settings
+        })
+    };
+
+    // A template that wouldn't work is left out rather than saved
+    let on_map_tiles = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            let template = input.value().trim().to_string();
+            settings.map_tiles = if location::is_valid_template(&template) {
+                template
+            } else {
+                input.set_value(&settings.map_tiles);
+                settings.map_tiles.clone()
+            };
+            settings
+        })
+    };
+
+    let on_send_delay = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.send_delay_secs = select.value().parse().unwrap_or(0);
+            settings
+        })
+    };
+
+    let on_send_limit = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            if let Some((messages, secs)) = select.value().split_once('/') {
+                settings.send_limit = messages.parse().unwrap_or(settings.send_limit);
+                settings.send_limit_secs = secs.parse().unwrap_or(settings.send_limit_secs);
+            }
+            settings
+        })
+    };
+
+    let on_contrast = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.contrast = Contrast::ALL
+                .into_iter()
+                .find(|c| c.value() == select.value())
+                .unwrap_or_default();
+            settings
+        })
+    };
+
+    let on_motion = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.motion = Motion::ALL
+                .into_iter()
+                .find(|m| m.value() == select.value())
+                .unwrap_or_default();
+            settings
+        })
+    };
+
+    let on_layout = {
+        let settings = props.settings.clone();
+        props.on_change.reform(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.layout = Layout::ALL
+                .into_iter()
+                .find(|l| l.value() == select.value())
+                .unwrap_or_default();
+            settings
+        })
+    };
+
+    // Why the last picked file couldn't be imported, or that it was
+    let import_status = use_state(|| None::<Result<(), String>>);
+    let export_href = format!(
+        "data:application/json;charset=utf-8,{}",
+        js_sys::encode_uri_component(&props.settings.export())
+    );
+    let on_import = {
+        let on_change = props.on_change.clone();
+        let import_status = import_status.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let file = match input.files().and_then(|files| files.get(0)) {
+                Some(file) => file,
+                None => return,
+            };
+            // Picking the same file again should import it again
+            input.set_value("");
+            let on_change = on_change.clone();
+            let import_status = import_status.clone();
+            spawn_local(async move {
+                let text = JsFuture::from(file.text()).await.ok().and_then(|text| text.as_string());
+                let result = text
+                    .ok_or_else(|| "The file couldn't be read".to_string())
+                    .and_then(|text| Settings::import(&text));
+                if let Ok(settings) = &result {
+                    on_change.emit(settings.clone());
+                }
+                import_status.set(Some(result.map(|_| ())));
+            });
+        })
+    };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-80 max-h-[90vh] overflow-y-auto bg-white rounded-lg shadow-lg">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Settings"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="px-6 py-4 space-y-4">
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Autoplay GIFs"}</div>
+                            <div class="text-xs text-gray-500">{"When off, GIFs only load once you click them"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.autoplay_gifs}
+                            onchange={toggle(|s, on| s.autoplay_gifs = on)}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Blur all images"}</div>
+                            <div class="text-xs text-gray-500">{"Images marked sensitive are always blurred until you show them"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.blur_media}
+                            onchange={toggle(|s, on| s.blur_media = on)}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Remove photo metadata"}</div>
+                            <div class="text-xs text-gray-500">{"Location, device and time taken are stripped before photos are sent"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.strip_metadata}
+                            onchange={toggle(|s, on| s.strip_metadata = on)}
+                        />
+                    </label>
+                    <label class="block">
+                        <div class="text-sm font-medium text-gray-700">{"Map provider"}</div>
+                        <div class="text-xs text-gray-500">{"For shared locations: a tile URL with {z}/{x}/{y}, or a static map URL with {lat}, {lon} and {zoom}"}</div>
+                        <input
+                            type="url"
+                            class="w-full mt-2 p-2 text-sm bg-gray-100 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-400"
+                            value={props.settings.map_tiles.clone()}
+                            onchange={on_map_tiles}
+                        />
+                    </label>
+                    <label class="block">
+                        <div class="text-sm font-medium text-gray-700">{"Highlight keywords"}</div>
+                        <div class="text-xs text-gray-500">{"Comma-separated whole words, e.g. deploy, lunch"}</div>
+                        <input
+                            type="text"
+                            class="w-full mt-2 p-2 text-sm bg-gray-100 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-400"
+                            value={props.settings.keywords.join(", ")}
+                            onchange={on_keywords}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Notify on keywords"}</div>
+                            <div class="text-xs text-gray-500">{"Mentions of your name always notify"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.notify_keywords}
+                            onchange={toggle(|s, on| s.notify_keywords = on)}
+                        />
+                    </label>
+                    <label class="block">
+                        <div class="text-sm font-medium text-gray-700">{"Strip tracking from links"}</div>
+                        <div class="text-xs text-gray-500">{"Parameters removed from shown and pasted links; * matches any ending"}</div>
+                        <input
+                            type="text"
+                            class="w-full mt-2 p-2 text-sm bg-gray-100 rounded-md focus:outline-none focus:ring-2 focus:ring-blue-400"
+                            value={props.settings.tracking_params.join(", ")}
+                            onchange={on_tracking_params}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Undo send"}</div>
+                            <div class="text-xs text-gray-500">{"Hold messages back so you can take them back"}</div>
+                        </div>
+                        <select class="p-1 text-sm bg-gray-100 rounded-md focus:outline-none" onchange={on_send_delay}>
+                            {
+                                SEND_DELAYS.iter().map(|secs| html! {
+                                    <option value={secs.to_string()} selected={*secs == props.settings.send_delay_secs}>
+                                        {if *secs == 0 { "Off".to_string() } else { format!("{} s", secs) }}
+                                    </option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                    </label>
+                    <label class="flex items-center justify-between">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Send limit"}</div>
+                            <div class="text-xs text-gray-500">{"Messages past this wait for a short cooldown"}</div>
+                        </div>
+                        <select class="p-1 text-sm bg-gray-100 rounded-md focus:outline-none" onchange={on_send_limit}>
+                            {
+                                SEND_LIMITS.iter().map(|(messages, secs)| html! {
+                                    <option
+                                        value={format!("{}/{}", messages, secs)}
+                                        selected={(*messages, *secs) == (props.settings.send_limit, props.settings.send_limit_secs)}
+                                    >
+                                        {format!("{} per {} s", messages, secs)}
+                                    </option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                    </label>
+                    <label class="flex items-center justify-between">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Layout"}</div>
+                            <div class="text-xs text-gray-500">{"Compact shows one line per message"}</div>
+                        </div>
+                        <select class="p-1 text-sm bg-gray-100 rounded-md focus:outline-none" onchange={on_layout}>
+                            {
+                                Layout::ALL.iter().map(|l| html! {
+                                    <option value={l.value()} selected={*l == props.settings.layout}>{l.label()}</option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                    </label>
+                    <label class="flex items-center justify-between">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Contrast"}</div>
+                            <div class="text-xs text-gray-500">{"Solid colours and borders for easier reading"}</div>
+                        </div>
+                        <select class="p-1 text-sm bg-gray-100 rounded-md focus:outline-none" onchange={on_contrast}>
+                            {
+                                Contrast::ALL.iter().map(|c| html! {
+                                    <option value={c.value()} selected={*c == props.settings.contrast}>{c.label()}</option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                    </label>
+                    <label class="flex items-center justify-between">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Motion"}</div>
+                            <div class="text-xs text-gray-500">{"Turn off animations and transitions"}</div>
+                        </div>
+                        <select class="p-1 text-sm bg-gray-100 rounded-md focus:outline-none" onchange={on_motion}>
+                            {
+                                Motion::ALL.iter().map(|m| html! {
+                                    <option value={m.value()} selected={*m == props.settings.motion}>{m.label()}</option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Presence shapes"}</div>
+                            <div class="text-xs text-gray-500">{"Dot, ring, dash or outline instead of colour alone"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.presence_shapes}
+                            onchange={toggle(|s, on| s.presence_shapes = on)}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Quiet message floods"}</div>
+                            <div class="text-xs text-gray-500">{"No alerts while someone sends many messages in a row"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.quiet_bursts}
+                            onchange={toggle(|s, on| s.quiet_bursts = on)}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Sender's local time"}</div>
+                            <div class="text-xs text-gray-500">{"Show what time it was for them, if they shared a timezone"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.sender_time}
+                            onchange={toggle(|s, on| s.sender_time = on)}
+                        />
+                    </label>
+                    <label class="flex items-center justify-between cursor-pointer">
+                        <div>
+                            <div class="text-sm font-medium text-gray-700">{"Sync drafts"}</div>
+                            <div class="text-xs text-gray-500">{"Finish on another device what you started typing here, when signed in. Off keeps drafts on this device"}</div>
+                        </div>
+                        <input
+                            type="checkbox"
+                            class="w-4 h-4"
+                            checked={props.settings.sync_drafts}
+                            onchange={toggle(|s, on| s.sync_drafts = on)}
+                        />
+                    </label>
+                </div>
+                <div class="px-6 py-4 border-t">
+                    <div class="text-xs text-gray-500">{"Settings stay on this device. Take them to another with a settings file."}</div>
+                    <div class="flex items-center gap-2 mt-2 text-sm">
+                        <a
+                            href={export_href}
+                            download="yewchat-settings.json"
+                            class="px-3 py-1 text-gray-700 bg-gray-100 rounded-md hover:bg-gray-200"
+                        >
+                            {"Export"}
+                        </a>
+                        <label class="px-3 py-1 text-gray-700 bg-gray-100 rounded-md cursor-pointer hover:bg-gray-200">
+                            {"Import…"}
+                            <input type="file" accept="application/json,.json" class="sr-only" onchange={on_import} />
+                        </label>
+                        <button
+                            onclick={props.on_reset.reform(|_: MouseEvent| ())}
+                            class="ml-auto px-3 py-1 text-red-600 rounded-md hover:bg-red-50"
+                        >
+                            {"Reset to defaults"}
+                        </button>
+                    </div>
+                    {
+                        match &*import_status {
+                            Some(Ok(())) => html! { <div class="mt-2 text-xs text-green-700" role="status">{"Settings imported"}</div> },
+                            Some(Err(error)) => html! { <div class="mt-2 text-xs text-red-600" role="alert">{error}</div> },
+                            None => html! {},
+                        }
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests;
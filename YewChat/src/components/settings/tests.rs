@@ -0,0 +1,41 @@
+use wasm_bindgen_test::*;
+
+use super::{Layout, Settings};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn export_round_trips() {
+    let settings = Settings {
+        keywords: vec!["deploy".into()],
+        layout: Layout::Compact,
+        send_delay_secs: 10,
+        send_limit: 10,
+        send_limit_secs: 10,
+        ..Default::default()
+    };
+    assert_eq!(Settings::import(&settings.export()), Ok(settings));
+}
+
+#[wasm_bindgen_test]
+fn bare_settings_are_migrated() {
+    let imported = Settings::import(r#"{ "autoplay_gifs": false, "layout": "compact" }"#).unwrap();
+    assert!(!imported.autoplay_gifs);
+    assert_eq!(imported.layout, Layout::Compact);
+    // Left out, so they keep their defaults
+    assert!(imported.strip_metadata);
+    assert_eq!((imported.send_limit, imported.send_limit_secs), (5, 5));
+}
+
+#[wasm_bindgen_test]
+fn newer_versions_are_refused() {
+    let file = r#"{ "format": "yewchat-settings", "version": 99, "settings": {} }"#;
+    assert!(Settings::import(file).unwrap_err().contains("newer"));
+}
+
+#[wasm_bindgen_test]
+fn other_files_are_refused() {
+    assert!(Settings::import(r#"{ "format": "something-else", "version": 1 }"#).is_err());
+    assert!(Settings::import("[1, 2, 3]").is_err());
+    assert!(Settings::import("not json").is_err());
+}
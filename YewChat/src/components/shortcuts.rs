@@ -0,0 +1,75 @@
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+use crate::services::hotkeys::{self, Area, SHORTCUTS};
+
+#[derive(Properties, PartialEq)]
+pub struct ShortcutSheetProps {
+    pub on_close: Callback<()>,
+}
+
+/// Every keyboard shortcut, grouped by where it works.
+#[function_component(ShortcutSheet)]
+pub fn shortcut_sheet(props: &ShortcutSheetProps) -> Html {
+    let root = use_node_ref();
+    {
+        // Take focus so Escape closes it straight away
+        let root = root.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(el) = root.cast::<HtmlElement>() {
+                    let _ = el.focus();
+                }
+                || ()
+            },
+            (),
+        );
+    }
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let onkeydown = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if hotkeys::CLOSE.matches(&e) {
+                on_close.emit(());
+            }
+        })
+    };
+    let group = |area: Area| {
+        html! {
+            <section class="mt-4 first:mt-0">
+                <h3 class="text-xs font-semibold tracking-wide text-gray-500 uppercase">{area.label()}</h3>
+                <dl class="mt-2 space-y-1.5">
+                    {
+                        SHORTCUTS.iter().filter(|s| s.area == area).map(|shortcut| html! {
+                            <div class="flex items-center justify-between gap-4">
+                                <dt class="text-sm text-gray-700">{shortcut.description}</dt>
+                                <dd>
+                                    <kbd class="px-2 py-0.5 font-mono text-xs text-gray-700 bg-gray-100 border border-gray-300 rounded">
+                                        {shortcut.label()}
+                                    </kbd>
+                                </dd>
+                            </div>
+                        }).collect::<Html>()
+                    }
+                </dl>
+            </section>
+        }
+    };
+
+    html! {
+        <div ref={root} class="fixed inset-0 z-50 flex items-center justify-center outline-none" tabindex="-1" {onkeydown}>
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-[28rem] max-h-[80vh] overflow-y-auto bg-white rounded-lg shadow-lg" role="dialog" aria-label="Keyboard shortcuts">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Keyboard shortcuts"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="px-6 py-4">
+                    {for Area::ALL.into_iter().map(group)}
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+use crate::{components::chat::MessageData, services::storage};
+
+const STORAGE_KEY: &str = "usage_stats";
+// Older days are dropped; the chart only ever shows the last few weeks
+const DAYS_KEPT: usize = 90;
+const DAYS_SHOWN: u32 = 14;
+const TOP_EMOJIS: usize = 8;
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DayCount {
+    pub sent: u32,
+    pub received: u32,
+}
+
+/// Running totals of this user's chat activity, kept only on this device.
+/// Messages are tallied as they arrive, never by rereading the history, so
+/// the numbers cover the time since counting started here.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    /// By local date, `YYYY-MM-DD`
+    pub days: BTreeMap<String, DayCount>,
+    /// Messages seen in each local hour of the day, sent or received
+    pub hours: [u32; 24],
+    /// Used in our own messages or as reactions
+    pub emojis: BTreeMap<String, u32>,
+    /// Messages seen per room
+    pub rooms: BTreeMap<String, u32>,
+    // Newest message counted, so history replayed after a reconnect isn't counted twice
+    counted_until: Option<i64>,
+}
+
+impl UsageStats {
+    pub fn load() -> Self {
+        storage::load(STORAGE_KEY).unwrap_or_default()
+    }
+
+    /// Drops everything counted on this device, e.g. when signing out.
+    pub fn forget() {
+        storage::remove(STORAGE_KEY);
+    }
+
+    /// Counts `m` in `room` unless it's older than what's been counted already.
+    pub fn record(&mut self, m: &MessageData, username: &str, room: &str) {
+        let time = match m.timestamp {
+            Some(time) if !m.system && Some(time) > self.counted_until => time,
+            _ => return,
+        };
+        self.counted_until = Some(time);
+        let date = js_sys::Date::new(&(time as f64).into());
+        let day = self.days.entry(day_key(&date)).or_default();
+        if m.from == username {
+            day.sent += 1;
+            for emoji in emojis(&m.message) {
+                *self.emojis.entry(emoji).or_default() += 1;
+            }
+        } else {
+            day.received += 1;
+        }
+        self.hours[date.get_hours() as usize] += 1;
+        *self.rooms.entry(room.to_string()).or_default() += 1;
+        let excess = self.days.len().saturating_sub(DAYS_KEPT);
+        let stale: Vec<String> = self.days.keys().take(excess).cloned().collect();
+        for key in stale {
+            self.days.remove(&key);
+        }
+        storage::save(STORAGE_KEY, self);
+    }
+
+    /// Counts a reaction we gave.
+    pub fn reacted(&mut self, emoji: &str) {
+        *self.emojis.entry(emoji.to_string()).or_default() += 1;
+        storage::save(STORAGE_KEY, self);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.days.is_empty() && self.emojis.is_empty()
+    }
+}
+
+fn day_key(date: &js_sys::Date) -> String {
+    format!("{:04}-{:02}-{:02}", date.get_full_year(), date.get_month() + 1, date.get_date())
+}
+
+fn is_pictograph(c: char) -> bool {
+    matches!(c as u32, 0x1F300..=0x1F5FF | 0x1F600..=0x1F64F | 0x1F680..=0x1F6FF | 0x1F900..=0x1F9FF | 0x1FA70..=0x1FAFF | 0x2600..=0x27BF)
+}
+
+// Skin tones, variation selectors and the joiner belong to the emoji before them
+fn is_modifier(c: char) -> bool {
+    matches!(c as u32, 0x1F3FB..=0x1F3FF | 0xFE0E | 0xFE0F | 0x200D)
+}
+
+/// The emoji in `text`, with joined sequences like 👩‍💻 kept whole.
+fn emojis(text: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut current = String::new();
+    let mut joining = false;
+    for c in text.chars() {
+        if !current.is_empty() && (is_modifier(c) || (joining && is_pictograph(c))) {
+            current.push(c);
+            joining = c == '\u{200D}';
+            continue;
+        }
+        if !current.is_empty() {
+            found.push(std::mem::take(&mut current));
+        }
+        joining = false;
+        if is_pictograph(c) {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        found.push(current);
+    }
+    found
+}
+
+#[derive(Properties, PartialEq)]
+pub struct StatsPanelProps {
+    pub stats: UsageStats,
+    pub on_clear: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+/// Charts of the user's own activity, worked out from [`UsageStats`].
+#[function_component(StatsPanel)]
+pub fn stats_panel(props: &StatsPanelProps) -> Html {
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    let stats = &props.stats;
+
+    // The last two weeks, oldest first, including days without any messages
+    let now = js_sys::Date::now();
+    let days: Vec<(js_sys::Date, DayCount)> = (0..DAYS_SHOWN)
+        .rev()
+        .map(|ago| {
+            let date = js_sys::Date::new(&(now - f64::from(ago) * MS_PER_DAY).into());
+            let count = stats.days.get(&day_key(&date)).copied().unwrap_or_default();
+            (date, count)
+        })
+        .collect();
+    let busiest_day = days.iter().map(|(_, c)| c.sent + c.received).max().unwrap_or(0).max(1);
+    let busiest_hour = stats.hours.iter().copied().max().unwrap_or(0).max(1);
+    let mut top_emojis: Vec<(&String, &u32)> = stats.emojis.iter().collect();
+    top_emojis.sort_by(|a, b| b.1.cmp(a.1));
+    top_emojis.truncate(TOP_EMOJIS);
+    let mut rooms: Vec<(&String, &u32)> = stats.rooms.iter().collect();
+    rooms.sort_by(|a, b| b.1.cmp(a.1));
+    let total_sent: u32 = stats.days.values().map(|d| d.sent).sum();
+    let total_received: u32 = stats.days.values().map(|d| d.received).sum();
+
+    let percent = |n: u32, of: u32| format!("{}%", n * 100 / of);
+    let bar = |label: String, n: u32, of: u32| {
+        html! {
+            <div class="flex items-center gap-2 text-sm">
+                <span class="w-16 truncate">{label}</span>
+                <div class="grow h-2 bg-gray-100 rounded">
+                    <div class="h-2 bg-blue-500 rounded" style={format!("width: {}", percent(n, of))}></div>
+                </div>
+                <span class="w-8 text-right text-gray-500">{n}</span>
+            </div>
+        }
+    };
+    let heading = |text: &str| html! { <h3 class="mt-5 mb-2 text-xs font-semibold tracking-wide text-gray-500 uppercase">{text.to_string()}</h3> };
+
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            // Backdrop
+            <div class="absolute inset-0 bg-black bg-opacity-40" onclick={close.clone()}></div>
+
+            <div class="relative w-[32rem] max-h-[90vh] overflow-y-auto bg-white rounded-lg shadow-lg" role="dialog" aria-label="Your stats">
+                <div class="flex items-center justify-between px-6 py-4 border-b">
+                    <div class="text-lg font-semibold text-gray-800">{"Your stats"}</div>
+                    <button onclick={close} class="p-1 text-gray-400 hover:text-gray-600 focus:outline-none">{"✕"}</button>
+                </div>
+                <div class="px-6 py-4">
+                    if stats.is_empty() {
+                        <div class="p-2 text-sm text-gray-400 bg-gray-100 rounded-md">{"Nothing counted yet. Stats build up on this device as you chat."}</div>
+                    } else {
+                        <div class="text-sm text-gray-700">
+                            {format!("{} sent and {} received on this device", total_sent, total_received)}
+                        </div>
+
+                        {heading("Last two weeks")}
+                        <div class="flex items-end h-24 gap-1" aria-hidden="true">
+                            {
+                                days.iter().map(|(date, count)| html! {
+                                    <div
+                                        class="flex flex-col justify-end h-full grow"
+                                        title={format!("{}: {} sent, {} received", day_key(date), count.sent, count.received)}
+                                    >
+                                        <div class="bg-blue-300 rounded-t" style={format!("height: {}", percent(count.received, busiest_day))}></div>
+                                        <div class="bg-blue-600" style={format!("height: {}", percent(count.sent, busiest_day))}></div>
+                                    </div>
+                                }).collect::<Html>()
+                            }
+                        </div>
+                        <div class="flex justify-between mt-1 text-xs text-gray-400">
+                            <span>{format!("{} days ago", DAYS_SHOWN - 1)}</span>
+                            <span>
+                                <span class="inline-block w-2 h-2 mr-1 bg-blue-600 rounded-sm"></span>{"Sent"}
+                                <span class="inline-block w-2 h-2 ml-3 mr-1 bg-blue-300 rounded-sm"></span>{"Received"}
+                            </span>
+                            <span>{"Today"}</span>
+                        </div>
+
+                        {heading("Busiest hours")}
+                        <div class="flex items-end h-16 gap-px" aria-hidden="true">
+                            {
+                                stats.hours.iter().enumerate().map(|(hour, n)| html! {
+                                    <div class="flex flex-col justify-end h-full grow" title={format!("{:02}:00 – {} messages", hour, n)}>
+                                        <div class="bg-indigo-500 rounded-t" style={format!("height: {}", percent(*n, busiest_hour))}></div>
+                                    </div>
+                                }).collect::<Html>()
+                            }
+                        </div>
+                        <div class="flex justify-between mt-1 text-xs text-gray-400">
+                            <span>{"00"}</span><span>{"06"}</span><span>{"12"}</span><span>{"18"}</span><span>{"23"}</span>
+                        </div>
+
+                        if !top_emojis.is_empty() {
+                            {heading("Top emoji")}
+                            <div class="space-y-1">
+                                {
+                                    top_emojis.iter().map(|(emoji, n)| bar(emoji.to_string(), **n, *top_emojis[0].1)).collect::<Html>()
+                                }
+                            </div>
+                        }
+
+                        if !rooms.is_empty() {
+                            {heading("Most active rooms")}
+                            <div class="space-y-1">
+                                {
+                                    rooms.iter().map(|(room, n)| bar(format!("#{}", room), **n, *rooms[0].1)).collect::<Html>()
+                                }
+                            </div>
+                        }
+                    }
+                </div>
+                <div class="flex items-center justify-between px-6 py-3 text-xs text-gray-500 border-t">
+                    <span>{"Counted here only, never sent anywhere"}</span>
+                    <button onclick={props.on_clear.reform(|_: MouseEvent| ())} class="text-red-600 hover:underline">
+                        {"Clear"}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests;
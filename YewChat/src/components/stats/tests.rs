@@ -0,0 +1,38 @@
+use wasm_bindgen_test::*;
+
+use super::{emojis, UsageStats};
+use crate::components::chat::MessageData;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn message(from: &str, text: &str, timestamp: i64) -> MessageData {
+    MessageData {
+        from: from.into(),
+        message: text.into(),
+        timestamp: Some(timestamp),
+        ..Default::default()
+    }
+}
+
+#[wasm_bindgen_test]
+fn finds_emoji_sequences() {
+    assert_eq!(emojis("nice 👍🏽 ship it 🚀🚀"), vec!["👍🏽", "🚀", "🚀"]);
+    assert_eq!(emojis("👩\u{200D}💻 at work"), vec!["👩\u{200D}💻"]);
+    assert!(emojis("plain text\u{FE0F}").is_empty());
+}
+
+#[wasm_bindgen_test]
+fn counts_each_message_once() {
+    let mut stats = UsageStats::default();
+    stats.record(&message("alice", "hi 👋", 1_000), "alice", "main");
+    stats.record(&message("bob", "hello", 2_000), "alice", "main");
+    // Replayed after a reconnect
+    stats.record(&message("alice", "hi 👋", 1_000), "alice", "main");
+
+    let day = stats.days.values().next().copied().unwrap();
+    assert_eq!((day.sent, day.received), (1, 1));
+    assert_eq!(stats.emojis.get("👋"), Some(&1));
+    assert_eq!(stats.rooms.get("main"), Some(&2));
+    assert_eq!(stats.hours.iter().sum::<u32>(), 2);
+    UsageStats::forget();
+}
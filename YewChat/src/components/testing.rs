@@ -0,0 +1,42 @@
+use gloo_timers::future::TimeoutFuture;
+use web_sys::Element;
+use yew::{AppHandle, Component};
+
+/// Long enough for pending renders, effects and event bus frames to go through.
+pub async fn settle() {
+    TimeoutFuture::new(100).await;
+}
+
+/// A component mounted with fixed props in a fresh element of its own. The
+/// first render happens right away; call `settle` for anything after that.
+pub struct Mounted<C: Component> {
+    pub root: Element,
+    app: AppHandle<C>,
+}
+
+impl<C: Component> Mounted<C> {
+    pub fn new(props: C::Properties) -> Self {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let root = document.create_element("div").unwrap();
+        document.body().unwrap().append_child(&root).unwrap();
+        let app = yew::start_app_with_props_in_element::<C>(root.clone(), props);
+        Self { root, app }
+    }
+
+    pub fn find(&self, selector: &str) -> Option<Element> {
+        self.root.query_selector(selector).unwrap()
+    }
+
+    pub fn count(&self, selector: &str) -> u32 {
+        self.root.query_selector_all(selector).unwrap().length()
+    }
+
+    pub fn text(&self) -> String {
+        self.root.text_content().unwrap_or_default()
+    }
+
+    pub fn unmount(self) {
+        self.app.destroy();
+        self.root.remove();
+    }
+}
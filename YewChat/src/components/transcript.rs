@@ -0,0 +1,101 @@
+use yew::prelude::*;
+
+use crate::components::chat::{DeliveryStatus, MessageData};
+
+#[derive(Properties, PartialEq)]
+pub struct TranscriptProps {
+    pub title: String,
+    pub messages: Vec<MessageData>,
+    pub on_close: Callback<()>,
+}
+
+// Page margins for the printed copy; Tailwind has no utility for @page
+const PRINT_STYLE: &str = "@page { margin: 2cm; } @media print { body { background: white; } }";
+
+/// Plain black-on-white rendering of the conversation meant for `window.print()`
+/// (or "Save as PDF"), with full dates instead of the chat's relative times.
+#[function_component(Transcript)]
+pub fn transcript(props: &TranscriptProps) -> Html {
+    let print = Callback::from(|_: MouseEvent| {
+        if let Some(window) = web_sys::window() {
+            if let Err(e) = window.print() {
+                log::error!("print: {:?}", e);
+            }
+        }
+    });
+    let close = props.on_close.reform(|_: MouseEvent| ());
+    // Messages that never reached the server aren't part of the conversation
+    let messages = props.messages.iter().filter(|m| m.status == DeliveryStatus::Sent);
+    let printed_at = format_datetime(js_sys::Date::now() as i64);
+
+    html! {
+        <div class="min-h-screen bg-white text-black">
+            <style>{PRINT_STYLE}</style>
+            <div class="sticky top-0 flex justify-end gap-2 p-4 bg-gray-100 border-b print:hidden">
+                <button onclick={close} class="px-4 py-2 rounded-md text-gray-700 hover:bg-gray-200">{"Back to chat"}</button>
+                <button onclick={print} class="px-4 py-2 rounded-md bg-blue-600 text-white hover:bg-blue-700">{"Print / Save as PDF"}</button>
+            </div>
+
+            <article class="max-w-3xl mx-auto px-8 py-8 print:p-0 font-serif text-sm leading-relaxed">
+                <header class="mb-6 pb-2 border-b border-black">
+                    <h1 class="text-2xl font-bold">{props.title.clone()}</h1>
+                    <div class="text-xs">{format!("Transcript generated {}", printed_at)}</div>
+                </header>
+                {
+                    messages.map(|m| {
+                        let time = m.timestamp.map(format_datetime).unwrap_or_default();
+                        if m.system {
+                            return html! {
+                                <p class="my-2 italic break-inside-avoid">{format!("{} — {}", time, m.message)}</p>
+                            };
+                        }
+                        html! {
+                            <div class="my-3 break-inside-avoid">
+                                <div>
+                                    <span class="font-mono text-xs mr-2">{time}</span>
+                                    <span class="font-bold">{m.from.clone()}</span>
+                                </div>
+                                if let Some(reply) = m.reply_to.as_ref() {
+                                    <div class="ml-4 pl-2 border-l border-black text-xs">
+                                        {format!("In reply to {}: {}", reply.from, reply.message)}
+                                    </div>
+                                }
+                                <div class="ml-4 whitespace-pre-wrap break-words">
+                                    if m.deleted {
+                                        <span class="italic">{"(message deleted)"}</span>
+                                    } else {
+                                        {m.message.clone()}
+                                    }
+                                </div>
+                                if !m.reactions.is_empty() {
+                                    <div class="ml-4 text-xs">
+                                        {
+                                            m.reactions
+                                                .iter()
+                                                .map(|(emoji, users)| format!("{} {}", emoji, users.len()))
+                                                .collect::<Vec<_>>()
+                                                .join("  ")
+                                        }
+                                    </div>
+                                }
+                            </div>
+                        }
+                    }).collect::<Html>()
+                }
+            </article>
+        </div>
+    }
+}
+
+// Absolute local date and time ("2024-03-05 14:07"), since a printout has no "now" to be relative to
+fn format_datetime(ts: i64) -> String {
+    let date = js_sys::Date::new(&(ts as f64).into());
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
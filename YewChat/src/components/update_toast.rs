@@ -0,0 +1,31 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct UpdateToastProps {
+    pub on_dismiss: Callback<()>,
+}
+
+fn reload() {
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().reload();
+    }
+}
+
+/// Floats at the bottom of the chat once a newer client has been deployed, so
+/// a tab left open for days doesn't keep speaking an old protocol.
+#[function_component(UpdateToast)]
+pub fn update_toast(props: &UpdateToastProps) -> Html {
+    html! {
+        <div class="fixed inset-x-0 bottom-6 z-40 flex justify-center pointer-events-none">
+            <div class="flex items-center gap-3 px-4 py-3 text-sm text-white bg-gray-800 rounded-lg shadow-lg pointer-events-auto" role="status">
+                <span>{"A new version is available"}</span>
+                <button onclick={Callback::from(|_: MouseEvent| reload())} class="px-3 py-1 font-medium rounded-md bg-blue-600 hover:bg-blue-500">
+                    {"Reload"}
+                </button>
+                <button onclick={props.on_dismiss.reform(|_: MouseEvent| ())} class="px-1 text-gray-400 hover:text-gray-200" aria-label="Not now">
+                    {"✕"}
+                </button>
+            </div>
+        </div>
+    }
+}
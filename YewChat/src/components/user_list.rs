@@ -0,0 +1,225 @@
+use std::collections::BTreeSet;
+
+use web_sys::HtmlElement;
+use yew::prelude::*;
+
+use crate::components::{
+    avatar::Avatar,
+    chat::{SidebarSection, UserProfile},
+    motion::animate,
+    name_color::name_color,
+    presence::PresenceDot,
+};
+
+// Rows have fixed heights so the visible window can be computed without measuring the DOM
+const HEADER_HEIGHT: i32 = 32;
+const ROW_HEIGHT: i32 = 64;
+// Extra rows rendered above and below the viewport to avoid flicker while scrolling
+const OVERSCAN: i32 = 6 * ROW_HEIGHT;
+
+#[derive(Clone, PartialEq)]
+pub struct UserSection {
+    pub section: SidebarSection,
+    pub label: &'static str,
+    pub users: Vec<UserProfile>,
+    pub collapsed: bool,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct UserListProps {
+    pub sections: Vec<UserSection>,
+    pub favorites: BTreeSet<String>,
+    pub on_toggle_section: Callback<SidebarSection>,
+    pub on_toggle_favorite: Callback<String>,
+    pub on_open_profile: Callback<String>,
+    /// Draw presence as distinct shapes rather than colour alone
+    pub presence_shapes: bool,
+}
+
+enum Row<'a> {
+    Header(&'a UserSection),
+    User(&'a UserProfile),
+}
+
+impl Row<'_> {
+    fn height(&self) -> i32 {
+        match self {
+            Row::Header(_) => HEADER_HEIGHT,
+            Row::User(_) => ROW_HEIGHT,
+        }
+    }
+}
+
+/// Virtualized sidebar user list: only the rows around the viewport are in the
+/// DOM, so rooms with hundreds of participants stay responsive.
+#[function_component(UserList)]
+pub fn user_list(props: &UserListProps) -> Html {
+    let container = use_node_ref();
+    // (scroll_top, client_height) of the scroll container
+    let viewport = use_state(|| (0, 800));
+
+    {
+        // Measure the real height once mounted
+        let container = container.clone();
+        let viewport = viewport.clone();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(el) = container.cast::<HtmlElement>() {
+                    viewport.set((el.scroll_top(), el.client_height()));
+                }
+                || ()
+            },
+            (),
+        );
+    }
+
+    let onscroll = {
+        let container = container.clone();
+        let viewport = viewport.clone();
+        Callback::from(move |_: Event| {
+            if let Some(el) = container.cast::<HtmlElement>() {
+                viewport.set((el.scroll_top(), el.client_height()));
+            }
+        })
+    };
+
+    let rows: Vec<Row> = props
+        .sections
+        .iter()
+        .filter(|s| !s.users.is_empty() || s.section == SidebarSection::Online)
+        .flat_map(|s| {
+            let users = s.users.iter().filter(move |_| !s.collapsed).map(Row::User);
+            std::iter::once(Row::Header(s)).chain(users)
+        })
+        .collect();
+
+    let mut tops = Vec::with_capacity(rows.len() + 1);
+    let mut total = 0;
+    for row in rows.iter() {
+        tops.push(total);
+        total += row.height();
+    }
+    tops.push(total);
+
+    let (scroll_top, height) = *viewport;
+    let start = tops
+        .partition_point(|top| *top < scroll_top - OVERSCAN)
+        .saturating_sub(1)
+        .min(rows.len());
+    let end = tops.partition_point(|top| *top < scroll_top + height + OVERSCAN).min(rows.len());
+
+    // First row for each initial letter, favorites excluded since they're pinned anyway
+    let mut letters: Vec<(char, i32)> = Vec::new();
+    let mut in_favorites = false;
+    for (i, row) in rows.iter().enumerate() {
+        match row {
+            Row::Header(s) => in_favorites = s.section == SidebarSection::Favorites,
+            Row::User(u) if !in_favorites => {
+                let letter = u.name.chars().next().map_or('#', |c| c.to_ascii_uppercase());
+                let letter = if letter.is_ascii_alphabetic() { letter } else { '#' };
+                if !letters.iter().any(|(l, _)| *l == letter) {
+                    letters.push((letter, tops[i]));
+                }
+            }
+            Row::User(_) => {}
+        }
+    }
+    letters.sort_by_key(|(l, _)| *l);
+
+    html! {
+        <div class="relative grow min-h-0">
+            <div ref={container.clone()} {onscroll} class="h-full overflow-y-auto pr-4">
+                <div style={format!("height: {}px;", tops[start])}></div>
+                {
+                    rows[start..end].iter().map(|row| match row {
+                        Row::Header(s) => section_header(props, s),
+                        Row::User(u) => user_row(props, u),
+                    }).collect::<Html>()
+                }
+                <div style={format!("height: {}px;", total - tops[end])}></div>
+            </div>
+
+            // Alphabetical jump rail, only worth showing for long lists
+            if rows.len() * ROW_HEIGHT as usize > height as usize {
+                <div class="absolute right-0 top-0 bottom-0 w-4 flex flex-col justify-center text-center select-none">
+                    {
+                        letters.into_iter().map(|(letter, top)| {
+                            let container = container.clone();
+                            let onclick = Callback::from(move |_: MouseEvent| {
+                                if let Some(el) = container.cast::<HtmlElement>() {
+                                    el.set_scroll_top(top);
+                                }
+                            });
+                            html! {
+                                <button {onclick} class="text-[10px] leading-4 font-medium text-blue-500 hover:text-blue-700">
+                                    {letter}
+                                </button>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            }
+        </div>
+    }
+}
+
+fn section_header(props: &UserListProps, s: &UserSection) -> Html {
+    let section = s.section;
+    let onclick = props.on_toggle_section.reform(move |_: MouseEvent| section);
+    html! {
+        <button
+            {onclick}
+            aria-expanded={(!s.collapsed).to_string()}
+            class="flex items-center w-full h-8 px-2 text-xs font-medium text-gray-500 hover:text-gray-700 focus:outline-none"
+        >
+            <span class="w-4">{if s.collapsed { "▸" } else { "▾" }}</span>
+            <span>{s.label}</span>
+            <span class="ml-auto px-2 rounded-full bg-gray-100">{s.users.len()}</span>
+        </button>
+    }
+}
+
+fn user_row(props: &UserListProps, u: &UserProfile) -> Html {
+    let favorite = props.favorites.contains(&u.name);
+    let name = u.name.clone();
+    let toggle_favorite = props.on_toggle_favorite.reform(move |e: MouseEvent| {
+        // Starring shouldn't also open the profile
+        e.stop_propagation();
+        name.clone()
+    });
+    let name = u.name.clone();
+    let open_profile = props.on_open_profile.reform(move |_: MouseEvent| name.clone());
+
+    html! {
+        <div onclick={open_profile} title="View profile" class={classes!(
+            "group", "flex", "items-center", "h-16", "px-3", "hover:bg-gray-100", "rounded-lg", "cursor-pointer",
+            if u.online() { animate("transition-colors") } else { "opacity-60" }
+        )}>
+            <div class="relative">
+                if u.online() {
+                    <Avatar name={u.name.clone()} src={u.avatar.clone()} />
+                } else {
+                    <Avatar name={u.name.clone()} src={u.avatar.clone()} class={classes!("w-10", "h-10", "grayscale")} />
+                }
+                <PresenceDot presence={u.presence} shapes={props.presence_shapes} />
+            </div>
+            <div class="ml-3 grow min-w-0">
+                <div class={classes!("font-medium", "truncate", if u.online() { name_color(&u.name) } else { "" })}>{u.name.clone()}</div>
+                <div class="text-xs text-gray-500">{u.presence.label()}</div>
+            </div>
+            <button
+                onclick={toggle_favorite}
+                title={if favorite { "Remove from favorites" } else { "Add to favorites" }}
+                class={classes!(
+                    "p-1", "focus:outline-none",
+                    if favorite { "text-yellow-500" } else { "text-gray-300 opacity-0 group-hover:opacity-100 focus:opacity-100" }
+                )}
+            >
+                {if favorite { "★" } else { "☆" }}
+            </button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests;
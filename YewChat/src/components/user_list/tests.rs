@@ -0,0 +1,68 @@
+use std::collections::BTreeSet;
+
+use wasm_bindgen_test::*;
+use yew::Callback;
+
+use super::{UserList, UserListProps, UserSection};
+use crate::components::{
+    avatar::identicon_url,
+    chat::{Role, SidebarSection, UserProfile},
+    presence::Presence,
+    testing::Mounted,
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn user(name: &str, presence: Presence) -> UserProfile {
+    UserProfile {
+        name: name.into(),
+        avatar: identicon_url(name),
+        presence,
+        role: Role::Member,
+        profile: Default::default(),
+    }
+}
+
+fn props(online: Vec<UserProfile>, offline: Vec<UserProfile>) -> UserListProps {
+    let section = |section, label, users| UserSection { section, label, users, collapsed: false };
+    UserListProps {
+        sections: vec![
+            section(SidebarSection::Favorites, "Favorites", vec![]),
+            section(SidebarSection::Online, "Online", online),
+            section(SidebarSection::Offline, "Offline", offline),
+        ],
+        favorites: BTreeSet::new(),
+        on_toggle_section: Callback::noop(),
+        on_toggle_favorite: Callback::noop(),
+        on_open_profile: Callback::noop(),
+        presence_shapes: false,
+    }
+}
+
+#[wasm_bindgen_test]
+fn empty_list_keeps_the_online_header() {
+    let list = Mounted::<UserList>::new(props(vec![], vec![]));
+    // Empty sections are left out, except Online so there's always something to see
+    assert_eq!(list.count("button[aria-expanded]"), 1);
+    let header = list.find("button[aria-expanded]").unwrap().text_content().unwrap_or_default();
+    assert!(header.contains("Online"));
+    assert!(header.ends_with('0'));
+    assert_eq!(list.count("[title='View profile']"), 0);
+    list.unmount();
+}
+
+#[wasm_bindgen_test]
+fn members_are_listed_by_section() {
+    let list = Mounted::<UserList>::new(props(
+        vec![user("alice", Presence::Online), user("bob", Presence::Away)],
+        vec![user("carol", Presence::Offline)],
+    ));
+    assert_eq!(list.count("button[aria-expanded]"), 2);
+    assert_eq!(list.count("[title='View profile']"), 3);
+    // Offline members are dimmed
+    assert_eq!(list.count("[title='View profile'].opacity-60"), 1);
+    let text = list.text();
+    assert!(text.find("alice").unwrap() < text.find("Offline").unwrap());
+    assert!(text.find("Offline").unwrap() < text.find("carol").unwrap());
+    list.unmount();
+}
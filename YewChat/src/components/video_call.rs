@@ -0,0 +1,147 @@
+use gloo_timers::callback::Interval;
+use web_sys::{HtmlMediaElement, MediaStream};
+use yew::prelude::*;
+
+use crate::{components::avatar::identicon_url, services::call::format_duration};
+
+#[derive(Properties, PartialEq)]
+pub struct IncomingCallProps {
+    pub from: String,
+    pub on_accept: Callback<()>,
+    pub on_decline: Callback<()>,
+}
+
+/// Shown while someone is ringing us.
+#[function_component(IncomingCallDialog)]
+pub fn incoming_call_dialog(props: &IncomingCallProps) -> Html {
+    let button = "px-4 py-2 text-sm font-medium text-white rounded-md";
+    html! {
+        <div class="fixed inset-0 z-50 flex items-center justify-center">
+            <div class="absolute inset-0 bg-black bg-opacity-40"></div>
+
+            <div class="relative w-80 px-6 py-6 text-center bg-white rounded-lg shadow-lg" role="alertdialog" aria-label={format!("Incoming video call from {}", props.from)}>
+                <img class="w-16 h-16 mx-auto rounded-full animate-pulse" src={identicon_url(&props.from)} alt="" />
+                <div class="mt-3 text-lg font-semibold text-gray-800">{&props.from}</div>
+                <div class="text-sm text-gray-500">{"is video calling you"}</div>
+                <div class="flex justify-center gap-3 mt-5">
+                    <button onclick={props.on_decline.reform(|_: MouseEvent| ())} class={classes!(button, "bg-red-600", "hover:bg-red-500")}>
+                        {"Decline"}
+                    </button>
+                    <button onclick={props.on_accept.reform(|_: MouseEvent| ())} class={classes!(button, "bg-emerald-600", "hover:bg-emerald-500")}>
+                        {"Accept"}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct VideoProps {
+    stream: Option<MediaStream>,
+    classes: Classes,
+    /// Our own preview: never played back, so we don't hear ourselves
+    #[prop_or_default]
+    muted: bool,
+}
+
+#[function_component(Video)]
+fn video(props: &VideoProps) -> Html {
+    let node = use_node_ref();
+    {
+        // Streams can't go through an attribute, so they're set on the element itself
+        let node = node.clone();
+        use_effect_with_deps(
+            move |stream: &Option<MediaStream>| {
+                if let Some(video) = node.cast::<HtmlMediaElement>() {
+                    video.set_src_object(stream.as_ref());
+                }
+                || ()
+            },
+            props.stream.clone(),
+        );
+    }
+    html! {
+        <video ref={node} class={props.classes.clone()} autoplay=true playsinline=true muted={props.muted}></video>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CallWindowProps {
+    pub peer: String,
+    pub local: MediaStream,
+    /// Their video, once it arrives
+    pub remote: Option<MediaStream>,
+    /// When they picked up; `None` while it rings
+    pub started: Option<f64>,
+    pub muted: bool,
+    pub camera_off: bool,
+    pub on_toggle_mute: Callback<()>,
+    pub on_toggle_camera: Callback<()>,
+    pub on_hang_up: Callback<()>,
+}
+
+/// The call in progress: their video filling the window, ours in a corner,
+/// and the controls underneath.
+#[function_component(CallWindow)]
+pub fn call_window(props: &CallWindowProps) -> Html {
+    let now = use_state(js_sys::Date::now);
+    {
+        let now = now.clone();
+        use_effect_with_deps(
+            move |started: &Option<f64>| {
+                let ticker = started.map(|_| Interval::new(1_000, move || now.set(js_sys::Date::now())));
+                move || drop(ticker)
+            },
+            props.started,
+        );
+    }
+    let status = match (props.started, &props.remote) {
+        (None, _) => "Ringing…".to_string(),
+        (Some(_), None) => "Connecting…".to_string(),
+        (Some(started), Some(_)) => format_duration(*now - started),
+    };
+    let button = "px-3 py-2 text-sm font-medium rounded-full";
+    html! {
+        <div class="fixed inset-0 z-50 flex flex-col bg-gray-900" role="dialog" aria-label={format!("Video call with {}", props.peer)}>
+            <div class="flex items-center justify-between px-4 py-3 text-white">
+                <span class="font-semibold">{&props.peer}</span>
+                <span class="text-sm text-gray-300" aria-live="polite">{status}</span>
+            </div>
+            <div class="relative grow overflow-hidden">
+                if props.remote.is_some() {
+                    <Video stream={props.remote.clone()} classes={classes!("w-full", "h-full", "object-contain")} />
+                } else {
+                    <div class="flex items-center justify-center h-full">
+                        <img class="w-24 h-24 rounded-full" src={identicon_url(&props.peer)} alt="" />
+                    </div>
+                }
+                <div class="absolute w-40 overflow-hidden bg-black rounded-lg shadow-lg bottom-4 right-4 aspect-video ring-1 ring-white/20">
+                    <Video stream={Some(props.local.clone())} classes={classes!("w-full", "h-full", "object-cover", "-scale-x-100")} muted=true />
+                    if props.camera_off {
+                        <div class="absolute inset-0 flex items-center justify-center text-xs text-gray-300 bg-gray-800">{"Camera off"}</div>
+                    }
+                </div>
+            </div>
+            <div class="flex justify-center gap-3 py-4">
+                <button
+                    onclick={props.on_toggle_mute.reform(|_: MouseEvent| ())}
+                    class={classes!(button, "text-white", "bg-gray-700", "hover:bg-gray-600")}
+                    aria-pressed={props.muted.to_string()}
+                >
+                    {if props.muted { "🔇 Unmute" } else { "🎙️ Mute" }}
+                </button>
+                <button
+                    onclick={props.on_toggle_camera.reform(|_: MouseEvent| ())}
+                    class={classes!(button, "text-white", "bg-gray-700", "hover:bg-gray-600")}
+                    aria-pressed={props.camera_off.to_string()}
+                >
+                    {if props.camera_off { "📷 Camera on" } else { "📷 Camera off" }}
+                </button>
+                <button onclick={props.on_hang_up.reform(|_: MouseEvent| ())} class={classes!(button, "text-white", "bg-red-600", "hover:bg-red-500")}>
+                    {"Hang up"}
+                </button>
+            </div>
+        </div>
+    }
+}
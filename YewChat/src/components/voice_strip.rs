@@ -0,0 +1,71 @@
+use std::collections::BTreeSet;
+
+use yew::prelude::*;
+
+use crate::{components::avatar::identicon_url, services::voice::Participant};
+
+#[derive(Properties, PartialEq)]
+pub struct VoiceStripProps {
+    pub participants: Vec<Participant>,
+    /// Whose audio is above the speaking threshold right now
+    pub speaking: BTreeSet<String>,
+    /// Whether we're in the call ourselves
+    pub joined: bool,
+    pub muted: bool,
+    pub on_join: Callback<()>,
+    pub on_leave: Callback<()>,
+    pub on_toggle_mute: Callback<()>,
+}
+
+/// Who's in the room's voice channel, under the header, with the controls for
+/// joining, muting and leaving.
+#[function_component(VoiceStrip)]
+pub fn voice_strip(props: &VoiceStripProps) -> Html {
+    let button = "px-3 py-1 text-xs font-medium rounded-md";
+    html! {
+        <div class="flex items-center gap-3 px-4 py-2 text-sm border-b bg-emerald-50 border-emerald-200" role="region" aria-label="Voice channel">
+            <span class="text-emerald-800 font-medium" aria-hidden="true">{"🔊 Voice"}</span>
+            <ul class="flex items-center gap-2 grow overflow-x-auto">
+                {
+                    props.participants.iter().map(|p| {
+                        let speaking = props.speaking.contains(&p.name) && !p.muted;
+                        let label = match (p.muted, speaking) {
+                            (true, _) => format!("{}, muted", p.name),
+                            (false, true) => format!("{}, speaking", p.name),
+                            (false, false) => p.name.clone(),
+                        };
+                        html! {
+                            <li key={p.name.clone()} class="flex items-center gap-1 shrink-0" title={label.clone()} aria-label={label.clone()}>
+                                <img
+                                    class={classes!("w-7", "h-7", "rounded-full", "ring-2", if speaking { "ring-emerald-500" } else { "ring-transparent" })}
+                                    src={identicon_url(&p.name)}
+                                    alt=""
+                                />
+                                <span class="text-xs text-gray-700">{&p.name}</span>
+                                if p.muted {
+                                    <span class="text-xs" aria-hidden="true">{"🔇"}</span>
+                                }
+                            </li>
+                        }
+                    }).collect::<Html>()
+                }
+            </ul>
+            if props.joined {
+                <button
+                    onclick={props.on_toggle_mute.reform(|_: MouseEvent| ())}
+                    class={classes!(button, "text-gray-700", "bg-white", "border", "hover:bg-gray-50")}
+                    aria-pressed={props.muted.to_string()}
+                >
+                    {if props.muted { "Unmute" } else { "Mute" }}
+                </button>
+                <button onclick={props.on_leave.reform(|_: MouseEvent| ())} class={classes!(button, "text-white", "bg-red-600", "hover:bg-red-500")}>
+                    {"Leave"}
+                </button>
+            } else {
+                <button onclick={props.on_join.reform(|_: MouseEvent| ())} class={classes!(button, "text-white", "bg-emerald-600", "hover:bg-emerald-500")}>
+                    {"Join voice"}
+                </button>
+            }
+        </div>
+    }
+}
@@ -5,6 +5,10 @@ mod services;
 
 use components::login::Login;
 use components::chat::Chat;
+use components::invite::JoinInvite;
+use components::permalink::{OpenPermalink, Permalink};
+use components::captcha::Challenge;
+use services::{account::Session, endpoints::Endpoints, rejoin::LastConversation};
 
 
 use wasm_bindgen::prelude::*;
@@ -22,12 +26,16 @@ use std::rc::Rc;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-#[derive(Debug, Clone, Copy, PartialEq, Routable)]
+#[derive(Debug, Clone, PartialEq, Routable)]
 pub enum Route {
     #[at("/")]
     Login,
     #[at("/chat")]
     Chat,
+    #[at("/chat/:room")]
+    Room { room: String },
+    #[at("/join/:token")]
+    Join { token: String },
     #[not_found]
     #[at("/404")]
     NotFound,
@@ -38,12 +46,26 @@ pub type User = Rc<UserInner>;
 #[derive(Debug, PartialEq)]
 pub struct UserInner {
     pub username: RefCell<String>,
+    // Reason the server refused the last registration, shown on the login form
+    pub register_error: RefCell<Option<String>>,
+    // Invite token from a `/join/<token>` link, redeemed once the chat connects
+    pub pending_invite: RefCell<Option<String>>,
+    // Message from a permalink, scrolled to once the chat has loaded it
+    pub pending_permalink: RefCell<Option<Permalink>>,
+    // CAPTCHA the server asked for before it lets us register
+    pub challenge: RefCell<Option<Challenge>>,
+    // Solution to that CAPTCHA, sent ahead of the next registration
+    pub captcha_token: RefCell<Option<String>>,
+    // Signed-in account; guests have none
+    pub session: RefCell<Option<Session>>,
 }
 
 fn switch(selected_route: &Route) -> Html {
     match selected_route {
         Route::Login => html! {<Login />},
         Route::Chat => html! {<Chat />},
+        Route::Room { room } => html! {<OpenPermalink room={room.clone()} />},
+        Route::Join { token } => html! {<JoinInvite token={token.clone()} />},
         Route::NotFound => html! {<h1>{"404 baby"}</h1>},
     }
 }
@@ -51,11 +73,37 @@ fn switch(selected_route: &Route) -> Html {
 #[function_component(Main)]
 fn main() -> Html {
     let ctx = use_state(|| {
+        let session = Session::restore();
         Rc::new(UserInner {
-            username: RefCell::new("initial".into()),
+            username: RefCell::new(match (&session, LastConversation::load()) {
+                (Some(session), _) => session.name.clone(),
+                // Reloading on /chat carries on under the name we had
+                (None, Some(last)) => last.name,
+                (None, None) => "initial".into(),
+            }),
+            register_error: RefCell::new(None),
+            pending_invite: RefCell::new(None),
+            pending_permalink: RefCell::new(None),
+            challenge: RefCell::new(None),
+            captcha_token: RefCell::new(None),
+            session: RefCell::new(session),
         })
     });
 
+    // The static shell from index.html has done its job once we've rendered
+    use_effect_with_deps(
+        |_| {
+            if let Some(shell) = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.get_element_by_id("shell"))
+            {
+                shell.remove();
+            }
+            || ()
+        },
+        (),
+    );
+
     html! {
         <ContextProvider<User> context={(*ctx).clone()}>
         <BrowserRouter>
@@ -70,6 +118,10 @@ fn main() -> Html {
 #[wasm_bindgen]
 pub fn run_app() -> Result<(), JsValue> {
     wasm_logger::init(wasm_logger::Config::default());
-    yew::start_app::<Main>();
+    // The server's address may come from config.json, so that's read first
+    wasm_bindgen_futures::spawn_local(async {
+        Endpoints::resolve().await;
+        yew::start_app::<Main>();
+    });
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use futures::{SinkExt, StreamExt};
+use reqwasm::websocket::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::services::{endpoints::Endpoints, storage, websocket::connect};
+
+const SESSION_KEY: &str = "session";
+const RECENT_KEY: &str = "recent_identities";
+// Enough for a few people sharing a classroom machine without the list getting long
+const RECENT_LIMIT: usize = 5;
+const UNREACHABLE: &str = "Couldn't reach the server, please try again";
+const QUERY_SERVER: &str = "Signing in is turned off for servers picked in the page's address";
+
+/// A signed-in account. The token goes along when the chat connects, so the
+/// server lets us use the account's name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub name: String,
+    pub token: String,
+}
+
+impl Session {
+    /// The session kept from an earlier sign-in, if there is one.
+    pub fn restore() -> Option<Session> {
+        storage::load(SESSION_KEY).or_else(|| storage::load_for_tab(SESSION_KEY))
+    }
+
+    /// Keeps the session for this tab, or across visits with "remember me".
+    pub fn save(&self, remember: bool) {
+        if remember {
+            storage::save(SESSION_KEY, self);
+        } else {
+            storage::save_for_tab(SESSION_KEY, self);
+        }
+    }
+
+    pub fn forget() {
+        storage::remove(SESSION_KEY);
+    }
+}
+
+/// A name used on this device before, offered again on the login form so
+/// switching between people on a shared machine is one click.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecentIdentity {
+    pub name: String,
+    /// An account's password is asked for again; a guest name goes straight in
+    pub account: bool,
+}
+
+impl RecentIdentity {
+    /// Most recently used first.
+    pub fn all() -> Vec<Self> {
+        storage::load(RECENT_KEY).unwrap_or_default()
+    }
+
+    /// Puts `name` at the top of the list, pushing the oldest off the end.
+    pub fn remember(name: &str, account: bool) {
+        let mut recent = Self::all();
+        recent.retain(|r| r.name != name);
+        recent.insert(0, Self { name: name.to_string(), account });
+        recent.truncate(RECENT_LIMIT);
+        storage::save(RECENT_KEY, &recent);
+    }
+
+    pub fn forget(name: &str) {
+        let mut recent = Self::all();
+        recent.retain(|r| r.name != name);
+        storage::save(RECENT_KEY, &recent);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuthMode {
+    SignIn,
+    SignUp,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    message_type: String,
+    data: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Credentials<'a> {
+    name: &'a str,
+    password: &'a str,
+}
+
+/// Signs in, or creates the account first. This uses a short-lived socket of its
+/// own, since the chat connection needs the resulting token to open.
+pub async fn authenticate(mode: AuthMode, name: &str, password: &str) -> Result<Session, String> {
+    let request = Frame {
+        message_type: match mode {
+            AuthMode::SignIn => "login",
+            AuthMode::SignUp => "signup",
+        }
+        .into(),
+        data: Some(serde_json::to_string(&Credentials { name, password }).unwrap()),
+    };
+    let endpoints = Endpoints::current();
+    if endpoints.from_query {
+        return Err(QUERY_SERVER.into());
+    }
+    let mut ws = connect(&endpoints.websocket).await.ok_or(UNREACHABLE)?;
+    ws.send(Message::Text(serde_json::to_string(&request).unwrap()))
+        .await
+        .map_err(|_| UNREACHABLE)?;
+    while let Some(Ok(msg)) = ws.next().await {
+        let reply: Frame = match msg {
+            Message::Text(text) => match serde_json::from_str(&text) {
+                Ok(frame) => frame,
+                Err(_) => continue,
+            },
+            Message::Bytes(_) => continue,
+        };
+        match reply.message_type.as_str() {
+            "session" => {
+                return reply
+                    .data
+                    .and_then(|data| serde_json::from_str(&data).ok())
+                    .ok_or_else(|| UNREACHABLE.to_string());
+            }
+            "autherror" => return Err(reply.data.unwrap_or_else(|| "Sign-in was rejected".into())),
+            _ => {}
+        }
+    }
+    Err(UNREACHABLE.into())
+}
+
+/// Tells the server to stop accepting `token`. Best effort: the session is
+/// forgotten locally either way.
+pub async fn revoke(token: String) {
+    let endpoints = Endpoints::current();
+    if endpoints.from_query {
+        return;
+    }
+    if let Some(mut ws) = connect(&endpoints.websocket).await {
+        let request = Frame { message_type: "logout".into(), data: Some(token) };
+        let _ = ws.send(Message::Text(serde_json::to_string(&request).unwrap())).await;
+    }
+}
@@ -0,0 +1,28 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use reqwasm::http::Request;
+use serde::de::DeserializeOwned;
+
+thread_local! {
+    // Bodies already downloaded this session, by path
+    static LOADED: RefCell<HashMap<&'static str, Rc<str>>> = RefCell::new(HashMap::new());
+}
+
+/// Data that would bloat the wasm bundle is served from `static/` instead and
+/// fetched the first time a feature needs it. Later calls reuse the download;
+/// `None` if it couldn't be fetched or parsed.
+pub async fn load<T: DeserializeOwned>(path: &'static str) -> Option<T> {
+    let cached = LOADED.with(|l| l.borrow().get(path).cloned());
+    let body = match cached {
+        Some(body) => body,
+        None => {
+            let response = Request::get(path).send().await.ok().filter(|r| r.ok())?;
+            let body: Rc<str> = response.text().await.ok()?.into();
+            LOADED.with(|l| l.borrow_mut().insert(path, body.clone()));
+            body
+        }
+    };
+    serde_json::from_str(&body)
+        .map_err(|e| log::error!("assets: unreadable {}: {:?}", path, e))
+        .ok()
+}
@@ -0,0 +1,212 @@
+use std::rc::Rc;
+
+use futures::{future::LocalBoxFuture, stream, FutureExt, StreamExt};
+use js_sys::{Date, Promise};
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+use crate::services::transport::{Frames, Inbox, Transport};
+
+const DEFAULT_MESSAGES: usize = 1_000;
+const DEFAULT_USERS: usize = 100;
+// How far the message list is scrolled each frame
+const SCROLL_STEP_PX: i32 = 120;
+// Stops a phase that never finishes, e.g. because the list isn't on screen
+const MAX_FRAMES: usize = 3_000;
+const SAMPLE_TEXTS: [&str; 4] = [
+    "Short one",
+    "A message of about average length, the kind most of a conversation is made of",
+    "https://media.example.org/cat.gif",
+    "A longer message that wraps over a few lines in the bubble, so the list has rows of different heights to lay out and scroll through, much like a real room",
+];
+
+/// Fills the chat with synthetic messages and members instead of connecting,
+/// then times rendering them and scrolling through them. Turned on with
+/// `/chat?bench`, sized with `&messages=N&users=M`.
+#[derive(Clone, Copy, Debug)]
+pub struct Bench {
+    messages: usize,
+    users: usize,
+}
+
+/// Frame times of one run, in milliseconds. Logged, and left on
+/// `window.yewchatBench` as JSON for scripts driving a browser.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    messages: usize,
+    users: usize,
+    /// From handing over the frames to the last message being on screen
+    render_ms: f64,
+    render: FrameStats,
+    scroll: FrameStats,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameStats {
+    frames: usize,
+    mean_ms: f64,
+    p95_ms: f64,
+    max_ms: f64,
+}
+
+impl FrameStats {
+    fn from_times(times: &[f64]) -> Self {
+        let mut deltas: Vec<f64> = times.windows(2).map(|w| w[1] - w[0]).collect();
+        deltas.sort_by(|a, b| a.total_cmp(b));
+        let at = |q: f64| deltas.get(((deltas.len() as f64 * q) as usize).min(deltas.len().saturating_sub(1))).copied();
+        Self {
+            frames: deltas.len(),
+            mean_ms: if deltas.is_empty() { 0.0 } else { deltas.iter().sum::<f64>() / deltas.len() as f64 },
+            p95_ms: at(0.95).unwrap_or_default(),
+            max_ms: deltas.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl Bench {
+    /// What the page's URL asks for, if it asks for a benchmark at all.
+    pub fn requested() -> Option<Self> {
+        let search = web_sys::window()?.location().search().ok()?;
+        let mut bench = Self {
+            messages: DEFAULT_MESSAGES,
+            users: DEFAULT_USERS,
+        };
+        let mut requested = false;
+        for pair in search.trim_start_matches('?').split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "bench" => requested = true,
+                "messages" => bench.messages = value.parse().unwrap_or(DEFAULT_MESSAGES),
+                "users" => bench.users = value.parse().unwrap_or(DEFAULT_USERS),
+                _ => {}
+            }
+        }
+        requested.then_some(bench)
+    }
+
+    fn user(&self, i: usize) -> String {
+        format!("user{:04}", i % self.users.max(1))
+    }
+
+    fn message(&self, i: usize, now: f64) -> Value {
+        json!({
+            "id": (i + 1).to_string(),
+            "seq": i + 1,
+            "from": self.user(i),
+            "message": format!("{} #{}", SAMPLE_TEXTS[i % SAMPLE_TEXTS.len()], i + 1),
+            // A second apart, ending now
+            "time": now as i64 - (self.messages - i) as i64 * 1_000,
+        })
+    }
+
+    async fn run(self, inbox: Rc<Inbox>) {
+        let users: Vec<String> = (0..self.users).map(|i| self.user(i)).collect();
+        inbox.push(json!({ "messageType": "users", "dataArray": users }).to_string());
+
+        let now = Date::now();
+        let last = format!("#{}", self.messages);
+        let start = next_frame().await;
+        for i in 0..self.messages {
+            let data = self.message(i, now).to_string();
+            inbox.push(json!({ "messageType": "message", "data": data }).to_string());
+        }
+        let mut render = vec![start];
+        while render.len() < MAX_FRAMES && !on_screen(&last) {
+            render.push(next_frame().await);
+        }
+        let render_ms = render.last().copied().unwrap_or(start) - start;
+
+        // Up to the oldest message and back down, as far as the list lets us
+        let mut scroll = vec![next_frame().await];
+        for step in [-SCROLL_STEP_PX, SCROLL_STEP_PX] {
+            while scroll.len() < MAX_FRAMES {
+                let container = match message_container() {
+                    Some(container) => container,
+                    None => break,
+                };
+                let before = container.scroll_top();
+                container.set_scroll_top(before + step);
+                scroll.push(next_frame().await);
+                if container.scroll_top() == before {
+                    break;
+                }
+            }
+        }
+
+        let report = Report {
+            messages: self.messages,
+            users: self.users,
+            render_ms,
+            render: FrameStats::from_times(&render),
+            scroll: FrameStats::from_times(&scroll),
+        };
+        log::info!("Benchmark: {:?}", report);
+        if let (Some(window), Ok(json)) = (web_sys::window(), serde_json::to_string(&report)) {
+            let _ = js_sys::Reflect::set(&window, &"yewchatBench".into(), &JsValue::from_str(&json));
+        }
+    }
+}
+
+/// Resolves on the next animation frame with its timestamp.
+async fn next_frame() -> f64 {
+    let promise = Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            let _ = window.request_animation_frame(&resolve);
+        }
+    });
+    JsFuture::from(promise).await.ok().and_then(|t| t.as_f64()).unwrap_or_else(Date::now)
+}
+
+fn message_container() -> Option<web_sys::Element> {
+    web_sys::window()?.document()?.get_element_by_id("message-container")
+}
+
+fn on_screen(text: &str) -> bool {
+    message_container()
+        .and_then(|c| c.text_content())
+        .map(|content| content.contains(text))
+        .unwrap_or(false)
+}
+
+/// Plays the server for a benchmark run.
+pub struct BenchTransport {
+    bench: Bench,
+    inbox: Rc<Inbox>,
+}
+
+impl BenchTransport {
+    pub fn new(bench: Bench) -> Self {
+        Self {
+            bench,
+            inbox: Rc::new(Inbox::default()),
+        }
+    }
+}
+
+impl Transport for BenchTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        self.inbox.reset();
+        async { true }.boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        let frame: Value = serde_json::from_str(&frame).unwrap_or_default();
+        match frame["messageType"].as_str() {
+            Some("register") => spawn_local(self.bench.run(self.inbox.clone())),
+            // Everything there is arrives once registered
+            Some("history") => self.inbox.push(json!({ "messageType": "history", "dataArray": [] }).to_string()),
+            _ => {}
+        }
+        async { true }.boxed_local()
+    }
+
+    fn events(&self) -> Frames<'_> {
+        self.inbox.merge(stream::pending().boxed_local())
+    }
+
+    fn close(&self) {}
+}
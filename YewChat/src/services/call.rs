@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{MediaStream, RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType, RtcTrackEvent};
+use yew::Callback;
+
+use crate::services::rtc::{self, IceCandidate, PendingCandidates};
+
+/// Setting up and ending a one-to-one call, passed by the server in `call`
+/// frames to the user named in `to`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CallSignal {
+    /// Filled in by the server from the sending connection
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub payload: CallPayload,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum CallPayload {
+    /// Rings the other side
+    Offer { sdp: String },
+    /// They picked up
+    Answer { sdp: String },
+    Candidate(IceCandidate),
+    Decline,
+    /// Already in another call
+    Busy,
+    /// Sent by either side to end the call, or by the caller to stop ringing
+    HangUp,
+}
+
+/// Someone ringing us, until we pick up or they give up.
+pub struct IncomingCall {
+    pub from: String,
+    offer: String,
+    // Routes sent while it rang, for once there's a connection to add them to
+    candidates: Vec<IceCandidate>,
+}
+
+impl IncomingCall {
+    pub fn new(from: String, offer: String) -> Self {
+        Self { from, offer, candidates: Vec::new() }
+    }
+
+    pub fn add_candidate(&mut self, candidate: IceCandidate) {
+        self.candidates.push(candidate);
+    }
+}
+
+/// A video call with one other user. Dropping it hangs up and turns the
+/// camera and microphone off; telling the other side is up to the caller.
+pub struct Call {
+    pub peer: String,
+    /// Whether we rang them
+    pub outgoing: bool,
+    pub local: MediaStream,
+    pub remote: Option<MediaStream>,
+    /// When they picked up, in ms since the epoch
+    pub started: Option<f64>,
+    pub muted: bool,
+    pub camera_off: bool,
+    connection: RtcPeerConnection,
+    pending: PendingCandidates,
+    _on_candidate: Closure<dyn FnMut(RtcPeerConnectionIceEvent)>,
+    _on_track: Closure<dyn FnMut(RtcTrackEvent)>,
+}
+
+impl Call {
+    /// Rings `peer`, sending them our offer.
+    pub fn start(
+        peer: &str,
+        local: MediaStream,
+        on_signal: Callback<CallSignal>,
+        on_remote: Callback<MediaStream>,
+    ) -> Result<Self, String> {
+        let call = Self::connect(peer, local, true, on_signal.clone(), on_remote)?;
+        let connection = call.connection.clone();
+        let to = peer.to_string();
+        spawn_local(async move {
+            match rtc::local_description(&connection, RtcSdpType::Offer).await {
+                Ok(sdp) => on_signal.emit(CallSignal {
+                    from: String::new(),
+                    to,
+                    payload: CallPayload::Offer { sdp },
+                }),
+                Err(e) => log::error!("call: calling {}: {:?}", to, e),
+            }
+        });
+        Ok(call)
+    }
+
+    /// Picks up `incoming`, sending back our answer.
+    pub fn accept(
+        incoming: IncomingCall,
+        local: MediaStream,
+        on_signal: Callback<CallSignal>,
+        on_remote: Callback<MediaStream>,
+    ) -> Result<Self, String> {
+        let mut call = Self::connect(&incoming.from, local, false, on_signal.clone(), on_remote)?;
+        call.started = Some(js_sys::Date::now());
+        for candidate in &incoming.candidates {
+            call.add_candidate(candidate);
+        }
+        let connection = call.connection.clone();
+        let pending = call.pending.clone();
+        let IncomingCall { from, offer, .. } = incoming;
+        spawn_local(async move {
+            let answer = async {
+                rtc::set_remote(&connection, &pending, RtcSdpType::Offer, &offer).await?;
+                rtc::local_description(&connection, RtcSdpType::Answer).await
+            };
+            match answer.await {
+                Ok(sdp) => on_signal.emit(CallSignal {
+                    from: String::new(),
+                    to: from,
+                    payload: CallPayload::Answer { sdp },
+                }),
+                Err(e) => log::error!("call: answering {}: {:?}", from, e),
+            }
+        });
+        Ok(call)
+    }
+
+    /// The answer to our offer: they picked up.
+    pub fn answered(&mut self, sdp: String) {
+        self.started = Some(js_sys::Date::now());
+        let connection = self.connection.clone();
+        let pending = self.pending.clone();
+        spawn_local(async move {
+            if let Err(e) = rtc::set_remote(&connection, &pending, RtcSdpType::Answer, &sdp).await {
+                log::error!("call: applying answer: {:?}", e);
+            }
+        });
+    }
+
+    pub fn add_candidate(&self, candidate: &IceCandidate) {
+        candidate.apply(&self.connection, &self.pending);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        rtc::set_enabled(&self.local, true, !muted);
+    }
+
+    pub fn set_camera_off(&mut self, off: bool) {
+        self.camera_off = off;
+        rtc::set_enabled(&self.local, false, !off);
+    }
+
+    /// How long it's been going, in ms; `None` while it's still ringing.
+    pub fn duration(&self) -> Option<f64> {
+        self.started.map(|started| js_sys::Date::now() - started)
+    }
+
+    fn connect(
+        peer: &str,
+        local: MediaStream,
+        outgoing: bool,
+        on_signal: Callback<CallSignal>,
+        on_remote: Callback<MediaStream>,
+    ) -> Result<Self, String> {
+        let connection = rtc::peer_connection(&local).map_err(|e| {
+            log::error!("call: {:?}", e);
+            "This browser can't make calls".to_string()
+        })?;
+        let on_candidate = {
+            let to = peer.to_string();
+            Closure::wrap(Box::new(move |e: RtcPeerConnectionIceEvent| {
+                if let Some(candidate) = e.candidate() {
+                    on_signal.emit(CallSignal {
+                        from: String::new(),
+                        to: to.clone(),
+                        payload: CallPayload::Candidate(IceCandidate::from_rtc(&candidate)),
+                    });
+                }
+            }) as Box<dyn FnMut(RtcPeerConnectionIceEvent)>)
+        };
+        connection.set_onicecandidate(Some(on_candidate.as_ref().unchecked_ref()));
+        let on_track = Closure::wrap(Box::new(move |e: RtcTrackEvent| {
+            // Audio and video come as two tracks of the same stream
+            if let Ok(stream) = e.streams().get(0).dyn_into::<MediaStream>() {
+                on_remote.emit(stream);
+            }
+        }) as Box<dyn FnMut(RtcTrackEvent)>);
+        connection.set_ontrack(Some(on_track.as_ref().unchecked_ref()));
+        Ok(Self {
+            peer: peer.to_string(),
+            outgoing,
+            local,
+            remote: None,
+            started: None,
+            muted: false,
+            camera_off: false,
+            connection,
+            pending: rtc::pending_candidates(),
+            _on_candidate: on_candidate,
+            _on_track: on_track,
+        })
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.connection.close();
+        rtc::stop(&self.local);
+    }
+}
+
+/// A call's length as `m:ss`, or `h:mm:ss` past the hour.
+pub fn format_duration(ms: f64) -> String {
+    let seconds = (ms / 1000.0).max(0.0) as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
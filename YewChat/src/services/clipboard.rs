@@ -0,0 +1,31 @@
+use js_sys::{Function, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Copies `text` to the system clipboard via `navigator.clipboard.writeText`.
+///
+/// The async Clipboard API is still behind `web_sys_unstable_apis`, so it is
+/// looked up dynamically instead of through typed bindings.
+pub fn copy_text(text: &str) {
+    let navigator = match web_sys::window() {
+        Some(window) => JsValue::from(window.navigator()),
+        None => return,
+    };
+    let write_text = Reflect::get(&navigator, &"clipboard".into())
+        .ok()
+        .filter(|c| !c.is_undefined())
+        .and_then(|clipboard| {
+            Reflect::get(&clipboard, &"writeText".into())
+                .ok()
+                .and_then(|f| f.dyn_into::<Function>().ok())
+                .map(|f| (clipboard, f))
+        });
+
+    match write_text {
+        Some((clipboard, f)) => {
+            if let Err(e) = f.call1(&clipboard, &text.into()) {
+                log::error!("clipboard: {:?}", e);
+            }
+        }
+        None => log::debug!("Clipboard API not available"),
+    }
+}
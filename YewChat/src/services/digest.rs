@@ -0,0 +1,12 @@
+use js_sys::Uint8Array;
+use wasm_bindgen_futures::JsFuture;
+
+/// SHA-256 of `text` as lowercase hex, computed with Web Crypto. `None` where
+/// that isn't available, i.e. outside https:// and localhost.
+pub async fn sha256_hex(text: &str) -> Option<String> {
+    let subtle = web_sys::window()?.crypto().ok()?.subtle();
+    let data = Uint8Array::from(text.as_bytes());
+    let promise = subtle.digest_with_str_and_buffer_source("SHA-256", &data).ok()?;
+    let digest = JsFuture::from(promise).await.ok()?;
+    Some(Uint8Array::new(&digest).to_vec().iter().map(|b| format!("{:02x}", b)).collect())
+}
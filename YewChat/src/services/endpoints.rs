@@ -0,0 +1,129 @@
+use std::cell::RefCell;
+
+use reqwasm::http::Request;
+use serde::Deserialize;
+
+// Built in at compile time, e.g. `YEWCHAT_WS_URL=wss://chat.example.org`
+const BUILD_WS_URL: Option<&str> = option_env!("YEWCHAT_WS_URL");
+const BUILD_WT_URL: Option<&str> = option_env!("YEWCHAT_WEBTRANSPORT_URL");
+const DEFAULT_WS_URL: &str = "ws://127.0.0.1:7000";
+// Served next to index.html, so one build can be pointed at different servers
+const CONFIG_URL: &str = "/config.json";
+
+thread_local! {
+    static CURRENT: RefCell<Option<Endpoints>> = RefCell::new(None);
+}
+
+/// Where the chat server is. Each layer overrides the one before it: what the
+/// build was given, then `config.json` next to the app, then `?ws=` and `?wt=`
+/// on the page's URL. Anyone can send a link, so release builds only take
+/// the last layer for servers on the page's own host.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Endpoints {
+    pub websocket: String,
+    /// Only used by builds with the `webtransport` feature
+    pub webtransport: Option<String>,
+    /// Set when the page's URL picked the server; it then never gets a
+    /// session token or a password
+    pub from_query: bool,
+}
+
+// Anything left out keeps the value from the layer below
+#[derive(Default, Deserialize)]
+struct Overrides {
+    websocket: Option<String>,
+    webtransport: Option<String>,
+}
+
+impl Overrides {
+    async fn from_config() -> Self {
+        let response = match Request::get(CONFIG_URL).send().await {
+            Ok(response) if response.ok() => response,
+            // Not having one is normal
+            _ => return Self::default(),
+        };
+        match response.json().await {
+            Ok(overrides) => overrides,
+            Err(e) => {
+                log::warn!("Ignoring unreadable {}: {:?}", CONFIG_URL, e);
+                Self::default()
+            }
+        }
+    }
+
+    fn from_query() -> Self {
+        let search = web_sys::window()
+            .and_then(|w| w.location().search().ok())
+            .unwrap_or_default();
+        let mut overrides = Self::default();
+        for pair in search.trim_start_matches('?').split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = js_sys::decode_uri_component(value).ok().and_then(|v| v.as_string());
+            let value = value.filter(|v| !v.is_empty() && query_allowed(v));
+            match key {
+                "ws" => overrides.websocket = value,
+                "wt" => overrides.webtransport = value,
+                _ => {}
+            }
+        }
+        overrides
+    }
+
+    fn is_empty(&self) -> bool {
+        self.websocket.is_none() && self.webtransport.is_none()
+    }
+}
+
+/// Whether `?ws=` or `?wt=` may point at `url`: anywhere in debug builds,
+/// otherwise only the host the page itself came from.
+fn query_allowed(url: &str) -> bool {
+    if cfg!(debug_assertions) {
+        return true;
+    }
+    let page_host = web_sys::window().and_then(|w| w.location().host().ok());
+    let allowed = page_host.map_or(false, |page_host| url_host(url) == Some(page_host.as_str()));
+    if !allowed {
+        log::warn!("Ignoring server {} from the page's URL", url);
+    }
+    allowed
+}
+
+// `host:port` out of `scheme://host:port/path?query`
+fn url_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    rest.split(['/', '?', '#']).next().filter(|host| !host.is_empty())
+}
+
+impl Endpoints {
+    fn built_in() -> Self {
+        Self {
+            websocket: BUILD_WS_URL.unwrap_or(DEFAULT_WS_URL).to_string(),
+            webtransport: BUILD_WT_URL.map(String::from),
+            from_query: false,
+        }
+    }
+
+    fn apply(self, overrides: Overrides) -> Self {
+        let filled = |value: Option<String>| value.filter(|v| !v.is_empty());
+        Self {
+            websocket: filled(overrides.websocket).unwrap_or(self.websocket),
+            webtransport: filled(overrides.webtransport).or(self.webtransport),
+            from_query: self.from_query,
+        }
+    }
+
+    /// Works out the endpoints for this page. Runs once, before the app starts.
+    pub async fn resolve() {
+        let query = Overrides::from_query();
+        let from_query = !query.is_empty();
+        let mut endpoints = Self::built_in().apply(Overrides::from_config().await).apply(query);
+        endpoints.from_query = from_query;
+        log::debug!("Using {:?}", endpoints);
+        CURRENT.with(|c| *c.borrow_mut() = Some(endpoints));
+    }
+
+    /// The endpoints worked out at startup, or the built-in ones before that.
+    pub fn current() -> Self {
+        CURRENT.with(|c| c.borrow().clone()).unwrap_or_else(Self::built_in)
+    }
+}
@@ -0,0 +1,40 @@
+use js_sys::{Promise, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::components::location::Location;
+
+// `GeolocationPositionError.code` values
+const PERMISSION_DENIED: u32 = 1;
+const TIMEOUT: u32 = 3;
+
+/// Asks the browser where we are. The first call shows a permission prompt,
+/// and like the camera it only works on secure pages.
+pub async fn current_position() -> Result<Location, String> {
+    let geolocation = web_sys::window()
+        .and_then(|window| window.navigator().geolocation().ok())
+        .ok_or_else(|| "This browser can't share its location".to_string())?;
+    let request = Promise::new(&mut |resolve, reject| {
+        if let Err(e) = geolocation.get_current_position_with_error_callback(&resolve, Some(&reject)) {
+            let _ = reject.call1(&JsValue::NULL, &e);
+        }
+    });
+    let position = JsFuture::from(request).await.map_err(|e| {
+        match number(&e, "code") as u32 {
+            PERMISSION_DENIED => "Location access was blocked",
+            TIMEOUT => "Finding your location took too long",
+            _ => "Your location isn't available right now",
+        }
+        .to_string()
+    })?;
+    let coords = Reflect::get(&position, &JsValue::from_str("coords")).unwrap_or(JsValue::UNDEFINED);
+    Ok(Location {
+        lat: number(&coords, "latitude"),
+        lon: number(&coords, "longitude"),
+        accuracy: number(&coords, "accuracy"),
+    })
+}
+
+fn number(object: &JsValue, key: &str) -> f64 {
+    Reflect::get(object, &JsValue::from_str(key)).ok().and_then(|v| v.as_f64()).unwrap_or_default()
+}
@@ -0,0 +1,149 @@
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{HtmlElement, KeyboardEvent, Window};
+use yew::Callback;
+
+/// Listens for key presses anywhere on the page, for shortcuts that work
+/// whatever has focus. The listener is removed when this is dropped.
+pub struct HotkeyListener {
+    window: Window,
+    closure: Closure<dyn Fn(KeyboardEvent)>,
+}
+
+impl HotkeyListener {
+    pub fn new(on_key: Callback<KeyboardEvent>) -> Option<Self> {
+        let window = web_sys::window()?;
+        let closure = Closure::wrap(Box::new(move |e: KeyboardEvent| on_key.emit(e)) as Box<dyn Fn(KeyboardEvent)>);
+        window
+            .add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref())
+            .ok()?;
+        Some(Self { window, closure })
+    }
+}
+
+impl Drop for HotkeyListener {
+    fn drop(&mut self) {
+        let _ = self
+            .window
+            .remove_event_listener_with_callback("keydown", self.closure.as_ref().unchecked_ref());
+    }
+}
+
+/// Where a shortcut works, which is how the cheat sheet groups them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Area {
+    Anywhere,
+    Messages,
+    Composer,
+    Dialogs,
+}
+
+impl Area {
+    pub const ALL: [Area; 4] = [Area::Anywhere, Area::Messages, Area::Composer, Area::Dialogs];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Area::Anywhere => "Anywhere",
+            Area::Messages => "Message list",
+            Area::Composer => "Composer",
+            Area::Dialogs => "Dialogs and popups",
+        }
+    }
+}
+
+/// A key the app responds to. Handlers check events against these, and the
+/// cheat sheet lists [`SHORTCUTS`], so the two can't disagree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shortcut {
+    /// As in `KeyboardEvent.key`
+    pub key: &'static str,
+    /// Ctrl, or ⌘ on a Mac
+    pub ctrl: bool,
+    pub area: Area,
+    pub description: &'static str,
+}
+
+impl Shortcut {
+    const fn new(key: &'static str, area: Area, description: &'static str) -> Self {
+        Self { key, ctrl: false, area, description }
+    }
+
+    const fn with_ctrl(self) -> Self {
+        Self { ctrl: true, ..self }
+    }
+
+    pub fn matches(&self, e: &KeyboardEvent) -> bool {
+        let ctrl = e.ctrl_key() || e.meta_key();
+        if self.ctrl {
+            // Shift turns letters uppercase, which doesn't make it a different shortcut
+            ctrl && e.key().eq_ignore_ascii_case(self.key)
+        } else {
+            // Shift isn't checked, as some keys such as `?` need it
+            !ctrl && !e.alt_key() && e.key() == self.key
+        }
+    }
+
+    /// How the key is written on the cheat sheet, e.g. `Ctrl+K` or `↑`.
+    pub fn label(&self) -> String {
+        let key = match self.key {
+            "ArrowUp" => "↑",
+            "ArrowDown" => "↓",
+            "PageUp" => "Page Up",
+            "PageDown" => "Page Down",
+            "Escape" => "Esc",
+            key => key,
+        };
+        if self.ctrl {
+            format!("Ctrl+{}", key.to_uppercase())
+        } else {
+            key.to_string()
+        }
+    }
+}
+
+pub const COMMAND_PALETTE: Shortcut = Shortcut::new("k", Area::Anywhere, "Open the command palette").with_ctrl();
+pub const CHEAT_SHEET: Shortcut = Shortcut::new("?", Area::Anywhere, "Show these shortcuts");
+pub const PREVIOUS_MESSAGE: Shortcut = Shortcut::new("ArrowUp", Area::Messages, "Select the previous message");
+pub const NEXT_MESSAGE: Shortcut = Shortcut::new("ArrowDown", Area::Messages, "Select the next message");
+pub const PAGE_UP: Shortcut = Shortcut::new("PageUp", Area::Messages, "Move up a page of messages");
+pub const PAGE_DOWN: Shortcut = Shortcut::new("PageDown", Area::Messages, "Move down a page of messages");
+pub const FIRST_MESSAGE: Shortcut = Shortcut::new("Home", Area::Messages, "Select the oldest loaded message");
+pub const LAST_MESSAGE: Shortcut = Shortcut::new("End", Area::Messages, "Select the newest message");
+pub const REPLY: Shortcut = Shortcut::new("r", Area::Messages, "Reply to the selected message");
+pub const REACT: Shortcut = Shortcut::new("e", Area::Messages, "React to or act on the selected message");
+pub const COPY: Shortcut = Shortcut::new("c", Area::Messages, "Copy the selected message");
+pub const DESELECT: Shortcut = Shortcut::new("Escape", Area::Messages, "Clear the selection");
+pub const SEND: Shortcut = Shortcut::new("Enter", Area::Composer, "Send the message");
+pub const EDIT_LAST: Shortcut = Shortcut::new("ArrowUp", Area::Composer, "Edit your last message, when the composer is empty");
+pub const OLDER_SENT: Shortcut = Shortcut::new("ArrowUp", Area::Composer, "Bring back what you sent before");
+pub const NEWER_SENT: Shortcut = Shortcut::new("ArrowDown", Area::Composer, "Step forward again through what you sent");
+pub const CLOSE: Shortcut = Shortcut::new("Escape", Area::Dialogs, "Close it");
+
+/// Every shortcut, in the order the cheat sheet lists them.
+pub const SHORTCUTS: &[Shortcut] = &[
+    COMMAND_PALETTE,
+    CHEAT_SHEET,
+    PREVIOUS_MESSAGE,
+    NEXT_MESSAGE,
+    PAGE_UP,
+    PAGE_DOWN,
+    FIRST_MESSAGE,
+    LAST_MESSAGE,
+    REPLY,
+    REACT,
+    COPY,
+    DESELECT,
+    SEND,
+    EDIT_LAST,
+    OLDER_SENT,
+    NEWER_SENT,
+    CLOSE,
+];
+
+/// Whether the key went to somewhere text is typed, where plain keys are just text.
+pub fn is_typing(e: &KeyboardEvent) -> bool {
+    let target = match e.target().and_then(|t| t.dyn_into::<HtmlElement>().ok()) {
+        Some(target) => target,
+        None => return false,
+    };
+    matches!(target.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT") || target.is_content_editable()
+}
@@ -0,0 +1,407 @@
+use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
+
+use futures::{
+    future::{self, LocalBoxFuture},
+    lock::Mutex,
+    stream::{self, SplitSink, SplitStream},
+    FutureExt, SinkExt, StreamExt,
+};
+use reqwasm::websocket::{futures::WebSocket, Message};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::services::{
+    storage,
+    transport::{Frames, Inbox, Transport},
+    websocket::connect,
+};
+
+// Conservative; servers cut lines at 512 bytes including the command and prefix
+const MAX_TEXT_LEN: usize = 400;
+
+/// An IRC channel reached through a websocket-to-IRC gateway such as webircgateway.
+/// Kept in localStorage under `yewchat.irc`, e.g.
+/// `{"gateway":"wss://irc.example.org/webirc/websocket/","channel":"#yewchat"}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IrcConfig {
+    pub gateway: String,
+    pub channel: String,
+    /// Channel key for `+k` channels
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Server password, sent as `PASS`
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl IrcConfig {
+    pub const STORAGE_KEY: &'static str = "irc";
+
+    pub fn load() -> Option<Self> {
+        storage::load(Self::STORAGE_KEY)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    message_type: String,
+    #[serde(default)]
+    data: Option<String>,
+    #[serde(default)]
+    client_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RenameRequest {
+    to: String,
+}
+
+/// One line from the server, e.g. `@time=… :nick!user@host PRIVMSG #chan :hello`.
+struct Line<'a> {
+    time: Option<f64>,
+    source: Option<&'a str>,
+    command: &'a str,
+    params: Vec<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    fn parse(mut raw: &'a str) -> Option<Self> {
+        let mut time = None;
+        if let Some(rest) = raw.strip_prefix('@') {
+            let (tags, rest) = rest.split_once(' ')?;
+            time = tags
+                .split(';')
+                .find_map(|tag| tag.strip_prefix("time="))
+                .map(js_sys::Date::parse)
+                .filter(|t| !t.is_nan());
+            raw = rest;
+        }
+        let mut source = None;
+        if let Some(rest) = raw.strip_prefix(':') {
+            let (prefix, rest) = rest.split_once(' ')?;
+            source = Some(prefix);
+            raw = rest;
+        }
+        let (middle, trailing) = match raw.split_once(" :") {
+            Some((middle, trailing)) => (middle, Some(trailing)),
+            None => (raw, None),
+        };
+        let mut words = middle.split(' ').filter(|w| !w.is_empty());
+        let command = words.next()?;
+        let mut params: Vec<&str> = words.collect();
+        params.extend(trailing);
+        Some(Self { time, source, command, params })
+    }
+
+    /// Nickname part of the source, `nick` in `nick!user@host`.
+    fn nick(&self) -> &'a str {
+        let source = self.source.unwrap_or_default();
+        source.split('!').next().unwrap_or(source)
+    }
+
+    fn last(&self) -> &'a str {
+        self.params.last().copied().unwrap_or_default()
+    }
+}
+
+/// What we know about our connection to the channel.
+struct Channel {
+    config: IrcConfig,
+    inbox: Rc<Inbox>,
+    nick: String,
+    // Welcomed by the server, so nickname errors are about a rename from here on
+    registered: bool,
+    names: BTreeSet<String>,
+    // NAMES replies are collected until the end-of-list numeric
+    incoming_names: BTreeSet<String>,
+}
+
+impl Channel {
+    fn deliver(&self, frame: Value) {
+        self.inbox.push(frame.to_string());
+    }
+
+    fn is_channel(&self, target: &str) -> bool {
+        target.eq_ignore_ascii_case(&self.config.channel)
+    }
+
+    /// Whether the `index`th parameter names our channel.
+    fn on_channel(&self, line: &Line, index: usize) -> bool {
+        matches!(line.params.get(index), Some(target) if self.is_channel(target))
+    }
+
+    fn users(&self) {
+        let mut users: Vec<&String> = self.names.iter().collect();
+        users.sort_by_key(|name| name.to_lowercase());
+        self.deliver(json!({ "messageType": "users", "dataArray": users }));
+    }
+
+    /// IRC lines for a frame the chat wants to send to the server.
+    fn outgoing(&mut self, frame: Frame) -> Vec<String> {
+        let data = frame.data.unwrap_or_default();
+        match frame.message_type.as_str() {
+            "register" => {
+                self.nick = data;
+                self.registered = false;
+                let mut lines: Vec<String> = self.config.password.iter().map(|p| format!("PASS {}", p)).collect();
+                lines.push(format!("NICK {}", self.nick));
+                lines.push(format!("USER {} 0 * :{}", self.nick, self.nick));
+                lines
+            }
+            "message" => {
+                let now = js_sys::Date::now();
+                // Servers don't echo our own messages, so confirm the optimistic copy here
+                let message = json!({
+                    "id": format!("{}-{}", self.nick, now),
+                    "from": self.nick,
+                    "message": data,
+                    "time": now as i64,
+                    "clientId": frame.client_id,
+                });
+                self.deliver(json!({ "messageType": "message", "data": message.to_string() }));
+                data.lines()
+                    .filter(|l| !l.is_empty())
+                    .flat_map(|l| chunks(l, MAX_TEXT_LEN))
+                    .map(|l| format!("PRIVMSG {} :{}", self.config.channel, l))
+                    .collect()
+            }
+            "rename" => match serde_json::from_str::<RenameRequest>(&data) {
+                Ok(rename) => vec![format!("NICK {}", rename.to)],
+                Err(_) => Vec::new(),
+            },
+            // Round-trips through the server so link quality still means something
+            "ping" => vec![format!("PING :{}", data)],
+            "history" => {
+                // IRC keeps no history; an empty page tells the chat there's nothing older
+                self.deliver(json!({ "messageType": "history", "dataArray": [] }));
+                Vec::new()
+            }
+            other => {
+                log::debug!("irc: no equivalent for {}", other);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Turns a server line into frames for the chat, returning any line to send back.
+    fn incoming(&mut self, line: &Line) -> Option<String> {
+        match line.command {
+            "PING" => return Some(format!("PONG :{}", line.last())),
+            "PONG" => self.deliver(json!({ "messageType": "pong", "data": line.last() })),
+            // RPL_WELCOME: the server may have shortened our nick
+            "001" => {
+                self.registered = true;
+                if let Some(nick) = line.params.first() {
+                    self.nick = nick.to_string();
+                }
+                return Some(match &self.config.key {
+                    Some(key) => format!("JOIN {} {}", self.config.channel, key),
+                    None => format!("JOIN {}", self.config.channel),
+                });
+            }
+            // ERR_ERRONEUSNICKNAME, ERR_NICKNAMEINUSE
+            "432" | "433" => {
+                let kind = if self.registered { "renameerror" } else { "registererror" };
+                let nick = line.params.get(1).copied().unwrap_or_default();
+                self.deliver(json!({ "messageType": kind, "data": format!("{}: {}", nick, line.last()) }));
+            }
+            // RPL_NAMREPLY
+            "353" if self.on_channel(line, 2) => {
+                let names = line.last().split(' ').filter(|n| !n.is_empty());
+                self.incoming_names
+                    .extend(names.map(|n| n.trim_start_matches(|c| "~&@%+".contains(c)).to_string()));
+            }
+            // RPL_ENDOFNAMES
+            "366" if self.on_channel(line, 1) => {
+                self.names = std::mem::take(&mut self.incoming_names);
+                self.users();
+            }
+            "JOIN" if self.on_channel(line, 0) => {
+                self.names.insert(line.nick().to_string());
+                self.users();
+            }
+            "PART" if self.on_channel(line, 0) => {
+                self.names.remove(line.nick());
+                self.users();
+            }
+            "KICK" if self.on_channel(line, 0) => {
+                if let Some(nick) = line.params.get(1) {
+                    self.names.remove(*nick);
+                    self.users();
+                }
+            }
+            "QUIT" => {
+                if self.names.remove(line.nick()) {
+                    self.users();
+                }
+            }
+            "NICK" => {
+                let (from, to) = (line.nick().to_string(), line.last().to_string());
+                if from == self.nick {
+                    self.nick = to.clone();
+                }
+                if self.names.remove(&from) {
+                    self.names.insert(to.clone());
+                }
+                self.deliver(json!({ "messageType": "rename", "data": json!({ "from": from, "to": to }).to_string() }));
+            }
+            "PRIVMSG" if self.on_channel(line, 0) => {
+                let text = line.last();
+                let text = match text.strip_prefix("\u{1}ACTION ") {
+                    Some(action) => format!("* {} {}", line.nick(), action.trim_end_matches('\u{1}')),
+                    // Other CTCP requests aren't meant to be shown
+                    None if text.starts_with('\u{1}') => return None,
+                    None => text.to_string(),
+                };
+                let time = line.time.unwrap_or_else(js_sys::Date::now);
+                let message = json!({
+                    "id": format!("{}-{}", line.nick(), time),
+                    "from": line.nick(),
+                    "message": text,
+                    "time": time as i64,
+                });
+                self.deliver(json!({ "messageType": "message", "data": message.to_string() }));
+            }
+            "ERROR" => log::error!("irc: {}", line.last()),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// An IRC channel as a chat backend.
+pub struct IrcTransport {
+    gateway: String,
+    channel: RefCell<Channel>,
+    inbox: Rc<Inbox>,
+    write: Mutex<Option<SplitSink<WebSocket, Message>>>,
+    read: RefCell<Option<SplitStream<WebSocket>>>,
+}
+
+impl IrcTransport {
+    /// The channel saved in localStorage, if there is one.
+    pub fn configured() -> Option<Self> {
+        IrcConfig::load().map(Self::new)
+    }
+
+    pub fn new(config: IrcConfig) -> Self {
+        let inbox = Rc::new(Inbox::default());
+        Self {
+            gateway: config.gateway.clone(),
+            channel: RefCell::new(Channel {
+                config,
+                inbox: inbox.clone(),
+                nick: String::new(),
+                registered: false,
+                names: BTreeSet::new(),
+                incoming_names: BTreeSet::new(),
+            }),
+            inbox,
+            write: Mutex::new(None),
+            read: RefCell::new(None),
+        }
+    }
+
+    async fn write_lines(&self, lines: Vec<String>) -> bool {
+        let mut write = self.write.lock().await;
+        let write = match write.as_mut() {
+            Some(write) => write,
+            None => return false,
+        };
+        for line in lines {
+            if let Err(e) = write.send(Message::Text(line)).await {
+                log::error!("irc send: {:?}", e);
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Transport for IrcTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let (write, read) = match connect(&self.gateway).await {
+                Some(ws) => ws.split(),
+                None => return false,
+            };
+            *self.write.lock().await = Some(write);
+            *self.read.borrow_mut() = Some(read);
+            self.inbox.reset();
+            true
+        }
+        .boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let lines = match serde_json::from_str::<Frame>(&frame) {
+                Ok(frame) => self.channel.borrow_mut().outgoing(frame),
+                Err(e) => {
+                    log::error!("irc: unreadable frame {}: {:?}", frame, e);
+                    return true;
+                }
+            };
+            self.write_lines(lines).await
+        }
+        .boxed_local()
+    }
+
+    fn events(&self) -> Frames<'_> {
+        let read = self.read.borrow_mut().take();
+        // Lines only change what we know about the channel; the frames that
+        // results in come out of the inbox
+        let lines = stream::iter(read)
+            .flatten()
+            .then(move |msg| async move {
+                let text = match msg {
+                    Ok(Message::Text(text)) => text,
+                    Ok(Message::Bytes(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
+                    Err(e) => {
+                        log::error!("irc: {:?}", e);
+                        return;
+                    }
+                };
+                // Gateways may batch several lines into one websocket message
+                let replies: Vec<String> = text
+                    .lines()
+                    .map(|l| l.trim_end_matches('\r'))
+                    .filter(|l| !l.is_empty())
+                    .filter_map(|raw| {
+                        log::debug!("From IRC: {}", raw);
+                        Line::parse(raw).and_then(|line| self.channel.borrow_mut().incoming(&line))
+                    })
+                    .collect();
+                if !replies.is_empty() {
+                    self.write_lines(replies).await;
+                }
+            })
+            .filter_map(|()| future::ready(None));
+        self.inbox.merge(lines.boxed_local())
+    }
+
+    fn close(&self) {
+        self.read.borrow_mut().take();
+        if let Some(mut write) = self.write.try_lock() {
+            write.take();
+        }
+    }
+}
+
+/// Splits `text` into pieces of at most `max` bytes without cutting a character.
+fn chunks(text: &str, max: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > max {
+        let mut end = max;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (piece, tail) = rest.split_at(end);
+        pieces.push(piece);
+        rest = tail;
+    }
+    pieces.push(rest);
+    pieces
+}
@@ -0,0 +1,134 @@
+use std::cell::{Cell, RefCell};
+
+use futures::{future::LocalBoxFuture, stream, FutureExt, StreamExt};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::services::{
+    sse::{self, Ended},
+    transport::{Frames, Transport},
+};
+
+#[derive(Deserialize)]
+struct Opened {
+    id: String,
+}
+
+/// `ws://host:7000/?token=…` → (`http://host:7000/poll`, `?token=…`)
+fn poll_url(socket_url: &str) -> (String, String) {
+    let (address, query) = match socket_url.split_once('?') {
+        Some((address, query)) => (address, format!("?{}", query)),
+        None => (socket_url, String::new()),
+    };
+    let address = address
+        .replacen("wss://", "https://", 1)
+        .replacen("ws://", "http://", 1);
+    (format!("{}/poll", address.trim_end_matches('/')), query)
+}
+
+/// One held-open GET's worth of frames, `None` once the server has dropped the session.
+async fn poll(session: &str) -> Option<Vec<String>> {
+    let response = Request::get(session).send().await.ok()?;
+    if !response.ok() {
+        return None;
+    }
+    serde_json::from_str(&response.text().await.ok()?).ok()
+}
+
+/// The chat server over plain HTTP, for networks that block websockets: each
+/// frame is POSTed, and what arrives comes back as Server-Sent Events, or through
+/// GETs held open by the server where the event stream doesn't get through.
+pub struct HttpTransport {
+    base: String,
+    query: String,
+    session: RefCell<Option<String>>,
+    // Decided once per tab: off for good after a stream turns out to be buffered
+    streaming: Cell<bool>,
+}
+
+impl HttpTransport {
+    pub fn new(socket_url: &str) -> Self {
+        let (base, query) = poll_url(socket_url);
+        Self {
+            base,
+            query,
+            session: RefCell::new(None),
+            streaming: Cell::new(sse::supported()),
+        }
+    }
+}
+
+impl Transport for HttpTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let response = match Request::post(&format!("{}{}", self.base, self.query)).send().await {
+                Ok(response) if response.ok() => response,
+                _ => return false,
+            };
+            let opened = response.text().await.ok().and_then(|body| serde_json::from_str::<Opened>(&body).ok());
+            match opened {
+                Some(opened) => {
+                    *self.session.borrow_mut() = Some(format!("{}/{}", self.base, opened.id));
+                    true
+                }
+                None => false,
+            }
+        }
+        .boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let session = match self.session.borrow().clone() {
+                Some(session) => session,
+                None => return false,
+            };
+            let sent = Request::post(&session)
+                .header("Content-Type", "text/plain")
+                .body(frame)
+                .send()
+                .await;
+            matches!(sent, Ok(ref response) if response.ok())
+        }
+        .boxed_local()
+    }
+
+    fn events(&self) -> Frames<'_> {
+        let session = self.session.borrow().clone();
+        async move {
+            let session = match session {
+                Some(session) => session,
+                None => return stream::empty().boxed_local(),
+            };
+            if self.streaming.get() {
+                match sse::open(&format!("{}/events", session)).await {
+                    Ok(events) => return events,
+                    Err(Ended::Closed) => return stream::empty().boxed_local(),
+                    Err(Ended::Buffered) => {
+                        log::warn!("Event stream is being buffered, polling instead");
+                        self.streaming.set(false);
+                    }
+                }
+            }
+            stream::unfold(session, |session| async move {
+                let frames = poll(&session).await?;
+                Some((stream::iter(frames), session))
+            })
+            .flatten()
+            .boxed_local()
+        }
+        .into_stream()
+        .flatten()
+        .boxed_local()
+    }
+
+    fn close(&self) {
+        // Let the server forget us now rather than on timeout
+        if let Some(session) = self.session.borrow_mut().take() {
+            spawn_local(async move {
+                let _ = Request::delete(&session).send().await;
+            });
+        }
+    }
+}
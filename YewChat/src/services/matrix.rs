@@ -0,0 +1,319 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use futures::{
+    future::{self, LocalBoxFuture},
+    stream, FutureExt, StreamExt,
+};
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::services::{
+    storage,
+    transport::{Frames, Inbox, Transport},
+};
+
+// How long the homeserver may hold each /sync open when nothing happens
+const SYNC_TIMEOUT_MS: u32 = 30_000;
+
+/// Which Matrix room to use instead of our own server. Kept in localStorage
+/// under `yewchat.matrix`, e.g.
+/// `{"homeserver":"https://matrix.org","roomId":"!abc:matrix.org","accessToken":"syt_…"}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub room_id: String,
+    pub access_token: String,
+}
+
+impl MatrixConfig {
+    pub const STORAGE_KEY: &'static str = "matrix";
+
+    pub fn load() -> Option<Self> {
+        storage::load(Self::STORAGE_KEY)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    message_type: String,
+    #[serde(default)]
+    data: Option<String>,
+    #[serde(default)]
+    client_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HistoryRequest {
+    limit: usize,
+}
+
+/// Where the next page of older messages starts.
+enum Page {
+    Latest,
+    From(String),
+    Done,
+}
+
+/// A Matrix room as a chat backend, through the client-server API.
+pub struct MatrixTransport {
+    config: MatrixConfig,
+    // The Matrix ID the access token belongs to, shown under the name we registered with
+    me: RefCell<Option<String>>,
+    nick: RefCell<String>,
+    // Display names by Matrix ID
+    names: RefCell<HashMap<String, String>>,
+    page: RefCell<Page>,
+    // Where the next /sync picks up
+    since: RefCell<Option<String>>,
+    inbox: Inbox,
+}
+
+impl MatrixTransport {
+    /// The room saved in localStorage, if there is one.
+    pub fn configured() -> Option<Self> {
+        MatrixConfig::load().map(Self::new)
+    }
+
+    pub fn new(config: MatrixConfig) -> Self {
+        Self {
+            config,
+            me: RefCell::new(None),
+            nick: RefCell::new(String::new()),
+            names: RefCell::new(HashMap::new()),
+            page: RefCell::new(Page::Latest),
+            since: RefCell::new(None),
+            inbox: Inbox::default(),
+        }
+    }
+
+    fn deliver(&self, frame: Value) {
+        self.inbox.push(frame.to_string());
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/_matrix/client/v3{}", self.config.homeserver.trim_end_matches('/'), path)
+    }
+
+    fn room_path(&self, rest: &str) -> String {
+        format!("/rooms/{}{}", encode(&self.config.room_id), rest)
+    }
+
+    async fn call(&self, request: Request) -> Option<Value> {
+        let response = request
+            .header("Authorization", &format!("Bearer {}", self.config.access_token))
+            .send()
+            .await
+            .map_err(|e| log::error!("matrix: {:?}", e))
+            .ok()?;
+        let body = response.text().await.ok()?;
+        if !response.ok() {
+            log::error!("matrix: {} {}", response.status(), body);
+            return None;
+        }
+        serde_json::from_str(&body).ok()
+    }
+
+    async fn get(&self, path: &str) -> Option<Value> {
+        self.call(Request::get(&self.url(path))).await
+    }
+
+    async fn put(&self, path: &str, body: Value) -> Option<Value> {
+        let request = Request::put(&self.url(path))
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+        self.call(request).await
+    }
+
+    /// The name `user_id` goes by in the chat: us under our nickname, others
+    /// by display name, falling back to the localpart of their ID.
+    fn name_of(&self, user_id: &str) -> String {
+        if self.me.borrow().as_deref() == Some(user_id) {
+            return self.nick.borrow().clone();
+        }
+        self.names.borrow().get(user_id).cloned().unwrap_or_else(|| localpart(user_id))
+    }
+
+    /// Fetches the member list and hands it to the chat as a `users` frame.
+    async fn refresh_members(&self) {
+        let joined = match self.get(&self.room_path("/joined_members")).await {
+            Some(joined) => joined,
+            None => return,
+        };
+        let names: HashMap<String, String> = joined["joined"]
+            .as_object()
+            .map(|members| {
+                members
+                    .iter()
+                    .map(|(id, member)| {
+                        let name = member["display_name"].as_str().map(String::from).unwrap_or_else(|| localpart(id));
+                        (id.clone(), name)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        *self.names.borrow_mut() = names;
+        let mut users: Vec<String> = self.names.borrow().keys().map(|id| self.name_of(id)).collect();
+        users.sort_by_key(|name| name.to_lowercase());
+        self.deliver(json!({ "messageType": "users", "dataArray": users }));
+    }
+
+    /// A timeline event as the JSON of a chat message, if it's one we can show.
+    fn message(&self, event: &Value) -> Option<String> {
+        if event["type"] != "m.room.message" {
+            return None;
+        }
+        let body = event["content"]["body"].as_str()?;
+        let message = json!({
+            "id": event["event_id"],
+            "from": self.name_of(event["sender"].as_str()?),
+            "message": body,
+            "time": event["origin_server_ts"],
+            "clientId": event["unsigned"]["transaction_id"],
+        });
+        Some(message.to_string())
+    }
+
+    async fn history(&self, limit: usize) {
+        let from = match &*self.page.borrow() {
+            Page::Latest => Some(String::new()),
+            Page::From(token) => Some(format!("&from={}", encode(token))),
+            Page::Done => None,
+        };
+        let mut page = Vec::new();
+        if let Some(from) = from {
+            let path = self.room_path(&format!("/messages?dir=b&limit={}{}", limit, from));
+            if let Some(response) = self.get(&path).await {
+                let events = response["chunk"].as_array().cloned().unwrap_or_default();
+                // Newest first on the wire; the chat wants them oldest first
+                page = events.iter().rev().filter_map(|e| self.message(e)).collect();
+                *self.page.borrow_mut() = match response["end"].as_str() {
+                    Some(end) if !events.is_empty() => Page::From(end.to_string()),
+                    _ => Page::Done,
+                };
+            }
+        }
+        self.deliver(json!({ "messageType": "history", "dataArray": page }));
+    }
+
+    /// Carries out what the chat asked the server for, as far as Matrix has an
+    /// equivalent; `false` if a message couldn't be posted.
+    async fn handle(&self, frame: Frame) -> bool {
+        let data = frame.data.unwrap_or_default();
+        match frame.message_type.as_str() {
+            "register" => {
+                *self.nick.borrow_mut() = data;
+                self.refresh_members().await;
+            }
+            "message" => {
+                // The client ID doubles as the transaction ID, so retries aren't posted twice
+                let txn = frame.client_id.unwrap_or_else(|| js_sys::Date::now().to_string());
+                let path = self.room_path(&format!("/send/m.room.message/{}", encode(&txn)));
+                return self.put(&path, json!({ "msgtype": "m.text", "body": data })).await.is_some();
+            }
+            "history" => {
+                let limit = serde_json::from_str::<HistoryRequest>(&data).map(|r| r.limit).unwrap_or(50);
+                self.history(limit).await;
+            }
+            "ping" => {
+                if self.get("/account/whoami").await.is_some() {
+                    self.deliver(json!({ "messageType": "pong", "data": data }));
+                }
+            }
+            other => log::debug!("matrix: no equivalent for {}", other),
+        }
+        true
+    }
+
+    /// One round of following the room's timeline, `false` if the homeserver couldn't be reached.
+    async fn sync(&self) -> bool {
+        let filter = encode(&json!({ "room": { "rooms": [self.config.room_id], "timeline": { "limit": 1 } } }).to_string());
+        let since = self.since.borrow().clone();
+        let path = match &since {
+            Some(since) => format!("/sync?filter={}&timeout={}&since={}", filter, SYNC_TIMEOUT_MS, encode(since)),
+            // Only after a starting point here; history is fetched separately
+            None => format!("/sync?filter={}&timeout=0", filter),
+        };
+        let response = match self.get(&path).await {
+            Some(response) => response,
+            None => return false,
+        };
+
+        if since.is_some() {
+            let events = response["rooms"]["join"][&self.config.room_id]["timeline"]["events"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            let mut members_changed = false;
+            for event in events.iter() {
+                members_changed |= event["type"] == "m.room.member";
+                if let Some(message) = self.message(event) {
+                    self.deliver(json!({ "messageType": "message", "data": message }));
+                }
+            }
+            if members_changed {
+                self.refresh_members().await;
+            }
+        }
+        if let Some(next) = response["next_batch"].as_str() {
+            *self.since.borrow_mut() = Some(next.to_string());
+        }
+        true
+    }
+}
+
+impl Transport for MatrixTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        async move {
+            // Also checks the access token is still good
+            let me = match self.get("/account/whoami").await {
+                Some(me) => me,
+                None => return false,
+            };
+            *self.me.borrow_mut() = me["user_id"].as_str().map(String::from);
+            // The sync token is kept across reconnects, so nothing is missed in between
+            if self.since.borrow().is_none() && !self.sync().await {
+                return false;
+            }
+            self.inbox.reset();
+            true
+        }
+        .boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        async move {
+            match serde_json::from_str::<Frame>(&frame) {
+                Ok(frame) => self.handle(frame).await,
+                Err(e) => {
+                    log::error!("matrix: unreadable frame {}: {:?}", frame, e);
+                    true
+                }
+            }
+        }
+        .boxed_local()
+    }
+
+    fn events(&self) -> Frames<'_> {
+        // Each sync only updates what we know; the frames that results in come out of the inbox
+        let syncing = stream::unfold((), move |()| async move { self.sync().await.then_some(((), ())) })
+            .filter_map(|()| future::ready(None));
+        self.inbox.merge(syncing.boxed_local())
+    }
+
+    fn close(&self) {
+        // Nothing stays open between requests
+    }
+}
+
+fn encode(component: &str) -> String {
+    js_sys::encode_uri_component(component).into()
+}
+
+/// `@ana:example.org` → `ana`
+fn localpart(user_id: &str) -> String {
+    let id = user_id.trim_start_matches('@');
+    id.split(':').next().unwrap_or(id).to_string()
+}
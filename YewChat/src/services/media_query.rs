@@ -0,0 +1,7 @@
+/// Whether the browser currently matches a CSS media query such as
+/// `(prefers-contrast: more)`; `false` where `matchMedia` isn't available.
+pub fn matches(query: &str) -> bool {
+    web_sys::window()
+        .and_then(|window| window.match_media(query).ok().flatten())
+        .map_or(false, |list| list.matches())
+}
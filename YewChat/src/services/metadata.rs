@@ -0,0 +1,51 @@
+use futures::channel::oneshot;
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, CanvasRenderingContext2d, File, HtmlCanvasElement, ImageBitmap};
+
+// Photos a canvas can write back out. GIFs are left alone, as redrawing keeps only their first frame
+const REENCODED_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+// High enough that the second encode isn't visible
+const QUALITY: f64 = 0.92;
+
+/// Whether `file` is a photo whose metadata [`strip`] can take out.
+pub fn strippable(file: &File) -> bool {
+    REENCODED_TYPES.contains(&file.type_().as_str())
+}
+
+async fn encode(canvas: &HtmlCanvasElement, mime: &str) -> Option<Blob> {
+    let (tx, rx) = oneshot::channel();
+    let done = Closure::once_into_js(move |blob: Option<Blob>| {
+        let _ = tx.send(blob);
+    });
+    canvas
+        .to_blob_with_type_and_encoder_options(done.unchecked_ref(), mime, &QUALITY.into())
+        .ok()?;
+    rx.await.ok()?
+}
+
+/// `file` drawn onto a canvas and encoded again, which leaves behind its EXIF
+/// metadata: where the photo was taken, the device, when. The browser applies
+/// the orientation the metadata gave while drawing, so the photo still stands
+/// the right way up. `None` if it couldn't be redrawn.
+pub async fn strip(file: &File) -> Option<File> {
+    let window = web_sys::window()?;
+    let bitmap: ImageBitmap = JsFuture::from(window.create_image_bitmap_with_blob(file).ok()?)
+        .await
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    let canvas: HtmlCanvasElement = window.document()?.create_element("canvas").ok()?.dyn_into().ok()?;
+    canvas.set_width(bitmap.width());
+    canvas.set_height(bitmap.height());
+    let context: CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+    context.draw_image_with_image_bitmap(&bitmap, 0.0, 0.0).ok()?;
+    bitmap.close();
+
+    let blob = encode(&canvas, &file.type_()).await?;
+    let options = Object::new();
+    Reflect::set(&options, &"type".into(), &blob.type_().into()).ok()?;
+    Reflect::set(&options, &"lastModified".into(), &file.last_modified().into()).ok()?;
+    File::new_with_blob_sequence_and_options(&Array::of1(&blob), &file.name(), options.unchecked_ref()).ok()
+}
@@ -1,2 +1,31 @@
 pub mod websocket;
 pub mod event_bus;
+pub mod clipboard;
+pub mod viewport;
+pub mod storage;
+pub mod shared_socket;
+pub mod notify;
+pub mod media_query;
+pub mod hotkeys;
+pub mod account;
+pub mod rejoin;
+pub mod timezone;
+pub mod tracking;
+pub mod upload;
+pub mod version;
+pub mod rtc;
+pub mod voice;
+pub mod call;
+pub mod geolocation;
+pub mod metadata;
+pub mod digest;
+pub mod matrix;
+pub mod irc;
+pub mod long_poll;
+pub mod sse;
+#[cfg(feature = "webtransport")]
+pub mod webtransport;
+pub mod transport;
+pub mod endpoints;
+pub mod assets;
+pub mod bench;
@@ -0,0 +1,56 @@
+use web_sys::{AudioContext, Notification, NotificationOptions, NotificationPermission, OscillatorType};
+
+/// Asks for desktop notification permission if the user hasn't decided yet.
+/// Browsers only show the prompt in response to a user gesture, so call this from one.
+pub fn request_permission() {
+    if Notification::permission() == NotificationPermission::Default {
+        if let Err(e) = Notification::request_permission() {
+            log::error!("notify: {:?}", e);
+        }
+    }
+}
+
+fn page_hidden() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .map_or(false, |d| d.hidden())
+}
+
+/// Shows a desktop notification, but only while the page is in the background;
+/// otherwise the message is already on screen.
+pub fn desktop(title: &str, body: &str) {
+    if !page_hidden() || Notification::permission() != NotificationPermission::Granted {
+        return;
+    }
+    let mut options = NotificationOptions::new();
+    options.body(body).tag("yewchat");
+    if let Err(e) = Notification::new_with_options(title, &options) {
+        log::error!("notify: {:?}", e);
+    }
+}
+
+/// Plays a short two-tone chime, synthesized so we don't need to ship an audio file.
+pub fn chime() {
+    let result = (|| {
+        let ctx = AudioContext::new()?;
+        let gain = ctx.create_gain()?;
+        gain.connect_with_audio_node(&ctx.destination())?;
+        let now = ctx.current_time();
+        gain.gain().set_value_at_time(0.15, now)?;
+        gain.gain().exponential_ramp_to_value_at_time(0.001, now + 0.4)?;
+
+        for (i, freq) in [880.0, 1320.0].iter().enumerate() {
+            let osc = ctx.create_oscillator()?;
+            osc.set_type(OscillatorType::Sine);
+            osc.frequency().set_value(*freq);
+            osc.connect_with_audio_node(&gain)?;
+            let start = now + i as f64 * 0.12;
+            osc.start_with_when(start)?;
+            osc.stop_with_when(start + 0.2)?;
+        }
+        Ok::<_, wasm_bindgen::JsValue>(())
+    })();
+    if let Err(e) = result {
+        log::error!("notify: {:?}", e);
+    }
+}
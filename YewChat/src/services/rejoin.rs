@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::storage;
+
+const STORAGE_KEY: &str = "last_conversation";
+
+/// A room we were in, with the password hash it accepted if it's locked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JoinedRoom {
+    pub room: String,
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// Where we were when the app was last open, so the next visit picks up there
+/// rather than at the login form.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LastConversation {
+    pub name: String,
+    pub rooms: Vec<JoinedRoom>,
+    /// The room that had focus
+    pub active: Option<String>,
+}
+
+impl LastConversation {
+    /// What was kept from the last visit, if we were in a conversation at all.
+    pub fn load() -> Option<Self> {
+        storage::load::<Self>(STORAGE_KEY).filter(|last| !last.name.is_empty())
+    }
+
+    fn save(&self) {
+        storage::save(STORAGE_KEY, self);
+    }
+
+    /// Signing out or being sent away means there's nothing to go back to.
+    pub fn forget() {
+        storage::remove(STORAGE_KEY);
+    }
+
+    /// The password hash `room` accepted last time.
+    pub fn key_for(&self, room: &str) -> Option<String> {
+        self.rooms.iter().find(|r| r.room == room)?.key.clone()
+    }
+
+    /// Notes that we're in `room` as `name`, and that it's the one in front.
+    pub fn entered(name: &str, room: &str) {
+        let mut last = Self::load().unwrap_or_default();
+        last.name = name.to_string();
+        if !last.rooms.iter().any(|r| r.room == room) {
+            last.rooms.push(JoinedRoom { room: room.to_string(), key: None });
+        }
+        last.active = Some(room.to_string());
+        last.save();
+    }
+
+    /// Remembers the password hash `room` took, or drops it once it's refused.
+    pub fn set_key(room: &str, key: Option<String>) {
+        if let Some(mut last) = Self::load() {
+            if let Some(joined) = last.rooms.iter_mut().find(|r| r.room == room) {
+                joined.key = key;
+                last.save();
+            }
+        }
+    }
+
+    pub fn renamed(to: &str) {
+        if let Some(mut last) = Self::load() {
+            last.name = to.to_string();
+            last.save();
+        }
+    }
+}
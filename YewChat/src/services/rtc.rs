@@ -0,0 +1,139 @@
+use std::{cell::RefCell, rc::Rc};
+
+use js_sys::{Array, Reflect};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    MediaStream, MediaStreamConstraints, MediaStreamTrack, RtcConfiguration, RtcIceCandidate, RtcIceCandidateInit,
+    RtcIceServer, RtcPeerConnection, RtcSdpType, RtcSessionDescriptionInit,
+};
+
+// For finding our public address behind NAT; calls within one network work without it
+const STUN_SERVERS: [&str; 1] = ["stun:stun.l.google.com:19302"];
+
+/// Candidates that came before the offer or answer they belong to; `None`
+/// once that has been applied and they can be added straight away.
+pub type PendingCandidates = Rc<RefCell<Option<Vec<RtcIceCandidateInit>>>>;
+
+pub fn pending_candidates() -> PendingCandidates {
+    Rc::new(RefCell::new(Some(Vec::new())))
+}
+
+/// A route to the other side, as sent in `candidate` signals.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IceCandidate {
+    pub candidate: String,
+    #[serde(default, rename = "sdpMid")]
+    pub sdp_mid: Option<String>,
+    #[serde(default, rename = "sdpMLineIndex")]
+    pub sdp_m_line_index: Option<u16>,
+}
+
+impl IceCandidate {
+    pub fn from_rtc(candidate: &RtcIceCandidate) -> Self {
+        Self {
+            candidate: candidate.candidate(),
+            sdp_mid: candidate.sdp_mid(),
+            sdp_m_line_index: candidate.sdp_m_line_index(),
+        }
+    }
+
+    /// Adds it to `connection`, or holds on to it until the remote description is in.
+    pub fn apply(&self, connection: &RtcPeerConnection, pending: &PendingCandidates) {
+        let mut init = RtcIceCandidateInit::new(&self.candidate);
+        init.sdp_mid(self.sdp_mid.as_deref()).sdp_m_line_index(self.sdp_m_line_index);
+        match pending.borrow_mut().as_mut() {
+            Some(pending) => pending.push(init),
+            None => add_candidate(connection, &init),
+        }
+    }
+}
+
+/// Asks for the microphone, and the camera too with `video`. Browsers only
+/// allow either on secure pages.
+pub async fn user_media(video: bool) -> Result<MediaStream, String> {
+    let devices = web_sys::window()
+        .and_then(|window| window.navigator().media_devices().ok())
+        .ok_or_else(|| "Calls need the page to be served over HTTPS".to_string())?;
+    let mut constraints = MediaStreamConstraints::new();
+    constraints.audio(&JsValue::TRUE).video(&JsValue::from_bool(video));
+    let request = devices
+        .get_user_media_with_constraints(&constraints)
+        .map_err(|_| "This browser can't use a microphone or camera".to_string())?;
+    JsFuture::from(request).await.map(|stream| stream.unchecked_into()).map_err(|_| {
+        if video {
+            "Camera or microphone access was blocked, or there isn't one".to_string()
+        } else {
+            "Microphone access was blocked or there isn't one".to_string()
+        }
+    })
+}
+
+/// Ends every track, which turns off the browser's recording indicator.
+pub fn stop(stream: &MediaStream) {
+    for track in stream.get_tracks().iter() {
+        track.unchecked_into::<MediaStreamTrack>().stop();
+    }
+}
+
+/// Turns the microphone (`audio`) or camera tracks of `stream` on or off.
+pub fn set_enabled(stream: &MediaStream, audio: bool, enabled: bool) {
+    let tracks = if audio { stream.get_audio_tracks() } else { stream.get_video_tracks() };
+    for track in tracks.iter() {
+        track.unchecked_into::<MediaStreamTrack>().set_enabled(enabled);
+    }
+}
+
+/// A connection sending every track of `local`.
+pub fn peer_connection(local: &MediaStream) -> Result<RtcPeerConnection, JsValue> {
+    let servers = Array::new();
+    for url in STUN_SERVERS {
+        let mut server = RtcIceServer::new();
+        server.urls(&JsValue::from_str(url));
+        servers.push(&server);
+    }
+    let mut config = RtcConfiguration::new();
+    config.ice_servers(&servers);
+    let connection = RtcPeerConnection::new_with_configuration(&config)?;
+    for track in local.get_tracks().iter() {
+        connection.add_track(&track.unchecked_into(), local, &Array::new());
+    }
+    Ok(connection)
+}
+
+/// Creates our offer or answer and applies it, returning it to send to the other side.
+pub async fn local_description(connection: &RtcPeerConnection, kind: RtcSdpType) -> Result<String, JsValue> {
+    let created = match kind {
+        RtcSdpType::Offer => connection.create_offer(),
+        _ => connection.create_answer(),
+    };
+    let created = JsFuture::from(created).await?;
+    let sdp = Reflect::get(&created, &JsValue::from_str("sdp"))?.as_string().unwrap_or_default();
+    let mut description = RtcSessionDescriptionInit::new(kind);
+    description.sdp(&sdp);
+    JsFuture::from(connection.set_local_description(&description)).await?;
+    Ok(sdp)
+}
+
+/// Applies the other side's offer or answer, then any candidates that came early.
+pub async fn set_remote(
+    connection: &RtcPeerConnection,
+    pending: &PendingCandidates,
+    kind: RtcSdpType,
+    sdp: &str,
+) -> Result<(), JsValue> {
+    let mut description = RtcSessionDescriptionInit::new(kind);
+    description.sdp(sdp);
+    JsFuture::from(connection.set_remote_description(&description)).await?;
+    let early = pending.borrow_mut().take().unwrap_or_default();
+    for candidate in early {
+        add_candidate(connection, &candidate);
+    }
+    Ok(())
+}
+
+fn add_candidate(connection: &RtcPeerConnection, candidate: &RtcIceCandidateInit) {
+    // Failures are reported through the promise and only mean one less route to try
+    let _ = connection.add_ice_candidate_with_opt_rtc_ice_candidate_init(Some(candidate));
+}
@@ -0,0 +1,148 @@
+use futures::{
+    channel::{mpsc::Receiver, oneshot},
+    future::Either,
+    StreamExt,
+};
+use js_sys::{Object, Reflect};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{MessageEvent, MessagePort, SharedWorker};
+use yew::Callback;
+use yew_agent::Dispatched;
+
+use crate::services::{
+    event_bus::{EventBus, Request},
+    websocket::{ConnectionStatus, FAILURES_BEFORE_POLLING},
+};
+
+// Served next to the wasm bundle from `static/`
+const WORKER_URL: &str = "socket-worker.js";
+
+/// A tab's handle on the websocket owned by `static/socket-worker.js`, shared by
+/// every open tab so the user only registers (and shows up) once.
+pub struct SharedSocket {
+    port: MessagePort,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl SharedSocket {
+    /// Connects to the shared worker, or `None` where SharedWorker isn't supported.
+    /// If the worker's socket keeps failing without ever connecting, the tab stops
+    /// using it and hands `outgoing` to `on_unreachable` instead.
+    pub fn open(
+        url: &str,
+        mut outgoing: Receiver<String>,
+        on_status: Callback<ConnectionStatus>,
+        on_unreachable: impl FnOnce(Receiver<String>) + 'static,
+    ) -> Option<Self> {
+        let worker = SharedWorker::new(WORKER_URL)
+            .map_err(|e| log::debug!("SharedWorker unavailable, using a per-tab socket: {:?}", e))
+            .ok()?;
+        let port = worker.port();
+        let mut event_bus = EventBus::dispatcher();
+        // Fired once the socket is judged unreachable; dropped unfired once it connects
+        let (unreachable_tx, mut unreachable_rx) = oneshot::channel::<()>();
+        let mut unreachable_tx = Some(unreachable_tx);
+        let mut failures: u32 = 0;
+        let mut gave_up = false;
+
+        let onmessage = Closure::wrap(Box::new(move |e: MessageEvent| {
+            if gave_up {
+                return;
+            }
+            let msg = e.data();
+            let field = |name: &str| Reflect::get(&msg, &name.into()).ok().and_then(|v| v.as_string());
+            match field("type").as_deref() {
+                Some("frame") => {
+                    if let Some(data) = field("data") {
+                        log::debug!("From shared websocket: {}", data);
+                        event_bus.send(Request::EventBusMsg(data));
+                    }
+                }
+                Some("status") => match field("status").as_deref() {
+                    Some("connecting") => on_status.emit(ConnectionStatus::Connecting),
+                    Some("connected") => {
+                        unreachable_tx = None;
+                        on_status.emit(ConnectionStatus::Connected);
+                    }
+                    Some("reconnecting") => {
+                        failures += 1;
+                        if failures >= FAILURES_BEFORE_POLLING {
+                            if let Some(tx) = unreachable_tx.take() {
+                                gave_up = true;
+                                let _ = tx.send(());
+                                return;
+                            }
+                        }
+                        on_status.emit(ConnectionStatus::Reconnecting);
+                    }
+                    other => log::error!("shared socket: unknown status {:?}", other),
+                },
+                other => log::error!("shared socket: unknown message {:?}", other),
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        port.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        port.start();
+
+        post(&port, &[("type", "connect"), ("url", url)]);
+
+        let sender = port.clone();
+        spawn_local(async move {
+            enum Next {
+                Send(String),
+                Done,
+                GiveUp,
+                Stay,
+            }
+            loop {
+                let next = match futures::future::select(outgoing.next(), &mut unreachable_rx).await {
+                    Either::Left((Some(data), _)) => Next::Send(data),
+                    Either::Left((None, _)) => Next::Done,
+                    Either::Right((Ok(()), _)) => Next::GiveUp,
+                    // Connected at some point, so the socket does get through
+                    Either::Right((Err(_), _)) => Next::Stay,
+                };
+                match next {
+                    Next::Send(data) => {
+                        log::debug!("Got event from channel! {}", data);
+                        post(&sender, &[("type", "send"), ("data", &data)]);
+                    }
+                    Next::Done => return,
+                    Next::GiveUp => {
+                        post(&sender, &[("type", "close")]);
+                        on_unreachable(outgoing);
+                        return;
+                    }
+                    Next::Stay => break,
+                }
+            }
+            while let Some(data) = outgoing.next().await {
+                log::debug!("Got event from channel! {}", data);
+                post(&sender, &[("type", "send"), ("data", &data)]);
+            }
+        });
+
+        Some(Self {
+            port,
+            _onmessage: onmessage,
+        })
+    }
+}
+
+impl Drop for SharedSocket {
+    fn drop(&mut self) {
+        // Lets the worker close the socket once the last tab is gone
+        post(&self.port, &[("type", "close")]);
+        self.port.set_onmessage(None);
+    }
+}
+
+fn post(port: &MessagePort, fields: &[(&str, &str)]) {
+    let msg = Object::new();
+    for (key, value) in fields {
+        let _ = Reflect::set(&msg, &JsValue::from_str(key), &JsValue::from_str(value));
+    }
+    if let Err(e) = port.post_message(&msg) {
+        log::error!("shared socket: {:?}", e);
+    }
+}
@@ -0,0 +1,112 @@
+use futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    future::{self, Either},
+    StreamExt,
+};
+use gloo_timers::future::TimeoutFuture;
+use js_sys::Reflect;
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{EventSource, MessageEvent};
+
+use crate::services::transport::Frames;
+
+// The server opens every stream with a `ready` event; a proxy that buffers the
+// response holds it back, and then the stream is no use to us
+const READY_TIMEOUT_MS: u32 = 5_000;
+
+/// Why a stream couldn't be used.
+pub enum Ended {
+    /// Failed to open; a new session may well work
+    Closed,
+    /// Never got going, so something in between is buffering it
+    Buffered,
+}
+
+enum Event {
+    Ready,
+    Frame(String),
+    Error,
+}
+
+/// Whether this browser can receive Server-Sent Events at all.
+pub fn supported() -> bool {
+    web_sys::window()
+        .and_then(|w| Reflect::has(&w, &"EventSource".into()).ok())
+        .unwrap_or(false)
+}
+
+// Closes the source when the stream future is dropped, e.g. because sending failed
+struct Source {
+    source: EventSource,
+    _closures: Vec<Closure<dyn FnMut(web_sys::Event)>>,
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
+
+fn listen(source: &EventSource, events: UnboundedSender<Event>) -> Vec<Closure<dyn FnMut(web_sys::Event)>> {
+    let ready = {
+        let events = events.clone();
+        Closure::wrap(Box::new(move |_: web_sys::Event| {
+            let _ = events.unbounded_send(Event::Ready);
+        }) as Box<dyn FnMut(web_sys::Event)>)
+    };
+    let message = {
+        let events = events.clone();
+        Closure::wrap(Box::new(move |e: web_sys::Event| {
+            let data = e.dyn_into::<MessageEvent>().ok().and_then(|e| e.data().as_string());
+            if let Some(data) = data {
+                let _ = events.unbounded_send(Event::Frame(data));
+            }
+        }) as Box<dyn FnMut(web_sys::Event)>)
+    };
+    let error = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        let _ = events.unbounded_send(Event::Error);
+    }) as Box<dyn FnMut(web_sys::Event)>);
+
+    let _ = source.add_event_listener_with_callback("ready", ready.as_ref().unchecked_ref());
+    source.set_onmessage(Some(message.as_ref().unchecked_ref()));
+    source.set_onerror(Some(error.as_ref().unchecked_ref()));
+    vec![ready, message, error]
+}
+
+/// Opens an event stream on `url`, once the server's `ready` event shows that
+/// frames will actually get through. The stream ends on the first error: the
+/// browser would retry on its own, but against a session the server may have dropped.
+pub async fn open(url: &str) -> Result<Frames<'static>, Ended> {
+    let source = match EventSource::new(url) {
+        Ok(source) => source,
+        Err(e) => {
+            log::error!("sse: {:?}", e);
+            return Err(Ended::Closed);
+        }
+    };
+    let (tx, mut events) = unbounded();
+    let source = Source {
+        _closures: listen(&source, tx),
+        source,
+    };
+
+    let first = events.next();
+    let timeout = TimeoutFuture::new(READY_TIMEOUT_MS);
+    match futures::future::select(first, timeout).await {
+        Either::Left((Some(Event::Ready), _)) => {}
+        Either::Left(_) => return Err(Ended::Closed),
+        Either::Right(_) => return Err(Ended::Buffered),
+    }
+
+    let frames = events
+        .take_while(|event| future::ready(!matches!(event, Event::Error)))
+        .filter_map(move |event| {
+            // The source closes once the stream is dropped
+            let _source = &source;
+            future::ready(match event {
+                Event::Frame(frame) => Some(frame),
+                _ => None,
+            })
+        });
+    Ok(frames.boxed_local())
+}
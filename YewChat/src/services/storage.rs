@@ -0,0 +1,60 @@
+use serde::{de::DeserializeOwned, Serialize};
+use web_sys::Storage;
+
+// Every key we write is namespaced so we never clash with other apps on the same origin
+const PREFIX: &str = "yewchat.";
+
+fn local_storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn session_storage() -> Option<Storage> {
+    web_sys::window()?.session_storage().ok()?
+}
+
+fn read<T: DeserializeOwned>(storage: Option<Storage>, key: &str) -> Option<T> {
+    let raw = storage?.get_item(&format!("{}{}", PREFIX, key)).ok()??;
+    serde_json::from_str(&raw).ok()
+}
+
+fn write<T: Serialize>(storage: Option<Storage>, key: &str, value: &T) {
+    let result = serde_json::to_string(value)
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|json| {
+            storage
+                .ok_or_else(|| "storage unavailable".to_string())?
+                .set_item(&format!("{}{}", PREFIX, key), &json)
+                .map_err(|e| format!("{:?}", e))
+        });
+    if let Err(e) = result {
+        log::error!("storage: failed to save {}: {}", key, e);
+    }
+}
+
+/// Reads a JSON value from localStorage, `None` if missing, unreadable or from an older shape.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    read(local_storage(), key)
+}
+
+/// Writes a JSON value to localStorage. Failures (quota, private mode) are only logged.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    write(local_storage(), key, value)
+}
+
+/// Like `load`, but from sessionStorage, which is cleared when the tab closes.
+pub fn load_for_tab<T: DeserializeOwned>(key: &str) -> Option<T> {
+    read(session_storage(), key)
+}
+
+/// Like `save`, but only for as long as the tab stays open.
+pub fn save_for_tab<T: Serialize>(key: &str, value: &T) {
+    write(session_storage(), key, value)
+}
+
+/// Deletes a value from both localStorage and sessionStorage.
+pub fn remove(key: &str) {
+    let key = format!("{}{}", PREFIX, key);
+    for storage in [local_storage(), session_storage()].into_iter().flatten() {
+        let _ = storage.remove_item(&key);
+    }
+}
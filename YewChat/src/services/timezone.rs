@@ -0,0 +1,30 @@
+use js_sys::{Array, Date, Function, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+// `new Intl.DateTimeFormat(locale, options)`, with a bad timezone reported as `None`
+// rather than thrown
+fn date_format(options: &Object) -> Option<JsValue> {
+    let intl = Reflect::get(&js_sys::global(), &"Intl".into()).ok()?;
+    let constructor: Function = Reflect::get(&intl, &"DateTimeFormat".into()).ok()?.dyn_into().ok()?;
+    Reflect::construct(&constructor, &Array::of2(&"en-GB".into(), options)).ok()
+}
+
+/// The viewer's IANA timezone, e.g. "Europe/Berlin".
+pub fn local() -> Option<String> {
+    let format = date_format(&Object::new())?;
+    let resolved: Function = Reflect::get(&format, &"resolvedOptions".into()).ok()?.dyn_into().ok()?;
+    let options = resolved.call0(&format).ok()?;
+    Reflect::get(&options, &"timeZone".into()).ok()?.as_string()
+}
+
+/// `ts` (ms since the epoch) as HH:MM on the wall clock of `timezone`, or
+/// `None` if the browser doesn't know that zone.
+pub fn time_in(ts: f64, timezone: &str) -> Option<String> {
+    let options = Object::new();
+    for (key, value) in [("timeZone", timezone), ("hour", "2-digit"), ("minute", "2-digit"), ("hourCycle", "h23")] {
+        Reflect::set(&options, &key.into(), &value.into()).ok()?;
+    }
+    let format = date_format(&options)?;
+    let format_fn: Function = Reflect::get(&format, &"format".into()).ok()?.dyn_into().ok()?;
+    format_fn.call1(&format, &Date::new(&ts.into())).ok()?.as_string()
+}
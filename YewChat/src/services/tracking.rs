@@ -0,0 +1,83 @@
+use crate::components::links::extract_urls;
+
+/// Query parameters that only say where a click came from. A trailing `*`
+/// matches any ending, so `utm_*` covers `utm_source`, `utm_medium` and so on.
+pub const DEFAULT_RULES: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid",
+    "_hsenc", "_hsmi", "mkt_tok", "ref_src",
+];
+
+fn matches_rule(name: &str, rule: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let rule = rule.trim().to_ascii_lowercase();
+    match rule.strip_suffix('*') {
+        Some(prefix) => !prefix.is_empty() && name.starts_with(prefix),
+        None => !rule.is_empty() && name == rule,
+    }
+}
+
+/// A link with its tracking parameters taken out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cleaned {
+    pub url: String,
+    /// Names of the parameters that were dropped, in the order they appeared
+    pub removed: Vec<String>,
+}
+
+/// `url` without the query parameters `rules` match, or `None` if it had none.
+pub fn clean(url: &str, rules: &[String]) -> Option<Cleaned> {
+    let (rest, fragment) = match url.find('#') {
+        Some(i) => url.split_at(i),
+        None => (url, ""),
+    };
+    let (base, query) = rest.split_once('?')?;
+    let mut removed = Vec::new();
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default();
+            let tracking = rules.iter().any(|rule| matches_rule(name, rule));
+            if tracking {
+                removed.push(name.to_string());
+            }
+            !tracking
+        })
+        .collect();
+    if removed.is_empty() {
+        return None;
+    }
+    let url = if kept.is_empty() {
+        format!("{}{}", base, fragment)
+    } else {
+        format!("{}?{}{}", base, kept.join("&"), fragment)
+    };
+    Some(Cleaned { url, removed })
+}
+
+/// `text` with every link in it cleaned and the parameters dropped, or `None`
+/// if none of them needed it.
+pub fn clean_text(text: &str, rules: &[String]) -> Option<(String, Vec<String>)> {
+    let mut out = String::with_capacity(text.len());
+    let mut removed = Vec::new();
+    let mut rest = text;
+    for url in extract_urls(text) {
+        let start = match rest.find(url) {
+            Some(start) => start,
+            None => continue,
+        };
+        out.push_str(&rest[..start]);
+        match clean(url, rules) {
+            Some(cleaned) => {
+                out.push_str(&cleaned.url);
+                removed.extend(cleaned.removed);
+            }
+            None => out.push_str(url),
+        }
+        rest = &rest[start + url.len()..];
+    }
+    out.push_str(rest);
+    (!removed.is_empty()).then_some((out, removed))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,66 @@
+use wasm_bindgen_test::*;
+
+use super::{clean, clean_text, Cleaned, DEFAULT_RULES};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn rules() -> Vec<String> {
+    DEFAULT_RULES.iter().map(|rule| rule.to_string()).collect()
+}
+
+#[wasm_bindgen_test]
+fn wildcard_covers_every_utm_parameter() {
+    let cleaned = clean("https://example.org/post?utm_source=x&id=7&utm_medium=y&utm_=z", &rules());
+    assert_eq!(
+        cleaned,
+        Some(Cleaned {
+            url: "https://example.org/post?id=7".into(),
+            removed: vec!["utm_source".into(), "utm_medium".into(), "utm_".into()],
+        })
+    );
+    // Only as a prefix
+    assert_eq!(clean("https://example.org/?my_utm_source=x", &rules()), None);
+}
+
+#[wasm_bindgen_test]
+fn parameter_names_ignore_case() {
+    let cleaned = clean("https://example.org/?UTM_Source=x&FBCLID=y&q=1", &rules()).unwrap();
+    assert_eq!(cleaned.url, "https://example.org/?q=1");
+    // Reported as they were written
+    assert_eq!(cleaned.removed, ["UTM_Source", "FBCLID"]);
+    let custom = clean("https://example.org/?Ref=1", &[" REF ".to_string()]).unwrap();
+    assert_eq!(custom.url, "https://example.org/");
+}
+
+#[wasm_bindgen_test]
+fn fragment_is_kept() {
+    let cleaned = clean("https://example.org/docs?gclid=1&page=2#install?utm_source=x", &rules()).unwrap();
+    assert_eq!(cleaned.url, "https://example.org/docs?page=2#install?utm_source=x");
+    assert_eq!(cleaned.removed, ["gclid"]);
+}
+
+#[wasm_bindgen_test]
+fn question_mark_goes_when_nothing_is_left() {
+    assert_eq!(clean("https://example.org/a?utm_source=x&fbclid=y", &rules()).unwrap().url, "https://example.org/a");
+    assert_eq!(clean("https://example.org/a?fbclid=y#top", &rules()).unwrap().url, "https://example.org/a#top");
+}
+
+#[wasm_bindgen_test]
+fn untouched_urls_stay_byte_identical() {
+    for url in [
+        "https://example.org/",
+        "https://example.org/search?q=a%20b&Q=&&x",
+        "https://example.org/#utm_source=x",
+    ] {
+        assert_eq!(clean(url, &rules()), None, "{}", url);
+    }
+    assert_eq!(clean_text("see https://example.org/?q=1&x and https://example.org/b", &rules()), None);
+}
+
+#[wasm_bindgen_test]
+fn text_keeps_everything_but_the_parameters() {
+    let text = "look: https://example.org/a?utm_source=x&id=1, then https://example.org/b?x=%41 and https://example.org/c?fbclid=2!";
+    let (cleaned, removed) = clean_text(text, &rules()).unwrap();
+    assert_eq!(cleaned, "look: https://example.org/a?id=1, then https://example.org/b?x=%41 and https://example.org/c!");
+    assert_eq!(removed, ["utm_source", "fbclid"]);
+}
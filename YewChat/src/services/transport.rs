@@ -0,0 +1,178 @@
+use std::cell::RefCell;
+
+use futures::{
+    channel::mpsc::{unbounded, Receiver, UnboundedReceiver, UnboundedSender},
+    future::{self, Either, LocalBoxFuture},
+    stream::{self, LocalBoxStream},
+    FutureExt, StreamExt,
+};
+use gloo_timers::future::TimeoutFuture;
+use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
+use yew_agent::Dispatched;
+
+use crate::services::{
+    event_bus::{EventBus, Request},
+    websocket::ConnectionStatus,
+};
+
+#[cfg(test)]
+pub mod mock;
+#[cfg(test)]
+mod tests;
+
+// Reconnect attempts back off exponentially up to this delay
+const RECONNECT_BASE_DELAY_MS: u32 = 1_000;
+const RECONNECT_MAX_DELAY_MS: u32 = 30_000;
+// Failed attempts, without a success or anywhere left to fall back to, before
+// the server counts as unreachable. Attempts carry on regardless.
+const UNREACHABLE_AFTER: u32 = 3;
+
+/// Frames coming in from a backend.
+pub type Frames<'a> = LocalBoxStream<'a, String>;
+
+/// A way of exchanging the chat's JSON frames with a backend.
+///
+/// `connect` is called again after every drop, so per-connection state lives
+/// inside the implementation. While connected, `send` and `events` are used at
+/// the same time, which is why everything takes `&self`.
+pub trait Transport {
+    /// Sets up a connection, `false` if the backend couldn't be reached.
+    fn connect(&self) -> LocalBoxFuture<'_, bool>;
+    /// Sends one frame, `false` if the connection broke on the way.
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool>;
+    /// What the backend sends, ending when the connection drops.
+    fn events(&self) -> Frames<'_>;
+    /// Lets go of the connection, after a drop or once nobody is sending anymore.
+    fn close(&self);
+}
+
+/// Frames a transport makes up itself, such as answers to requests its backend
+/// has no equivalent for, queued to come out of `events` with what it receives.
+#[derive(Default)]
+pub struct Inbox {
+    tx: RefCell<Option<UnboundedSender<String>>>,
+    rx: RefCell<Option<UnboundedReceiver<String>>>,
+}
+
+impl Inbox {
+    /// Starts afresh for a new connection, dropping anything left from the last one.
+    pub fn reset(&self) {
+        let (tx, rx) = unbounded();
+        *self.tx.borrow_mut() = Some(tx);
+        *self.rx.borrow_mut() = Some(rx);
+    }
+
+    pub fn push(&self, frame: String) {
+        if let Some(tx) = &*self.tx.borrow() {
+            let _ = tx.unbounded_send(frame);
+        }
+    }
+
+    /// `received` with the queued frames mixed in, ending when `received` does.
+    pub fn merge<'a>(&self, received: Frames<'a>) -> Frames<'a> {
+        let queued = stream::iter(self.rx.borrow_mut().take()).flatten().map(Some);
+        let received = received.map(Some).chain(stream::once(future::ready(None)));
+        stream::select(queued, received)
+            .take_while(|frame| future::ready(frame.is_some()))
+            .filter_map(future::ready)
+            .boxed_local()
+    }
+}
+
+/// Where to go when a transport never gets through.
+pub struct Fallback {
+    /// Failed attempts, without a single success, before giving up
+    pub after: u32,
+    /// Takes over the frames the chat sends
+    pub then: Box<dyn FnOnce(Receiver<String>)>,
+}
+
+/// Keeps `transport` connected, reconnecting with backoff whenever it drops.
+/// Frames written to `rx` while disconnected wait for the next connection, and
+/// everything received goes to the event bus for the chat to pick up.
+pub fn run(
+    transport: impl Transport + 'static,
+    mut rx: Receiver<String>,
+    on_status: Callback<ConnectionStatus>,
+    mut fallback: Option<Fallback>,
+) {
+    spawn_local(async move {
+        let mut event_bus = EventBus::dispatcher();
+        let mut attempt: u32 = 0;
+        let mut connected_once = false;
+        // A frame that didn't make it before a drop, sent first on the next connection
+        let mut unsent: Option<String> = None;
+        loop {
+            // Nobody is left to send for, e.g. after the chat started over on a new connection
+            if unsent.is_none() {
+                match rx.next().now_or_never() {
+                    Some(None) => return,
+                    Some(Some(frame)) => unsent = Some(frame),
+                    None => {}
+                }
+            }
+            on_status.emit(if attempt == 0 {
+                ConnectionStatus::Connecting
+            } else if !connected_once && fallback.is_none() && attempt >= UNREACHABLE_AFTER {
+                ConnectionStatus::Unreachable
+            } else {
+                ConnectionStatus::Reconnecting
+            });
+
+            if transport.connect().await {
+                attempt = 0;
+                connected_once = true;
+                on_status.emit(ConnectionStatus::Connected);
+
+                let writer = async {
+                    loop {
+                        let frame = match unsent.take() {
+                            Some(frame) => frame,
+                            // Every sender is gone, so nobody needs the connection anymore
+                            None => match rx.next().await {
+                                Some(frame) => frame,
+                                None => return true,
+                            },
+                        };
+                        log::debug!("Got event from channel! {}", frame);
+                        if !transport.send(frame.clone()).await {
+                            unsent = Some(frame);
+                            return false;
+                        }
+                    }
+                };
+
+                let reader = async {
+                    let mut events = transport.events();
+                    while let Some(frame) = events.next().await {
+                        log::debug!("Received: {}", frame);
+                        event_bus.send(Request::EventBusMsg(frame));
+                    }
+                    log::debug!("Connection closed!");
+                };
+
+                let finished = {
+                    futures::pin_mut!(writer, reader);
+                    matches!(futures::future::select(writer, reader).await, Either::Left((true, _)))
+                };
+                transport.close();
+                if finished {
+                    return;
+                }
+            }
+
+            attempt += 1;
+            if !connected_once && matches!(&fallback, Some(f) if attempt >= f.after) {
+                if let Some(fallback) = fallback.take() {
+                    (fallback.then)(rx);
+                    return;
+                }
+            }
+            let delay = RECONNECT_BASE_DELAY_MS
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(RECONNECT_MAX_DELAY_MS);
+            TimeoutFuture::new(delay).await;
+        }
+    });
+}
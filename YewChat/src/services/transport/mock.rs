@@ -0,0 +1,91 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use futures::{future::LocalBoxFuture, stream, FutureExt, StreamExt};
+use serde_json::{json, Value};
+
+use crate::services::transport::{Frames, Inbox, Transport};
+
+#[derive(Default)]
+struct Server {
+    inbox: Inbox,
+    sent: RefCell<Vec<Value>>,
+    users: RefCell<Vec<String>>,
+    next_seq: Cell<u64>,
+    refuse: Cell<bool>,
+}
+
+/// Answers the way the chat server does for registering and messages, so tests
+/// can run without one. Clones share the same server.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    server: Rc<Server>,
+}
+
+impl MockTransport {
+    /// Frames the client has sent so far.
+    pub fn sent(&self) -> Vec<Value> {
+        self.server.sent.borrow().clone()
+    }
+
+    /// Delivers a frame as if the server had sent it unprompted.
+    pub fn push(&self, frame: Value) {
+        self.server.inbox.push(frame.to_string());
+    }
+
+    /// Delivers text as-is, whether or not it's a valid frame.
+    pub fn push_raw(&self, frame: String) {
+        self.server.inbox.push(frame);
+    }
+
+    /// Turns connection attempts away while set.
+    pub fn refuse(&self, refuse: bool) {
+        self.server.refuse.set(refuse);
+    }
+}
+
+impl Transport for MockTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        let accepted = !self.server.refuse.get();
+        if accepted {
+            self.server.inbox.reset();
+        }
+        async move { accepted }.boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        let frame: Value = serde_json::from_str(&frame).unwrap_or_default();
+        let server = &self.server;
+        let data = frame["data"].as_str().unwrap_or_default().to_string();
+        match frame["messageType"].as_str() {
+            Some("register") => {
+                server.users.borrow_mut().push(data);
+                self.push(json!({ "messageType": "users", "dataArray": *server.users.borrow() }));
+            }
+            Some("message") => {
+                let seq = server.next_seq.get() + 1;
+                server.next_seq.set(seq);
+                let message = json!({
+                    "id": seq.to_string(),
+                    "seq": seq,
+                    "from": server.users.borrow().last().cloned().unwrap_or_default(),
+                    "message": data,
+                    "clientId": frame["clientId"],
+                    "time": 0,
+                });
+                self.push(json!({ "messageType": "message", "data": message.to_string() }));
+            }
+            _ => {}
+        }
+        server.sent.borrow_mut().push(frame);
+        async { true }.boxed_local()
+    }
+
+    fn events(&self) -> Frames<'_> {
+        self.server.inbox.merge(stream::pending().boxed_local())
+    }
+
+    fn close(&self) {}
+}
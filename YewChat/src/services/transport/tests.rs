@@ -0,0 +1,108 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::channel::mpsc;
+use gloo_timers::future::TimeoutFuture;
+use serde_json::{json, Value};
+use wasm_bindgen_test::*;
+use yew::Callback;
+use yew_agent::{Bridge, Bridged};
+
+use crate::services::{
+    event_bus::EventBus,
+    transport::{mock::MockTransport, run},
+    websocket::ConnectionStatus,
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+// Long enough for queued frames to make it through the event bus
+async fn settle() {
+    TimeoutFuture::new(50).await;
+}
+
+// Frames that reached the event bus, collected for as long as the bridge is kept
+type Received = Rc<RefCell<Vec<Value>>>;
+
+fn listen() -> (Received, Box<dyn Bridge<EventBus>>) {
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let bridge = {
+        let received = received.clone();
+        EventBus::bridge(Callback::from(move |frame: String| {
+            received.borrow_mut().push(serde_json::from_str(&frame).unwrap());
+        }))
+    };
+    (received, bridge)
+}
+
+fn frame(message_type: &str, data: &str) -> String {
+    json!({ "messageType": message_type, "data": data }).to_string()
+}
+
+#[wasm_bindgen_test]
+async fn register_then_message_reaches_the_event_bus() {
+    let (received, _bridge) = listen();
+    let (mut tx, rx) = mpsc::channel(10);
+    let mock = MockTransport::default();
+    run(mock.clone(), rx, Callback::noop(), None);
+
+    tx.try_send(frame("register", "alice")).unwrap();
+    tx.try_send(frame("message", "hi")).unwrap();
+    settle().await;
+
+    let received = received.borrow();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0]["messageType"], "users");
+    assert_eq!(received[0]["dataArray"], json!(["alice"]));
+    assert_eq!(received[1]["messageType"], "message");
+    let message: Value = serde_json::from_str(received[1]["data"].as_str().unwrap()).unwrap();
+    assert_eq!(message["from"], "alice");
+    assert_eq!(message["message"], "hi");
+    assert_eq!(mock.sent().len(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn frames_wait_for_a_connection() {
+    let statuses = Rc::new(RefCell::new(Vec::new()));
+    let on_status = {
+        let statuses = statuses.clone();
+        Callback::from(move |status| statuses.borrow_mut().push(status))
+    };
+    let (mut tx, rx) = mpsc::channel(10);
+    let mock = MockTransport::default();
+    mock.refuse(true);
+    run(mock.clone(), rx, on_status, None);
+
+    tx.try_send(frame("register", "alice")).unwrap();
+    settle().await;
+    assert!(mock.sent().is_empty());
+    assert_eq!(*statuses.borrow(), vec![ConnectionStatus::Connecting]);
+
+    mock.refuse(false);
+    // The first retry comes after the base backoff delay
+    TimeoutFuture::new(1_200).await;
+    assert_eq!(
+        *statuses.borrow(),
+        vec![ConnectionStatus::Connecting, ConnectionStatus::Reconnecting, ConnectionStatus::Connected]
+    );
+    assert_eq!(mock.sent()[0]["data"], "alice");
+}
+
+#[wasm_bindgen_test]
+async fn gives_up_once_nobody_is_sending() {
+    let statuses = Rc::new(RefCell::new(Vec::new()));
+    let on_status = {
+        let statuses = statuses.clone();
+        Callback::from(move |status| statuses.borrow_mut().push(status))
+    };
+    let (tx, rx) = mpsc::channel::<String>(10);
+    let mock = MockTransport::default();
+    mock.refuse(true);
+    run(mock.clone(), rx, on_status, None);
+    settle().await;
+
+    // As when the chat replaces its connection with a fresh one
+    drop(tx);
+    mock.refuse(false);
+    TimeoutFuture::new(1_200).await;
+    assert_eq!(*statuses.borrow(), vec![ConnectionStatus::Connecting]);
+}
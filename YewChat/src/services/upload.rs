@@ -0,0 +1,218 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{File, ProgressEvent, XmlHttpRequest};
+use yew::Callback;
+
+use crate::services::endpoints::Endpoints;
+
+#[derive(Deserialize)]
+struct Stored {
+    url: String,
+}
+
+/// Where the server keeps files, next to its websocket: `ws://host:7000` → `http://host:7000`.
+fn server_origin() -> String {
+    let socket = Endpoints::current().websocket;
+    let address = socket.split_once('?').map_or(socket.as_str(), |(address, _)| address);
+    address
+        .replacen("wss://", "https://", 1)
+        .replacen("ws://", "http://", 1)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+// What the server takes if it doesn't say, which is what it takes out of the box
+const DEFAULT_MAX_BYTES: f64 = 10.0 * 1024.0 * 1024.0;
+const DEFAULT_TYPES: &[&str] = &["image/*", "video/*", "audio/*", "application/pdf", "text/plain"];
+
+/// `34 MB`, `10.4 MB`, `512 KB` or `12 bytes`, to a tenth at most.
+pub fn format_size(bytes: f64) -> String {
+    scaled_size(bytes, f64::round)
+}
+
+// Rounded up, so a file just over a limit never reads the same as the limit
+fn format_size_up(bytes: f64) -> String {
+    scaled_size(bytes, f64::ceil)
+}
+
+fn scaled_size(bytes: f64, round: fn(f64) -> f64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    let (value, unit) = if bytes >= MB {
+        (bytes / MB, "MB")
+    } else if bytes >= KB {
+        (bytes / KB, "KB")
+    } else {
+        return format!("{} bytes", bytes);
+    };
+    format!("{} {}", round(value * 10.0) / 10.0, unit)
+}
+
+/// Whether MIME type `mime` is one `pattern` lets through; `image/*` covers every image.
+fn type_matches(mime: &str, pattern: &str) -> bool {
+    let (mime, pattern) = (mime.to_ascii_lowercase(), pattern.trim().to_ascii_lowercase());
+    if pattern == "*" || pattern == "*/*" {
+        return true;
+    }
+    match pattern.strip_suffix("/*") {
+        Some(kind) => matches!(mime.split_once('/'), Some((k, _)) if k == kind),
+        None => mime == pattern,
+    }
+}
+
+/// Which files the server accepts, checked before anything is sent.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    pub max_bytes: f64,
+    /// MIME types, with `type/*` for a whole kind
+    pub types: Vec<String>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            types: DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+impl Limits {
+    /// Asks the server for its limits; `None` if it doesn't say.
+    pub async fn fetch() -> Option<Self> {
+        let response = Request::get(&format!("{}/uploads", server_origin())).send().await.ok()?;
+        if !response.ok() {
+            return None;
+        }
+        response.json().await.ok()
+    }
+
+    /// Why `file` can't be uploaded, if it can't.
+    pub fn check(&self, file: &File) -> Result<(), String> {
+        let mime = file.type_();
+        if !self.types.iter().any(|pattern| type_matches(&mime, pattern)) {
+            let kind = if mime.is_empty() { "This type of file".to_string() } else { format!("{} files", mime) };
+            return Err(format!("{} can't be sent here", kind));
+        }
+        if file.size() > self.max_bytes {
+            return Err(format!(
+                "File is {}, limit is {}",
+                format_size_up(file.size()),
+                format_size(self.max_bytes)
+            ));
+        }
+        Ok(())
+    }
+
+    /// For the file picker's `accept`, so other files are greyed out to begin with.
+    pub fn accept(&self) -> String {
+        self.types.join(",")
+    }
+}
+
+/// What happens to an upload, as the server reports it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UploadEvent {
+    /// Bytes sent so far out of `total`
+    Progress { loaded: f64, total: f64 },
+    /// Stored; the file can be fetched from this URL
+    Done(String),
+    Failed(String),
+}
+
+fn failure(status: u16) -> String {
+    match status {
+        401 => "Join the room before sending files".to_string(),
+        413 => "Too large for the server".to_string(),
+        415 => "The server doesn't take this type of file".to_string(),
+        0 => "Couldn't reach the server".to_string(),
+        status => format!("The server refused it ({})", status),
+    }
+}
+
+/// One file on its way to the server. Dropping it cancels the upload.
+pub struct Upload {
+    xhr: XmlHttpRequest,
+    _on_progress: Closure<dyn FnMut(ProgressEvent)>,
+    _on_load: Closure<dyn FnMut()>,
+    _on_error: Closure<dyn FnMut()>,
+}
+
+impl Upload {
+    /// Starts sending `file`, reporting progress to `on_event` until it's done or failed.
+    /// `token` is the one handed out on registering, which the server only takes from someone in the room.
+    /// Events are passed on from a microtask, so whoever gets them can drop the upload there.
+    pub fn start(file: &File, token: &str, on_event: Callback<UploadEvent>) -> Option<Self> {
+        let emit = move |event: UploadEvent| {
+            let on_event = on_event.clone();
+            wasm_bindgen_futures::spawn_local(async move { on_event.emit(event) });
+        };
+        let xhr = XmlHttpRequest::new().ok()?;
+        let url = format!(
+            "{}/uploads?name={}",
+            server_origin(),
+            js_sys::encode_uri_component(&file.name())
+        );
+        xhr.open("POST", &url).ok()?;
+
+        let on_progress = {
+            let emit = emit.clone();
+            Closure::wrap(Box::new(move |e: ProgressEvent| {
+                if e.length_computable() {
+                    emit(UploadEvent::Progress { loaded: e.loaded(), total: e.total() });
+                }
+            }) as Box<dyn FnMut(ProgressEvent)>)
+        };
+        let on_load = {
+            let emit = emit.clone();
+            let xhr = xhr.clone();
+            Closure::wrap(Box::new(move || {
+                let status = xhr.status().unwrap_or(0);
+                let stored = xhr
+                    .response_text()
+                    .ok()
+                    .flatten()
+                    .and_then(|text| serde_json::from_str::<Stored>(&text).ok());
+                emit(match stored {
+                    Some(stored) if (200..300).contains(&status) => {
+                        UploadEvent::Done(format!("{}{}", server_origin(), stored.url))
+                    }
+                    _ => UploadEvent::Failed(failure(status)),
+                });
+            }) as Box<dyn FnMut()>)
+        };
+        let on_error = Closure::wrap(Box::new(move || emit(UploadEvent::Failed(failure(0)))) as Box<dyn FnMut()>);
+
+        xhr.upload().ok()?.set_onprogress(Some(on_progress.as_ref().unchecked_ref()));
+        xhr.set_onload(Some(on_load.as_ref().unchecked_ref()));
+        xhr.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        let _ = xhr.set_request_header("Authorization", &format!("Bearer {}", token));
+        if !file.type_().is_empty() {
+            let _ = xhr.set_request_header("Content-Type", &file.type_());
+        }
+        xhr.send_with_opt_blob(Some(file)).ok()?;
+        Some(Self {
+            xhr,
+            _on_progress: on_progress,
+            _on_load: on_load,
+            _on_error: on_error,
+        })
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        // Nothing may call into the closures once they're gone
+        if let Ok(upload) = self.xhr.upload() {
+            upload.set_onprogress(None);
+        }
+        self.xhr.set_onload(None);
+        self.xhr.set_onerror(None);
+        let _ = self.xhr.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,33 @@
+use wasm_bindgen_test::*;
+
+use super::{format_size, format_size_up, type_matches};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const MB: f64 = 1024.0 * 1024.0;
+
+#[wasm_bindgen_test]
+fn sizes_read_the_way_people_say_them() {
+    assert_eq!(format_size(12.0), "12 bytes");
+    assert_eq!(format_size(512.0 * 1024.0), "512 KB");
+    assert_eq!(format_size(10.0 * MB), "10 MB");
+    assert_eq!(format_size(10.44 * MB), "10.4 MB");
+}
+
+#[wasm_bindgen_test]
+fn a_file_over_the_limit_never_reads_as_the_limit() {
+    let limit = 10.0 * MB;
+    for size in [limit + 1.0, 10.04 * MB, 10.4 * MB] {
+        assert_ne!(format_size_up(size), format_size(limit), "{}", size);
+    }
+    assert_eq!(format_size_up(10.4 * MB), "10.4 MB");
+    assert_eq!(format_size_up(limit + 1.0), "10.1 MB");
+}
+
+#[wasm_bindgen_test]
+fn types_match_whole_kinds() {
+    assert!(type_matches("image/png", "image/*"));
+    assert!(type_matches("IMAGE/PNG", " image/png "));
+    assert!(!type_matches("video/mp4", "image/*"));
+    assert!(type_matches("application/zip", "*/*"));
+}
@@ -0,0 +1,50 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+
+/// The release this client was built from.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+// Set by the webpack build, usually to the commit; the same value goes into version.json
+const BUILD: Option<&str> = option_env!("YEWCHAT_BUILD");
+// Written next to index.html by every build
+const VERSION_URL: &str = "/version.json";
+
+/// What the deployed build says it is.
+#[derive(Deserialize)]
+struct Published {
+    version: String,
+    #[serde(default)]
+    build: Option<String>,
+}
+
+impl Published {
+    /// Builds are compared where both have one, as a version isn't bumped for every deploy.
+    fn differs(&self) -> bool {
+        match (BUILD, self.build.as_deref()) {
+            (Some(ours), Some(theirs)) => ours != theirs,
+            _ => self.version != VERSION,
+        }
+    }
+}
+
+/// This build with its commit, e.g. `0.1.0 (3b1d35e)`.
+pub fn describe() -> String {
+    match BUILD {
+        Some(build) => format!("{} ({})", VERSION, build),
+        None => VERSION.to_string(),
+    }
+}
+
+/// Whether a different build has been deployed since this tab loaded. Builds
+/// without a version.json, such as the dev server's, never report one.
+pub async fn update_available() -> bool {
+    // A cached copy would only ever agree with us
+    let url = format!("{}?t={}", VERSION_URL, js_sys::Date::now() as u64);
+    let response = match Request::get(&url).send().await {
+        Ok(response) if response.ok() => response,
+        _ => return false,
+    };
+    match response.json::<Published>().await {
+        Ok(published) => published.differs(),
+        Err(_) => false,
+    }
+}
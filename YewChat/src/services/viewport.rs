@@ -0,0 +1,56 @@
+use js_sys::Reflect;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::EventTarget;
+use yew::Callback;
+
+/// Subscribes to `window.visualViewport` resize events, which is how mobile
+/// browsers report the on-screen keyboard opening and closing.
+///
+/// `VisualViewport` has no typed bindings in our web-sys version, so it is
+/// accessed through `Reflect`. The listener is removed when this is dropped.
+pub struct ViewportListener {
+    target: EventTarget,
+    closure: Closure<dyn Fn()>,
+}
+
+impl ViewportListener {
+    /// Calls `on_resize` with the visible viewport height in CSS pixels.
+    /// Returns `None` on browsers without the Visual Viewport API.
+    pub fn new(on_resize: Callback<f64>) -> Option<Self> {
+        let target: EventTarget = visual_viewport()?.unchecked_into();
+        let closure = Closure::wrap(Box::new(move || {
+            if let Some(height) = visible_height() {
+                on_resize.emit(height);
+            }
+        }) as Box<dyn Fn()>);
+
+        for event in ["resize", "scroll"] {
+            target
+                .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                .ok()?;
+        }
+        Some(Self { target, closure })
+    }
+}
+
+impl Drop for ViewportListener {
+    fn drop(&mut self) {
+        for event in ["resize", "scroll"] {
+            let _ = self
+                .target
+                .remove_event_listener_with_callback(event, self.closure.as_ref().unchecked_ref());
+        }
+    }
+}
+
+fn visual_viewport() -> Option<JsValue> {
+    let window = web_sys::window()?;
+    Reflect::get(&window, &"visualViewport".into())
+        .ok()
+        .filter(|v| !v.is_undefined() && !v.is_null())
+}
+
+/// Height of the part of the page not covered by the on-screen keyboard.
+pub fn visible_height() -> Option<f64> {
+    Reflect::get(&visual_viewport()?, &"height".into()).ok()?.as_f64()
+}
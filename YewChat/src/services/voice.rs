@@ -0,0 +1,304 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{
+    AnalyserNode, AudioContext, HtmlAudioElement, MediaStream, RtcPeerConnection, RtcPeerConnectionIceEvent, RtcSdpType,
+    RtcTrackEvent,
+};
+use yew::Callback;
+
+use crate::services::rtc::{self, IceCandidate, PendingCandidates};
+
+// Mean distance from silence in a frame of samples, out of 128, that counts as speaking
+const SPEAKING_LEVEL: f64 = 3.0;
+const METER_FFT_SIZE: u32 = 512;
+
+/// Someone in a room's voice channel.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Participant {
+    pub name: String,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+/// Everyone in a room's voice channel, sent by the server in a `voice` frame
+/// whenever someone joins, leaves or mutes.
+#[derive(Deserialize)]
+pub struct Roster {
+    pub room: String,
+    pub participants: Vec<Participant>,
+}
+
+/// Joining, leaving or muting, in the `data` of a `voice` frame we send.
+#[derive(Serialize)]
+pub struct VoiceUpdate {
+    pub room: String,
+    pub joined: bool,
+    pub muted: bool,
+}
+
+/// Connection setup between two participants, passed on by the server in
+/// `signal` frames. The audio itself goes directly between browsers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Signal {
+    /// Filled in by the server from the sending connection
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from: String,
+    pub to: String,
+    #[serde(flatten)]
+    pub payload: SignalPayload,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SignalPayload {
+    Offer {
+        sdp: String,
+    },
+    Answer {
+        sdp: String,
+    },
+    Candidate(IceCandidate),
+}
+
+// A connection to one other participant
+struct Peer {
+    connection: RtcPeerConnection,
+    pending: PendingCandidates,
+    audio: HtmlAudioElement,
+    _on_candidate: Closure<dyn FnMut(RtcPeerConnectionIceEvent)>,
+    _on_track: Closure<dyn FnMut(RtcTrackEvent)>,
+}
+
+impl Drop for Peer {
+    fn drop(&mut self) {
+        self.connection.close();
+        self.audio.set_src_object(None);
+    }
+}
+
+/// Our part in a room's voice channel: the microphone and a connection to
+/// each other participant. Dropping it hangs up.
+pub struct Voice {
+    username: String,
+    microphone: MediaStream,
+    audio: Option<AudioContext>,
+    // Level meters by participant, ourselves included; filled in as their audio arrives
+    meters: Rc<RefCell<BTreeMap<String, AnalyserNode>>>,
+    peers: BTreeMap<String, Peer>,
+    on_signal: Callback<Signal>,
+}
+
+impl Voice {
+    pub fn new(username: &str, microphone: MediaStream, on_signal: Callback<Signal>) -> Self {
+        let audio = AudioContext::new().ok();
+        let meters = Rc::new(RefCell::new(BTreeMap::new()));
+        if let Some(meter) = audio.as_ref().and_then(|audio| meter(audio, &microphone)) {
+            meters.borrow_mut().insert(username.to_string(), meter);
+        }
+        Self {
+            username: username.to_string(),
+            microphone,
+            audio,
+            meters,
+            peers: BTreeMap::new(),
+            on_signal,
+        }
+    }
+
+    pub fn set_muted(&self, muted: bool) {
+        rtc::set_enabled(&self.microphone, true, !muted);
+    }
+
+    /// Our name changed; everyone else connects to us again under the new one.
+    pub fn rename(&mut self, to: &str) {
+        let mut meters = self.meters.borrow_mut();
+        if let Some(meter) = meters.remove(&self.username) {
+            meters.insert(to.to_string(), meter);
+        }
+        self.username = to.to_string();
+        self.peers.clear();
+    }
+
+    /// Calls whoever is new in `participants` and hangs up on whoever left.
+    /// Of each pair, the one whose name sorts first makes the call, so two
+    /// offers never cross.
+    pub fn sync(&mut self, participants: &[Participant]) {
+        let present = |name: &String| participants.iter().any(|p| &p.name == name);
+        self.peers.retain(|name, _| present(name));
+        let username = &self.username;
+        self.meters.borrow_mut().retain(|name, _| name == username || present(name));
+        let callees: Vec<String> = participants
+            .iter()
+            .filter(|p| p.name > self.username && !self.peers.contains_key(&p.name))
+            .map(|p| p.name.clone())
+            .collect();
+        for name in callees {
+            self.call(name);
+        }
+    }
+
+    /// Applies a signal another participant sent us.
+    pub fn handle(&mut self, signal: Signal) {
+        let from = signal.from;
+        match signal.payload {
+            SignalPayload::Offer { sdp } => {
+                // A new offer starts over, e.g. after they reconnected
+                self.peers.remove(&from);
+                let (connection, pending) = match self.connect(&from) {
+                    Some(connected) => connected,
+                    None => return,
+                };
+                let on_signal = self.on_signal.clone();
+                spawn_local(async move {
+                    let answer = async {
+                        rtc::set_remote(&connection, &pending, RtcSdpType::Offer, &sdp).await?;
+                        rtc::local_description(&connection, RtcSdpType::Answer).await
+                    };
+                    match answer.await {
+                        Ok(sdp) => on_signal.emit(Signal {
+                            from: String::new(),
+                            to: from,
+                            payload: SignalPayload::Answer { sdp },
+                        }),
+                        Err(e) => log::error!("voice: answering {}: {:?}", from, e),
+                    }
+                });
+            }
+            SignalPayload::Answer { sdp } => {
+                if let Some(peer) = self.peers.get(&from) {
+                    let connection = peer.connection.clone();
+                    let pending = peer.pending.clone();
+                    spawn_local(async move {
+                        if let Err(e) = rtc::set_remote(&connection, &pending, RtcSdpType::Answer, &sdp).await {
+                            log::error!("voice: {} answered: {:?}", from, e);
+                        }
+                    });
+                }
+            }
+            SignalPayload::Candidate(candidate) => {
+                if let Some(peer) = self.peers.get(&from) {
+                    candidate.apply(&peer.connection, &peer.pending);
+                }
+            }
+        }
+    }
+
+    /// Who is making sound right now, ourselves included.
+    pub fn speaking(&self) -> BTreeSet<String> {
+        self.meters
+            .borrow()
+            .iter()
+            .filter(|(_, meter)| level(meter) > SPEAKING_LEVEL)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    fn call(&mut self, to: String) {
+        let (connection, _) = match self.connect(&to) {
+            Some(connected) => connected,
+            None => return,
+        };
+        let on_signal = self.on_signal.clone();
+        spawn_local(async move {
+            match rtc::local_description(&connection, RtcSdpType::Offer).await {
+                Ok(sdp) => on_signal.emit(Signal {
+                    from: String::new(),
+                    to,
+                    payload: SignalPayload::Offer { sdp },
+                }),
+                Err(e) => log::error!("voice: calling {}: {:?}", to, e),
+            }
+        });
+    }
+
+    // Sets up a connection to `name` carrying our microphone, ready for an offer or answer
+    fn connect(&mut self, name: &str) -> Option<(RtcPeerConnection, PendingCandidates)> {
+        let connection = match rtc::peer_connection(&self.microphone) {
+            Ok(connection) => connection,
+            Err(e) => {
+                log::error!("voice: connecting to {}: {:?}", name, e);
+                return None;
+            }
+        };
+
+        let on_candidate = {
+            let on_signal = self.on_signal.clone();
+            let to = name.to_string();
+            Closure::wrap(Box::new(move |e: RtcPeerConnectionIceEvent| {
+                // The last one is empty, marking the end of gathering
+                if let Some(candidate) = e.candidate() {
+                    on_signal.emit(Signal {
+                        from: String::new(),
+                        to: to.clone(),
+                        payload: SignalPayload::Candidate(IceCandidate::from_rtc(&candidate)),
+                    });
+                }
+            }) as Box<dyn FnMut(RtcPeerConnectionIceEvent)>)
+        };
+        connection.set_onicecandidate(Some(on_candidate.as_ref().unchecked_ref()));
+
+        let audio = HtmlAudioElement::new().expect("audio elements can be created");
+        audio.set_autoplay(true);
+        let on_track = {
+            let audio_element = audio.clone();
+            let context = self.audio.clone();
+            let meters = self.meters.clone();
+            let name = name.to_string();
+            Closure::wrap(Box::new(move |e: RtcTrackEvent| {
+                if let Ok(stream) = e.streams().get(0).dyn_into::<MediaStream>() {
+                    audio_element.set_src_object(Some(&stream));
+                    let _ = audio_element.play();
+                    if let Some(meter) = context.as_ref().and_then(|context| meter(context, &stream)) {
+                        meters.borrow_mut().insert(name.clone(), meter);
+                    }
+                }
+            }) as Box<dyn FnMut(RtcTrackEvent)>)
+        };
+        connection.set_ontrack(Some(on_track.as_ref().unchecked_ref()));
+
+        let pending = rtc::pending_candidates();
+        self.peers.insert(
+            name.to_string(),
+            Peer {
+                connection: connection.clone(),
+                pending: pending.clone(),
+                audio,
+                _on_candidate: on_candidate,
+                _on_track: on_track,
+            },
+        );
+        Some((connection, pending))
+    }
+}
+
+impl Drop for Voice {
+    fn drop(&mut self) {
+        self.peers.clear();
+        rtc::stop(&self.microphone);
+        if let Some(audio) = &self.audio {
+            let _ = audio.close();
+        }
+    }
+}
+
+fn meter(audio: &AudioContext, stream: &MediaStream) -> Option<AnalyserNode> {
+    let source = audio.create_media_stream_source(stream).ok()?;
+    let analyser = audio.create_analyser().ok()?;
+    analyser.set_fft_size(METER_FFT_SIZE);
+    source.connect_with_audio_node(&analyser).ok()?;
+    Some(analyser)
+}
+
+fn level(meter: &AnalyserNode) -> f64 {
+    let mut samples = vec![0u8; meter.fft_size() as usize];
+    meter.get_byte_time_domain_data(&mut samples);
+    let total: f64 = samples.iter().map(|s| (f64::from(*s) - 128.0).abs()).sum();
+    total / samples.len().max(1) as f64
+}
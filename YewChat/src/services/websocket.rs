@@ -1,50 +1,251 @@
-use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
-use reqwasm::websocket::{futures::WebSocket, Message};
-use yew_agent::Dispatched;
-use crate::services::event_bus::{EventBus, Request};
+use std::cell::RefCell;
 
-use wasm_bindgen_futures::spawn_local;
+use futures::{
+    channel::mpsc::{Receiver, Sender},
+    future::LocalBoxFuture,
+    lock::Mutex,
+    stream::{self, SplitSink, SplitStream},
+    FutureExt, SinkExt, StreamExt,
+};
+use gloo_timers::future::TimeoutFuture;
+use reqwasm::websocket::{futures::WebSocket, Message, State};
+use yew::Callback;
+use crate::services::{
+    bench::{Bench, BenchTransport},
+    endpoints::Endpoints,
+    irc::IrcTransport,
+    long_poll::HttpTransport,
+    matrix::MatrixTransport,
+    shared_socket::SharedSocket,
+    storage,
+    transport::{self, Fallback, Frames, Transport},
+};
+#[cfg(feature = "webtransport")]
+use crate::services::webtransport::{self, WebTransport};
+
+// Failed attempts without ever getting through before assuming websockets are blocked
+pub const FAILURES_BEFORE_POLLING: u32 = 3;
+// Set for the tab once we've fallen back, so reloads don't wait for the socket again
+const POLLING_KEY: &str = "polling";
+
+#[cfg(test)]
+thread_local! {
+    // Taken by the next chat that connects, in place of a real server
+    pub static MOCK: RefCell<Option<transport::mock::MockTransport>> = RefCell::new(None);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Connected,
+    Reconnecting,
+    /// Never got through, even after falling back; still retrying in the background
+    Unreachable,
+}
 
 pub struct WebsocketService {
     pub tx: Sender<String>,
+    shared: Option<SharedSocket>,
 }
 
 impl WebsocketService {
-    pub fn new() -> Self {
-        let ws = WebSocket::open("ws://127.0.0.1:7000").unwrap();
-        let (mut write, mut read) = ws.split();
-        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<String>(1000);
-        let mut event_bus = EventBus::dispatcher();
-
-        spawn_local(async move {
-            while let Some(s) = in_rx.next().await {
-                log::debug!("Got event from channel! {}", s);
-                write.send(Message::Text(s)).await.unwrap();
+    /// Connects through the cross-tab shared worker where available, so several
+    /// tabs share one socket, and falls back to a socket owned by this tab.
+    /// A session `token` lets the server know which account we signed in as.
+    /// With a Matrix room or IRC channel configured, that is used instead of our server.
+    /// Where websockets never get through, this falls back to plain HTTP.
+    pub fn new(token: Option<&str>, on_status: Callback<ConnectionStatus>) -> Self {
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        #[cfg(test)]
+        if let Some(transport) = MOCK.with(|m| m.borrow_mut().take()) {
+            transport::run(transport, in_rx, on_status, None);
+            return Self::unshared(in_tx);
+        }
+        if let Some(bench) = Bench::requested() {
+            transport::run(BenchTransport::new(bench), in_rx, on_status, None);
+            return Self::unshared(in_tx);
+        }
+        if let Some(transport) = MatrixTransport::configured() {
+            transport::run(transport, in_rx, on_status, None);
+            return Self::unshared(in_tx);
+        }
+        if let Some(transport) = IrcTransport::configured() {
+            transport::run(transport, in_rx, on_status, None);
+            return Self::unshared(in_tx);
+        }
+        let endpoints = Endpoints::current();
+        // A server from the page's URL could be anyone's
+        let token = token.filter(|_| !endpoints.from_query);
+        let url = match token {
+            Some(token) => format!("{}/?token={}", endpoints.websocket, token),
+            None => endpoints.websocket.clone(),
+        };
+        if storage::load_for_tab(POLLING_KEY).unwrap_or(false) {
+            let transport = HttpTransport::new(&url);
+            transport::run(transport, in_rx, on_status, None);
+            return Self::unshared(in_tx);
+        }
+        #[cfg(feature = "webtransport")]
+        if let Some(wt_url) = endpoints.webtransport.filter(|_| webtransport::supported()) {
+            let wt_url = match token {
+                Some(token) => format!("{}?token={}", wt_url, token),
+                None => wt_url,
+            };
+            // Straight to the websocket if the very first session can't be set up
+            let fallback = Fallback {
+                after: 1,
+                then: {
+                    let (url, on_status) = (url.clone(), on_status.clone());
+                    Box::new(move |rx| dedicated(url, rx, on_status))
+                },
+            };
+            let transport = WebTransport::new(wt_url);
+            transport::run(transport, in_rx, on_status, Some(fallback));
+            return Self::unshared(in_tx);
+        }
+        let unreachable = {
+            let (url, on_status) = (url.clone(), on_status.clone());
+            move |rx| fall_back(&url, rx, on_status)
+        };
+        if let Some(shared) = SharedSocket::open(&url, in_rx, on_status.clone(), unreachable) {
+            return Self {
+                tx: in_tx,
+                shared: Some(shared),
+            };
+        }
+        // `in_rx` was moved into the failed attempt; start over with a fresh channel
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        dedicated(url, in_rx, on_status);
+        Self::unshared(in_tx)
+    }
+
+    /// For transports run by this tab, which only need the sending end kept.
+    fn unshared(tx: Sender<String>) -> Self {
+        Self { tx, shared: None }
+    }
+
+    /// Hangs up for good: the connection closes once anything already queued
+    /// has gone, and nothing sent afterwards goes anywhere.
+    pub fn close(&mut self) {
+        self.tx.close_channel();
+        self.shared = None;
+    }
+}
+
+/// Forgets that websockets didn't get through in this tab, so the next
+/// connection tries them again before falling back.
+pub fn forget_fallback() {
+    storage::remove(POLLING_KEY);
+}
+
+/// A socket owned by this tab alone, falling back to HTTP if it never gets through.
+fn dedicated(url: String, rx: Receiver<String>, on_status: Callback<ConnectionStatus>) {
+    let fallback = Fallback {
+        after: FAILURES_BEFORE_POLLING,
+        then: {
+            let (url, on_status) = (url.clone(), on_status.clone());
+            Box::new(move |rx| fall_back(&url, rx, on_status))
+        },
+    };
+    transport::run(SocketTransport::new(url), rx, on_status, Some(fallback));
+}
+
+/// Gives up on websockets for this tab and carries on over HTTP.
+fn fall_back(url: &str, rx: Receiver<String>, on_status: Callback<ConnectionStatus>) {
+    log::warn!("Websocket unreachable after {} attempts, falling back to HTTP", FAILURES_BEFORE_POLLING);
+    storage::save_for_tab(POLLING_KEY, &true);
+    transport::run(HttpTransport::new(url), rx, on_status, None);
+}
+
+/// The chat server over a websocket of our own.
+pub struct SocketTransport {
+    url: String,
+    write: Mutex<Option<SplitSink<WebSocket, Message>>>,
+    read: RefCell<Option<SplitStream<WebSocket>>>,
+}
+
+impl SocketTransport {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            write: Mutex::new(None),
+            read: RefCell::new(None),
+        }
+    }
+}
+
+impl Transport for SocketTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let (write, read) = match connect(&self.url).await {
+                Some(ws) => ws.split(),
+                None => return false,
+            };
+            *self.write.lock().await = Some(write);
+            *self.read.borrow_mut() = Some(read);
+            true
+        }
+        .boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let mut write = self.write.lock().await;
+            let write = match write.as_mut() {
+                Some(write) => write,
+                None => return false,
+            };
+            match write.send(Message::Text(frame)).await {
+                Ok(()) => true,
+                Err(e) => {
+                    log::error!("ws send: {:?}", e);
+                    false
+                }
             }
-        });
+        }
+        .boxed_local()
+    }
 
-        spawn_local(async move {
-            while let Some(msg) = read.next().await {
+    fn events(&self) -> Frames<'_> {
+        let read = self.read.borrow_mut().take();
+        stream::iter(read)
+            .flatten()
+            .filter_map(|msg| async move {
                 match msg {
-                    Ok(Message::Text(data)) => {
-                        log::debug!("From websocket: {}", data);
-                        event_bus.send(Request::EventBusMsg(data));
-                    }
-                    Ok(Message::Bytes(b)) => {
-                        let decoded = std::str::from_utf8(&b);
-                        if let Ok(val) = decoded {
-                            log::debug!("From websocket: {}", val);
-                            event_bus.send(Request::EventBusMsg(val.into()));
-                        }
-                    }
+                    Ok(Message::Text(data)) => Some(data),
+                    Ok(Message::Bytes(b)) => String::from_utf8(b).ok(),
                     Err(e) => {
                         log::error!("ws: {:?}", e);
+                        None
                     }
                 }
-            }
-            log::debug!("WebSocket closed!");
-        });
+            })
+            .boxed_local()
+    }
 
-        Self { tx: in_tx }
+    fn close(&self) {
+        // Dropping both halves closes the socket
+        self.read.borrow_mut().take();
+        if let Some(mut write) = self.write.try_lock() {
+            write.take();
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Opens a socket and waits for the handshake, `None` if it never opens.
+pub async fn connect(url: &str) -> Option<WebSocket> {
+    let ws = match WebSocket::open(url) {
+        Ok(ws) => ws,
+        Err(e) => {
+            log::error!("ws open: {:?}", e);
+            return None;
+        }
+    };
+    while let State::Connecting = ws.state() {
+        TimeoutFuture::new(50).await;
+    }
+    match ws.state() {
+        State::Open => Some(ws),
+        _ => None,
+    }
+}
@@ -0,0 +1,133 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures::{future::LocalBoxFuture, stream, FutureExt, StreamExt};
+use js_sys::{Array, Function, Promise, Reflect, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::services::transport::{Frames, Transport};
+
+fn get(target: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(target, &key.into()).ok().filter(|v| !v.is_undefined())
+}
+
+fn call(target: &JsValue, method: &str, args: &[JsValue]) -> Option<JsValue> {
+    let function: Function = get(target, method)?.dyn_into().ok()?;
+    function.apply(target, &args.iter().collect::<Array>()).ok()
+}
+
+async fn resolve(promise: JsValue) -> Option<JsValue> {
+    JsFuture::from(promise.dyn_into::<Promise>().ok()?).await.ok()
+}
+
+/// Whether this browser has the WebTransport API at all.
+pub fn supported() -> bool {
+    get(&js_sys::global(), "WebTransport").is_some()
+}
+
+/// One bidirectional stream on a WebTransport session, carrying the same JSON
+/// frames as the websocket, one per line.
+struct Connection {
+    transport: JsValue,
+    writer: JsValue,
+    reader: JsValue,
+}
+
+impl Connection {
+    async fn open(url: &str) -> Option<Self> {
+        let constructor: Function = get(&js_sys::global(), "WebTransport")?.dyn_into().ok()?;
+        let transport = Reflect::construct(&constructor, &Array::of1(&url.into()))
+            .map_err(|e| log::error!("webtransport: {:?}", e))
+            .ok()?;
+        resolve(get(&transport, "ready")?).await?;
+        let stream = resolve(call(&transport, "createBidirectionalStream", &[])?).await?;
+        let writer = call(&get(&stream, "writable")?, "getWriter", &[])?;
+        let reader = call(&get(&stream, "readable")?, "getReader", &[])?;
+        Some(Self { transport, writer, reader })
+    }
+
+    async fn send(&self, frame: &str) -> bool {
+        let bytes = Uint8Array::from(format!("{}\n", frame).as_bytes());
+        match call(&self.writer, "write", &[bytes.into()]) {
+            Some(written) => resolve(written).await.is_some(),
+            None => false,
+        }
+    }
+
+    /// The next chunk of bytes, `None` once the stream is done or broken.
+    async fn read(&self) -> Option<Vec<u8>> {
+        let chunk = resolve(call(&self.reader, "read", &[])?).await?;
+        if get(&chunk, "done").and_then(|d| d.as_bool()).unwrap_or(false) {
+            return None;
+        }
+        Some(get(&chunk, "value")?.dyn_into::<Uint8Array>().ok()?.to_vec())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = call(&self.transport, "close", &[]);
+    }
+}
+
+/// The chat over WebTransport, which avoids head-of-line blocking on lossy links.
+pub struct WebTransport {
+    url: String,
+    connection: RefCell<Option<Rc<Connection>>>,
+}
+
+impl WebTransport {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            connection: RefCell::new(None),
+        }
+    }
+}
+
+impl Transport for WebTransport {
+    fn connect(&self) -> LocalBoxFuture<'_, bool> {
+        async move {
+            let connection = Connection::open(&self.url).await.map(Rc::new);
+            let connected = connection.is_some();
+            *self.connection.borrow_mut() = connection;
+            connected
+        }
+        .boxed_local()
+    }
+
+    fn send(&self, frame: String) -> LocalBoxFuture<'_, bool> {
+        let connection = self.connection.borrow().clone();
+        async move {
+            match connection {
+                Some(connection) => connection.send(&frame).await,
+                None => false,
+            }
+        }
+        .boxed_local()
+    }
+
+    fn events(&self) -> Frames<'_> {
+        let connection = match self.connection.borrow().clone() {
+            Some(connection) => connection,
+            None => return stream::empty().boxed_local(),
+        };
+        stream::unfold((connection, Vec::new()), |(connection, mut pending)| async move {
+            loop {
+                // Frames may be split across chunks, or several may share one
+                if let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    let frame = String::from_utf8_lossy(&line[..end]).into_owned();
+                    return Some((frame, (connection, pending)));
+                }
+                pending.extend(connection.read().await?);
+            }
+        })
+        .boxed_local()
+    }
+
+    fn close(&self) {
+        // The session closes once the last handle on it is dropped
+        self.connection.borrow_mut().take();
+    }
+}
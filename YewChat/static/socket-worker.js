@@ -0,0 +1,270 @@
+// SharedWorker that owns the chat websockets for every open tab of the app.
+//
+// Tabs talk to it over their MessagePort:
+//   tab -> worker: { type: 'connect', url } | { type: 'send', data } | { type: 'close' }
+//   worker -> tab: { type: 'frame', data } | { type: 'status', status }
+//
+// Tabs on the same URL that register the same username share one socket, which
+// keeps the user list free of duplicates. A tab registering a different name
+// gets a socket of its own: the server knows who sent a frame only by its socket,
+// and frames meant for one identity must never reach another's tabs.
+
+const RECONNECT_BASE_DELAY_MS = 1000;
+const RECONNECT_MAX_DELAY_MS = 30000;
+
+// Server replies that only the requesting tab should see, keyed by request type
+const REPLY_TYPES = {
+    history: 'history',
+    resync: 'resync',
+    catchup: 'catchup',
+    unfurl: 'unfurl',
+    createinvite: 'invite',
+    joinbytoken: 'joined',
+    joinroom: 'joinedroom',
+    ping: 'pong',
+};
+const ERROR_TYPES = { register: 'registererror', rename: 'renameerror' };
+// Answers that mean the last `register` didn't go through, so the name may be tried again
+const REGISTER_REFUSALS = ['registererror', 'challenge'];
+// Latest room state, replayed to tabs that join under an already registered name
+const STATE_TYPES = ['users', 'permissions', 'room'];
+
+// Every open socket, each with the tabs using it
+const connections = new Set();
+// The connection each tab is on
+const connectionOf = new Map();
+
+const parse = (data) => {
+    try {
+        return JSON.parse(data);
+    } catch (e) {
+        // Not ours to judge; just pass it on
+        return null;
+    }
+};
+
+const post = (ports, message) => ports.forEach((port) => port.postMessage(message));
+
+const setStatus = (connection, status) => {
+    connection.status = status;
+    post(connection.ports, { type: 'status', status });
+};
+
+// A socket to `url`, registered as `name` once a tab asks; `null` until then
+const createConnection = (url, name) => {
+    const connection = {
+        url,
+        name,
+        // Whether `name` has been sent to the current socket
+        registered: false,
+        // Whether the server ever accepted `name`
+        confirmed: false,
+        ports: new Set(),
+        socket: null,
+        status: 'connecting',
+        attempt: 0,
+        reconnectTimer: null,
+        // Frames sent while the socket isn't open yet, flushed once it is
+        outbox: [],
+        lastState: {},
+        // Ports waiting for a reply, in request order
+        pendingReplies: {},
+        lastRequester: {},
+    };
+    connections.add(connection);
+    open(connection);
+    return connection;
+};
+
+const open = (connection) => {
+    setStatus(connection, connection.attempt === 0 ? 'connecting' : 'reconnecting');
+    const socket = new WebSocket(connection.url);
+    connection.socket = socket;
+    connection.registered = false;
+
+    socket.onopen = () => {
+        connection.attempt = 0;
+        connection.outbox.forEach((data) => socket.send(data));
+        connection.outbox = [];
+        setStatus(connection, 'connected');
+    };
+
+    socket.onmessage = (event) => {
+        const data = event.data;
+        const frame = parse(data);
+        const type = frame && frame.messageType;
+        if (STATE_TYPES.includes(type)) {
+            connection.lastState[type] = data;
+        }
+
+        if (type === 'registered') {
+            connection.confirmed = true;
+        }
+        if (REGISTER_REFUSALS.includes(type)) {
+            connection.registered = false;
+            if (!connection.confirmed) {
+                connection.name = null;
+            }
+        }
+        if (type === 'rename') {
+            const rename = parse(frame.data);
+            if (rename && rename.from === connection.name) {
+                connection.name = rename.to;
+            }
+        }
+
+        const request = Object.keys(REPLY_TYPES).find((t) => REPLY_TYPES[t] === type);
+        const errorFor = type === 'challenge' ? 'register' : Object.keys(ERROR_TYPES).find((t) => ERROR_TYPES[t] === type);
+        const waiting = request && connection.pendingReplies[request];
+        const requester = errorFor && connection.lastRequester[errorFor];
+        if (waiting && waiting.length > 0) {
+            waiting.shift().postMessage({ type: 'frame', data });
+        } else if (requester && connection.ports.has(requester)) {
+            requester.postMessage({ type: 'frame', data });
+        } else {
+            post(connection.ports, { type: 'frame', data });
+        }
+    };
+
+    socket.onclose = () => {
+        if (connection.socket !== socket) {
+            return;
+        }
+        connection.socket = null;
+        if (connection.ports.size === 0) {
+            return;
+        }
+        connection.attempt += 1;
+        const delay = Math.min(RECONNECT_BASE_DELAY_MS * 2 ** (connection.attempt - 1), RECONNECT_MAX_DELAY_MS);
+        setStatus(connection, 'reconnecting');
+        connection.reconnectTimer = setTimeout(() => {
+            connection.reconnectTimer = null;
+            open(connection);
+        }, delay);
+    };
+};
+
+const transmit = (connection, data) => {
+    if (connection.socket && connection.socket.readyState === WebSocket.OPEN) {
+        connection.socket.send(data);
+    } else {
+        connection.outbox.push(data);
+    }
+};
+
+// Takes `port` off its connection, closing the socket once no tab is left on it
+const detach = (port) => {
+    const connection = connectionOf.get(port);
+    if (!connection) {
+        return;
+    }
+    connectionOf.delete(port);
+    connection.ports.delete(port);
+    Object.values(connection.pendingReplies).forEach((queue) => {
+        const index = queue.indexOf(port);
+        if (index !== -1) {
+            queue.splice(index, 1);
+        }
+    });
+    if (connection.ports.size === 0) {
+        connections.delete(connection);
+        clearTimeout(connection.reconnectTimer);
+        const socket = connection.socket;
+        connection.socket = null;
+        if (socket) {
+            socket.close();
+        }
+    }
+};
+
+const attach = (port, connection) => {
+    detach(port);
+    connection.ports.add(port);
+    connectionOf.set(port, connection);
+    port.postMessage({ type: 'status', status: connection.status });
+};
+
+const find = (url, name) => [...connections].find((c) => c.url === url && c.name === name);
+
+// Registering picks the socket: the one already registered under that name,
+// the tab's own if it hasn't got a name yet, or else a new one
+const register = (port, connection, name, data) => {
+    const existing = find(connection.url, name);
+    if (existing && existing !== connection) {
+        attach(port, existing);
+        connection = existing;
+    } else if (connection.name !== null && connection.name !== name) {
+        if (connection.ports.size > 1) {
+            connection = createConnection(connection.url, name);
+            attach(port, connection);
+        } else {
+            // Only this tab used the old name, so the socket can be reused under the new one
+            connection.name = name;
+            connection.confirmed = false;
+            connection.registered = false;
+            connection.lastState = {};
+            // The server would treat a second `register` on this socket as a rename
+            connection.socket && connection.socket.close();
+            connection.socket = null;
+            connection.attempt = 0;
+            open(connection);
+        }
+    }
+    connection.name = name;
+    if (connection.registered) {
+        // Another tab already joined under this name; just bring this one up to date
+        Object.values(connection.lastState).forEach((frame) => port.postMessage({ type: 'frame', data: frame }));
+        return;
+    }
+    connection.registered = true;
+    connection.lastRequester.register = port;
+    transmit(connection, data);
+};
+
+const send = (port, data) => {
+    const connection = connectionOf.get(port);
+    if (!connection) {
+        return;
+    }
+    const message = parse(data);
+    if (message && message.messageType === 'register') {
+        register(port, connection, message.data, data);
+        return;
+    }
+    if (message && REPLY_TYPES[message.messageType]) {
+        const queue = (connection.pendingReplies[message.messageType] = connection.pendingReplies[message.messageType] || []);
+        queue.push(port);
+    }
+    if (message && ERROR_TYPES[message.messageType]) {
+        connection.lastRequester[message.messageType] = port;
+    }
+    transmit(connection, data);
+};
+
+onconnect = (event) => {
+    const port = event.ports[0];
+
+    port.onmessage = (e) => {
+        const msg = e.data;
+        switch (msg.type) {
+            case 'connect': {
+                // Until it registers, a tab shares any socket to its URL that has no name yet;
+                // tabs that signed in or out connect with a different token, and so a different URL
+                const current = connectionOf.get(port);
+                if (current && current.url === msg.url) {
+                    port.postMessage({ type: 'status', status: current.status });
+                    break;
+                }
+                attach(port, find(msg.url, null) || createConnection(msg.url, null));
+                break;
+            }
+            case 'send':
+                send(port, msg.data);
+                break;
+            case 'close':
+                detach(port);
+                break;
+        }
+    };
+    port.start();
+};
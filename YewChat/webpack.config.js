@@ -1,13 +1,47 @@
+const fs = require('fs');
 const path = require('path');
+const { execSync } = require('child_process');
+const webpack = require('webpack');
 const CopyWebpackPlugin = require('copy-webpack-plugin');
 const WasmPackPlugin = require('@wasm-tool/wasm-pack-plugin');
 
 const distPath = path.resolve(__dirname, 'dist');
+// Served from our own origin rather than a CDN, so what renders math is exactly what was built
+const katexPath = path.dirname(require.resolve('katex/dist/katex.min.js'));
+
+// Identifies this build: baked into the client and written to version.json, so
+// open tabs notice when a different one has been deployed
+const build =
+    process.env.YEWCHAT_BUILD ||
+    (() => {
+        try {
+            return execSync('git rev-parse --short HEAD', { stdio: ['ignore', 'pipe', 'ignore'] }).toString().trim();
+        } catch (e) {
+            return String(Date.now());
+        }
+    })();
+// wasm-pack runs cargo with our environment, where `option_env!` picks it up
+process.env.YEWCHAT_BUILD = build;
+// The crate's version, which is what the client compares against when it has no build id
+const version = fs.readFileSync(path.resolve(__dirname, 'Cargo.toml'), 'utf8').match(/^version\s*=\s*"([^"]+)"/m)[1];
+
+class VersionFilePlugin {
+    apply(compiler) {
+        compiler.hooks.thisCompilation.tap('VersionFilePlugin', (compilation) => {
+            compilation.hooks.processAssets.tap(
+                { name: 'VersionFilePlugin', stage: webpack.Compilation.PROCESS_ASSETS_STAGE_ADDITIONAL },
+                () => compilation.emitAsset('version.json', new webpack.sources.RawSource(JSON.stringify({ version, build })))
+            );
+        });
+    }
+}
 
 module.exports = {
     mode: 'production',
     devServer: {
         port: 8000,
+        // Deep links such as /join/<token> and /chat/<room>#msg-<id> are routed in the browser
+        historyApiFallback: true,
     },
     entry: './bootstrap.js',
     output: {
@@ -17,13 +51,19 @@ module.exports = {
     },
     plugins: [
         new CopyWebpackPlugin({
-            patterns: [{ from: './static', to: distPath }],
+            patterns: [
+                { from: './static', to: distPath },
+                { from: path.join(katexPath, 'katex.min.js'), to: path.join(distPath, 'katex') },
+                { from: path.join(katexPath, 'katex.min.css'), to: path.join(distPath, 'katex') },
+                { from: path.join(katexPath, 'fonts'), to: path.join(distPath, 'katex', 'fonts') },
+            ],
         }),
         new WasmPackPlugin({
             crateDirectory: '.',
             extraArgs: '-- --features wee_alloc',
             outName: 'yewchat',
         }),
+        new VersionFilePlugin(),
     ],
     experiments: {
         asyncWebAssembly: true,
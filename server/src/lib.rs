@@ -0,0 +1,132 @@
+//! The chat server as a library, so tests can run it on a port of their own.
+
+mod protocol;
+mod room;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::Instant,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{net::TcpListener, sync::mpsc};
+
+use crate::{
+    protocol::{Frame, Limits, ServerInfo},
+    room::{Room, HISTORY_LIMIT, MAX_NICK_LENGTH},
+};
+
+// Where clients end up when they don't ask for a room
+const DEFAULT_ROOM: &str = "main";
+// In characters; names come from whoever connects, and each room is kept in memory
+const MAX_ROOM_NAME_LENGTH: usize = 64;
+
+type Rooms = Arc<Mutex<HashMap<String, Room>>>;
+
+// For the uptime in `serverinfo`
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct Params {
+    room: Option<String>,
+}
+
+/// Serves chat on `listener` until the process ends, with every room starting out empty.
+pub async fn serve(listener: TcpListener) -> std::io::Result<()> {
+    STARTED.get_or_init(Instant::now);
+    let app = Router::new().route("/", get(upgrade)).with_state(Rooms::default());
+    axum::serve(listener, app).await
+}
+
+// e.g. ws://host:7000/?room=rust; rooms are created on first use and dropped once everyone has left,
+// apart from the default room
+async fn upgrade(ws: WebSocketUpgrade, Query(params): Query<Params>, State(rooms): State<Rooms>) -> Response {
+    let room = params
+        .room
+        .filter(|room| !room.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_ROOM.to_string());
+    if room.chars().count() > MAX_ROOM_NAME_LENGTH {
+        return (StatusCode::BAD_REQUEST, "Room name is too long").into_response();
+    }
+    ws.on_upgrade(move |socket| connection(socket, room, rooms))
+}
+
+async fn connection(socket: WebSocket, room: String, rooms: Rooms) {
+    println!("ws connected to {}", room);
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let id = rooms
+        .lock()
+        .unwrap()
+        .entry(room.clone())
+        .or_insert_with(|| Room::new(&room))
+        .join(tx.clone());
+
+    let writer = tokio::spawn(async move {
+        while let Some(frame) = rx.recv().await {
+            if sink.send(Message::Text(frame.into())).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(Ok(message)) = stream.next().await {
+        match message {
+            // About the whole server rather than the room, so it's answered here
+            Message::Text(text) if is_server_info_request(text.as_str()) => {
+                let _ = tx.send(server_info(&rooms.lock().unwrap()));
+            }
+            Message::Text(text) => {
+                if let Some(room) = rooms.lock().unwrap().get_mut(&room) {
+                    room.handle(id, text.as_str());
+                }
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    {
+        let mut rooms = rooms.lock().unwrap();
+        if let Some(left) = rooms.get_mut(&room) {
+            left.leave(id);
+            if left.is_empty() && room != DEFAULT_ROOM {
+                rooms.remove(&room);
+            }
+        }
+    }
+    writer.abort();
+    println!("ws disconnected from {}", room);
+}
+
+fn is_server_info_request(text: &str) -> bool {
+    matches!(serde_json::from_str::<Frame>(text), Ok(frame) if frame.message_type == "serverinfo")
+}
+
+fn server_info(rooms: &HashMap<String, Room>) -> String {
+    let info = ServerInfo {
+        software: "YewChat Server",
+        version: env!("CARGO_PKG_VERSION"),
+        uptime: STARTED.get().map(|started| started.elapsed().as_secs()).unwrap_or_default(),
+        users: rooms.values().map(Room::member_count).sum(),
+        rooms: rooms.len(),
+        limits: Limits {
+            max_nick_length: MAX_NICK_LENGTH,
+            history_size: HISTORY_LIMIT,
+        },
+    };
+    json!({ "messageType": "serverinfo", "data": json!(info).to_string() }).to_string()
+}
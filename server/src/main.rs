@@ -0,0 +1,15 @@
+use std::{env, net::SocketAddr};
+
+use tokio::net::TcpListener;
+
+const DEFAULT_PORT: u16 = 7000;
+
+#[tokio::main]
+async fn main() {
+    let port = env::var("PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(DEFAULT_PORT);
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))
+        .await
+        .expect("failed to bind");
+    println!("Listening on port {}", port);
+    yewchat_server::serve(listener).await.expect("server failed");
+}
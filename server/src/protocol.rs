@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A frame as clients send it.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Frame {
+    pub message_type: String,
+    #[serde(default)]
+    pub data: Option<String>,
+    // Passed through to everyone untouched
+    #[serde(default)]
+    pub reply_to: Option<Value>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub media: Option<Value>,
+    #[serde(default)]
+    pub location: Option<Value>,
+    // With `register`, the token from the client's previous registration
+    #[serde(default)]
+    pub reclaim: Option<String>,
+}
+
+/// A chat message as stored in history and broadcast in the `data` of a `message` frame.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageData {
+    pub id: String,
+    pub seq: u64,
+    pub from: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Value>,
+    pub time: i64,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryRequest {
+    #[serde(default)]
+    pub before: Option<i64>,
+    pub limit: usize,
+}
+
+#[derive(Deserialize)]
+pub struct CatchupRequest {
+    pub after: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ResyncRequest {
+    pub from: u64,
+    pub to: u64,
+}
+
+/// How far a reader got in a room, in the `data` of a `readmarker` frame.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReadMarker {
+    pub room: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    pub time: i64,
+}
+
+impl ReadMarker {
+    /// Whether this points past `other`, by sequence number where both have one.
+    pub fn is_after(&self, other: &ReadMarker) -> bool {
+        match (self.seq, other.seq) {
+            (Some(a), Some(b)) => a > b,
+            _ => self.time > other.time,
+        }
+    }
+}
+
+/// Joining, leaving or muting in a room's voice channel, in the `data` of a `voice` frame.
+#[derive(Deserialize)]
+pub struct VoiceUpdate {
+    pub room: String,
+    pub joined: bool,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    pub to: String,
+}
+
+/// What the server says about itself, in the `data` of a `serverinfo` frame.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    pub software: &'static str,
+    pub version: &'static str,
+    /// Seconds since it started
+    pub uptime: u64,
+    /// Registered across every room
+    pub users: usize,
+    pub rooms: usize,
+    pub limits: Limits,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    pub max_nick_length: usize,
+    /// Messages kept per room
+    pub history_size: usize,
+}
@@ -0,0 +1,383 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde_json::json;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::protocol::{
+    CatchupRequest, Frame, HistoryRequest, MessageData, ReadMarker, RenameRequest, ResyncRequest, VoiceUpdate,
+};
+
+// Messages kept per room for clients paging back through history
+pub const HISTORY_LIMIT: usize = 1000;
+pub const MAX_NICK_LENGTH: usize = 20;
+
+struct Client {
+    tx: UnboundedSender<String>,
+    // Set once they've registered
+    nick: Option<String>,
+    // Handed out on registering; presenting it again takes the nickname back from this connection
+    reclaim: Option<String>,
+    // Whether they're muted, while in the room's voice channel
+    voice: Option<bool>,
+}
+
+// A message already serialized, with what history requests filter on
+struct Stored {
+    time: i64,
+    seq: u64,
+    json: String,
+}
+
+/// Everyone connected to one room, and what has been said in it.
+pub struct Room {
+    name: String,
+    // Keyed by connection, in the order they joined
+    clients: BTreeMap<u64, Client>,
+    next_client: u64,
+    history: VecDeque<Stored>,
+    next_message_id: u64,
+    // Gapless so clients can detect dropped frames
+    next_seq: u64,
+    // Where each nickname has read up to, kept across reconnects
+    read_markers: HashMap<String, ReadMarker>,
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn reply(message_type: &str, data: impl Into<String>) -> String {
+    json!({ "messageType": message_type, "data": data.into() }).to_string()
+}
+
+fn reply_list(message_type: &str, data_array: Vec<String>) -> String {
+    json!({ "messageType": message_type, "dataArray": data_array }).to_string()
+}
+
+impl Room {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            clients: BTreeMap::new(),
+            next_client: 1,
+            history: VecDeque::new(),
+            next_message_id: 1,
+            next_seq: 1,
+            read_markers: HashMap::new(),
+        }
+    }
+
+    /// Adds a connection, returning the ID its frames are handled under.
+    pub fn join(&mut self, tx: UnboundedSender<String>) -> u64 {
+        let id = self.next_client;
+        self.next_client += 1;
+        self.clients.insert(id, Client { tx, nick: None, reclaim: None, voice: None });
+        id
+    }
+
+    pub fn leave(&mut self, id: u64) {
+        let (registered, in_voice) = match self.clients.remove(&id) {
+            Some(client) => (client.nick.is_some(), client.voice.is_some()),
+            None => return,
+        };
+        if registered {
+            self.broadcast(&self.users());
+        }
+        if in_voice {
+            self.broadcast(&self.voice());
+        }
+    }
+
+    fn send(&self, id: u64, frame: &str) {
+        if let Some(client) = self.clients.get(&id) {
+            let _ = client.tx.send(frame.to_string());
+        }
+    }
+
+    fn broadcast(&self, frame: &str) {
+        for client in self.clients.values() {
+            let _ = client.tx.send(frame.to_string());
+        }
+    }
+
+    fn nick(&self, id: u64) -> Option<String> {
+        self.clients.get(&id).and_then(|c| c.nick.clone())
+    }
+
+    /// Whether nobody is connected, registered or not.
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// How many have registered, as opposed to just connected.
+    pub fn member_count(&self) -> usize {
+        self.nicks().count()
+    }
+
+    fn nicks(&self) -> impl Iterator<Item = &String> {
+        self.clients.values().filter_map(|c| c.nick.as_ref())
+    }
+
+    // `dataArray` is the nickname list older clients read; `data` names the room too
+    fn users(&self) -> String {
+        let members: Vec<_> = self.nicks().map(|nick| json!({ "nick": nick })).collect();
+        json!({
+            "messageType": "users",
+            "dataArray": self.nicks().collect::<Vec<_>>(),
+            "data": json!({ "room": self.name, "members": members }).to_string(),
+        })
+        .to_string()
+    }
+
+    // Who's in the voice channel; the audio goes directly between their browsers
+    fn voice(&self) -> String {
+        let participants: Vec<_> = self
+            .clients
+            .values()
+            .filter_map(|c| Some(json!({ "name": c.nick.as_ref()?, "muted": c.voice? })))
+            .collect();
+        reply("voice", json!({ "room": self.name, "participants": participants }).to_string())
+    }
+
+    fn in_voice(&self, id: u64) -> bool {
+        matches!(self.clients.get(&id), Some(Client { voice: Some(_), .. }))
+    }
+
+    /// Why a nickname can't be used, or `None` if it can.
+    fn validate_nick(&self, nick: &str) -> Option<String> {
+        if nick.trim().is_empty() {
+            return Some("Username is required".to_string());
+        }
+        if nick.chars().count() > MAX_NICK_LENGTH {
+            return Some(format!("Username can be at most {} characters", MAX_NICK_LENGTH));
+        }
+        if !nick.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+            return Some("Username may only contain letters, digits, '_', '-' or '.'".to_string());
+        }
+        if self.nicks().any(|n| n == nick) {
+            return Some(format!("The name \"{}\" is already taken", nick));
+        }
+        None
+    }
+
+    /// Handles one frame from connection `id`; unreadable frames are logged and dropped.
+    pub fn handle(&mut self, id: u64, text: &str) {
+        let frame: Frame = match serde_json::from_str(text) {
+            Ok(frame) => frame,
+            Err(e) => {
+                eprintln!("Error in message {}: {}", text, e);
+                return;
+            }
+        };
+        let data = frame.data.clone().unwrap_or_default();
+        match frame.message_type.as_str() {
+            "register" => {
+                // A connection can look alive long after the client lost it; with the nickname's
+                // token the client takes the name over instead of being told it's taken
+                let stale = self.clients.iter().find_map(|(&other, client)| {
+                    let reclaims = frame.reclaim.is_some() && client.reclaim == frame.reclaim;
+                    (other != id && client.nick.as_deref() == Some(data.as_str()) && reclaims).then_some(other)
+                });
+                if let Some(stale) = stale {
+                    // Dropping its sender ends the connection's writer
+                    self.clients.remove(&stale);
+                }
+                if let Some(error) = self.validate_nick(&data) {
+                    self.send(id, &reply("registererror", error));
+                    return;
+                }
+                let token = format!("{:032x}", rand::random::<u128>());
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.nick = Some(data.clone());
+                    client.reclaim = Some(token.clone());
+                }
+                self.send(id, &reply("registered", json!({ "nick": data, "token": token }).to_string()));
+                self.broadcast(&self.users());
+                if let Some(marker) = self.read_markers.get(&data) {
+                    self.send(id, &reply("readmarker", json!(marker).to_string()));
+                }
+                if self.clients.values().any(|c| c.voice.is_some()) {
+                    self.send(id, &self.voice());
+                }
+            }
+            "rename" => {
+                let from = match self.nick(id) {
+                    Some(from) => from,
+                    None => return,
+                };
+                let to = match serde_json::from_str::<RenameRequest>(&data) {
+                    Ok(request) => request.to,
+                    Err(_) => return,
+                };
+                if let Some(error) = self.validate_nick(&to) {
+                    self.send(id, &reply("renameerror", error));
+                    return;
+                }
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.nick = Some(to.clone());
+                }
+                if let Some(marker) = self.read_markers.remove(&from) {
+                    self.read_markers.insert(to.clone(), marker);
+                }
+                self.broadcast(&reply("rename", json!({ "from": from, "to": to }).to_string()));
+                self.broadcast(&self.users());
+                if self.in_voice(id) {
+                    self.broadcast(&self.voice());
+                }
+            }
+            "message" => {
+                let from = match self.nick(id) {
+                    Some(from) => from,
+                    None => return,
+                };
+                let time = now();
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                let message = MessageData {
+                    id: self.next_message_id.to_string(),
+                    seq,
+                    from,
+                    message: data,
+                    reply_to: frame.reply_to,
+                    client_id: frame.client_id,
+                    media: frame.media,
+                    location: frame.location,
+                    time,
+                };
+                self.next_message_id += 1;
+                let json = json!(message).to_string();
+                self.broadcast(&reply("message", json.clone()));
+                self.history.push_back(Stored { time, seq, json });
+                if self.history.len() > HISTORY_LIMIT {
+                    self.history.pop_front();
+                }
+            }
+            "history" => {
+                let request = match serde_json::from_str::<HistoryRequest>(&data) {
+                    Ok(request) => request,
+                    Err(_) => return,
+                };
+                let older: Vec<_> = self
+                    .history
+                    .iter()
+                    .filter(|m| match request.before {
+                        Some(before) => m.time < before,
+                        None => true,
+                    })
+                    .map(|m| m.json.clone())
+                    .collect();
+                let page = older[older.len().saturating_sub(request.limit)..].to_vec();
+                self.send(id, &reply_list("history", page));
+            }
+            "catchup" => {
+                if let Ok(request) = serde_json::from_str::<CatchupRequest>(&data) {
+                    let missed = self.history.iter().filter(|m| m.seq > request.after).map(|m| m.json.clone()).collect();
+                    self.send(id, &reply_list("catchup", missed));
+                }
+            }
+            "resync" => {
+                if let Ok(request) = serde_json::from_str::<ResyncRequest>(&data) {
+                    let range = request.from..=request.to;
+                    let missed = self.history.iter().filter(|m| range.contains(&m.seq)).map(|m| m.json.clone()).collect();
+                    self.send(id, &reply_list("resync", missed));
+                }
+            }
+            // Markers only move forward; an older one is answered with the newer one kept here
+            "readmarker" => {
+                let (nick, marker) = match (self.nick(id), serde_json::from_str::<ReadMarker>(&data)) {
+                    (Some(nick), Ok(marker)) if marker.room == self.name => (nick, marker),
+                    _ => return,
+                };
+                match self.read_markers.get(&nick) {
+                    Some(known) if !marker.is_after(known) => {
+                        if known.is_after(&marker) {
+                            self.send(id, &reply("readmarker", json!(known).to_string()));
+                        }
+                    }
+                    _ => {
+                        self.read_markers.insert(nick, marker);
+                    }
+                }
+            }
+            // Without accounts a nickname is anyone's once it's free, so drafts aren't kept
+            // where the next person to take it would be handed them
+            "draft" => {}
+            "voice" => {
+                let update = match serde_json::from_str::<VoiceUpdate>(&data) {
+                    Ok(update) if update.room == self.name => update,
+                    _ => return,
+                };
+                let client = match self.clients.get_mut(&id) {
+                    Some(client) if client.nick.is_some() => client,
+                    _ => return,
+                };
+                let voice = update.joined.then_some(update.muted);
+                if client.voice != voice {
+                    client.voice = voice;
+                    self.broadcast(&self.voice());
+                }
+            }
+            // Offers, answers and ICE candidates, passed only between members of the call
+            "signal" => {
+                let from = match self.nick(id) {
+                    Some(from) if self.in_voice(id) => from,
+                    _ => return,
+                };
+                let mut payload = match serde_json::from_str(&data) {
+                    Ok(serde_json::Value::Object(payload)) => payload,
+                    _ => return,
+                };
+                let to = match payload.get("to").and_then(|to| to.as_str()) {
+                    Some(to) => to.to_string(),
+                    None => return,
+                };
+                payload.insert("from".to_string(), json!(from));
+                let frame = reply("signal", serde_json::Value::Object(payload).to_string());
+                for client in self.clients.values() {
+                    if client.nick.as_deref() == Some(to.as_str()) && client.voice.is_some() {
+                        let _ = client.tx.send(frame.clone());
+                    }
+                }
+            }
+            // Video call setup, passed to whoever is named in `to`
+            "call" => {
+                let from = match self.nick(id) {
+                    Some(from) => from,
+                    None => return,
+                };
+                let mut payload = match serde_json::from_str(&data) {
+                    Ok(serde_json::Value::Object(payload)) => payload,
+                    _ => return,
+                };
+                let to = match payload.get("to").and_then(|to| to.as_str()) {
+                    Some(to) => to.to_string(),
+                    None => return,
+                };
+                payload.insert("from".to_string(), json!(from));
+                let frame = reply("call", serde_json::Value::Object(payload).to_string());
+                for client in self.clients.values() {
+                    if client.nick.as_deref() == Some(to.as_str()) {
+                        let _ = client.tx.send(frame.clone());
+                    }
+                }
+            }
+            // Lets clients measure round-trip time
+            "ping" => self.send(id, &reply("pong", data)),
+            // The sender is always taken from the connection, never trusted from the payload
+            "edit" | "reaction" | "delete" => {
+                let from = match self.nick(id) {
+                    Some(from) => from,
+                    None => return,
+                };
+                if let Ok(serde_json::Value::Object(mut payload)) = serde_json::from_str(&data) {
+                    payload.insert("from".to_string(), json!(from));
+                    self.broadcast(&reply(&frame.message_type, serde_json::Value::Object(payload).to_string()));
+                }
+            }
+            other => eprintln!("Unsupported message type {}", other),
+        }
+    }
+}
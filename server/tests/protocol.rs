@@ -0,0 +1,242 @@
+//! The websocket protocol as the client speaks it, against a server on a port of its own.
+
+use std::{net::SocketAddr, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+// Long enough for a loaded CI machine, short enough that a missing reply fails quickly
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn start() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(yewchat_server::serve(listener));
+    address
+}
+
+struct Client {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl Client {
+    async fn connect(address: SocketAddr, room: Option<&str>) -> Self {
+        let url = match room {
+            Some(room) => format!("ws://{}/?room={}", address, room),
+            None => format!("ws://{}/", address),
+        };
+        let (socket, _) = connect_async(url).await.unwrap();
+        Self { socket }
+    }
+
+    async fn send(&mut self, frame: Value) {
+        self.socket.send(Message::Text(frame.to_string().into())).await.unwrap();
+    }
+
+    /// The next frame of type `message_type`, skipping any others.
+    async fn expect(&mut self, message_type: &str) -> Value {
+        loop {
+            let message = timeout(REPLY_TIMEOUT, self.socket.next())
+                .await
+                .unwrap_or_else(|_| panic!("no {} frame", message_type))
+                .unwrap()
+                .unwrap();
+            if let Message::Text(text) = message {
+                let frame: Value = serde_json::from_str(text.as_str()).unwrap();
+                if frame["messageType"] == message_type {
+                    return frame;
+                }
+            }
+        }
+    }
+
+    /// Registers as `nick`, returning the `registered` reply's data.
+    async fn register(&mut self, nick: &str) -> Value {
+        self.send(json!({ "messageType": "register", "data": nick })).await;
+        data(&self.expect("registered").await)
+    }
+
+    async fn say(&mut self, text: &str) {
+        self.send(json!({ "messageType": "message", "data": text })).await;
+    }
+}
+
+// `data` holds JSON of its own
+fn data(frame: &Value) -> Value {
+    serde_json::from_str(frame["data"].as_str().unwrap()).unwrap()
+}
+
+// So does each entry of `dataArray` in `history`, `catchup` and `resync`
+fn data_array(frame: &Value) -> Vec<Value> {
+    frame["dataArray"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| serde_json::from_str(entry.as_str().unwrap()).unwrap())
+        .collect()
+}
+
+fn texts(messages: &[Value]) -> Vec<&str> {
+    messages.iter().map(|m| m["message"].as_str().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn register_hands_out_a_token_and_lists_users() {
+    let address = start().await;
+    let mut alice = Client::connect(address, None).await;
+    let registered = alice.register("alice").await;
+    assert_eq!(registered["nick"], "alice");
+    assert!(!registered["token"].as_str().unwrap().is_empty());
+    assert_eq!(alice.expect("users").await["dataArray"], json!(["alice"]));
+
+    let mut bob = Client::connect(address, None).await;
+    bob.register("bob").await;
+    let users = alice.expect("users").await;
+    assert_eq!(users["dataArray"], json!(["alice", "bob"]));
+    assert_eq!(data(&users), json!({ "room": "main", "members": [{ "nick": "alice" }, { "nick": "bob" }] }));
+}
+
+#[tokio::test]
+async fn register_rejects_a_taken_name() {
+    let address = start().await;
+    let mut alice = Client::connect(address, None).await;
+    alice.register("alice").await;
+    let mut other = Client::connect(address, None).await;
+    other.send(json!({ "messageType": "register", "data": "alice" })).await;
+    assert_eq!(other.expect("registererror").await["data"], "The name \"alice\" is already taken");
+}
+
+#[tokio::test]
+async fn reclaim_takes_a_name_back() {
+    let address = start().await;
+    let mut stale = Client::connect(address, None).await;
+    let token = stale.register("alice").await["token"].clone();
+    let mut again = Client::connect(address, None).await;
+    again.send(json!({ "messageType": "register", "data": "alice", "reclaim": token })).await;
+    assert_eq!(data(&again.expect("registered").await)["nick"], "alice");
+}
+
+#[tokio::test]
+async fn messages_reach_everyone_in_order() {
+    let address = start().await;
+    let mut alice = Client::connect(address, None).await;
+    alice.register("alice").await;
+    let mut bob = Client::connect(address, None).await;
+    bob.register("bob").await;
+
+    alice
+        .send(json!({ "messageType": "message", "data": "hi", "clientId": "c1", "replyTo": { "id": "7" } }))
+        .await;
+    for client in [&mut alice, &mut bob] {
+        let message = data(&client.expect("message").await);
+        assert_eq!(message["from"], "alice");
+        assert_eq!(message["message"], "hi");
+        assert_eq!(message["seq"], 1);
+        assert_eq!(message["clientId"], "c1");
+        assert_eq!(message["replyTo"], json!({ "id": "7" }));
+    }
+    bob.say("hello").await;
+    assert_eq!(data(&alice.expect("message").await)["seq"], 2);
+}
+
+#[tokio::test]
+async fn unregistered_messages_are_dropped() {
+    let address = start().await;
+    let mut lurker = Client::connect(address, None).await;
+    lurker.say("anyone?").await;
+    let mut alice = Client::connect(address, None).await;
+    alice.register("alice").await;
+    alice.say("hi").await;
+    assert_eq!(data(&alice.expect("message").await)["seq"], 1);
+}
+
+#[tokio::test]
+async fn history_pages_back_from_the_latest() {
+    let address = start().await;
+    let mut alice = Client::connect(address, None).await;
+    alice.register("alice").await;
+    for text in ["one", "two", "three"] {
+        alice.say(text).await;
+        alice.expect("message").await;
+    }
+
+    alice.send(json!({ "messageType": "history", "data": json!({ "limit": 2 }).to_string() })).await;
+    let page = data_array(&alice.expect("history").await);
+    assert_eq!(texts(&page), ["two", "three"]);
+
+    let before = page[0]["time"].as_i64().unwrap() + 1;
+    alice
+        .send(json!({ "messageType": "history", "data": json!({ "before": before, "limit": 5 }).to_string() }))
+        .await;
+    let older = data_array(&alice.expect("history").await);
+    assert!(texts(&older).starts_with(&["one"]));
+}
+
+#[tokio::test]
+async fn catchup_sends_what_was_missed() {
+    let address = start().await;
+    let mut alice = Client::connect(address, None).await;
+    alice.register("alice").await;
+    for text in ["one", "two", "three"] {
+        alice.say(text).await;
+        alice.expect("message").await;
+    }
+
+    let mut back = Client::connect(address, None).await;
+    back.register("bob").await;
+    back.send(json!({ "messageType": "catchup", "data": json!({ "after": 1 }).to_string() })).await;
+    let missed = data_array(&back.expect("catchup").await);
+    assert_eq!(texts(&missed), ["two", "three"]);
+    assert_eq!(missed.iter().map(|m| m["seq"].as_u64().unwrap()).collect::<Vec<_>>(), [2, 3]);
+}
+
+#[tokio::test]
+async fn rooms_are_kept_apart() {
+    let address = start().await;
+    let mut main = Client::connect(address, None).await;
+    main.register("alice").await;
+    let mut rust = Client::connect(address, Some("rust")).await;
+    // The same name is free in another room
+    rust.register("alice").await;
+    assert_eq!(data(&rust.expect("users").await)["room"], "rust");
+
+    main.say("in main").await;
+    main.expect("message").await;
+    rust.say("in rust").await;
+    let message = data(&rust.expect("message").await);
+    assert_eq!((message["message"].as_str(), message["seq"].as_u64()), (Some("in rust"), Some(1)));
+}
+
+#[tokio::test]
+async fn rooms_go_once_everyone_has_left() {
+    let address = start().await;
+    let mut rust = Client::connect(address, Some("rust")).await;
+    rust.register("alice").await;
+    drop(rust);
+
+    let mut main = Client::connect(address, None).await;
+    let rooms = timeout(REPLY_TIMEOUT, async {
+        loop {
+            main.send(json!({ "messageType": "serverinfo" })).await;
+            let rooms = data(&main.expect("serverinfo").await)["rooms"].clone();
+            if rooms == 1 {
+                return rooms;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await;
+    assert_eq!(rooms.ok(), Some(json!(1)));
+}
+
+#[tokio::test]
+async fn long_room_names_are_refused() {
+    let address = start().await;
+    let url = format!("ws://{}/?room={}", address, "r".repeat(65));
+    assert!(connect_async(url).await.is_err());
+}